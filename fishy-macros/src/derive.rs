@@ -0,0 +1,271 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use syn::spanned::Spanned;
+use syn::{
+    Data, DeriveInput, Expr, Fields, GenericArgument, Lit, LitInt, LitStr, PathArguments, Type,
+    UnOp,
+};
+
+use crate::toml::{known_types, toml_string, FIELD_TYPES, RELATION_TYPES};
+
+/// Name of the attribute to configure the derived schema.
+const ATTRIBUTE: &str = "p2panda";
+
+/// Returns the schema derived from a struct in the format of a schema file.
+pub fn to_toml(input: &DeriveInput) -> syn::Result<String> {
+    let mut name = to_snake_case(&input.ident.to_string());
+    let mut description = doc_comment(&input.attrs);
+
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident(ATTRIBUTE))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                name = meta.value()?.parse::<LitStr>()?.value();
+            } else if meta.path.is_ident("description") {
+                description = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else {
+                return Err(meta.error("Expected `name` or `description`"));
+            }
+
+            Ok(())
+        })?;
+    }
+
+    let description = description.ok_or_else(|| {
+        syn::Error::new(
+            input.ident.span(),
+            "Schema requires a description, add a doc comment or `#[p2panda(description = \"...\")]`",
+        )
+    })?;
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new(
+                    input.ident.span(),
+                    "Schemas can only be derived from structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                "Schemas can only be derived from structs",
+            ))
+        }
+    };
+
+    if fields.is_empty() {
+        return Err(syn::Error::new(
+            input.ident.span(),
+            format!("Schema `{name}` does not contain any fields"),
+        ));
+    }
+
+    let mut toml = format!(
+        "[{name}]\ndescription = {}\n\n[{name}.fields]\n",
+        toml_string(&description)
+    );
+
+    for field in fields {
+        let mut field_name = field
+            .ident
+            .as_ref()
+            .expect("Named fields have an identifier")
+            .to_string();
+        let mut field_type: Option<String> = None;
+        let mut schema: Option<String> = None;
+        let mut constraints: Vec<String> = Vec::new();
+
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident(ATTRIBUTE))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    field_name = meta.value()?.parse::<LitStr>()?.value();
+                } else if meta.path.is_ident("type") {
+                    let value = meta.value()?.parse::<LitStr>()?;
+
+                    if !FIELD_TYPES.contains(&value.value().as_str()) {
+                        return Err(syn::Error::new(
+                            value.span(),
+                            format!("Unknown field type, expected one of {}", known_types()),
+                        ));
+                    }
+
+                    field_type = Some(value.value());
+                } else if meta.path.is_ident("schema") {
+                    let value = meta.value()?.parse::<LitStr>()?.value();
+                    schema = Some(format!("{{ name = {} }}", toml_string(&value)));
+                } else if meta.path.is_ident("schema_id") {
+                    let value = meta.value()?.parse::<LitStr>()?.value();
+                    schema = Some(format!("{{ id = {} }}", toml_string(&value)));
+                } else if meta.path.is_ident("max_length") {
+                    let value = meta.value()?.parse::<LitInt>()?.base10_parse::<u64>()?;
+                    constraints.push(format!("max_length = {value}"));
+                } else if meta.path.is_ident("min") {
+                    constraints.push(format!("min = {}", number(&meta.value()?.parse()?)?));
+                } else if meta.path.is_ident("max") {
+                    constraints.push(format!("max = {}", number(&meta.value()?.parse()?)?));
+                } else if meta.path.is_ident("regex") {
+                    let value = meta.value()?.parse::<LitStr>()?.value();
+                    constraints.push(format!("regex = {}", toml_string(&value)));
+                } else if let Some(relation) = RELATION_TYPES
+                    .iter()
+                    .find(|relation| meta.path.is_ident(relation))
+                {
+                    field_type = Some(relation.to_string());
+                } else {
+                    return Err(meta.error(format!(
+                        "Expected `rename`, `type`, `schema`, `schema_id`, a constraint like \
+                        `max_length`, `min`, `max` or `regex` or one of {}",
+                        RELATION_TYPES
+                            .iter()
+                            .map(|name| format!("`{name}`"))
+                            .collect::<Vec<String>>()
+                            .join(", ")
+                    )));
+                }
+
+                Ok(())
+            })?;
+        }
+
+        let field_type = match field_type {
+            Some(field_type) => field_type,
+            None => field_type_of(&field.ty).ok_or_else(|| {
+                syn::Error::new(
+                    field.ty.span(),
+                    "Can not map this type to a p2panda field type, set it with \
+                    `#[p2panda(type = \"...\")]` or mark it as a relation",
+                )
+            })?,
+        };
+
+        if !constraints.is_empty() && RELATION_TYPES.contains(&field_type.as_str()) {
+            return Err(syn::Error::new(
+                field.span(),
+                "Relation fields can not have constraints",
+            ));
+        }
+
+        let value = match (RELATION_TYPES.contains(&field_type.as_str()), schema) {
+            (true, Some(schema)) => format!("{{ type = \"{field_type}\", schema = {schema} }}"),
+            (true, None) => {
+                return Err(syn::Error::new(
+                    field.span(),
+                    "Relation requires a schema, set it with `schema = \"<name>\"` or \
+                    `schema_id = \"<schema id>\"`",
+                ))
+            }
+            (false, Some(_)) => {
+                return Err(syn::Error::new(
+                    field.span(),
+                    "Only relation fields can point at a schema",
+                ))
+            }
+            (false, None) if constraints.is_empty() => format!("{{ type = \"{field_type}\" }}"),
+            (false, None) => format!(
+                "{{ type = \"{field_type}\", constraints = {{ {} }} }}",
+                constraints.join(", ")
+            ),
+        };
+
+        toml.push_str(&format!("{field_name} = {value}\n"));
+    }
+
+    Ok(toml)
+}
+
+/// Returns a number literal, optionally negative, in TOML format.
+fn number(expr: &Expr) -> syn::Result<String> {
+    match expr {
+        Expr::Lit(expr) => match &expr.lit {
+            Lit::Int(value) => Ok(value.base10_parse::<i64>()?.to_string()),
+            Lit::Float(value) => Ok(format!("{:?}", value.base10_parse::<f64>()?)),
+            _ => Err(syn::Error::new(expr.span(), "Expected a number")),
+        },
+        Expr::Unary(unary) if matches!(unary.op, UnOp::Neg(_)) => {
+            Ok(format!("-{}", number(&unary.expr)?))
+        }
+        _ => Err(syn::Error::new(expr.span(), "Expected a number")),
+    }
+}
+
+/// Maps Rust types to p2panda field types.
+fn field_type_of(ty: &Type) -> Option<String> {
+    let ty = match ty {
+        Type::Reference(reference) => reference.elem.as_ref(),
+        ty => ty,
+    };
+
+    let Type::Path(path) = ty else {
+        return None;
+    };
+
+    let segment = path.path.segments.last()?;
+
+    let field_type = match segment.ident.to_string().as_str() {
+        "bool" => "bool",
+        "i8" | "i16" | "i32" | "i64" | "u8" | "u16" | "u32" => "int",
+        "f32" | "f64" => "float",
+        "String" | "str" => "str",
+        "Vec" => match &segment.arguments {
+            PathArguments::AngleBracketed(arguments) => match arguments.args.first()? {
+                GenericArgument::Type(Type::Path(inner)) if inner.path.is_ident("u8") => "bytes",
+                _ => return None,
+            },
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    Some(field_type.to_owned())
+}
+
+/// Returns the doc comment of an item with surrounding whitespace removed.
+fn doc_comment(attrs: &[syn::Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta.require_name_value().ok()?.value {
+            Expr::Lit(expr) => match &expr.lit {
+                Lit::Str(value) => Some(value.value().trim().to_owned()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+
+    let doc = lines.join(" ").trim().to_owned();
+
+    if doc.is_empty() {
+        None
+    } else {
+        Some(doc)
+    }
+}
+
+/// Converts a struct name like `EventVenue` to `event_venue`.
+fn to_snake_case(name: &str) -> String {
+    let mut snake_case = String::with_capacity(name.len() + 4);
+
+    for (index, character) in name.chars().enumerate() {
+        if character.is_uppercase() {
+            if index > 0 {
+                snake_case.push('_');
+            }
+            snake_case.extend(character.to_lowercase());
+        } else {
+            snake_case.push(character);
+        }
+    }
+
+    snake_case
+}
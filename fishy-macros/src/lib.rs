@@ -0,0 +1,96 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Procedural macros to define p2panda schemas for fishy in Rust code.
+//!
+//! Use them through the `macros` feature of the `fishy` crate instead of depending on this crate
+//! directly.
+mod derive;
+mod schema;
+mod toml;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, DeriveInput};
+
+use crate::schema::Schemas;
+
+/// Defines p2panda schemas in Rust code and returns them as a `fishy::schema_file::SchemaFile`.
+///
+/// The syntax follows the schema file: Every schema has a name, a description and its fields.
+/// Relations either point at a schema defined in the same macro by its name or at an external
+/// schema by its id:
+///
+/// ```ignore
+/// let schema_file = fishy::schema! {
+///     venue: "A place to meet" {
+///         name: str,
+///     },
+///     event: "Something happening at a venue" {
+///         title: str,
+///         date: int,
+///         venue: relation(venue),
+///         organiser: relation(id = "organiser_0020c3accb0b0c8822ecc0309190e23de5f7f6c82f660ce08023a1d74e055a3d7c4d"),
+///     }
+/// }?;
+/// ```
+///
+/// Field types and relation arguments are checked at compile time, schema names, descriptions and
+/// schema ids are validated when the macro gets evaluated, returning a `fishy::Result`.
+#[proc_macro]
+pub fn schema(input: TokenStream) -> TokenStream {
+    let schemas = parse_macro_input!(input as Schemas);
+    let toml = schemas.to_toml();
+
+    quote! {
+        <::fishy::schema_file::SchemaFile as ::std::str::FromStr>::from_str(#toml)
+    }
+    .into()
+}
+
+/// Derives a p2panda schema from a struct, implementing `fishy::ToP2pandaSchema`.
+///
+/// The schema name is the struct name in snake case and the description is taken from the doc
+/// comment, both can be set with `#[p2panda(name = "...", description = "...")]`. Field types are
+/// derived from the Rust types (`bool`, integers up to `i64`, `f32`, `f64`, `String` and
+/// `Vec<u8>`), relations need to be marked explicitly. Constraints of fields are set with
+/// `max_length`, `min`, `max` and `regex`:
+///
+/// ```ignore
+/// /// Something happening at a venue
+/// #[derive(ToP2pandaSchema)]
+/// struct Event {
+///     #[p2panda(max_length = 120)]
+///     title: String,
+///     #[p2panda(type = "int")]
+///     date: Timestamp,
+///     #[p2panda(relation_list, schema = "venue")]
+///     venues: Vec<DocumentId>,
+///     #[p2panda(relation, schema_id = "organiser_0020c3accb0b0c8822ecc0309190e23de5f7f6c82f660ce08023a1d74e055a3d7c4d")]
+///     organiser: DocumentId,
+/// }
+///
+/// let schema = Event::to_p2panda_schema()?;
+/// ```
+#[proc_macro_derive(ToP2pandaSchema, attributes(p2panda))]
+pub fn derive_to_p2panda_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let toml = match derive::to_toml(&input) {
+        Ok(toml) => toml,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::fishy::ToP2pandaSchema for #ident #ty_generics #where_clause {
+            fn to_p2panda_schema() -> ::fishy::Result<::fishy::build::CurrentSchema> {
+                let schema_file: ::fishy::schema_file::SchemaFile = #toml.parse()?;
+                let mut schemas = ::fishy::build::get_current_schemas(&schema_file)?;
+                Ok(schemas.remove(0))
+            }
+        }
+    }
+    .into()
+}
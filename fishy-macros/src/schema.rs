@@ -0,0 +1,185 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::collections::HashSet;
+
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{braced, parenthesized, Ident, LitStr, Token};
+
+use crate::toml::{known_types, toml_string, FIELD_TYPES, RELATION_TYPES};
+
+/// All schemas defined in the macro.
+pub struct Schemas(Vec<Schema>);
+
+impl Parse for Schemas {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let schemas = Punctuated::<Schema, Token![,]>::parse_terminated(input)?;
+        let mut names = HashSet::new();
+
+        for schema in &schemas {
+            if !names.insert(schema.name.to_string()) {
+                return Err(syn::Error::new(
+                    schema.name.span(),
+                    format!("Schema `{}` is defined more than once", schema.name),
+                ));
+            }
+        }
+
+        Ok(Self(schemas.into_iter().collect()))
+    }
+}
+
+impl Schemas {
+    /// Returns the schemas in the format of a schema file.
+    pub fn to_toml(&self) -> String {
+        let mut toml = String::new();
+
+        for schema in &self.0 {
+            toml.push_str(&format!(
+                "[{}]\ndescription = {}\n\n[{}.fields]\n",
+                schema.name,
+                toml_string(&schema.description.value()),
+                schema.name
+            ));
+
+            for field in &schema.fields {
+                let value = match &field.relation {
+                    None => format!("{{ type = \"{}\" }}", field.field_type),
+                    Some(Relation::Name(name)) => format!(
+                        "{{ type = \"{}\", schema = {{ name = \"{}\" }} }}",
+                        field.field_type, name
+                    ),
+                    Some(Relation::Id(id)) => format!(
+                        "{{ type = \"{}\", schema = {{ id = {} }} }}",
+                        field.field_type,
+                        toml_string(&id.value())
+                    ),
+                };
+
+                toml.push_str(&format!("{} = {}\n", field.name, value));
+            }
+
+            toml.push('\n');
+        }
+
+        toml
+    }
+}
+
+/// Single schema with its description and fields.
+struct Schema {
+    name: Ident,
+    description: LitStr,
+    fields: Vec<Field>,
+}
+
+impl Parse for Schema {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name: Ident = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let description: LitStr = input.parse()?;
+
+        let content;
+        braced!(content in input);
+        let fields = Punctuated::<Field, Token![,]>::parse_terminated(&content)?;
+
+        if fields.is_empty() {
+            return Err(syn::Error::new(
+                name.span(),
+                format!("Schema `{name}` does not contain any fields"),
+            ));
+        }
+
+        let mut names = HashSet::new();
+        for field in &fields {
+            if !names.insert(field.name.to_string()) {
+                return Err(syn::Error::new(
+                    field.name.span(),
+                    format!("Field `{}` is defined more than once", field.name),
+                ));
+            }
+        }
+
+        Ok(Self {
+            name,
+            description,
+            fields: fields.into_iter().collect(),
+        })
+    }
+}
+
+/// Single schema field with its type.
+struct Field {
+    name: Ident,
+    field_type: Ident,
+    relation: Option<Relation>,
+}
+
+impl Parse for Field {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name: Ident = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let field_type: Ident = input.parse()?;
+        let type_str = field_type.to_string();
+
+        let relation = if RELATION_TYPES.contains(&type_str.as_str()) {
+            if !input.peek(syn::token::Paren) {
+                return Err(syn::Error::new(
+                    field_type.span(),
+                    format!(
+                        "Relation `{type_str}` requires a schema, for example `{type_str}(venue)`"
+                    ),
+                ));
+            }
+
+            let content;
+            parenthesized!(content in input);
+            Some(content.parse()?)
+        } else if FIELD_TYPES.contains(&type_str.as_str()) {
+            None
+        } else {
+            return Err(syn::Error::new(
+                field_type.span(),
+                format!(
+                    "Unknown field type `{type_str}`, expected one of {}",
+                    known_types()
+                ),
+            ));
+        };
+
+        Ok(Self {
+            name,
+            field_type,
+            relation,
+        })
+    }
+}
+
+/// Schema a relation field points at.
+enum Relation {
+    /// Schema defined in the same macro.
+    Name(Ident),
+
+    /// External schema identified by its id.
+    Id(LitStr),
+}
+
+impl Parse for Relation {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name: Ident = input.parse()?;
+
+        if input.peek(Token![=]) {
+            if name != "id" {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "Expected `id = \"<schema id>\"`",
+                ));
+            }
+
+            input.parse::<Token![=]>()?;
+            return Ok(Self::Id(input.parse()?));
+        }
+
+        Ok(Self::Name(name))
+    }
+}
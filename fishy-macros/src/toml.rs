@@ -0,0 +1,45 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+/// Basic field types as they are named in the schema file.
+pub const FIELD_TYPES: [&str; 5] = ["bool", "float", "int", "str", "bytes"];
+
+/// Relation field types as they are named in the schema file.
+pub const RELATION_TYPES: [&str; 4] = [
+    "relation",
+    "relation_list",
+    "pinned_relation",
+    "pinned_relation_list",
+];
+
+/// Returns a comma-separated list of all known field types for error messages.
+pub fn known_types() -> String {
+    FIELD_TYPES
+        .iter()
+        .chain(RELATION_TYPES.iter())
+        .map(|name| format!("`{name}`"))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Returns the given value as an escaped TOML string.
+pub fn toml_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+
+    for character in value.chars() {
+        match character {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            character if character.is_control() => {
+                escaped.push_str(&format!("\\u{:04X}", character as u32))
+            }
+            character => escaped.push(character),
+        }
+    }
+
+    escaped.push('"');
+    escaped
+}
@@ -0,0 +1,113 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Stable alias names for schema ids.
+//!
+//! Schema ids change with every new version of a schema. The aliases file maps a stable name
+//! (the upper-cased schema name) to the current schema id so applications do not need to hard-code
+//! raw view ids:
+//!
+//! ```toml
+//! EVENT = "event_0020c3accb0b0c8822ecc0309190e23de5f7f6c82f660ce08023a1d74e055a3d7c4d"
+//! VENUE = "venue_0020a1d4a4a4cfb1a8ac2a9d4e4b83c0a38c3a2b0a0bb5b6cb1c1dcd3a2b6d5c0a2d"
+//! ```
+use std::collections::btree_map::Iter;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use p2panda_rs::schema::{SchemaId, SchemaName};
+use serde::{Deserialize, Serialize};
+
+use crate::constants::GENERATED_FILE_HEADER;
+use crate::error::{Error, Result};
+use crate::utils::files;
+
+/// Mapping of stable alias names to current schema ids.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Aliases(BTreeMap<String, SchemaId>);
+
+impl Aliases {
+    /// Returns aliases for all given schema ids.
+    ///
+    /// Fails if the names of two schemas only differ in case, as they would get the same alias.
+    pub fn new(schema_ids: &[SchemaId]) -> Result<Self> {
+        let mut aliases: BTreeMap<String, SchemaId> = BTreeMap::new();
+
+        for schema_id in schema_ids {
+            let alias = alias_name(&schema_id.name());
+            if let Some(other) = aliases.get(&alias) {
+                return Err(Error::AliasCollision(alias, other.name(), schema_id.name()));
+            }
+
+            aliases.insert(alias, schema_id.clone());
+        }
+
+        Ok(Self(aliases))
+    }
+
+    /// Returns the schema id of the given alias.
+    pub fn get(&self, alias: &str) -> Option<&SchemaId> {
+        self.0.get(alias)
+    }
+
+    /// Returns an iterator over all aliases and their schema ids.
+    pub fn iter(&self) -> Iter<'_, String, SchemaId> {
+        self.0.iter()
+    }
+
+    /// Writes aliases to a .toml file at the given path.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let aliases_str = format!(
+            "{}\n\n{}",
+            GENERATED_FILE_HEADER,
+            toml::to_string_pretty(&self)?
+        );
+
+        files::write_file(path, &aliases_str)
+    }
+}
+
+/// Loads aliases from a .toml file at the given path.
+pub fn load(path: impl AsRef<Path>) -> Result<Aliases> {
+    let data = files::read_file(&path)?;
+    let aliases: Aliases = toml::from_str(&data).map_err(Error::InvalidAliasesFile)?;
+    Ok(aliases)
+}
+
+/// Returns the alias name for a schema, which is the upper-cased schema name.
+pub fn alias_name(schema_name: &SchemaName) -> String {
+    schema_name.to_string().to_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::schema::SchemaId;
+
+    use crate::error::Error;
+
+    use super::Aliases;
+
+    const VIEW_ID: &str = "0020c3accb0b0c8822ecc0309190e23de5f7f6c82f660ce08023a1d74e055a3d7c4d";
+
+    fn schema_id(name: &str) -> SchemaId {
+        format!("{name}_{VIEW_ID}").parse().unwrap()
+    }
+
+    #[test]
+    fn upper_cases_schema_names() {
+        let aliases = Aliases::new(&[schema_id("event"), schema_id("venue")]).unwrap();
+
+        assert_eq!(aliases.get("EVENT"), Some(&schema_id("event")));
+        assert_eq!(aliases.get("VENUE"), Some(&schema_id("venue")));
+    }
+
+    #[test]
+    fn rejects_names_differing_in_case() {
+        let result = Aliases::new(&[schema_id("event"), schema_id("Event")]);
+
+        assert!(matches!(
+            result,
+            Err(Error::AliasCollision(alias, first, second))
+                if alias == "EVENT" && first.to_string() == "event" && second.to_string() == "Event"
+        ));
+    }
+}
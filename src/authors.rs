@@ -0,0 +1,60 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Attribute the commits of lock files to the key pairs which signed them.
+//!
+//! Several maintainers can sign the schemas of one project, every one with their own key pair.
+//! Projects restrict who is allowed to do so with a set of trusted public keys in `fishy.toml`,
+//! commits of lock files get verified against it whenever they are loaded:
+//!
+//! ```toml
+//! trusted_keys = [
+//!     "2f8e50c2ede6d936ecc3144187ff1c273808185cfbc5ff3d3748d1ff7353fc96",
+//!     "af86452779d88935999966a7005223f8e20f9cb8e07b5feda295cb64d9b0bb29",
+//! ]
+//! ```
+use p2panda_rs::entry::decode::decode_entry;
+use p2panda_rs::entry::traits::AsEntry;
+use p2panda_rs::identity::PublicKey;
+use tracing::{debug, instrument};
+
+use crate::error::{Error, Result};
+use crate::lock_file::Commit;
+
+/// Returns the public key of every author who signed the given commits together with their number
+/// of commits, in the order they signed their first commit.
+pub fn commit_authors(commits: &[Commit]) -> Result<Vec<(PublicKey, usize)>> {
+    let mut authors: Vec<(PublicKey, usize)> = Vec::new();
+
+    for commit in commits {
+        let public_key = decode_entry(&commit.entry)?.public_key().to_owned();
+        match authors.iter_mut().find(|(author, _)| author == &public_key) {
+            Some((_, count)) => *count += 1,
+            None => authors.push((public_key, 1)),
+        }
+    }
+
+    Ok(authors)
+}
+
+/// Checks that all commits are signed by one of the trusted public keys.
+///
+/// Every author is trusted if no trusted keys are given.
+#[instrument(skip_all, fields(trusted_keys = trusted_keys.len()))]
+pub fn verify_commit_authors(commits: &[Commit], trusted_keys: &[PublicKey]) -> Result<()> {
+    if trusted_keys.is_empty() {
+        return Ok(());
+    }
+
+    for commit in commits {
+        let public_key = decode_entry(&commit.entry)?.public_key().to_owned();
+        if !trusted_keys.contains(&public_key) {
+            return Err(Error::UntrustedCommitAuthor(
+                commit.entry_hash.clone(),
+                public_key.to_string(),
+            ));
+        }
+    }
+    debug!("Verified authors of {} commits", commits.len());
+
+    Ok(())
+}
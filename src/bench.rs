@@ -0,0 +1,157 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Measure the publish throughput of a node with synthetic commits.
+use std::time::{Duration, Instant};
+
+use p2panda_rs::document::DocumentViewId;
+use p2panda_rs::entry::traits::AsEncodedEntry;
+use p2panda_rs::identity::KeyPair;
+use p2panda_rs::operation::{Operation, OperationAction, OperationBuilder};
+use p2panda_rs::schema::{FieldType, SchemaId};
+use p2panda_rs::test_utils::memory_store::MemoryStore;
+
+use crate::deploy::Publisher;
+use crate::error::Result;
+use crate::lock_file::Commit;
+use crate::signer::sign_and_publish;
+
+/// Name of the disposable schema used for benchmarks.
+const BENCH_SCHEMA_NAME: &str = "fishy_bench";
+
+/// Generates the given number of commits, signed by a new, throwaway key pair.
+///
+/// The commits create a disposable schema with one field, followed by updates of its
+/// description until the requested number of commits is reached.
+pub async fn generate_commits(count: usize) -> Result<Vec<Commit>> {
+    let store = MemoryStore::default();
+    let key_pair = KeyPair::new();
+    let mut commits = Vec::with_capacity(count);
+
+    if count == 0 {
+        return Ok(commits);
+    }
+
+    let field_operation = OperationBuilder::new(&SchemaId::SchemaFieldDefinition(1))
+        .action(OperationAction::Create)
+        .fields(&[("name", "value".into()), ("type", FieldType::String.into())])
+        .build()?;
+    let field_view_id = commit(&store, &key_pair, &field_operation, &mut commits).await?;
+
+    let mut schema_view_id: Option<DocumentViewId> = None;
+
+    for index in 1..count {
+        let operation = match &schema_view_id {
+            None => OperationBuilder::new(&SchemaId::SchemaDefinition(1))
+                .action(OperationAction::Create)
+                .fields(&[
+                    ("name", BENCH_SCHEMA_NAME.into()),
+                    ("description", "Disposable schema to benchmark nodes".into()),
+                    ("fields", vec![field_view_id.clone()].into()),
+                ])
+                .build()?,
+            Some(view_id) => OperationBuilder::new(&SchemaId::SchemaDefinition(1))
+                .action(OperationAction::Update)
+                .previous(view_id)
+                .fields(&[("description", format!("Benchmark commit #{index}").into())])
+                .build()?,
+        };
+
+        schema_view_id = Some(commit(&store, &key_pair, &operation, &mut commits).await?);
+    }
+
+    Ok(commits)
+}
+
+/// Signs and publishes an operation on the in-memory store and keeps track of the commit.
+async fn commit(
+    store: &MemoryStore,
+    key_pair: &KeyPair,
+    operation: &Operation,
+    commits: &mut Vec<Commit>,
+) -> Result<DocumentViewId> {
+    let (encoded_entry, encoded_operation) = sign_and_publish(store, key_pair, operation).await?;
+    commits.push(Commit::new(&encoded_entry, &encoded_operation));
+
+    Ok(encoded_entry.hash().into())
+}
+
+/// Publishes all commits one after another and measures the round-trip latency of each of them.
+///
+/// The `on_progress` callback gets called after every published commit.
+pub async fn run<P>(
+    publisher: &P,
+    commits: &[Commit],
+    mut on_progress: impl FnMut(&Commit),
+) -> Result<BenchReport>
+where
+    P: Publisher + ?Sized,
+{
+    let mut latencies = Vec::with_capacity(commits.len());
+    let started = Instant::now();
+
+    for commit in commits {
+        let now = Instant::now();
+        publisher.publish(commit).await?.into_result(commit)?;
+        latencies.push(now.elapsed());
+
+        on_progress(commit);
+    }
+
+    Ok(BenchReport {
+        total: started.elapsed(),
+        latencies,
+    })
+}
+
+/// Measured latencies of a benchmark run.
+#[derive(Clone, Debug)]
+pub struct BenchReport {
+    /// Duration of the whole run.
+    pub total: Duration,
+
+    /// Round-trip latency of every published commit, in publishing order.
+    pub latencies: Vec<Duration>,
+}
+
+impl BenchReport {
+    /// Returns the lowest latency.
+    pub fn min(&self) -> Duration {
+        self.latencies.iter().min().copied().unwrap_or_default()
+    }
+
+    /// Returns the highest latency.
+    pub fn max(&self) -> Duration {
+        self.latencies.iter().max().copied().unwrap_or_default()
+    }
+
+    /// Returns the average latency.
+    pub fn mean(&self) -> Duration {
+        if self.latencies.is_empty() {
+            return Duration::ZERO;
+        }
+
+        self.latencies.iter().sum::<Duration>() / self.latencies.len() as u32
+    }
+
+    /// Returns the latency below which the given percentage of all measurements lie.
+    pub fn percentile(&self, percent: f64) -> Duration {
+        if self.latencies.is_empty() {
+            return Duration::ZERO;
+        }
+
+        let mut sorted = self.latencies.clone();
+        sorted.sort();
+
+        let index = ((percent / 100.0) * (sorted.len() - 1) as f64).round() as usize;
+        sorted[index.min(sorted.len() - 1)]
+    }
+
+    /// Returns the number of published commits per second.
+    pub fn throughput(&self) -> f64 {
+        if self.total.is_zero() {
+            return 0.0;
+        }
+
+        self.latencies.len() as f64 / self.total.as_secs_f64()
+    }
+}
@@ -0,0 +1,169 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Swift and Kotlin bindings to parse, verify and deploy lock files from mobile apps.
+//!
+//! The scaffolding is generated with [uniffi](https://mozilla.github.io/uniffi-rs/). Build fishy
+//! as a library with the `bindings` feature and generate the foreign language code from it with
+//! `uniffi-bindgen` 0.28:
+//!
+//! ```bash
+//! cargo rustc --lib --release --no-default-features --features bindings --crate-type cdylib
+//! uniffi-bindgen generate --library target/release/libfishy.so --language swift --out-dir out
+//! ```
+use std::sync::Arc;
+use std::time::Duration;
+
+use p2panda_rs::identity::PublicKey;
+
+use crate::client::Client;
+use crate::deploy::{self, DeployReport as DeployReportInner};
+use crate::error::Error;
+use crate::lock_file::{Commit as CommitInner, LockFile as LockFileInner};
+use crate::lock_signature::LockSignature;
+use crate::verify::verify_lock_file;
+
+/// Errors returned to Swift and Kotlin, carrying the message of the underlying error.
+#[derive(Debug, thiserror::Error, uniffi::Error)]
+#[uniffi(flat_error)]
+pub enum FishyError {
+    /// Lock file could not be parsed or contains invalid commits.
+    #[error("{0}")]
+    InvalidLockFile(#[source] Error),
+
+    /// Publishing commits on the node failed.
+    #[error("{0}")]
+    Deploy(#[source] Error),
+
+    /// Lock file is not signed by the expected public key.
+    #[error("{0}")]
+    InvalidSignature(#[source] Error),
+}
+
+/// Single commit with hex-encoded entry and operation.
+#[derive(Clone, Debug, uniffi::Record)]
+pub struct Commit {
+    /// Hash of the entry.
+    pub entry_hash: String,
+
+    /// Encoded and signed p2panda entry.
+    pub entry: String,
+
+    /// Encoded p2panda operation.
+    pub operation: String,
+}
+
+impl From<&CommitInner> for Commit {
+    fn from(commit: &CommitInner) -> Self {
+        Self {
+            entry_hash: commit.entry_hash.to_string(),
+            entry: commit.entry.to_string(),
+            operation: commit.operation.to_string(),
+        }
+    }
+}
+
+/// Number of published and skipped commits after a deployment.
+#[derive(Clone, Debug, uniffi::Record)]
+pub struct DeployReport {
+    /// Commits which have been published.
+    pub published: u64,
+
+    /// Commits the node already knew about.
+    pub skipped: u64,
+}
+
+impl From<DeployReportInner> for DeployReport {
+    fn from(report: DeployReportInner) -> Self {
+        Self {
+            published: report.published as u64,
+            skipped: report.skipped as u64,
+        }
+    }
+}
+
+/// Lock file holding signed and encoded p2panda commits.
+#[derive(Debug, uniffi::Object)]
+pub struct LockFile(LockFileInner);
+
+#[uniffi::export(async_runtime = "tokio")]
+impl LockFile {
+    /// Parses the content of a .toml lock file.
+    #[uniffi::constructor]
+    pub fn parse(data: String) -> Result<Self, FishyError> {
+        let lock_file = data.parse().map_err(FishyError::InvalidLockFile)?;
+        Ok(Self(lock_file))
+    }
+
+    /// Decodes a CBOR bundle written with `fishy build --emit-bundle`.
+    #[uniffi::constructor]
+    pub fn from_bundle(data: Vec<u8>) -> Result<Self, FishyError> {
+        let lock_file = LockFileInner::from_cbor(&data).map_err(FishyError::InvalidLockFile)?;
+        Ok(Self(lock_file))
+    }
+
+    /// Returns all commits in the order they need to be published.
+    pub fn commits(&self) -> Vec<Commit> {
+        self.0.commits.iter().flatten().map(Commit::from).collect()
+    }
+
+    /// Returns the ids of all schemas defined by the commits, sorted by name.
+    pub fn schema_ids(&self) -> Result<Vec<String>, FishyError> {
+        let schema_ids = self.0.schema_ids().map_err(FishyError::InvalidLockFile)?;
+        Ok(schema_ids.iter().map(|id| id.to_string()).collect())
+    }
+
+    /// Checks the signatures, logs and schema definitions of all commits.
+    pub async fn verify(&self) -> Result<(), FishyError> {
+        verify_lock_file(&self.0)
+            .await
+            .map_err(FishyError::InvalidLockFile)?;
+        Ok(())
+    }
+}
+
+/// Checks that the content of a .toml lock file is signed by the given hex-encoded public key,
+/// with the content of its `schema.lock.sig` signature file.
+#[uniffi::export]
+pub fn verify_signature(
+    data: String,
+    signature: String,
+    public_key: String,
+) -> Result<(), FishyError> {
+    let public_key = PublicKey::new(&public_key).map_err(|err| {
+        FishyError::InvalidSignature(Error::InvalidLockSignature(err.to_string()))
+    })?;
+    let signature: LockSignature = signature.parse().map_err(FishyError::InvalidSignature)?;
+
+    signature
+        .verify(data.as_bytes(), &public_key)
+        .map_err(FishyError::InvalidSignature)
+}
+
+/// Publishes all commits of the lock file in order on the node with the given GraphQL endpoint.
+///
+/// Commits the node already knows about are skipped.
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn deploy(
+    endpoint: String,
+    lock_file: Arc<LockFile>,
+    timeout_secs: u64,
+) -> Result<DeployReport, FishyError> {
+    let client = Client::builder(&endpoint)
+        .timeout(Duration::from_secs(timeout_secs))
+        .build();
+    let commits = lock_file.0.commits.clone().unwrap_or_default();
+
+    let outcomes = deploy::deploy(&client, &commits, |_, _| {})
+        .await
+        .map_err(FishyError::Deploy)?;
+
+    // Report the first rejected commit as error
+    for (commit, outcome) in &outcomes {
+        outcome
+            .clone()
+            .into_result(commit)
+            .map_err(FishyError::Deploy)?;
+    }
+
+    Ok(DeployReportInner::from_outcomes(&outcomes).into())
+}
@@ -0,0 +1,40 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Synchronous wrappers around the async deploy API.
+//!
+//! Applications without an async runtime can use these methods, they run the async calls on an
+//! internal single-threaded runtime and block until they are finished. Do not call them from
+//! within an async context.
+use tokio::runtime::{Builder, Runtime};
+
+use crate::client::Client;
+use crate::deploy::{deploy, DeployOutcomes, PublishOutcome, Publisher};
+use crate::error::Result;
+use crate::lock_file::Commit;
+
+/// Returns a new runtime to block on async calls.
+fn runtime() -> Result<Runtime> {
+    let runtime = Builder::new_current_thread().enable_all().build()?;
+    Ok(runtime)
+}
+
+impl Client {
+    /// Publishes a commit on the node via GraphQL, blocking until the request is finished.
+    pub fn publish_blocking(&self, commit: &Commit) -> Result<PublishOutcome> {
+        runtime()?.block_on(self.publish(commit))
+    }
+}
+
+/// Publishes all commits in the given order, blocking until all of them are finished.
+///
+/// See `deploy` for the async version.
+pub fn deploy_blocking<P>(
+    publisher: &P,
+    commits: &[Commit],
+    on_progress: impl FnMut(&Commit, &PublishOutcome),
+) -> Result<DeployOutcomes>
+where
+    P: Publisher + ?Sized,
+{
+    runtime()?.block_on(deploy(publisher, commits, on_progress))
+}
@@ -0,0 +1,301 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Create the bootstrap documents of schemas which define `bootstrap` values for their fields.
+//!
+//! Bootstrap documents are application data, their commits are kept in a companion lock file next
+//! to the lock file of the schemas. Every schema version gets its own bootstrap document, changed
+//! values update the document of the current version.
+use p2panda_rs::api::publish;
+use p2panda_rs::document::traits::AsDocument;
+use p2panda_rs::document::DocumentViewId;
+use p2panda_rs::entry::traits::AsEncodedEntry;
+use p2panda_rs::operation::decode::decode_operation;
+use p2panda_rs::operation::traits::{Actionable, Schematic};
+use p2panda_rs::operation::{OperationAction, OperationBuilder, OperationValue};
+use p2panda_rs::schema::system::SchemaView;
+use p2panda_rs::schema::{FieldName, Schema, SchemaId};
+use p2panda_rs::storage_provider::traits::DocumentStore;
+use p2panda_rs::test_utils::memory_store::MemoryStore;
+use serde::Serialize;
+use tracing::{debug, instrument, trace};
+
+use crate::error::{Error, Result};
+use crate::lock_file::{Annotation, Commit, LockFile};
+use crate::schema_file::{FieldType, SchemaField};
+use crate::signer::{sign_and_publish_with_schema, Signers};
+
+use super::current::CurrentSchema;
+use super::executor::Plan;
+use super::previous::assemble_schema;
+
+/// Publishes the commits of the bootstrap lock file on the given store.
+///
+/// The schemas of the lock file need to be published on the store already, bootstrap documents
+/// can follow any of their versions.
+pub(crate) async fn publish_bootstrap_commits(
+    store: &MemoryStore,
+    bootstrap_lock: &LockFile,
+) -> Result<()> {
+    if !bootstrap_lock.segments.is_empty() {
+        return Err(Error::UnresolvedLockSegments);
+    }
+
+    for commit in bootstrap_lock.commits.iter().flatten() {
+        publish_bootstrap_commit(store, commit).await?;
+    }
+
+    Ok(())
+}
+
+/// Validates a single commit of a bootstrap document and publishes it on the given store.
+#[instrument(skip_all, fields(entry_hash = %commit.entry_hash))]
+pub(crate) async fn publish_bootstrap_commit(store: &MemoryStore, commit: &Commit) -> Result<()> {
+    commit.verify()?;
+
+    let plain_operation = decode_operation(&commit.operation)?;
+    let schema = get_schema(store, plain_operation.schema_id()).await?;
+
+    publish(
+        store,
+        &schema,
+        &commit.entry,
+        &plain_operation,
+        &commit.operation,
+    )
+    .await
+    .map_err(|err| Error::InvalidCommit(Box::new(err)))?;
+    trace!("Validated bootstrap commit");
+
+    Ok(())
+}
+
+/// Creates or updates the bootstrap document of every current schema with `bootstrap` values.
+///
+/// Bootstrap documents are signed by the signer of their schema. Returns the signed commits and
+/// what happened to the bootstrap document of every schema.
+#[instrument(skip_all)]
+pub(crate) async fn execute_bootstrap(
+    store: &MemoryStore,
+    signers: &Signers<'_>,
+    current_schemas: &[CurrentSchema],
+    plans: &[Plan],
+) -> Result<(Vec<Commit>, Vec<BootstrapPlan>)> {
+    let mut commits = Vec::new();
+    let mut bootstraps = Vec::new();
+
+    for current_schema in current_schemas {
+        let Some(values) = bootstrap_values(current_schema)? else {
+            continue;
+        };
+
+        let schema_id = plans
+            .iter()
+            .map(|plan| plan.schema_id())
+            .find(|schema_id| schema_id.name() == current_schema.name)
+            .expect("Current schema needs to be planned");
+        let schema = get_schema(store, &schema_id).await?;
+
+        let documents = store
+            .get_documents_by_schema(&schema_id)
+            .await
+            .map_err(|err| Error::Storage(err.to_string()))?;
+        let document_view = documents.iter().find_map(|document| document.view());
+
+        let operation = match &document_view {
+            // Update the values which changed since the document of this version was created
+            Some(document_view) => {
+                let changed: Vec<(&str, OperationValue)> = values
+                    .iter()
+                    .filter(|(field_name, value)| {
+                        document_view
+                            .get(field_name)
+                            .is_none_or(|current| current.value() != value)
+                    })
+                    .map(|(field_name, value)| (field_name.as_str(), value.clone()))
+                    .collect();
+
+                if changed.is_empty() {
+                    None
+                } else {
+                    Some(
+                        OperationBuilder::new(&schema_id)
+                            .action(OperationAction::Update)
+                            .previous(document_view.id())
+                            .fields(&changed)
+                            .build()?,
+                    )
+                }
+            }
+
+            // This schema version does not have a bootstrap document yet
+            None => {
+                let fields: Vec<(&str, OperationValue)> = values
+                    .iter()
+                    .map(|(field_name, value)| (field_name.as_str(), value.clone()))
+                    .collect();
+
+                Some(
+                    OperationBuilder::new(&schema_id)
+                        .action(OperationAction::Create)
+                        .fields(&fields)
+                        .build()?,
+                )
+            }
+        };
+
+        let status = match (&operation, &document_view) {
+            (None, _) => BootstrapStatus::Unchanged,
+            (Some(_), Some(_)) => BootstrapStatus::Updated,
+            (Some(_), None) => BootstrapStatus::Created,
+        };
+
+        if let Some(operation) = operation {
+            let signer = signers.get(&current_schema.name);
+            let (encoded_entry, encoded_operation) =
+                sign_and_publish_with_schema(store, signer, &schema, &operation).await?;
+            commits.push(
+                Commit::new(&encoded_entry, &encoded_operation).with_annotation(Annotation::new(
+                    &current_schema.name,
+                    operation.action(),
+                    None,
+                )),
+            );
+            trace!(entry_hash = %encoded_entry.hash(), %schema_id, "Signed bootstrap commit");
+        }
+
+        debug!(%schema_id, ?status, "Planned bootstrap document");
+        bootstraps.push(BootstrapPlan { schema_id, status });
+    }
+
+    Ok((commits, bootstraps))
+}
+
+/// Returns the bootstrap values of all fields of a schema, `None` if no field has one.
+fn bootstrap_values(
+    current_schema: &CurrentSchema,
+) -> Result<Option<Vec<(FieldName, OperationValue)>>> {
+    let has_bootstrap = current_schema.fields.iter().any(|(_, field)| {
+        matches!(
+            field,
+            SchemaField::Field {
+                bootstrap: Some(_),
+                ..
+            }
+        )
+    });
+    if !has_bootstrap {
+        return Ok(None);
+    }
+
+    // Documents are created with all fields of their schema
+    current_schema
+        .fields
+        .iter()
+        .map(|(field_name, field)| match field {
+            SchemaField::Field {
+                field_type,
+                bootstrap: Some(value),
+                constraints,
+                ..
+            } => {
+                constraints.check(field_type, value).map_err(|reason| {
+                    Error::ConstraintViolation(
+                        current_schema.name.clone(),
+                        field_name.clone(),
+                        reason,
+                    )
+                })?;
+
+                let value = to_operation_value(field_type, value).ok_or_else(|| {
+                    Error::InvalidBootstrapValue(
+                        current_schema.name.clone(),
+                        field_name.clone(),
+                        field.to_string(),
+                    )
+                })?;
+                Ok((field_name.clone(), value))
+            }
+            _ => Err(Error::IncompleteBootstrap(
+                current_schema.name.clone(),
+                field_name.clone(),
+            )),
+        })
+        .collect::<Result<_>>()
+        .map(Some)
+}
+
+/// Converts a bootstrap value into an operation value of the given field type, `None` if the
+/// types do not match.
+fn to_operation_value(field_type: &FieldType, value: &toml::Value) -> Option<OperationValue> {
+    match (field_type, value) {
+        (FieldType::Boolean, toml::Value::Boolean(value)) => Some((*value).into()),
+        (FieldType::Integer, toml::Value::Integer(value)) => Some((*value).into()),
+        (FieldType::Float, toml::Value::Float(value)) => Some((*value).into()),
+        (FieldType::Float, toml::Value::Integer(value)) => Some((*value as f64).into()),
+        (FieldType::String, toml::Value::String(value)) => Some(value.as_str().into()),
+        (FieldType::Bytes, toml::Value::String(value)) => {
+            hex::decode(value).ok().map(OperationValue::Bytes)
+        }
+        _ => None,
+    }
+}
+
+/// Assembles the application schema with the given id from the definitions on the store.
+async fn get_schema(store: &MemoryStore, schema_id: &SchemaId) -> Result<Schema> {
+    let SchemaId::Application(_, view_id) = schema_id else {
+        return Err(Error::InvalidCommitSchemaId(schema_id.to_owned()));
+    };
+
+    let schema_view = find_schema_view(store, view_id)
+        .await?
+        .ok_or_else(|| Error::UnknownBootstrapSchema(schema_id.to_owned()))?;
+    let (schema, _) = assemble_schema(store, &schema_view).await?;
+
+    Ok(schema)
+}
+
+/// Loads the schema definition at the given version, which does not need to be the latest one.
+async fn find_schema_view(
+    store: &MemoryStore,
+    view_id: &DocumentViewId,
+) -> Result<Option<SchemaView>> {
+    let document = store
+        .get_document_by_view_id(view_id)
+        .await
+        .map_err(|err| Error::Storage(err.to_string()))?;
+
+    // Only schema definitions can be assembled into schemas
+    let document_view = match &document {
+        Some(document) if document.schema_id() == &SchemaId::SchemaDefinition(1) => document.view(),
+        _ => None,
+    };
+
+    document_view
+        .map(SchemaView::try_from)
+        .transpose()
+        .map_err(Error::from)
+}
+
+/// Bootstrap document of a schema version after the build.
+#[derive(Clone, Debug, Serialize)]
+pub struct BootstrapPlan {
+    /// Id of the schema the bootstrap document follows.
+    pub schema_id: SchemaId,
+
+    /// What happened to the bootstrap document of this schema version.
+    pub status: BootstrapStatus,
+}
+
+/// Changes to a bootstrap document.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BootstrapStatus {
+    /// Document got created for a new schema version.
+    Created,
+
+    /// Bootstrap values changed and got updated in the existing document.
+    Updated,
+
+    /// Document exists already with the same values.
+    Unchanged,
+}
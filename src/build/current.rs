@@ -0,0 +1,189 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use p2panda_rs::hash::Hash;
+use p2panda_rs::schema::{SchemaDescription, SchemaName};
+
+use crate::checksum::schema_checksum;
+use crate::error::{Error, Result};
+use crate::schema_file::{system_schema_id, RelationId, SchemaField, SchemaFields, SchemaFile};
+
+/// Extracts all schema definitions from user file and returns them as current schemas.
+///
+/// Schemas with `enabled = false` are skipped, other schemas can not relate to them.
+pub fn get_current_schemas(schema_file: &SchemaFile) -> Result<Vec<CurrentSchema>> {
+    if !schema_file.includes().is_empty() {
+        return Err(Error::UnresolvedIncludes);
+    }
+
+    if schema_file.has_externals() {
+        return Err(Error::UnresolvedExternals);
+    }
+
+    if schema_file.prefix().is_some() {
+        return Err(Error::UnappliedPrefix);
+    }
+
+    schema_file
+        .iter()
+        .filter(|(_, schema_definition)| schema_definition.enabled)
+        .map(|(schema_name, schema_definition)| {
+            if schema_definition.fields.is_empty() {
+                return Err(Error::EmptySchema(schema_name.clone()));
+            }
+
+            reject_inline_schemas(schema_name, &schema_definition.fields)?;
+
+            // Constraints are not part of the schema but should fit the field types
+            for (field_name, schema_field) in schema_definition.fields.iter() {
+                if let SchemaField::Field {
+                    field_type,
+                    constraints,
+                    ..
+                } = schema_field
+                {
+                    constraints.validate(field_type).map_err(|reason| {
+                        Error::InvalidConstraints(schema_name.clone(), field_name.clone(), reason)
+                    })?;
+                }
+            }
+
+            let mut fields = schema_definition.fields.clone();
+            resolve_system_relations(schema_file, schema_name, &mut fields)?;
+
+            Ok(CurrentSchema {
+                rename_from: schema_definition.rename_from.clone(),
+                ..CurrentSchema::new(schema_name, &schema_definition.description, &fields)
+            })
+        })
+        .collect()
+}
+
+/// Fails if a relation still defines its schema inline.
+///
+/// Parsing a schema file or inserting a definition expands inline schemas into regular schemas,
+/// but schema files deserialized directly keep them.
+pub(super) fn reject_inline_schemas(schema_name: &SchemaName, fields: &SchemaFields) -> Result<()> {
+    for (field_name, schema_field) in fields.iter() {
+        if let SchemaField::Relation { schema, .. } = schema_field {
+            if let RelationId::Inline(_) = schema.id {
+                return Err(Error::UnexpandedInlineSchema(
+                    schema_name.clone(),
+                    field_name.clone(),
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Points relations to system schemas like `blob_v1` at their schema ids, unless the schema file
+/// defines a schema with the same name.
+///
+/// Relations to other unknown names are kept, a single derived schema can relate to schemas which
+/// are resolved by the caller.
+fn resolve_system_relations(
+    schema_file: &SchemaFile,
+    schema_name: &SchemaName,
+    fields: &mut SchemaFields,
+) -> Result<()> {
+    for (field_name, schema_field) in fields.iter_mut() {
+        let SchemaField::Relation { schema, .. } = schema_field else {
+            continue;
+        };
+
+        let RelationId::Name(linked_schema) = &schema.id else {
+            continue;
+        };
+
+        if let Some((_, linked_definition)) =
+            schema_file.iter().find(|(name, _)| *name == linked_schema)
+        {
+            if !linked_definition.enabled {
+                return Err(Error::DisabledRelationSchema(
+                    schema_name.clone(),
+                    field_name.clone(),
+                    linked_schema.clone(),
+                ));
+            }
+
+            continue;
+        }
+
+        if let Some(schema_id) = system_schema_id(linked_schema) {
+            schema.id = RelationId::Id(schema_id);
+        }
+    }
+
+    Ok(())
+}
+
+/// Schema which was defined in the user's schema file.
+#[derive(Clone, Debug)]
+pub struct CurrentSchema {
+    pub name: SchemaName,
+    pub description: SchemaDescription,
+    pub fields: SchemaFields,
+
+    /// Previous name of the schema, its committed document gets updated instead of creating a new
+    /// one.
+    pub rename_from: Option<SchemaName>,
+}
+
+impl CurrentSchema {
+    pub fn new(name: &SchemaName, description: &SchemaDescription, fields: &SchemaFields) -> Self {
+        Self {
+            name: name.clone(),
+            description: description.clone(),
+            fields: fields.clone(),
+            rename_from: None,
+        }
+    }
+
+    /// Returns a content hash of the schema definition, see `schema_checksum`.
+    pub fn checksum(&self) -> Hash {
+        schema_checksum(&self.name, &self.description.to_string(), &self.fields)
+    }
+}
+
+/// Types which can be converted into a p2panda schema definition.
+///
+/// With the `macros` feature enabled this can be derived with `#[derive(ToP2pandaSchema)]`, which
+/// makes the application's data model the single source of truth for its schemas.
+pub trait ToP2pandaSchema {
+    /// Returns the schema definition of this type.
+    fn to_p2panda_schema() -> Result<CurrentSchema>;
+
+    /// Returns a content hash of the schema definition, to check with `verify_binding` if the
+    /// schema deployed on a node matches this type.
+    fn schema_checksum() -> Result<Hash> {
+        Ok(Self::to_p2panda_schema()?.checksum())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::schema_file::SchemaFile;
+
+    use super::get_current_schemas;
+
+    #[test]
+    fn rejects_unexpanded_inline_schemas() {
+        // Deserializing directly skips the expansion done when parsing a schema file
+        let schema_file: SchemaFile = toml::from_str(
+            r#"
+            [events]
+            description = "Upcoming events"
+            fields = { venue = { type = "relation", schema = { inline = { description = "Venue", fields = { name = { type = "str" } } } } } }
+            "#,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            get_current_schemas(&schema_file),
+            Err(Error::UnexpandedInlineSchema(schema_name, field_name))
+                if schema_name.to_string() == "events" && field_name == "venue"
+        ));
+    }
+}
@@ -0,0 +1,404 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::fmt::{self, Display};
+
+use p2panda_rs::schema::system::{SchemaFieldView, SchemaView};
+use p2panda_rs::schema::{FieldName, SchemaDescription, SchemaId, SchemaName};
+use tracing::{debug, instrument};
+
+use crate::error::{Error, Result};
+use crate::graph::sort_dependencies;
+use crate::schema_file::{FieldType, RelationId, RelationType, SchemaField};
+
+use super::current::{reject_inline_schemas, CurrentSchema};
+use super::previous::{PreviousSchema, PreviousSchemas};
+
+/// Gathers the differences between the current and the previous versions and organises them in
+/// nested, topological order as some changes depend on each other.
+#[instrument(skip_all)]
+pub async fn get_diff(
+    previous_schemas: PreviousSchemas,
+    current_schemas: Vec<CurrentSchema>,
+) -> Result<Vec<SchemaDiff>> {
+    for current_schema in &current_schemas {
+        reject_inline_schemas(&current_schema.name, &current_schema.fields)?;
+    }
+
+    // Create a linked dependency graph from all schemas and their relations to each other: Fields
+    // are direct dependencies of schemas, relation fields are dependend on their linked schemas.
+    //
+    // We can apply topological ordering to determine which schemas need to be materialized first
+    // before the others can relate to them.
+    let graph = current_schemas.iter().map(|current_schema| {
+        let dependencies: Vec<SchemaName> = current_schema
+            .fields
+            .iter()
+            .filter_map(|(_, schema_field)| match schema_field {
+                SchemaField::Relation { schema, .. } => match &schema.id {
+                    RelationId::Name(linked_schema) => Some(linked_schema.clone()),
+                    // Do nothing here, external schemas are not a direct dependency and inline
+                    // schemas were rejected above
+                    RelationId::Id(_) | RelationId::Inline(_) => None,
+                },
+                SchemaField::Field { .. } => None,
+            })
+            .collect();
+
+        (current_schema.name.clone(), dependencies)
+    });
+
+    let sorted_schemas = sort_dependencies(graph).map_err(|err| {
+        Error::CyclicDependency(RelationCycle::from_schemas(&err.cycle, &current_schemas))
+    })?;
+    debug!("Sorted schemas in topological order: {:?}", sorted_schemas);
+
+    // Based on this sorted list in topological order we can now extend it with information about
+    // what was previously given and what the current state is. This will help us to determine the
+    // concrete changes required to get to the current version
+    let mut schema_diffs: Vec<SchemaDiff> = Vec::new();
+
+    for current_schema_name in &sorted_schemas {
+        // Get the previous (if it exists) and current schema versions
+        let current_schema = current_schemas
+            .iter()
+            .find(|item| &item.name == current_schema_name)
+            // Since we sorted everything in topological order we can be sure that this exists
+            .expect("Current schema needs to be given in array");
+        let previous_schema = match previous_schemas.get(current_schema_name) {
+            Some(previous_schema) => Some(previous_schema),
+            None => match &current_schema.rename_from {
+                Some(rename_from) => Some(get_renamed_schema(
+                    current_schema,
+                    rename_from,
+                    &previous_schemas,
+                    &current_schemas,
+                )?),
+                None => None,
+            },
+        };
+
+        // Get the regarding current or previously existing fields and derive plans from it
+        let mut field_diffs: Vec<FieldDiff> = Vec::new();
+
+        for (current_field_name, current_field) in current_schema.fields.iter() {
+            // Get the current field version
+            let current_field_type = match current_field {
+                SchemaField::Field { field_type, .. } => FieldTypeDiff::Field(field_type.clone()),
+                SchemaField::Relation {
+                    field_type, schema, ..
+                } => match &schema.id {
+                    RelationId::Name(linked_schema_name) => {
+                        let schema_diff = schema_diffs
+                            .iter()
+                            .find(|plan| &plan.name == linked_schema_name)
+                            // Since we sorted everything in topological order we can be sure that
+                            // this exists when we look for it
+                            .expect("Current schema needs to be given in array");
+
+                        FieldTypeDiff::Relation(field_type.clone(), schema_diff.clone())
+                    }
+                    RelationId::Id(schema_id) => {
+                        FieldTypeDiff::ExternalRelation(field_type.clone(), schema_id.to_owned())
+                    }
+                    RelationId::Inline(_) => {
+                        return Err(Error::UnexpandedInlineSchema(
+                            current_schema.name.clone(),
+                            current_field_name.clone(),
+                        ));
+                    }
+                },
+            };
+
+            // Get the previous field version (if it existed)
+            let previous_field_view = get_previous_field(
+                previous_schema,
+                current_schema,
+                current_field_name,
+                current_field,
+            )?;
+
+            let field_diff = FieldDiff {
+                name: current_field_name.clone(),
+                previous_field_view,
+                current_field_type,
+            };
+
+            field_diffs.push(field_diff);
+        }
+
+        // Get the previous schema version (if it existed)
+        let previous_schema_view = previous_schema.map(|schema| schema.schema_view.clone());
+
+        let schema_diff = SchemaDiff {
+            name: current_schema_name.clone(),
+            previous_schema_view,
+            current_description: current_schema.description.clone(),
+            current_fields: field_diffs,
+        };
+
+        schema_diffs.push(schema_diff);
+    }
+
+    let result: Vec<SchemaDiff> = sorted_schemas
+        .iter()
+        .map(|group| {
+            schema_diffs
+                .iter()
+                .find(|diff| &diff.name == group)
+                .cloned()
+                .expect("Diff exists at this point")
+        })
+        .collect();
+
+    Ok(result)
+}
+
+/// Returns the committed version of a schema which got renamed.
+///
+/// The previous name can not be used by another current schema, otherwise both would continue
+/// the same document.
+fn get_renamed_schema<'a>(
+    current_schema: &CurrentSchema,
+    rename_from: &SchemaName,
+    previous_schemas: &'a PreviousSchemas,
+    current_schemas: &[CurrentSchema],
+) -> Result<&'a PreviousSchema> {
+    let conflict = current_schemas.iter().any(|item| {
+        item.name != current_schema.name
+            && (&item.name == rename_from || item.rename_from.as_ref() == Some(rename_from))
+    });
+    if conflict {
+        return Err(Error::RenameConflict(
+            current_schema.name.clone(),
+            rename_from.clone(),
+        ));
+    }
+
+    let previous_schema = previous_schemas.get(rename_from).ok_or_else(|| {
+        Error::UnknownRenameSource(current_schema.name.clone(), rename_from.clone())
+    })?;
+    debug!(
+        "Schema {} continues committed schema {}",
+        current_schema.name, rename_from
+    );
+
+    Ok(previous_schema)
+}
+
+/// Returns the committed version of a field, renamed fields continue the version of their
+/// previous name.
+///
+/// The previous name can not be used by another current field of the schema.
+fn get_previous_field(
+    previous_schema: Option<&PreviousSchema>,
+    current_schema: &CurrentSchema,
+    field_name: &FieldName,
+    field: &SchemaField,
+) -> Result<Option<SchemaFieldView>> {
+    let find_field = |name: &str| {
+        previous_schema.and_then(|schema| {
+            schema
+                .schema_field_views
+                .iter()
+                .find(|field_view| field_view.name() == name)
+                .cloned()
+        })
+    };
+
+    if let Some(field_view) = find_field(field_name) {
+        return Ok(Some(field_view));
+    }
+
+    let Some(rename_from) = field.rename_from() else {
+        return Ok(None);
+    };
+
+    let conflict = current_schema.fields.iter().any(|(name, other)| {
+        name != field_name && (name == rename_from || other.rename_from() == Some(rename_from))
+    });
+    if conflict {
+        return Err(Error::FieldRenameConflict(
+            current_schema.name.clone(),
+            field_name.clone(),
+            rename_from.clone(),
+        ));
+    }
+
+    let field_view = find_field(rename_from).ok_or_else(|| {
+        Error::UnknownFieldRenameSource(
+            current_schema.name.clone(),
+            field_name.clone(),
+            rename_from.clone(),
+        )
+    })?;
+
+    Ok(Some(field_view))
+}
+
+/// Relation fields of schemas which relate to each other in a cycle.
+///
+/// Schema ids are derived from the ids of the schemas they relate to, schemas in a cycle would
+/// need to know their own id before it exists.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RelationCycle {
+    /// Every schema of the cycle with its field relating to the next schema, the last schema
+    /// relates to the first one again.
+    pub relations: Vec<(SchemaName, FieldName)>,
+}
+
+impl RelationCycle {
+    /// Looks up the relation fields connecting the schemas of a cycle.
+    fn from_schemas(cycle: &[SchemaName], current_schemas: &[CurrentSchema]) -> Self {
+        let relations = cycle
+            .iter()
+            .enumerate()
+            .map(|(index, schema_name)| {
+                let next_schema_name = &cycle[(index + 1) % cycle.len()];
+                let current_schema = current_schemas
+                    .iter()
+                    .find(|item| &item.name == schema_name)
+                    .expect("Schemas of a cycle are defined");
+
+                let (field_name, _) = current_schema
+                    .fields
+                    .iter()
+                    .find(|(_, schema_field)| {
+                        matches!(
+                            schema_field,
+                            SchemaField::Relation { schema, .. }
+                                if schema.id == RelationId::Name(next_schema_name.clone())
+                        )
+                    })
+                    .expect("Schemas of a cycle relate to the next schema");
+
+                (schema_name.clone(), field_name.clone())
+            })
+            .collect();
+
+        Self { relations }
+    }
+
+    /// Returns a suggestion how to resolve the cycle.
+    pub fn hint(&self) -> &'static str {
+        if self.relations.len() == 1 {
+            "Schemas can not relate to themselves yet. Relate to the id of a committed version \
+            with `schema = { id = \"...\" }` instead"
+        } else {
+            "Schemas can not relate to each other in both directions yet. Remove one of the \
+            relation fields or relate to the id of a committed version with \
+            `schema = { id = \"...\" }` instead"
+        }
+    }
+}
+
+impl Display for RelationCycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (schema_name, field_name) in &self.relations {
+            write!(f, "{schema_name} (field {field_name}) -> ")?;
+        }
+
+        match self.relations.first() {
+            Some((schema_name, _)) => write!(f, "{schema_name}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Information about the previous and current version of a schema.
+///
+/// The contained field definition documents are direct dependencies of the schema definition
+/// document.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SchemaDiff {
+    /// Name of the schema.
+    pub name: SchemaName,
+
+    /// Previous version of this schema (if it existed).
+    pub previous_schema_view: Option<SchemaView>,
+
+    /// Current version of the schema description.
+    pub current_description: SchemaDescription,
+
+    /// Current version of the schema fields.
+    pub current_fields: Vec<FieldDiff>,
+}
+
+impl SchemaDiff {
+    /// Returns the id of the previous version of this schema (if it existed).
+    ///
+    /// Renamed schemas were committed with their previous name.
+    pub fn previous_schema_id(&self) -> Result<Option<SchemaId>> {
+        self.previous_schema_view
+            .as_ref()
+            .map(|view| {
+                let name = SchemaName::new(view.name())?;
+                Ok(SchemaId::new_application(&name, view.view_id()))
+            })
+            .transpose()
+    }
+}
+
+/// Information about the previous and current version of a field.
+///
+/// A field of relation type links to a schema which is a direct dependency.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FieldDiff {
+    /// Name of the schema field.
+    pub name: FieldName,
+
+    /// Previous version of this field (if it existed).
+    pub previous_field_view: Option<SchemaFieldView>,
+
+    /// Current version of the field type.
+    pub current_field_type: FieldTypeDiff,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FieldTypeDiff {
+    /// Basic schema field type.
+    Field(FieldType),
+
+    /// Relation field type linked to a schema.
+    Relation(RelationType, SchemaDiff),
+
+    /// Relation field type linked to an external schema which is not defined in this context.
+    ExternalRelation(RelationType, SchemaId),
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::build::current::get_current_schemas;
+    use crate::build::previous::PreviousSchemas;
+    use crate::error::Error;
+    use crate::schema_file::SchemaFile;
+
+    use super::get_diff;
+
+    #[tokio::test]
+    async fn names_relations_of_cycle() {
+        let schema_file: SchemaFile = r#"
+            [events]
+            description = "Things happening"
+            fields = { venue = { type = "relation", schema = { name = "venues" } } }
+
+            [venues]
+            description = "Places to meet"
+            fields = { next_event = { type = "relation", schema = { name = "events" } } }
+        "#
+        .parse()
+        .unwrap();
+
+        let current_schemas = get_current_schemas(&schema_file).unwrap();
+        let Err(Error::CyclicDependency(cycle)) =
+            get_diff(PreviousSchemas::new(), current_schemas).await
+        else {
+            panic!("Expected cyclic dependency");
+        };
+
+        assert_eq!(
+            cycle.to_string(),
+            "events (field venue) -> venues (field next_event) -> events"
+        );
+        assert!(cycle.hint().contains("both directions"));
+    }
+}
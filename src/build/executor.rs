@@ -1,38 +1,50 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-
 use p2panda_rs::document::DocumentViewId;
 use p2panda_rs::entry::traits::AsEncodedEntry;
 use p2panda_rs::hash::Hash;
-use p2panda_rs::identity::KeyPair;
-use p2panda_rs::operation::encode::encode_operation;
-use p2panda_rs::operation::traits::Schematic;
+use p2panda_rs::identity::PublicKey;
+use p2panda_rs::operation::traits::{Actionable, Schematic};
 use p2panda_rs::operation::{
     Operation, OperationAction, OperationBuilder, OperationValue, PinnedRelationList,
 };
-use p2panda_rs::schema::{FieldType as PandaFieldType, Schema, SchemaId};
-use p2panda_rs::test_utils::memory_store::helpers::send_to_store;
+use p2panda_rs::schema::{FieldName, FieldType as PandaFieldType, SchemaId, SchemaName};
 use p2panda_rs::test_utils::memory_store::MemoryStore;
+use tracing::{debug, instrument, trace};
 
-use crate::lock_file::Commit;
+use crate::error::Result;
+use crate::lock_file::{Annotation, Commit};
 use crate::schema_file::{FieldType, RelationType};
+use crate::signer::{sign_and_publish, Signer, Signers};
 
 use super::diff::{FieldDiff, FieldTypeDiff, SchemaDiff};
 
 /// Execute the changes required to get from the previous version to the current.
 ///
-/// Returns a list of signed commits and information about the steps which have been taken.
-pub async fn execute_plan(
+/// Every schema is signed by its own signer, together with its fields. Returns a list of signed
+/// commits and information about the steps which have been taken.
+#[instrument(skip_all)]
+pub(crate) async fn execute_plan(
+    store: MemoryStore,
+    signers: &Signers<'_>,
+    diffs: Vec<SchemaDiff>,
+) -> Result<(Vec<Commit>, Vec<Plan>)> {
+    execute_plan_with_abort(store, signers, diffs, &|| Ok(())).await
+}
+
+/// Like `execute_plan`, but calls `abort` before signing every commit and stops the execution
+/// when it returns an error.
+pub(crate) async fn execute_plan_with_abort(
     store: MemoryStore,
-    key_pair: KeyPair,
+    signers: &Signers<'_>,
     diffs: Vec<SchemaDiff>,
+    abort: &(dyn Fn() -> Result<()> + Sync),
 ) -> Result<(Vec<Commit>, Vec<Plan>)> {
-    let mut executor = Executor::new(store, key_pair);
+    let mut executor = Executor::new(store, signers, abort);
 
     for diff in diffs {
-        diff.execute(&mut executor).await?;
+        diff.execute(&mut executor, signers.get(&diff.name)).await?;
     }
 
     Ok((executor.commits, executor.plans))
@@ -42,57 +54,88 @@ pub async fn execute_plan(
 ///
 /// It iterates over the dependency graph in a depth-first order, calculates the required changes
 /// and generates operations out of them.
-#[derive(Debug)]
-pub struct Executor {
+pub(crate) struct Executor<'a> {
     store: MemoryStore,
-    key_pair: KeyPair,
+    signers: &'a Signers<'a>,
+    abort: &'a (dyn Fn() -> Result<()> + Sync),
     commits: Vec<Commit>,
     plans: Vec<Plan>,
+
+    /// Names of the schemas currently being executed, the last one owns new field commits.
+    schemas: Vec<SchemaName>,
 }
 
-impl Executor {
+impl<'a> Executor<'a> {
     /// Returns a new instance of `Executor`.
-    fn new(store: MemoryStore, key_pair: KeyPair) -> Self {
+    fn new(
+        store: MemoryStore,
+        signers: &'a Signers<'a>,
+        abort: &'a (dyn Fn() -> Result<()> + Sync),
+    ) -> Self {
         Self {
             store,
-            key_pair,
+            signers,
+            abort,
             commits: Vec::new(),
             plans: Vec::new(),
+            schemas: Vec::new(),
         }
     }
 
-    /// Signs and publishes an operation and keeps track of the resulting commit.
-    async fn commit(&mut self, operation: &Operation) -> Result<Hash> {
-        // Encode operation
-        let schema = Schema::get_system(operation.schema_id().to_owned())?;
-        let encoded_operation = encode_operation(operation)?;
+    /// Signs and publishes an operation with the given signer and keeps track of the resulting
+    /// commit, annotated with the schema currently being executed and the given field.
+    async fn commit(
+        &mut self,
+        operation: &Operation,
+        signer: &dyn Signer,
+        field: Option<&FieldName>,
+    ) -> Result<Hash> {
+        (self.abort)()?;
 
         // Publish operation on node which might already contain data from previously published
         // schemas
-        let (encoded_entry, _) = send_to_store(&self.store, operation, schema, &self.key_pair)
-            .await
-            .map_err(|err| anyhow!("Critical storage failure: {err}"))?;
-
-        self.commits
-            .push(Commit::new(&encoded_entry, &encoded_operation));
+        let (encoded_entry, encoded_operation) =
+            sign_and_publish(&self.store, signer, operation).await?;
+
+        let schema = self
+            .schemas
+            .last()
+            .expect("Commits are made while executing a schema");
+        self.commits.push(
+            Commit::new(&encoded_entry, &encoded_operation).with_annotation(Annotation::new(
+                schema,
+                operation.action(),
+                field,
+            )),
+        );
+        trace!(
+            entry_hash = %encoded_entry.hash(),
+            schema_id = %operation.schema_id(),
+            public_key = %signer.public_key(),
+            "Signed commit"
+        );
 
         Ok(encoded_entry.hash())
     }
 }
 
 #[async_trait]
-pub trait Executable {
-    /// Iterate over dependencies and commit required changes.
-    async fn execute(&self, executor: &mut Executor) -> Result<DocumentViewId>;
+pub(crate) trait Executable {
+    /// Iterate over dependencies and commit required changes, signed by the given signer.
+    async fn execute(
+        &self,
+        executor: &mut Executor<'_>,
+        signer: &dyn Signer,
+    ) -> Result<DocumentViewId>;
 }
 
-/// After execution we know all changes and all resulting schema ids.
+/// After execution we know all changes, all resulting schema ids and who signed them.
 #[derive(Clone, Debug)]
-pub struct Plan(SchemaId, SchemaDiff);
+pub struct Plan(SchemaId, SchemaDiff, PublicKey);
 
 impl Plan {
-    pub fn new(schema_id: SchemaId, diff: &SchemaDiff) -> Self {
-        Self(schema_id, diff.clone())
+    pub fn new(schema_id: SchemaId, diff: &SchemaDiff, public_key: PublicKey) -> Self {
+        Self(schema_id, diff.clone(), public_key)
     }
 
     pub fn schema_id(&self) -> SchemaId {
@@ -102,16 +145,27 @@ impl Plan {
     pub fn schema_diff(&self) -> SchemaDiff {
         self.1.clone()
     }
+
+    pub fn public_key(&self) -> PublicKey {
+        self.2
+    }
 }
 
 #[async_trait]
 impl Executable for SchemaDiff {
-    async fn execute(&self, executor: &mut Executor) -> Result<DocumentViewId> {
-        // Execute all fields first, they are direct dependencies of a schema
+    async fn execute(
+        &self,
+        executor: &mut Executor<'_>,
+        signer: &dyn Signer,
+    ) -> Result<DocumentViewId> {
+        executor.schemas.push(self.name.clone());
+
+        // Execute all fields first, they are direct dependencies of a schema and signed by the
+        // same author
         let mut field_view_ids: Vec<DocumentViewId> = Vec::new();
 
         for field in &self.current_fields {
-            let field_view_id = field.execute(executor).await?;
+            let field_view_id = field.execute(executor, signer).await?;
             field_view_ids.push(field_view_id);
         }
 
@@ -120,6 +174,11 @@ impl Executable for SchemaDiff {
             Some(previous_schema_view) => {
                 let mut fields: Vec<(&str, OperationValue)> = Vec::new();
 
+                // Schema got renamed, keep updating the document of the previous name
+                if self.name.to_string() != previous_schema_view.name() {
+                    fields.push(("name", self.name.to_string().into()));
+                }
+
                 if self.current_description.to_string() != previous_schema_view.description() {
                     fields.push(("description", self.current_description.to_string().into()));
                 }
@@ -144,7 +203,7 @@ impl Executable for SchemaDiff {
             }
 
             // We can not safely determine a previous version, either it never existed or its name
-            // changed. Let's create a new document!
+            // changed without `rename_from`. Let's create a new document!
             None => {
                 let operation = OperationBuilder::new(&SchemaId::SchemaDefinition(1))
                     .action(OperationAction::Create)
@@ -162,7 +221,7 @@ impl Executable for SchemaDiff {
         // Get the document view id of the created / updated document
         let view_id = match operation {
             Some(operation) => {
-                let entry_hash = executor.commit(&operation).await?;
+                let entry_hash = executor.commit(&operation, signer, None).await?;
                 entry_hash.into()
             }
             None => self
@@ -175,7 +234,11 @@ impl Executable for SchemaDiff {
 
         // Derive the schema id and add it to our list of plans together with the diff
         let schema_id = SchemaId::new_application(&self.name, &view_id);
-        executor.plans.push(Plan::new(schema_id, self));
+        debug!(%schema_id, "Planned schema");
+        executor
+            .plans
+            .push(Plan::new(schema_id, self, signer.public_key()));
+        executor.schemas.pop();
 
         Ok(view_id)
     }
@@ -183,7 +246,11 @@ impl Executable for SchemaDiff {
 
 #[async_trait]
 impl Executable for FieldDiff {
-    async fn execute(&self, executor: &mut Executor) -> Result<DocumentViewId> {
+    async fn execute(
+        &self,
+        executor: &mut Executor<'_>,
+        signer: &dyn Signer,
+    ) -> Result<DocumentViewId> {
         let current_field_type = match &self.current_field_type {
             // Convert all basic field types
             FieldTypeDiff::Field(FieldType::String) => PandaFieldType::String,
@@ -203,9 +270,10 @@ impl Executable for FieldDiff {
                 let schema_id = match schema {
                     // Schema was already materialized
                     Some(schema) => schema.schema_id(),
-                    // Materialize schema first
+                    // Materialize schema first, it might be signed by another author
                     None => {
-                        let view_id = schema_plan.execute(executor).await?;
+                        let schema_signer = executor.signers.get(&schema_plan.name);
+                        let view_id = schema_plan.execute(executor, schema_signer).await?;
                         SchemaId::new_application(&schema_plan.name, &view_id)
                     }
                 };
@@ -237,11 +305,22 @@ impl Executable for FieldDiff {
         let operation: Option<Operation> = match &self.previous_field_view {
             // A previous version of this field existed already
             Some(previous_field_view) => {
+                let mut fields: Vec<(&str, OperationValue)> = Vec::new();
+
+                // Field got renamed, keep updating the document of the previous name
+                if self.name != previous_field_view.name() {
+                    fields.push(("name", self.name.clone().into()));
+                }
+
                 if previous_field_view.field_type() != &current_field_type {
+                    fields.push(("type", current_field_type.into()));
+                }
+
+                if !fields.is_empty() {
                     let operation = OperationBuilder::new(&SchemaId::SchemaFieldDefinition(1))
                         .action(OperationAction::Update)
                         .previous(previous_field_view.id()) // view_id
-                        .fields(&[("type", current_field_type.into())])
+                        .fields(&fields)
                         .build()?;
 
                     Some(operation)
@@ -267,7 +346,9 @@ impl Executable for FieldDiff {
 
         match operation {
             Some(operation) => {
-                let entry_hash = executor.commit(&operation).await?;
+                let entry_hash = executor
+                    .commit(&operation, signer, Some(&self.name))
+                    .await?;
                 Ok(entry_hash.into())
             }
             None => Ok(self
@@ -0,0 +1,131 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Calculate the difference between the previous and current schema versions and generate the
+//! commits required to get from one to the other.
+mod bootstrap;
+mod current;
+mod diff;
+mod executor;
+mod previous;
+mod render;
+mod summary;
+
+use p2panda_rs::schema::SchemaName;
+use p2panda_rs::test_utils::memory_store::MemoryStore;
+use tracing::{debug, instrument};
+
+use crate::error::Result;
+use crate::lock_file::{Commit, LockFile};
+use crate::schema_file::SchemaFile;
+use crate::signer::{Signer, Signers};
+
+pub(crate) use bootstrap::{
+    execute_bootstrap, publish_bootstrap_commit, publish_bootstrap_commits,
+};
+pub(crate) use executor::{execute_plan, execute_plan_with_abort};
+pub(crate) use previous::{
+    assemble_previous_schemas, assemble_schema, materialize_previous_schemas, publish_commit,
+};
+pub(crate) use summary::field_compatibility;
+
+pub use bootstrap::{BootstrapPlan, BootstrapStatus};
+pub use current::{get_current_schemas, CurrentSchema, ToP2pandaSchema};
+pub use diff::{get_diff, FieldDiff, FieldTypeDiff, RelationCycle, SchemaDiff};
+pub use executor::Plan;
+pub use previous::{get_previous_schemas, PreviousSchema, PreviousSchemas};
+pub use render::{render_plan, render_plan_items, RenderedField, RenderedSchema};
+pub use summary::{summarize_plans, ChangeStatus, Compatibility, FieldSummary, PlanSummary};
+
+/// Calculates the changes between the committed and the current schema versions and signs the
+/// commits required to apply them.
+///
+/// Bootstrap documents of schemas with `bootstrap` values are created or updated afterwards, their
+/// commits are kept in the separate bootstrap lock file.
+///
+/// Everything happens in memory, no files are read or written. The returned commits can be added
+/// to the lock files or deployed directly.
+pub async fn build(
+    schema_file: &SchemaFile,
+    lock_file: &LockFile,
+    bootstrap_lock: &LockFile,
+    signer: &dyn Signer,
+) -> Result<BuildResult> {
+    build_with_signers(
+        schema_file,
+        lock_file,
+        bootstrap_lock,
+        &Signers::new(signer),
+    )
+    .await
+}
+
+/// Builds like `build`, signing every schema with the signer set for it.
+///
+/// Schemas owned by different authors can be part of one project this way, every author signs
+/// the commits of their schemas in their own logs.
+#[instrument(skip_all, fields(public_key = %signers.default_signer().public_key()))]
+pub async fn build_with_signers(
+    schema_file: &SchemaFile,
+    lock_file: &LockFile,
+    bootstrap_lock: &LockFile,
+    signers: &Signers<'_>,
+) -> Result<BuildResult> {
+    let store = MemoryStore::default();
+
+    let previous_schemas = materialize_previous_schemas(&store, lock_file).await?;
+    debug!(
+        "Materialized {} previous schemas from lock file",
+        previous_schemas.len()
+    );
+
+    // Bootstrap documents use logs of the same author, new schemas can only be signed when they
+    // are known
+    publish_bootstrap_commits(&store, bootstrap_lock).await?;
+
+    let current_schemas = get_current_schemas(schema_file)?;
+    let diff = get_diff(previous_schemas.clone(), current_schemas.clone()).await?;
+    let (commits, plans) = execute_plan(store.clone(), signers, diff).await?;
+    debug!("Created {} new commits", commits.len());
+
+    let (bootstrap_commits, bootstraps) =
+        execute_bootstrap(&store, signers, &current_schemas, &plans).await?;
+
+    Ok(BuildResult {
+        commits,
+        plans,
+        previous_schemas,
+        bootstrap_commits,
+        bootstraps,
+        skipped: schema_file.disabled_schemas(),
+    })
+}
+
+/// New commits and the executed plan after building.
+#[derive(Clone, Debug)]
+pub struct BuildResult {
+    /// Signed commits which are not part of the lock file yet.
+    pub commits: Vec<Commit>,
+
+    /// Changes and resulting schema id for every current schema, in topological order.
+    pub plans: Vec<Plan>,
+
+    /// Schemas which were already committed in the lock file.
+    pub previous_schemas: PreviousSchemas,
+
+    /// Signed commits of bootstrap documents which are not part of the bootstrap lock file yet.
+    pub bootstrap_commits: Vec<Commit>,
+
+    /// Bootstrap document of every current schema with `bootstrap` values.
+    pub bootstraps: Vec<BootstrapPlan>,
+
+    /// Schemas which are disabled in the schema file and were not built.
+    pub skipped: Vec<SchemaName>,
+}
+
+impl BuildResult {
+    /// Returns a serializable summary of all changes, for example to render the plan in a custom
+    /// interface or as JSON.
+    pub fn summary(&self) -> Result<Vec<PlanSummary>> {
+        summarize_plans(&self.plans, &self.previous_schemas)
+    }
+}
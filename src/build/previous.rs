@@ -0,0 +1,199 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::collections::BTreeMap;
+
+use p2panda_rs::api::publish;
+use p2panda_rs::document::traits::AsDocument;
+use p2panda_rs::identity::PublicKey;
+use p2panda_rs::operation::decode::decode_operation;
+use p2panda_rs::operation::traits::{Schematic, WithPublicKey};
+use p2panda_rs::schema::system::{SchemaFieldView, SchemaView};
+use p2panda_rs::schema::{Schema, SchemaId, SchemaName};
+use p2panda_rs::storage_provider::traits::{DocumentStore, OperationStore};
+use p2panda_rs::test_utils::memory_store::MemoryStore;
+use tracing::{instrument, trace};
+
+use crate::error::{Error, Result};
+use crate::lock_file::{Commit, LockFile};
+
+/// Reads previously committed operations from lock file, materializes schema documents from them
+/// and returns these schemas.
+pub async fn get_previous_schemas(lock_file: &LockFile) -> Result<PreviousSchemas> {
+    let store = MemoryStore::default();
+    materialize_previous_schemas(&store, lock_file).await
+}
+
+/// Publishes previously committed operations from lock file on the given store, materializes
+/// schema documents from them and returns these schemas.
+pub(crate) async fn materialize_previous_schemas(
+    store: &MemoryStore,
+    lock_file: &LockFile,
+) -> Result<PreviousSchemas> {
+    if !lock_file.segments.is_empty() {
+        return Err(Error::UnresolvedLockSegments);
+    }
+
+    // Sometimes `commits` is not defined in the .toml file, set an empty array as a fallback
+    let commits = lock_file.commits.clone().unwrap_or_default();
+
+    // Publish every commit in our temporary, in-memory "node" to materialize schema documents
+    for commit in commits {
+        publish_commit(store, &commit).await?;
+    }
+
+    assemble_previous_schemas(store).await
+}
+
+/// Validates a single commit and publishes it on the given store.
+#[instrument(skip_all, fields(entry_hash = %commit.entry_hash))]
+pub(crate) async fn publish_commit(store: &MemoryStore, commit: &Commit) -> Result<()> {
+    // Check entry hash, signature and payload integrity
+    commit.verify()?;
+
+    // Decode operation
+    let plain_operation = decode_operation(&commit.operation)?;
+
+    // Derive schema definitions from the operation's schema id. This fails if there's an invalid
+    // id or unknown system schema version.
+    let schema = match plain_operation.schema_id() {
+        SchemaId::SchemaDefinition(version) => {
+            Schema::get_system(SchemaId::SchemaDefinition(*version))?
+        }
+        SchemaId::SchemaFieldDefinition(version) => {
+            Schema::get_system(SchemaId::SchemaFieldDefinition(*version))?
+        }
+        schema_id => {
+            return Err(Error::InvalidCommitSchemaId(schema_id.to_owned()));
+        }
+    };
+
+    // Publish commits to a in-memory node where they get materialized to documents. This fully
+    // validates the given entries and operations.
+    publish(
+        store,
+        schema,
+        &commit.entry,
+        &plain_operation,
+        &commit.operation,
+    )
+    .await
+    .map_err(|err| Error::InvalidCommit(Box::new(err)))?;
+    trace!("Validated commit");
+
+    Ok(())
+}
+
+/// Loads all materialized schema documents from the given store and assembles schemas from them.
+pub(crate) async fn assemble_previous_schemas(store: &MemoryStore) -> Result<PreviousSchemas> {
+    // Load materialized documents from node and assemble them
+    let mut previous_schemas = PreviousSchemas::new();
+
+    let definitions = store
+        .get_documents_by_schema(&SchemaId::SchemaDefinition(1))
+        .await
+        .map_err(|err| Error::Storage(err.to_string()))?;
+
+    for definition in definitions {
+        let document_view = definition.view();
+
+        // Skip over deleted documents
+        if document_view.is_none() {
+            continue;
+        }
+
+        // Convert document view into more specialized schema view. Unwrap here, since we know the
+        // document was not deleted at this point.
+        let schema_view = SchemaView::try_from(document_view.unwrap())?;
+        let (schema, schema_field_views) = assemble_schema(store, &schema_view).await?;
+
+        // Attribute the schema to the author of its latest version
+        let latest = schema_view
+            .view_id()
+            .iter()
+            .next()
+            .expect("Document views have at least one operation");
+        let public_key = store
+            .get_operation(latest)
+            .await
+            .map_err(|err| Error::Storage(err.to_string()))?
+            .map(|operation| operation.public_key().to_owned());
+
+        // Add it to the resulting map
+        let previous_schema = PreviousSchema::new(&schema, &schema_view, &schema_field_views);
+        previous_schemas.insert(
+            schema.id().name(),
+            match public_key {
+                Some(public_key) => previous_schema.with_public_key(public_key),
+                None => previous_schema,
+            },
+        );
+    }
+
+    Ok(previous_schemas)
+}
+
+/// Loads the field definitions of a schema definition from the given store and assembles the
+/// schema from them.
+pub(crate) async fn assemble_schema(
+    store: &MemoryStore,
+    schema_view: &SchemaView,
+) -> Result<(Schema, Vec<SchemaFieldView>)> {
+    let mut schema_field_views: Vec<SchemaFieldView> = Vec::new();
+
+    for view_id in schema_view.fields().iter() {
+        let field_definition = store
+            .get_document_by_view_id(view_id)
+            .await
+            .map_err(|err| Error::Storage(err.to_string()))?
+            .ok_or_else(|| {
+                Error::MissingFieldDefinition(view_id.clone(), schema_view.view_id().clone())
+            })?;
+
+        // Convert document view into more specialized schema field view
+        let document_view = field_definition
+            .view()
+            .ok_or(Error::DeletedFieldDefinition)?;
+        schema_field_views.push(SchemaFieldView::try_from(document_view)?);
+    }
+
+    let schema = Schema::from_views(schema_view.clone(), schema_field_views.clone())
+        .map_err(|err| Error::InvalidSchema(schema_view.view_id().clone(), err))?;
+
+    Ok((schema, schema_field_views))
+}
+
+/// Materialized schemas the user already committed.
+#[derive(Clone, Debug)]
+pub struct PreviousSchema {
+    pub schema: Schema,
+    pub schema_view: SchemaView,
+    pub schema_field_views: Vec<SchemaFieldView>,
+
+    /// Public key of the key pair which signed the latest version, if known.
+    pub public_key: Option<PublicKey>,
+}
+
+impl PreviousSchema {
+    pub fn new(
+        schema: &Schema,
+        schema_view: &SchemaView,
+        schema_field_views: &[SchemaFieldView],
+    ) -> Self {
+        Self {
+            schema: schema.clone(),
+            schema_view: schema_view.clone(),
+            schema_field_views: schema_field_views.to_vec(),
+            public_key: None,
+        }
+    }
+
+    /// Attributes the schema to the author of its latest version.
+    pub fn with_public_key(mut self, public_key: PublicKey) -> Self {
+        self.public_key = Some(public_key);
+        self
+    }
+}
+
+/// Committed schemas by name, sorted by name so everything derived from them comes in the same
+/// order on every build.
+pub type PreviousSchemas = BTreeMap<SchemaName, PreviousSchema>;
@@ -0,0 +1,225 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::fmt::Write;
+
+use p2panda_rs::identity::PublicKey;
+use serde::Serialize;
+
+use super::summary::{ChangeStatus, PlanSummary};
+
+/// Renders the plan as plain text, for example to write it into a log or a pull request comment.
+///
+/// Changes are marked with `+` (added), `~` (changed) and `-` (removed). If `only_changes` is
+/// set, schemas without any changes are omitted. Schemas which are not signed by the given public
+/// key show their own one.
+pub fn render_plan(
+    summaries: &[PlanSummary],
+    public_key: &PublicKey,
+    only_changes: bool,
+) -> String {
+    let mut output = String::new();
+
+    if only_changes {
+        output.push_str("The following changes (+ add, ~ change, - remove) will be applied:\n\n");
+    }
+
+    for schema in render_plan_items(summaries, only_changes) {
+        write_schema(&mut output, &schema, public_key).expect("Writing into a string never fails");
+    }
+
+    output.push_str(&format!("Public key used for signing: {public_key}\n"));
+
+    output
+}
+
+/// Renders the plan into display-ready items, leaving styling and layout to the caller.
+///
+/// If `only_changes` is set, schemas without any changes are omitted.
+pub fn render_plan_items(summaries: &[PlanSummary], only_changes: bool) -> Vec<RenderedSchema> {
+    summaries
+        .iter()
+        .filter(|summary| !only_changes || summary.has_changes())
+        .map(|summary| {
+            let current_description = &summary.current_description;
+            let (description, description_status) = match &summary.previous_description {
+                Some(previous_description) if previous_description != current_description => (
+                    format!("\"{previous_description}\" -> \"{current_description}\""),
+                    summary.status,
+                ),
+                Some(_) => (
+                    format!("\"{current_description}\""),
+                    ChangeStatus::Unchanged,
+                ),
+                None => (format!("\"{current_description}\""), ChangeStatus::Added),
+            };
+
+            let fields = summary
+                .fields
+                .iter()
+                .enumerate()
+                .map(|(index, field)| {
+                    let field_type = match (&field.current, &field.previous) {
+                        (Some(current), Some(previous)) if current != previous => {
+                            format!("{previous} -> {current}")
+                        }
+                        (Some(current), _) => current.to_string(),
+                        (None, Some(previous)) => previous.to_string(),
+                        (None, None) => unreachable!(),
+                    };
+
+                    let name = match &field.previous_name {
+                        Some(previous_name) => format!("{previous_name} -> {}", field.name),
+                        None => field.name.to_owned(),
+                    };
+
+                    RenderedField {
+                        index: index + 1,
+                        name,
+                        field_type,
+                        status: field.status,
+                    }
+                })
+                .collect();
+
+            RenderedSchema {
+                status: summary.status,
+                schema_id: summary.current_schema_id.to_string(),
+                previous_schema_id: summary
+                    .previous_schema_id
+                    .as_ref()
+                    .filter(|schema_id| *schema_id != &summary.current_schema_id)
+                    .map(|schema_id| schema_id.to_string()),
+                breaking: summary.is_breaking(),
+                name: summary.name.to_string(),
+                public_key: summary.public_key.to_string(),
+                previous_public_key: summary
+                    .previous_public_key
+                    .filter(|public_key| public_key != &summary.public_key)
+                    .map(|public_key| public_key.to_string()),
+                description,
+                description_status,
+                fields,
+            }
+        })
+        .collect()
+}
+
+/// Display-ready representation of the changes to a schema.
+#[derive(Clone, Debug, Serialize)]
+pub struct RenderedSchema {
+    /// Status of the schema compared to its previous version.
+    pub status: ChangeStatus,
+
+    /// Id of the schema after the changes have been applied.
+    pub schema_id: String,
+
+    /// Id of the previous version, only given if it differs from the current one.
+    pub previous_schema_id: Option<String>,
+
+    /// True if the changes are breaking for existing applications.
+    pub breaking: bool,
+
+    /// Name of the schema.
+    pub name: String,
+
+    /// Public key of the key pair signing this schema.
+    pub public_key: String,
+
+    /// Public key of the author of the previous version, only given if it differs from the one
+    /// signing this schema.
+    pub previous_public_key: Option<String>,
+
+    /// Description, showing previous and current version if it changed.
+    pub description: String,
+
+    /// Status of the description compared to the previous version.
+    pub description_status: ChangeStatus,
+
+    /// Current fields followed by removed fields.
+    pub fields: Vec<RenderedField>,
+}
+
+/// Display-ready representation of the changes to a field.
+#[derive(Clone, Debug, Serialize)]
+pub struct RenderedField {
+    /// Position of the field, starting at 1.
+    pub index: usize,
+
+    /// Name of the field, showing previous and current name if it got renamed.
+    pub name: String,
+
+    /// Field type, showing previous and current type if it changed.
+    pub field_type: String,
+
+    /// Status of the field compared to the previous version.
+    pub status: ChangeStatus,
+}
+
+/// Returns the marker of a status in plain text output.
+fn marker(status: ChangeStatus) -> char {
+    match status {
+        ChangeStatus::Added => '+',
+        ChangeStatus::Changed(_) => '~',
+        ChangeStatus::Removed => '-',
+        ChangeStatus::Unchanged => ' ',
+    }
+}
+
+fn write_schema(
+    output: &mut String,
+    schema: &RenderedSchema,
+    public_key: &PublicKey,
+) -> std::fmt::Result {
+    writeln!(output, "{} {}", marker(schema.status), schema.schema_id)?;
+
+    if let Some(previous_schema_id) = &schema.previous_schema_id {
+        writeln!(output, "  Previously: {previous_schema_id}")?;
+    }
+
+    if schema.breaking {
+        writeln!(
+            output,
+            "  Breaking change: fields were removed, renamed or changed their type"
+        )?;
+    }
+
+    writeln!(output)?;
+    writeln!(output, "  Name: {}", schema.name)?;
+    writeln!(output, "  Description: {}", schema.description)?;
+    if schema.public_key != public_key.to_string() {
+        writeln!(output, "  Signed by: {}", schema.public_key)?;
+    }
+    if let Some(previous_public_key) = &schema.previous_public_key {
+        writeln!(output, "  Previously signed by: {previous_public_key}")?;
+    }
+    writeln!(output)?;
+
+    // Align columns to the longest value
+    let index_width = schema.fields.len().to_string().len().max(1);
+    let name_width = schema
+        .fields
+        .iter()
+        .map(|field| field.name.len())
+        .chain(Some("Field Name".len()))
+        .max()
+        .unwrap_or_default();
+
+    writeln!(
+        output,
+        "  {:>index_width$} | {:<name_width$} | Field Type",
+        "#", "Field Name"
+    )?;
+
+    for field in &schema.fields {
+        writeln!(
+            output,
+            "{} {:>index_width$} | {:<name_width$} | {}",
+            marker(field.status),
+            field.index,
+            field.name,
+            field.field_type
+        )?;
+    }
+
+    writeln!(output)
+}
@@ -0,0 +1,305 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use p2panda_rs::identity::PublicKey;
+use p2panda_rs::schema::{FieldName, SchemaDescription, SchemaId, SchemaName};
+use serde::Serialize;
+
+use crate::error::Result;
+use crate::schema_file::{
+    Constraints, Metadata, RelationId, RelationSchema, RelationType, SchemaField,
+};
+
+use super::diff::FieldTypeDiff;
+use super::executor::Plan;
+use super::previous::PreviousSchemas;
+
+/// Summarizes the changes of all executed plans into serializable structures.
+pub fn summarize_plans(
+    plans: &[Plan],
+    previous_schemas: &PreviousSchemas,
+) -> Result<Vec<PlanSummary>> {
+    plans
+        .iter()
+        .map(|plan| summarize_plan(plan, plans, previous_schemas))
+        .collect()
+}
+
+fn summarize_plan(
+    plan: &Plan,
+    plans: &[Plan],
+    previous_schemas: &PreviousSchemas,
+) -> Result<PlanSummary> {
+    let schema_diff = plan.schema_diff();
+
+    // Schema id and description
+    let current_schema_id = plan.schema_id();
+    let previous_schema_id = schema_diff.previous_schema_id()?;
+    let previous_description = schema_diff
+        .previous_schema_view
+        .as_ref()
+        .map(|view| SchemaDescription::new(view.description()))
+        .transpose()?;
+    let previous_public_key = schema_diff.previous_schema_view.as_ref().and_then(|view| {
+        previous_schemas
+            .values()
+            .find(|item| item.schema_view.view_id() == view.view_id())
+            .and_then(|item| item.public_key)
+    });
+
+    // Current fields, relations to schemas of this project are expressed via their schema id to
+    // make them comparable with the previous fields
+    let mut fields: Vec<FieldSummary> = schema_diff
+        .current_fields
+        .into_iter()
+        .map(|field| {
+            let current = match field.current_field_type {
+                FieldTypeDiff::Field(field_type) => SchemaField::Field {
+                    field_type,
+                    rename_from: None,
+                    bootstrap: None,
+                    constraints: Constraints::default(),
+                    metadata: Metadata::default(),
+                },
+                FieldTypeDiff::Relation(field_type, schema_diff) => {
+                    let schema_id = plans
+                        .iter()
+                        .find(|plan| plan.schema_id().name() == schema_diff.name)
+                        .map(|plan| plan.schema_id())
+                        .expect("Schema should be known");
+
+                    relation_field(field_type, schema_id)
+                }
+                FieldTypeDiff::ExternalRelation(field_type, schema_id) => {
+                    relation_field(field_type, schema_id)
+                }
+            };
+
+            // Renamed fields were committed with their previous name
+            let previous_name = field
+                .previous_field_view
+                .as_ref()
+                .map(|field_view| field_view.name().to_owned())
+                .filter(|previous_name| previous_name != &field.name);
+
+            FieldSummary {
+                name: field.name,
+                previous_name,
+                status: ChangeStatus::Added,
+                previous: None,
+                current: Some(current),
+            }
+        })
+        .collect();
+
+    // Compare with fields of the previous version, fields which do not exist anymore get added
+    // after the current ones
+    if let Some(previous_view) = &schema_diff.previous_schema_view {
+        let previous_schema = previous_schemas
+            .values()
+            .find(|item| item.schema_view.view_id() == previous_view.view_id())
+            .expect("Needs to exist at this point");
+
+        for (field_name, field_type) in previous_schema.schema.fields().iter() {
+            let previous = SchemaField::from(field_type);
+
+            match fields
+                .iter_mut()
+                .find(|field| field.previous_name.as_ref().unwrap_or(&field.name) == field_name)
+            {
+                Some(field) => {
+                    field.status = match &field.current {
+                        // Applications need to query renamed fields by their new name
+                        Some(_) if field.previous_name.is_some() => {
+                            ChangeStatus::Changed(Compatibility::Breaking)
+                        }
+                        Some(current) if current == &previous => ChangeStatus::Unchanged,
+                        Some(current) => {
+                            ChangeStatus::Changed(field_compatibility(&previous, current))
+                        }
+                        None => unreachable!("Current fields always have a type"),
+                    };
+                    field.previous = Some(previous);
+                }
+                None => fields.push(FieldSummary {
+                    name: field_name.clone(),
+                    previous_name: None,
+                    status: ChangeStatus::Removed,
+                    previous: Some(previous),
+                    current: None,
+                }),
+            }
+        }
+    }
+
+    // Schemas are only breaking if one of their fields is, additions and description changes
+    // are compatible
+    let status = match &previous_schema_id {
+        Some(schema_id) if schema_id == &current_schema_id => ChangeStatus::Unchanged,
+        Some(_) => {
+            let breaking = fields.iter().any(|field| {
+                matches!(
+                    field.status,
+                    ChangeStatus::Removed | ChangeStatus::Changed(Compatibility::Breaking)
+                )
+            });
+
+            ChangeStatus::Changed(if breaking {
+                Compatibility::Breaking
+            } else {
+                Compatibility::Compatible
+            })
+        }
+        None => ChangeStatus::Added,
+    };
+
+    Ok(PlanSummary {
+        name: schema_diff.name,
+        public_key: plan.public_key(),
+        previous_public_key,
+        status,
+        previous_schema_id,
+        current_schema_id,
+        previous_description,
+        current_description: schema_diff.current_description,
+        fields,
+    })
+}
+
+/// Changing the type of a field is breaking, pointing a relation at another version of the same
+/// schema is compatible.
+pub(crate) fn field_compatibility(previous: &SchemaField, current: &SchemaField) -> Compatibility {
+    match (previous, current) {
+        (
+            SchemaField::Relation {
+                field_type: previous_type,
+                schema: previous_schema,
+                ..
+            },
+            SchemaField::Relation {
+                field_type: current_type,
+                schema: current_schema,
+                ..
+            },
+        ) if previous_type == current_type
+            && relation_name(previous_schema).is_some()
+            && relation_name(previous_schema) == relation_name(current_schema) =>
+        {
+            Compatibility::Compatible
+        }
+        _ => Compatibility::Breaking,
+    }
+}
+
+/// Returns the name of the schema a relation points at.
+fn relation_name(schema: &RelationSchema) -> Option<SchemaName> {
+    match &schema.id {
+        RelationId::Id(schema_id) => Some(schema_id.name()),
+        RelationId::Name(name) => Some(name.clone()),
+        RelationId::Inline(_) => None,
+    }
+}
+
+fn relation_field(field_type: RelationType, schema_id: SchemaId) -> SchemaField {
+    SchemaField::Relation {
+        field_type,
+        schema: RelationSchema {
+            id: RelationId::Id(schema_id),
+            external: None,
+            rev: None,
+        },
+        rename_from: None,
+        metadata: Metadata::default(),
+    }
+}
+
+/// Serializable summary of the changes applied to a schema.
+#[derive(Clone, Debug, Serialize)]
+pub struct PlanSummary {
+    /// Name of the schema.
+    pub name: SchemaName,
+
+    /// Public key of the key pair signing this schema.
+    pub public_key: PublicKey,
+
+    /// Public key of the key pair which signed the previous version of this schema (if it
+    /// existed).
+    pub previous_public_key: Option<PublicKey>,
+
+    /// Status of the schema compared to its previous version.
+    pub status: ChangeStatus,
+
+    /// Id of the previous version of this schema (if it existed).
+    pub previous_schema_id: Option<SchemaId>,
+
+    /// Id of this schema after the changes have been applied.
+    pub current_schema_id: SchemaId,
+
+    /// Description of the previous version of this schema (if it existed).
+    pub previous_description: Option<SchemaDescription>,
+
+    /// Current version of the schema description.
+    pub current_description: SchemaDescription,
+
+    /// Current fields followed by removed fields of the previous version.
+    pub fields: Vec<FieldSummary>,
+}
+
+impl PlanSummary {
+    /// Returns true if this schema is new or has changed.
+    pub fn has_changes(&self) -> bool {
+        self.status != ChangeStatus::Unchanged
+    }
+
+    /// Returns true if this schema has changes which are breaking for existing applications.
+    pub fn is_breaking(&self) -> bool {
+        self.status == ChangeStatus::Changed(Compatibility::Breaking)
+    }
+}
+
+/// Serializable summary of the changes applied to a schema field.
+#[derive(Clone, Debug, Serialize)]
+pub struct FieldSummary {
+    /// Name of the field.
+    pub name: FieldName,
+
+    /// Name of the field in the previous schema version, if it got renamed.
+    pub previous_name: Option<FieldName>,
+
+    /// Status of the field compared to the previous schema version.
+    pub status: ChangeStatus,
+
+    /// Field type in the previous schema version (if it existed).
+    pub previous: Option<SchemaField>,
+
+    /// Field type in the current schema version (if it was not removed).
+    pub current: Option<SchemaField>,
+}
+
+/// Status of a schema or field compared to its previous version.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeStatus {
+    /// Did not exist in the previous version.
+    Added,
+
+    /// Existed in the previous version but got changed.
+    Changed(Compatibility),
+
+    /// Existed in the previous version but got removed.
+    Removed,
+
+    /// Existed in the previous version without any changes.
+    Unchanged,
+}
+
+/// Whether a change keeps a schema or field compatible with its previous version.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Compatibility {
+    /// Added fields, changed descriptions or relations pointing at another version of the same
+    /// schema.
+    Compatible,
+
+    /// Removed or renamed fields, changed field types or relations pointing at another schema.
+    Breaking,
+}
@@ -0,0 +1,268 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Record and replay GraphQL requests to a node for deterministic tests.
+//!
+//! In `Record` mode every request the `Client` sends is stored together with the node's response
+//! in a JSON "cassette" file. In `Replay` mode no requests are sent, instead the recorded responses
+//! are returned in the same order, which allows testing deploy logic against captured real-node
+//! behaviour without running a node.
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+use crate::transport::TransportError;
+use crate::utils::files;
+
+/// Whether a cassette captures or plays back requests.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CassetteMode {
+    /// Send requests to the node and store every response.
+    Record,
+
+    /// Do not send any requests, return the stored responses instead.
+    Replay,
+}
+
+/// Recorded GraphQL requests and responses, stored in a JSON file.
+#[derive(Debug)]
+pub struct Cassette {
+    path: PathBuf,
+    mode: CassetteMode,
+    state: Mutex<CassetteState>,
+}
+
+#[derive(Debug, Default)]
+struct CassetteState {
+    interactions: Vec<Interaction>,
+    cursor: usize,
+}
+
+impl Cassette {
+    /// Returns a cassette recording all requests into a new file at the given path.
+    pub fn record(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            mode: CassetteMode::Record,
+            state: Mutex::new(CassetteState::default()),
+        }
+    }
+
+    /// Loads a previously recorded cassette from the given path to replay it.
+    pub fn replay(path: impl AsRef<Path>) -> Result<Self> {
+        let data = files::read_file(&path)?;
+        let interactions: Vec<Interaction> =
+            serde_json::from_str(&data).map_err(Error::InvalidCassette)?;
+
+        Ok(Self {
+            path: path.as_ref().to_path_buf(),
+            mode: CassetteMode::Replay,
+            state: Mutex::new(CassetteState {
+                interactions,
+                cursor: 0,
+            }),
+        })
+    }
+
+    /// Returns the mode of this cassette.
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    /// Returns the recorded response for a query or sends it and records the response, depending
+    /// on the mode.
+    pub(crate) async fn query<F, Fut>(&self, query: &str, send: F) -> Result<Value, TransportError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Value, TransportError>>,
+    {
+        let response = match self.mode {
+            CassetteMode::Replay => self.next_response(query)?,
+            CassetteMode::Record => {
+                let response = match send().await {
+                    Ok(data) => Response::Data(data),
+                    Err(err) => Response::from_error(&err),
+                };
+
+                self.push(query, &response)?;
+                response
+            }
+        };
+
+        match response {
+            Response::Data(data) => Ok(data),
+            Response::RequestError(message) => Err(TransportError::Request(message)),
+            Response::GraphQLError(messages) => Err(TransportError::GraphQl(messages)),
+        }
+    }
+
+    /// Returns the next recorded response, failing if the query does not match the recording.
+    fn next_response(&self, query: &str) -> Result<Response, TransportError> {
+        let mut state = self.state.lock().expect("Lock cassette state");
+
+        let interaction = state
+            .interactions
+            .get(state.cursor)
+            .cloned()
+            .ok_or_else(|| {
+                TransportError::Request("No more recorded responses in cassette".into())
+            })?;
+
+        if interaction.query != normalize_query(query) {
+            return Err(TransportError::Request(format!(
+                "Query does not match recorded query #{} in cassette",
+                state.cursor
+            )));
+        }
+
+        state.cursor += 1;
+
+        Ok(interaction.response)
+    }
+
+    /// Records an interaction and writes the whole cassette to its file.
+    fn push(&self, query: &str, response: &Response) -> Result<(), TransportError> {
+        let mut state = self.state.lock().expect("Lock cassette state");
+
+        state.interactions.push(Interaction {
+            query: normalize_query(query),
+            response: response.clone(),
+        });
+
+        let data = serde_json::to_string_pretty(&state.interactions)
+            .map_err(|err| TransportError::Request(err.to_string()))?;
+        files::write_file(&self.path, &data)
+            .map_err(|err| TransportError::Request(format!("Could not write cassette: {err}")))?;
+
+        Ok(())
+    }
+}
+
+/// Single recorded request and the response of the node.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Interaction {
+    query: String,
+    response: Response,
+}
+
+/// Recorded response of the node.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Response {
+    /// Node responded with data.
+    Data(Value),
+
+    /// Node responded with GraphQL errors.
+    #[serde(rename = "graphql_error")]
+    GraphQLError(Vec<String>),
+
+    /// Request failed, for example because the node could not be reached.
+    RequestError(String),
+}
+
+impl Response {
+    fn from_error(err: &TransportError) -> Self {
+        match err {
+            TransportError::GraphQl(messages) => Self::GraphQLError(messages.clone()),
+            TransportError::Request(message) => Self::RequestError(message.clone()),
+        }
+    }
+}
+
+/// Removes indentation and line breaks so formatting changes do not invalidate recordings.
+fn normalize_query(query: &str) -> String {
+    query.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::client::Client;
+    use crate::deploy::{deploy, DeployReport, PublishOutcome, Rejection, SkipReason};
+    use crate::error::Error;
+    use crate::lock_file::Commit;
+    use crate::test_utils::{build_commits, key_pair};
+
+    use super::Cassette;
+
+    /// Responses to deploying the commits of `SCHEMA_FILE`, the node knew the first commit
+    /// already, accepted the second and rejected the third.
+    const CASSETTE: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/deploy.cassette.json"
+    );
+
+    const SCHEMA_FILE: &str = r#"
+        [venues]
+        description = "Places to meet"
+        fields = { name = { type = "str" }, city = { type = "str" } }
+
+        [events]
+        description = "Things happening"
+        fields = { title = { type = "str" } }
+    "#;
+
+    fn client() -> Client {
+        Client::builder("http://localhost:2020/graphql")
+            .cassette(Cassette::replay(CASSETTE).unwrap())
+            .build()
+    }
+
+    async fn commits() -> Vec<Commit> {
+        build_commits(SCHEMA_FILE, &[], &key_pair(1)).await
+    }
+
+    #[tokio::test]
+    async fn replays_deploy_outcomes() {
+        let commits = commits().await;
+        assert_eq!(commits.len(), 5);
+
+        let outcomes: Vec<PublishOutcome> = deploy(&client(), &commits, |_, _| ())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(_, outcome)| outcome)
+            .collect();
+
+        assert_eq!(
+            outcomes,
+            vec![
+                PublishOutcome::AlreadyKnown,
+                PublishOutcome::Published,
+                PublishOutcome::Rejected {
+                    node_error:
+                        "Entry's claimed log id of 2 is already in use for given public key".into()
+                },
+                PublishOutcome::Skipped {
+                    reason: SkipReason::PreviousRejected
+                },
+                PublishOutcome::Skipped {
+                    reason: SkipReason::PreviousRejected
+                },
+            ]
+        );
+        assert_eq!(outcomes[2].rejection(), Some(Rejection::LogId));
+
+        let report = DeployReport::from_outcomes(
+            &commits
+                .into_iter()
+                .zip(outcomes)
+                .collect::<Vec<(Commit, PublishOutcome)>>(),
+        );
+        assert_eq!(report.published, 1);
+        assert_eq!(report.skipped, 1);
+        assert!(!report.cancelled);
+    }
+
+    #[tokio::test]
+    async fn fails_on_unrecorded_query() {
+        // Commits in a different order send queries the cassette does not know
+        let mut commits = commits().await;
+        commits.reverse();
+
+        let result = deploy(&client(), &commits, |_, _| ()).await;
+        assert!(matches!(result, Err(Error::Request(message)) if message.contains("#0")));
+    }
+}
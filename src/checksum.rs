@@ -0,0 +1,52 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Content hashes of schema definitions.
+//!
+//! Applications can store the checksum of the schemas they were compiled against, for example via
+//! `ToP2pandaSchema::schema_checksum`, and assert at startup with `verify_binding` that the schema
+//! deployed on a node still matches.
+use p2panda_rs::hash::Hash;
+#[cfg(feature = "client")]
+use p2panda_rs::schema::SchemaId;
+use p2panda_rs::schema::SchemaName;
+
+#[cfg(feature = "client")]
+use crate::client::Client;
+#[cfg(feature = "client")]
+use crate::error::{Error, Result};
+use crate::schema_file::SchemaFields;
+
+/// Returns a content hash of a schema definition, covering its name, description and fields.
+///
+/// Relations are identified by the name of the schema they point at, so schemas defined in a
+/// schema file and schemas materialized on a node result in the same checksum.
+pub fn schema_checksum(name: &SchemaName, description: &str, fields: &SchemaFields) -> Hash {
+    let mut content = format!("{name}\n{description}\n");
+    for (field_name, field) in fields.iter() {
+        content.push_str(&format!("{field_name}: {field}\n"));
+    }
+
+    Hash::new_from_bytes(content.as_bytes())
+}
+
+/// Checks if the schema with the given id on the node matches the expected checksum.
+///
+/// Returns an error if the node does not know the schema or its definition differs.
+#[cfg(feature = "client")]
+pub async fn verify_binding(schema_id: &SchemaId, checksum: &Hash, client: &Client) -> Result<()> {
+    let schema = client
+        .schema_by_id(schema_id)
+        .await?
+        .ok_or_else(|| Error::SchemaNotFound(schema_id.clone()))?;
+
+    let actual = schema.checksum();
+    if &actual != checksum {
+        return Err(Error::ChecksumMismatch(
+            schema_id.clone(),
+            checksum.clone(),
+            actual,
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,820 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use p2panda_rs::document::{DocumentId, DocumentViewId};
+use p2panda_rs::entry::decode::decode_entry;
+use p2panda_rs::entry::traits::AsEntry;
+use p2panda_rs::entry::{LogId, SeqNum};
+use p2panda_rs::hash::Hash;
+use p2panda_rs::identity::PublicKey;
+use p2panda_rs::schema::{FieldType as PandaFieldType, SchemaId, SchemaName};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use tracing::{debug, instrument, warn};
+
+#[cfg(feature = "cassette")]
+use crate::cassette::Cassette;
+use crate::checksum::schema_checksum;
+use crate::deploy::{PublishOutcome, Publisher};
+use crate::error::{Error, Result};
+use crate::lock_file::Commit;
+use crate::schema_file::{SchemaField, SchemaFields};
+use crate::transport::{GraphQlTransport, HttpTransport, TransportError};
+
+/// Default timeout for requests to the node.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Time to wait before retrying a failed request.
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// GraphQL client to publish commits on a p2panda node.
+#[derive(Clone, Debug)]
+pub struct Client {
+    transport: Arc<dyn GraphQlTransport>,
+    retries: usize,
+    #[cfg(feature = "cassette")]
+    cassette: Option<Arc<Cassette>>,
+}
+
+impl Client {
+    /// Returns a new instance of `Client` connecting to the given GraphQL endpoint with default
+    /// settings.
+    pub fn new(endpoint: &str) -> Self {
+        Self::builder(endpoint).build()
+    }
+
+    /// Returns a builder to configure the HTTP behaviour of the client.
+    pub fn builder(endpoint: &str) -> ClientBuilder {
+        ClientBuilder::new(endpoint)
+    }
+
+    /// Sends a GraphQL query to the node, retrying it if the request failed.
+    ///
+    /// Only use this for queries which do not change state on the node, see `mutate`.
+    async fn query<T: DeserializeOwned>(&self, query: &str) -> Result<T, TransportError> {
+        self.request(query, self.retries).await
+    }
+
+    /// Sends a GraphQL mutation to the node exactly once.
+    ///
+    /// A mutation which failed with a timeout might still have been applied by the node, so
+    /// callers need to check the state on the node before sending it again.
+    async fn mutate<T: DeserializeOwned>(&self, query: &str) -> Result<T, TransportError> {
+        self.request(query, 0).await
+    }
+
+    /// Sends a GraphQL query to the node.
+    ///
+    /// If a cassette is set, the request gets recorded or replayed from it.
+    async fn request<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        retries: usize,
+    ) -> Result<T, TransportError> {
+        #[cfg(feature = "cassette")]
+        let data = match &self.cassette {
+            Some(cassette) => cassette.query(query, || self.send(query, retries)).await?,
+            None => self.send(query, retries).await?,
+        };
+
+        #[cfg(not(feature = "cassette"))]
+        let data = self.send(query, retries).await?;
+
+        serde_json::from_value(data)
+            .map_err(|err| TransportError::Request(format!("Invalid response from node: {err}")))
+    }
+
+    /// Sends a GraphQL query via the transport.
+    ///
+    /// Requests which failed for other reasons than a GraphQL error returned by the node (for
+    /// example a timeout) get retried up to the given number of times.
+    async fn send(&self, query: &str, retries: usize) -> Result<serde_json::Value, TransportError> {
+        let mut attempts = 0;
+
+        loop {
+            match self.transport.query(query).await {
+                Err(TransportError::Request(err)) if attempts < retries => {
+                    attempts += 1;
+                    warn!(
+                        attempt = attempts,
+                        "Request to node failed, retrying: {}", err
+                    );
+                    tokio::time::sleep(RETRY_DELAY).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Asks the node for the arguments required to publish the next entry in the log of the given
+    /// document view.
+    ///
+    /// Returns `None` if the node does not know about this document yet.
+    pub async fn next_args(
+        &self,
+        public_key: &PublicKey,
+        view_id: &Hash,
+    ) -> Result<Option<NextArguments>> {
+        let query = format!(
+            r#"
+            {{
+                nextArgs(publicKey: "{}", viewId: "{}") {{
+                    logId
+                    seqNum
+                    skiplink
+                    backlink
+                }}
+            }}
+            "#,
+            public_key, view_id,
+        );
+
+        let response = self.query::<NextArgsResponse>(&query).await;
+
+        Ok(response.ok().map(|result| result.next_args))
+    }
+
+    /// Returns all schemas with the given name which are known to the node.
+    ///
+    /// Only the first 100 schemas and fields are taken into account.
+    pub async fn schemas_by_name(&self, name: &SchemaName) -> Result<Vec<RemoteSchema>> {
+        let query = format!(
+            r#"
+            {{
+                all_schema_definition_v1(first: 100, filter: {{ name: {{ eq: "{}" }} }}) {{
+                    documents {{
+                        meta {{
+                            viewId
+                            owner
+                        }}
+                        fields {{
+                            name
+                            description
+                            fields(first: 100) {{
+                                documents {{
+                                    fields {{
+                                        name
+                                        type
+                                    }}
+                                }}
+                            }}
+                        }}
+                    }}
+                }}
+            }}
+            "#,
+            name
+        );
+
+        let response = self
+            .query::<SchemasResponse>(&query)
+            .await
+            .map_err(|err| Error::Request(err.to_string()))?;
+
+        response
+            .all_schema_definition_v1
+            .documents
+            .into_iter()
+            .map(|document| RemoteSchema::from_document(name, document))
+            .collect()
+    }
+
+    /// Returns the latest versions of all schemas known to the node, together with the id of
+    /// their schema definition document.
+    ///
+    /// Schemas are fetched in pages of 100, only the first 100 fields of every schema are taken
+    /// into account.
+    pub async fn all_schemas(&self) -> Result<Vec<(DocumentId, RemoteSchema)>> {
+        let mut schemas = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let after = cursor
+                .map(|cursor| format!(", after: \"{cursor}\""))
+                .unwrap_or_default();
+            let query = format!(
+                r#"
+                {{
+                    all_schema_definition_v1(first: 100{}) {{
+                        hasNextPage
+                        endCursor
+                        documents {{
+                            meta {{
+                                documentId
+                                viewId
+                                owner
+                            }}
+                            fields {{
+                                name
+                                description
+                                fields(first: 100) {{
+                                    documents {{
+                                        fields {{
+                                            name
+                                            type
+                                        }}
+                                    }}
+                                }}
+                            }}
+                        }}
+                    }}
+                }}
+                "#,
+                after
+            );
+
+            let response = self
+                .query::<SchemasResponse>(&query)
+                .await
+                .map_err(|err| Error::Request(err.to_string()))?;
+            let collection = response.all_schema_definition_v1;
+
+            for document in collection.documents {
+                let document_id: DocumentId = document
+                    .meta
+                    .document_id
+                    .as_deref()
+                    .unwrap_or_default()
+                    .parse()
+                    .map_err(|_| Error::Request("Invalid document id in response".into()))?;
+                let name = SchemaName::new(&document.fields.name)
+                    .map_err(|_| Error::Request("Invalid schema name in response".into()))?;
+
+                schemas.push((document_id, RemoteSchema::from_document(&name, document)?));
+            }
+
+            match collection.end_cursor {
+                Some(end_cursor) if collection.has_next_page => cursor = Some(end_cursor),
+                _ => return Ok(schemas),
+            }
+        }
+    }
+
+    /// Returns the schema with the given id if the node knows about it.
+    ///
+    /// Only the first 100 fields are taken into account.
+    pub async fn schema_by_id(&self, schema_id: &SchemaId) -> Result<Option<RemoteSchema>> {
+        let view_id = match schema_id {
+            SchemaId::Application(_, view_id) => view_id,
+            _ => return Ok(None),
+        };
+
+        let query = format!(
+            r#"
+            {{
+                schema_definition_v1(viewId: "{}") {{
+                    meta {{
+                        viewId
+                        owner
+                    }}
+                    fields {{
+                        name
+                        description
+                        fields(first: 100) {{
+                            documents {{
+                                fields {{
+                                    name
+                                    type
+                                }}
+                            }}
+                        }}
+                    }}
+                }}
+            }}
+            "#,
+            view_id
+        );
+
+        let response = self
+            .query::<SchemaResponse>(&query)
+            .await
+            .map_err(|err| Error::Request(err.to_string()))?;
+
+        response
+            .schema_definition_v1
+            .map(|document| RemoteSchema::from_document(&schema_id.name(), document))
+            .transpose()
+    }
+
+    /// Returns the names of all fields of a type in the GraphQL API of the node, using
+    /// introspection.
+    ///
+    /// Returns `None` if the node does not expose a type with this name.
+    pub async fn type_fields(&self, type_name: &str) -> Result<Option<Vec<String>>> {
+        let query = format!(
+            r#"
+            {{
+                __type(name: "{}") {{
+                    fields {{
+                        name
+                    }}
+                }}
+            }}
+            "#,
+            type_name
+        );
+
+        let response = self
+            .query::<TypeResponse>(&query)
+            .await
+            .map_err(|err| Error::Request(err.to_string()))?;
+
+        Ok(response.introspected_type.map(|introspected_type| {
+            introspected_type
+                .fields
+                .unwrap_or_default()
+                .into_iter()
+                .map(|field| field.name)
+                .collect()
+        }))
+    }
+
+    /// Returns the names of all queries and mutations the GraphQL API of the node offers, using
+    /// introspection.
+    pub async fn root_fields(&self) -> Result<RootFields> {
+        let query = r#"
+            {
+                __schema {
+                    queryType {
+                        fields {
+                            name
+                        }
+                    }
+                    mutationType {
+                        fields {
+                            name
+                        }
+                    }
+                }
+            }
+            "#;
+
+        let response = self
+            .query::<SchemaIntrospectionResponse>(query)
+            .await
+            .map_err(|err| Error::Request(err.to_string()))?;
+
+        let names = |introspected_type: Option<IntrospectedType>| -> Vec<String> {
+            introspected_type
+                .and_then(|introspected_type| introspected_type.fields)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|field| field.name)
+                .collect()
+        };
+
+        Ok(RootFields {
+            queries: names(response.schema.query_type),
+            mutations: names(response.schema.mutation_type),
+        })
+    }
+}
+
+#[async_trait]
+impl Publisher for Client {
+    /// Publishes a commit on the node via GraphQL.
+    ///
+    /// Publishing is not idempotent: if a request fails, the node might have stored the entry
+    /// anyhow. Before resending it, the node is asked again for the next arguments of the log and
+    /// an entry it already knows counts as published.
+    #[instrument(skip_all, fields(entry_hash = %commit.entry_hash))]
+    async fn publish(&self, commit: &Commit) -> Result<PublishOutcome> {
+        let entry = decode_entry(&commit.entry)?;
+
+        let query = format!(
+            r#"
+            mutation Publish {{
+                publish(entry: "{}", operation: "{}") {{
+                    logId
+                    seqNum
+                    skiplink
+                    backlink
+                }}
+            }}
+            "#,
+            commit.entry, commit.operation
+        );
+
+        let mut attempts = 0;
+
+        loop {
+            if let Some(args) = self
+                .next_args(entry.public_key(), &commit.entry_hash)
+                .await?
+            {
+                if entry.log_id() != &args.log_id {
+                    return Err(Error::Inconsistency);
+                }
+
+                // Check if node already knows about this commit
+                if entry.seq_num() < &args.seq_num {
+                    if attempts > 0 {
+                        debug!("Node stored commit of failed request");
+                        return Ok(PublishOutcome::Published);
+                    }
+
+                    debug!("Node already knows about commit");
+                    return Ok(PublishOutcome::AlreadyKnown);
+                }
+            }
+
+            match self.mutate::<PublishResponse>(&query).await {
+                Ok(_) => {
+                    debug!("Published commit");
+                    return Ok(PublishOutcome::Published);
+                }
+                Err(err @ TransportError::GraphQl(_)) => {
+                    warn!("Node rejected commit: {}", err);
+                    return Ok(PublishOutcome::Rejected {
+                        node_error: err.to_string(),
+                    });
+                }
+                Err(TransportError::Request(err)) if attempts < self.retries => {
+                    attempts += 1;
+                    warn!(
+                        attempt = attempts,
+                        "Publishing commit failed, retrying: {}", err
+                    );
+                    tokio::time::sleep(RETRY_DELAY).await;
+                }
+                Err(TransportError::Request(message)) => return Err(Error::Request(message)),
+            }
+        }
+    }
+}
+
+/// Builder to configure a `Client`.
+#[derive(Clone, Debug)]
+pub struct ClientBuilder {
+    endpoint: String,
+    timeout: Duration,
+    headers: HashMap<String, String>,
+    retries: usize,
+    user_agent: String,
+    transport: Option<Arc<dyn GraphQlTransport>>,
+    #[cfg(feature = "cassette")]
+    cassette: Option<Arc<Cassette>>,
+}
+
+impl ClientBuilder {
+    /// Returns a new builder with default settings.
+    fn new(endpoint: &str) -> Self {
+        Self {
+            endpoint: endpoint.to_owned(),
+            timeout: DEFAULT_TIMEOUT,
+            headers: HashMap::new(),
+            retries: 0,
+            user_agent: format!("fishy/{}", env!("CARGO_PKG_VERSION")),
+            transport: None,
+            #[cfg(feature = "cassette")]
+            cassette: None,
+        }
+    }
+
+    /// Sets the timeout for every request.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Adds a custom HTTP header which gets sent with every request, for example `Authorization`.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.insert(name.to_owned(), value.to_owned());
+        self
+    }
+
+    /// Authenticates every request with the given token in an `Authorization: Bearer` header.
+    pub fn bearer_token(self, token: &str) -> Self {
+        self.header("Authorization", &format!("Bearer {token}"))
+    }
+
+    /// Sets how often failed requests get retried.
+    ///
+    /// Queries are sent again right away, a failed `publish` only after checking that the node
+    /// did not store the entry in the meantime.
+    pub fn retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Sets the user agent sent with every request.
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = user_agent.to_owned();
+        self
+    }
+
+    /// Sends all queries via a custom transport instead of the default `HttpTransport`.
+    ///
+    /// Endpoint, timeout, headers and user agent are ignored in this case, they need to be
+    /// configured on the custom transport.
+    pub fn transport(mut self, transport: impl GraphQlTransport + 'static) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Records all requests to or replays them from the given cassette.
+    #[cfg(feature = "cassette")]
+    pub fn cassette(mut self, cassette: Cassette) -> Self {
+        self.cassette = Some(Arc::new(cassette));
+        self
+    }
+
+    /// Returns the configured `Client`.
+    pub fn build(self) -> Client {
+        let transport = self.transport.unwrap_or_else(|| {
+            let mut headers = self.headers;
+            headers.insert("User-Agent".to_owned(), self.user_agent);
+
+            Arc::new(HttpTransport::new(&self.endpoint, self.timeout, headers))
+        });
+
+        Client {
+            transport,
+            retries: self.retries,
+            #[cfg(feature = "cassette")]
+            cassette: self.cassette,
+        }
+    }
+}
+
+/// Arguments required to publish the next entry in a log.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct NextArguments {
+    pub log_id: LogId,
+    pub seq_num: SeqNum,
+    pub skiplink: Option<Hash>,
+    pub backlink: Option<Hash>,
+}
+
+/// Names of the queries and mutations of the GraphQL API of a node.
+#[derive(Clone, Debug, Default)]
+pub struct RootFields {
+    pub queries: Vec<String>,
+    pub mutations: Vec<String>,
+}
+
+/// Schema definition which was found on a node.
+#[derive(Clone, Debug)]
+pub struct RemoteSchema {
+    /// Id of the latest version of this schema known to the node.
+    pub schema_id: SchemaId,
+
+    /// Public key of the author who created this schema.
+    pub owner: PublicKey,
+
+    /// Description of the schema.
+    pub description: String,
+
+    /// Fields of the schema, relations point at schema ids.
+    pub fields: SchemaFields,
+}
+
+impl RemoteSchema {
+    /// Returns a content hash of the schema definition, see `schema_checksum`.
+    pub fn checksum(&self) -> Hash {
+        schema_checksum(&self.schema_id.name(), &self.description, &self.fields)
+    }
+
+    /// Converts a schema definition document from a GraphQL response.
+    fn from_document(name: &SchemaName, document: Document<SchemaDocumentFields>) -> Result<Self> {
+        let view_id: DocumentViewId = document
+            .meta
+            .view_id
+            .parse()
+            .map_err(|_| Error::Request("Invalid view id in response".into()))?;
+        let owner: PublicKey = document
+            .meta
+            .owner
+            .parse()
+            .map_err(|_| Error::Request("Invalid public key in response".into()))?;
+
+        let mut fields = SchemaFields::new();
+        for field in document.fields.fields.documents {
+            let field_type: PandaFieldType = field
+                .fields
+                .field_type
+                .parse()
+                .map_err(|_| Error::Request("Invalid field type in response".into()))?;
+            fields.insert(&field.fields.name, &SchemaField::from(&field_type));
+        }
+
+        Ok(Self {
+            schema_id: SchemaId::new_application(name, &view_id),
+            owner,
+            description: document.fields.description,
+            fields,
+        })
+    }
+}
+
+/// GraphQL response for `nextArgs` query.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct NextArgsResponse {
+    next_args: NextArguments,
+}
+
+/// GraphQL response for `all_schema_definition_v1` query.
+#[derive(Deserialize, Debug)]
+struct SchemasResponse {
+    all_schema_definition_v1: Collection<SchemaDocumentFields>,
+}
+
+/// GraphQL response for `schema_definition_v1` query.
+#[derive(Deserialize, Debug)]
+struct SchemaResponse {
+    schema_definition_v1: Option<Document<SchemaDocumentFields>>,
+}
+
+/// Paginated collection of documents.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Collection<T> {
+    documents: Vec<Document<T>>,
+    #[serde(default)]
+    has_next_page: bool,
+    #[serde(default)]
+    end_cursor: Option<String>,
+}
+
+/// Document with its meta data and application fields.
+#[derive(Deserialize, Debug)]
+struct Document<T> {
+    #[serde(default)]
+    meta: DocumentMeta,
+    fields: T,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct DocumentMeta {
+    #[serde(default)]
+    document_id: Option<String>,
+    view_id: String,
+    owner: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct SchemaDocumentFields {
+    name: String,
+    description: String,
+    fields: Collection<FieldDocumentFields>,
+}
+
+#[derive(Deserialize, Debug)]
+struct FieldDocumentFields {
+    name: String,
+    #[serde(rename = "type")]
+    field_type: String,
+}
+
+/// GraphQL response for `__type` introspection query.
+#[derive(Deserialize, Debug)]
+struct TypeResponse {
+    #[serde(rename = "__type")]
+    introspected_type: Option<IntrospectedType>,
+}
+
+/// GraphQL response for `__schema` introspection query.
+#[derive(Deserialize, Debug)]
+struct SchemaIntrospectionResponse {
+    #[serde(rename = "__schema")]
+    schema: IntrospectedSchema,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct IntrospectedSchema {
+    query_type: Option<IntrospectedType>,
+    mutation_type: Option<IntrospectedType>,
+}
+
+#[derive(Deserialize, Debug)]
+struct IntrospectedType {
+    fields: Option<Vec<IntrospectedField>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct IntrospectedField {
+    name: String,
+}
+
+/// GraphQL response for `publish` mutation.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)]
+struct PublishResponse {
+    publish: NextArguments,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use async_trait::async_trait;
+    use serde_json::{json, Value};
+
+    use crate::deploy::{PublishOutcome, Publisher};
+    use crate::test_utils::{build_commits, key_pair};
+    use crate::transport::{GraphQlTransport, TransportError};
+
+    use super::Client;
+
+    /// Node which stores every published entry but times out on the first `publish` request.
+    #[derive(Debug, Default)]
+    struct FlakyNode {
+        /// Number of entries stored in the log.
+        stored: Mutex<u64>,
+
+        /// Number of received `publish` requests.
+        publish_requests: Mutex<usize>,
+    }
+
+    #[async_trait]
+    impl GraphQlTransport for FlakyNode {
+        async fn query(&self, query: &str) -> Result<Value, TransportError> {
+            let stored = *self.stored.lock().unwrap();
+
+            if query.contains("nextArgs") {
+                if stored == 0 {
+                    return Err(TransportError::GraphQl(vec!["Document not found".into()]));
+                }
+
+                return Ok(json!({
+                    "nextArgs": {
+                        "logId": "0",
+                        "seqNum": (stored + 1).to_string(),
+                        "skiplink": null,
+                        "backlink": null,
+                    }
+                }));
+            }
+
+            let mut publish_requests = self.publish_requests.lock().unwrap();
+            *publish_requests += 1;
+
+            if stored > 0 {
+                return Err(TransportError::GraphQl(vec![
+                    "Entry's claimed seq num of 1 does not match expected seq num of 2".into(),
+                ]));
+            }
+
+            *self.stored.lock().unwrap() += 1;
+
+            if *publish_requests == 1 {
+                return Err(TransportError::Request("operation timed out".into()));
+            }
+
+            Ok(json!({
+                "publish": { "logId": "0", "seqNum": "2", "skiplink": null, "backlink": null }
+            }))
+        }
+    }
+
+    /// Gives the test access to the state of the node after handing it to the client.
+    #[derive(Debug)]
+    struct SharedNode(Arc<FlakyNode>);
+
+    #[async_trait]
+    impl GraphQlTransport for SharedNode {
+        async fn query(&self, query: &str) -> Result<Value, TransportError> {
+            self.0.query(query).await
+        }
+    }
+
+    const SCHEMA_FILE: &str = r#"
+        [venues]
+        description = "Places to meet"
+        fields = { name = { type = "str" } }
+    "#;
+
+    #[tokio::test]
+    async fn does_not_resend_stored_entry_after_failed_publish() {
+        let commits = build_commits(SCHEMA_FILE, &[], &key_pair(1)).await;
+        let node = Arc::new(FlakyNode::default());
+        let client = Client::builder("http://localhost:2020/graphql")
+            .transport(SharedNode(node.clone()))
+            .retries(2)
+            .build();
+
+        let outcome = client.publish(&commits[0]).await.unwrap();
+        assert_eq!(outcome, PublishOutcome::Published);
+        assert_eq!(*node.publish_requests.lock().unwrap(), 1);
+        assert_eq!(*node.stored.lock().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_publish_without_retries() {
+        let commits = build_commits(SCHEMA_FILE, &[], &key_pair(1)).await;
+        let client = Client::builder("http://localhost:2020/graphql")
+            .transport(FlakyNode::default())
+            .build();
+
+        assert!(client.publish(&commits[0]).await.is_err());
+    }
+}
@@ -0,0 +1,47 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use fishy::bench;
+use fishy::deploy::Publisher;
+use indicatif::ProgressBar;
+
+use crate::terminal::{print_title, print_variable};
+
+/// Measure publish latency and throughput of a node with synthetic commits.
+pub async fn bench_node<P: Publisher>(publisher: P, endpoint: &str, count: usize) -> Result<()> {
+    print_title("Measure publish throughput of a node");
+    print_variable("endpoint", endpoint);
+    print_variable("count", count);
+    println!();
+
+    if count == 0 {
+        bail!("Number of commits needs to be larger than zero");
+    }
+
+    // Create commits for a disposable schema, signed by a throwaway key pair
+    let commits = bench::generate_commits(count).await?;
+
+    let progress = ProgressBar::new(count as u64);
+    let report = bench::run(&publisher, &commits, |_| progress.inc(1)).await?;
+    progress.finish_and_clear();
+
+    print_variable("total", format_duration(report.total));
+    print_variable(
+        "throughput",
+        format!("{:.2} commits/s", report.throughput()),
+    );
+    print_variable("latency min", format_duration(report.min()));
+    print_variable("latency mean", format_duration(report.mean()));
+    print_variable("latency p50", format_duration(report.percentile(50.0)));
+    print_variable("latency p95", format_duration(report.percentile(95.0)));
+    print_variable("latency max", format_duration(report.max()));
+
+    Ok(())
+}
+
+/// Formats a duration in milliseconds.
+fn format_duration(duration: Duration) -> String {
+    format!("{:.2}ms", duration.as_secs_f64() * 1000.0)
+}
@@ -1,104 +1,315 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-mod current;
-mod diff;
-mod executor;
-mod previous;
 mod print;
-mod write;
+mod reporter;
 
-use std::path::PathBuf;
+use std::fs;
+use std::path::Path;
 
 use anyhow::{bail, Context, Result};
+use console::{style, user_attended};
 use dialoguer::Confirm;
-use p2panda_rs::test_utils::memory_store::MemoryStore;
-
-use crate::commands::build::current::get_current_schemas;
-use crate::commands::build::diff::get_diff;
-use crate::commands::build::executor::execute_plan;
-use crate::commands::build::previous::get_previous_schemas;
-use crate::commands::build::print::print_plan;
-use crate::commands::build::write::write_to_lock_file;
-use crate::lock_file::LockFile;
-use crate::schema_file::SchemaFile;
-use crate::utils::files::absolute_path;
-use crate::utils::key_pair;
-use crate::utils::terminal::{print_title, print_variable};
+use fishy::client::Client;
+use fishy::duplicates::find_duplicates;
+use fishy::format::Formatters;
+use fishy::lock_file::LockFile;
+use fishy::pinned::{resolve_pinned_schemas, PinnedSchema};
+use fishy::project::Project;
+use fishy::remote_signer::RemoteSigner;
+use fishy::stale::find_stale_documents;
+use fishy::utils::files::{absolute_path, is_stdio};
+use fishy::utils::key_pair::{read_key_pair, regenerate_key_pair};
+use fishy::Error;
+
+use crate::commands::build::reporter::{FormatReporter, StreamReporter};
+use crate::stored_token;
+use crate::terminal::{eprint_variable, print_title, print_variable};
+
+pub use reporter::TerminalReporter;
 
 /// Automatically creates and signs p2panda data from a key pair and the defined schemas.
+///
+/// Commits get signed by the remote signing service at the `signer` URL instead, if given. With a
+/// `format` only the plan gets printed with the formatter registered under this name. Schemas
+/// which relations point at with their id are looked up on the `resolve_external` node, both
+/// nodes are given with the client to reach them and their endpoint.
+///
+/// Empty or invalid key pair files get replaced with a new key pair after confirmation, or
+/// directly if `regenerate_key` is set. All commits of the lock file get written into a CBOR
+/// bundle at `emit_bundle` afterwards, if given.
+///
+/// A lock path of `-` writes the lock file to stdout and commits without confirmation, all other
+/// output goes to stderr then.
+#[allow(clippy::too_many_arguments)]
 pub async fn build(
-    store: MemoryStore,
-    schema_path: PathBuf,
-    lock_path: PathBuf,
-    private_key_path: PathBuf,
+    mut project: Project,
     only_show_plan_and_exit: bool,
+    endpoint: Option<(Client, String)>,
+    resolve_external: Option<(Client, String)>,
+    signer: Option<&str>,
+    format: Option<&str>,
+    regenerate_key: bool,
+    emit_bundle: Option<&Path>,
 ) -> Result<()> {
-    print_title("Create operations and sign entries to update schema");
-    print_variable("schema_path", absolute_path(&schema_path)?.display());
-    print_variable("lock_path", absolute_path(&lock_path)?.display());
-    print_variable(
-        "private_key_path",
-        absolute_path(&private_key_path)?.display(),
-    );
-    println!();
+    let stream = is_stdio(&project.lock_path);
+    if stream && emit_bundle.is_some() {
+        bail!("Can not emit a bundle when writing the lock file to stdout");
+    }
+
+    if format.is_none() && !stream {
+        print_header(
+            &project,
+            endpoint.as_ref().map(|(_, endpoint)| endpoint.as_str()),
+            resolve_external
+                .as_ref()
+                .map(|(_, endpoint)| endpoint.as_str()),
+            signer,
+        )?;
+    }
 
-    // Load schema file
-    let schema_file = SchemaFile::from_path(&schema_path).context(format!(
-        "Try reading schema file from path '{}'",
-        schema_path.display()
-    ))?;
-    if schema_file.iter().len() == 0 {
-        bail!("Schema file is empty");
+    if signer.is_none() && project.private_key_path.exists() {
+        check_key_pair(&project.private_key_path, regenerate_key)?;
     }
 
-    // Load lock file or create new one when it does not exist yet
-    let lock_file = if lock_path.exists() {
-        LockFile::from_path(&lock_path)?
-    } else {
-        LockFile::new(&[])
+    if let Some(url) = signer {
+        let mut builder = RemoteSigner::builder(url);
+        if let Some(token) = stored_token(url)? {
+            builder = builder.bearer_token(&token);
+        }
+
+        let remote_signer = builder
+            .connect()
+            .await
+            .with_context(|| format!("Could not connect to signing service {url}"))?;
+        project = project.with_signer(remote_signer);
+    }
+
+    // Relations to pinned schema ids are not checked when building, make sure they exist
+    let pinned_schemas = match &resolve_external {
+        Some((client, endpoint)) => {
+            let pinned_schemas =
+                resolve_pinned_schemas(client, &project.plan().await.map_err(with_hint)?)
+                    .await
+                    .context("Could not look up pinned schemas on node")?;
+            check_pinned_schemas(&pinned_schemas, endpoint)?;
+            pinned_schemas
+        }
+        None => Vec::new(),
     };
 
-    // Load key pair
-    let key_pair = key_pair::read_key_pair(&private_key_path).context(format!(
-        "Try reading private key file from path '{}'",
-        private_key_path.display()
-    ))?;
-    let public_key = key_pair.public_key();
+    // Print only the plan, in a format other tools can parse
+    if let Some(format) = format {
+        let formatters = Formatters::default();
+        let Some(formatter) = formatters.get(format) else {
+            bail!(
+                "Unknown format '{format}', use one of: {}",
+                formatters.names().join(", ")
+            );
+        };
 
-    // Calculate diff between previous and current version
-    let previous_schemas = get_previous_schemas(&store, &lock_file).await?;
-    let current_schemas = get_current_schemas(&schema_file)?;
-    let diff = get_diff(previous_schemas.clone(), current_schemas).await?;
+        project
+            .inspect(&mut FormatReporter::new(formatter))
+            .await
+            .map_err(with_hint)?;
+        return Ok(());
+    }
+
+    // Check if another machine published newer updates of our schemas which are missing in the
+    // lock file, building on top of it would fork their history
+    let stale = match (&endpoint, project.lock_path.exists()) {
+        (Some((client, _)), true) => {
+            let lock_file = LockFile::from_path(&project.lock_path)?;
+            find_stale_documents(client, &lock_file)
+                .await
+                .context("Could not check node for newer schema updates")?
+        }
+        _ => Vec::new(),
+    };
 
-    // Execute plan on the diff
-    let (commits, plan) = execute_plan(store, key_pair, diff).await?;
+    for document in &stale {
+        eprintln!(
+            "{} Node knows {} newer update(s) of document {} which are missing in the lock file",
+            style("!").yellow(),
+            document.missing_updates(),
+            document.document_id,
+        );
+    }
 
     // We can also choose to only show the plan and exit directly, without committing any changes.
     // This is useful if we want to find out the schema id and state
     if only_show_plan_and_exit {
-        print_plan(plan, previous_schemas, public_key, false)?;
+        project
+            .inspect(&mut TerminalReporter::default().with_pinned_schemas(pinned_schemas))
+            .await
+            .map_err(with_hint)?;
         return Ok(());
     }
 
-    if commits.is_empty() {
-        println!("No new changes to commit.");
-    } else {
-        // Show plan to user and ask for confirmation
-        print_plan(plan, previous_schemas, public_key, true)?;
-
-        if Confirm::new()
-            .with_prompt(format!(
-                "Do you want to commit these changes ({} total)?",
-                commits.len()
-            ))
-            .interact()?
-        {
-            // Write commits to lock file
-            write_to_lock_file(commits, lock_file, lock_path)?;
-        } else {
-            println!("Abort. No changes committed.")
-        }
+    if !stale.is_empty() {
+        bail!(
+            "Lock file is outdated, update it with the latest version (for example with `git pull`) before building"
+        );
+    }
+
+    // Look for schemas which would be created again although they exist on the node already
+    let duplicates = match &endpoint {
+        Some((client, _)) => find_duplicates(client, &project.plan().await.map_err(with_hint)?)
+            .await
+            .context("Could not check node for existing schemas")?,
+        None => Vec::new(),
+    };
+
+    if stream {
+        project
+            .commit(&mut StreamReporter)
+            .await
+            .map_err(with_hint)?;
+        return Ok(());
+    }
+
+    project
+        .commit(&mut TerminalReporter::new(duplicates).with_pinned_schemas(pinned_schemas))
+        .await
+        .map_err(with_hint)?;
+
+    if let Some(path) = emit_bundle {
+        write_bundle(&project.lock_path, path)?;
     }
 
     Ok(())
 }
+
+/// Writes all commits of the lock file into a CBOR bundle at the given path.
+fn write_bundle(lock_path: &Path, path: &Path) -> Result<()> {
+    if !lock_path.exists() {
+        bail!("No lock file to bundle, commit some schemas first");
+    }
+
+    let lock_file = LockFile::from_path(lock_path)?;
+    fs::write(path, lock_file.to_cbor()?)
+        .with_context(|| format!("Could not write bundle to {}", path.display()))?;
+    println!(
+        "Successfully written {} commits to {} bundle",
+        lock_file.commits.map_or(0, |commits| commits.len()),
+        path.display()
+    );
+
+    Ok(())
+}
+
+/// Fails if the node does not know any of the pinned schemas, listing all of them.
+fn check_pinned_schemas(pinned_schemas: &[PinnedSchema], endpoint: &str) -> Result<()> {
+    let missing: Vec<&PinnedSchema> = pinned_schemas
+        .iter()
+        .filter(|pinned| !pinned.exists())
+        .collect();
+
+    for pinned in &missing {
+        let relations: Vec<String> = pinned
+            .relations
+            .iter()
+            .map(|(schema_name, field_name)| format!("{schema_name}.{field_name}"))
+            .collect();
+        eprintln!(
+            "{} Schema {} used by {} does not exist on the node",
+            style("✗").red(),
+            pinned.schema_id,
+            relations.join(", ")
+        );
+    }
+
+    if !missing.is_empty() {
+        bail!(
+            "{} pinned schemas are unknown to {endpoint}, deploy them first or fix their ids",
+            missing.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Makes sure the key pair file contains a valid private key, offering to replace it with a newly
+/// generated key pair otherwise.
+///
+/// Messages go to stderr, stdout might carry the lock file.
+fn check_key_pair(path: &Path, regenerate_key: bool) -> Result<()> {
+    let err = match read_key_pair(path) {
+        Ok(_) => return Ok(()),
+        Err(err @ Error::InvalidPrivateKeyFile(..)) => err,
+        Err(err) => return Err(err.into()),
+    };
+
+    eprintln!("{} {err}", style("!").yellow());
+
+    // Only ask when someone is there to answer
+    let regenerate = regenerate_key
+        || user_attended()
+            && Confirm::new()
+                .with_prompt(
+                    "Generate a new key pair? Schemas committed with the previous key can not be \
+                    updated with it",
+                )
+                .default(false)
+                .interact()?;
+    if !regenerate {
+        bail!("Fix the private key file or run `fishy build --regenerate-key` to replace it");
+    }
+
+    let (key_pair, backup_path) = regenerate_key_pair(path)?;
+    if let Some(backup_path) = backup_path {
+        eprintln!("Moved previous key pair file to {}", backup_path.display());
+    }
+    eprint_variable("public_key", key_pair.public_key());
+    eprintln!();
+
+    Ok(())
+}
+
+/// Prints the paths and settings used for building.
+fn print_header(
+    project: &Project,
+    endpoint: Option<&str>,
+    resolve_external: Option<&str>,
+    signer: Option<&str>,
+) -> Result<()> {
+    print_title("Create operations and sign entries to update schema");
+    match is_stdio(&project.schema_path) {
+        true => print_variable("schema_path", "stdin"),
+        false => print_variable(
+            "schema_path",
+            absolute_path(&project.schema_path)?.display(),
+        ),
+    }
+    print_variable("lock_path", absolute_path(&project.lock_path)?.display());
+    match signer {
+        Some(url) => print_variable("signer", url),
+        None => print_variable(
+            "private_key_path",
+            absolute_path(&project.private_key_path)?.display(),
+        ),
+    }
+    print_variable(
+        "aliases_path",
+        absolute_path(&project.aliases_path)?.display(),
+    );
+    if let Some(endpoint) = endpoint {
+        print_variable("endpoint", endpoint);
+    }
+    if let Some(resolve_external) = resolve_external {
+        print_variable("resolve_external", resolve_external);
+    }
+    println!();
+
+    Ok(())
+}
+
+/// Adds a suggestion how to resolve the error, if there is one.
+fn with_hint(err: Error) -> anyhow::Error {
+    match &err {
+        Error::CyclicDependency(cycle) => {
+            let hint = cycle.hint();
+            anyhow::Error::new(err).context(hint)
+        }
+        _ => err.into(),
+    }
+}
@@ -0,0 +1,277 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use console::style;
+use dialoguer::Confirm;
+use fishy::build::PlanSummary;
+use fishy::duplicates::Duplicate;
+use fishy::foreign::{ForeignCommit, ForeignReason};
+use fishy::format::Formatter;
+use fishy::pinned::PinnedSchema;
+use fishy::prune::UnusedField;
+use fishy::reporter::{Progress, Reporter};
+use p2panda_rs::identity::PublicKey;
+use p2panda_rs::schema::SchemaName;
+
+use crate::commands::build::print::{print_pinned_schemas, print_plan};
+
+/// Reports the build process in the terminal and asks for confirmation via an interactive prompt.
+#[derive(Default)]
+pub struct TerminalReporter {
+    /// New schemas which already exist on a node.
+    duplicates: Vec<Duplicate>,
+
+    /// Schemas outside of the project which relations point at with their id.
+    pinned_schemas: Vec<PinnedSchema>,
+}
+
+impl TerminalReporter {
+    /// Returns a reporter warning about the given duplicate schemas before confirming changes.
+    pub fn new(duplicates: Vec<Duplicate>) -> Self {
+        Self {
+            duplicates,
+            pinned_schemas: Vec::new(),
+        }
+    }
+
+    /// Shows the given pinned schemas after the plan.
+    pub fn with_pinned_schemas(mut self, pinned_schemas: Vec<PinnedSchema>) -> Self {
+        self.pinned_schemas = pinned_schemas;
+        self
+    }
+}
+
+impl Reporter for TerminalReporter {
+    fn plan(&mut self, summaries: &[PlanSummary], public_key: &PublicKey, only_changes: bool) {
+        print_plan(summaries, public_key, only_changes);
+        print_pinned_schemas(&self.pinned_schemas);
+
+        for duplicate in &self.duplicates {
+            println!(
+                "{} Schema {} already exists on the node as {} (created by {}).",
+                style("Warning:").yellow().bold(),
+                style(&duplicate.name).bold(),
+                duplicate.existing.schema_id,
+                duplicate.existing.owner,
+            );
+        }
+
+        if !self.duplicates.is_empty() {
+            println!(
+                "Restore the lock file of existing schemas to update them or refer to them via \
+                `schema = {{ id = \"<schema id>\" }}` instead of creating parallel schemas.\n"
+            );
+        }
+    }
+
+    fn confirm(&mut self, total: usize) -> fishy::Result<bool> {
+        let prompt = if self.duplicates.is_empty() {
+            format!("Do you want to commit these changes ({total} total)?")
+        } else {
+            format!("Do you still want to commit these changes ({total} total)?")
+        };
+
+        let confirmed = Confirm::new().with_prompt(prompt).interact()?;
+
+        Ok(confirmed)
+    }
+
+    fn progress(&mut self, event: &Progress) {
+        match event {
+            Progress::SchemasSkipped(schema_names) => {
+                for schema_name in schema_names {
+                    println!(
+                        "{} {} (disabled)",
+                        style("Skipped").dim(),
+                        style(schema_name).bold()
+                    );
+                }
+                println!();
+            }
+            Progress::ForeignCommits(foreign) => {
+                for warning in foreign_commit_warnings(foreign) {
+                    println!("{} {warning}", style("!").yellow());
+                }
+                println!();
+            }
+            Progress::UnusedFields(unused_fields) => {
+                for warning in unused_field_warnings(unused_fields) {
+                    println!("{} {warning}", style("!").yellow());
+                }
+                println!();
+            }
+            Progress::NoChanges => println!("No new changes to commit."),
+            Progress::Aborted => println!("Abort. No changes committed."),
+            Progress::CommitsWritten(total, path) => println!(
+                "Successfully written {total} new commits to {} file",
+                path.display()
+            ),
+            Progress::BootstrapCommitsWritten(total, path) => println!(
+                "Successfully written {total} new bootstrap commits to {} file",
+                path.display()
+            ),
+            Progress::AliasesWritten(path) => {
+                println!("Updated schema aliases in {}", path.display())
+            }
+            Progress::ToolingWritten(path) => {
+                println!("Updated tooling metadata in {}", path.display())
+            }
+        }
+    }
+}
+
+/// Prints the plan with the given formatter only, for example as JSON for other tools.
+pub struct FormatReporter<'a> {
+    formatter: &'a dyn Formatter,
+}
+
+impl<'a> FormatReporter<'a> {
+    /// Returns a reporter printing the plan with the given formatter.
+    pub fn new(formatter: &'a dyn Formatter) -> Self {
+        Self { formatter }
+    }
+}
+
+impl Reporter for FormatReporter<'_> {
+    fn plan(&mut self, summaries: &[PlanSummary], public_key: &PublicKey, only_changes: bool) {
+        print!(
+            "{}",
+            self.formatter.plan(summaries, public_key, only_changes)
+        );
+    }
+
+    fn confirm(&mut self, _total: usize) -> fishy::Result<bool> {
+        // Formatted output is not interactive
+        Ok(false)
+    }
+
+    fn progress(&mut self, _event: &Progress) {}
+}
+
+/// Reports progress on stderr only and commits without confirmation, stdout is reserved for the
+/// lock file when it gets written there.
+#[derive(Default)]
+pub struct StreamReporter;
+
+impl Reporter for StreamReporter {
+    fn plan(&mut self, _summaries: &[PlanSummary], _public_key: &PublicKey, _only_changes: bool) {}
+
+    fn confirm(&mut self, _total: usize) -> fishy::Result<bool> {
+        // Pipelines are not interactive
+        Ok(true)
+    }
+
+    fn progress(&mut self, event: &Progress) {
+        match event {
+            Progress::SchemasSkipped(schema_names) => {
+                for schema_name in schema_names {
+                    eprintln!("Skipped {schema_name} (disabled)");
+                }
+            }
+            Progress::ForeignCommits(foreign) => {
+                for warning in foreign_commit_warnings(foreign) {
+                    eprintln!("{} {warning}", style("!").yellow());
+                }
+            }
+            Progress::UnusedFields(unused_fields) => {
+                for warning in unused_field_warnings(unused_fields) {
+                    eprintln!("{} {warning}", style("!").yellow());
+                }
+            }
+            Progress::NoChanges => eprintln!("No new changes to commit."),
+            Progress::Aborted => eprintln!("Abort. No changes committed."),
+            Progress::CommitsWritten(total, _) => {
+                eprintln!("Successfully written {total} new commits to stdout")
+            }
+            Progress::BootstrapCommitsWritten(total, path) => eprintln!(
+                "Successfully written {total} new bootstrap commits to {} file",
+                path.display()
+            ),
+            Progress::AliasesWritten(path) => {
+                eprintln!("Updated schema aliases in {}", path.display())
+            }
+            Progress::ToolingWritten(_) => eprintln!("Written lock file to stdout"),
+        }
+    }
+}
+
+/// Returns one warning for every orphaned schema and unreferenced field definition with commits
+/// which can not be derived from the schema file, in the order of the lock file.
+fn foreign_commit_warnings(foreign: &[ForeignCommit]) -> Vec<String> {
+    let same_origin = |a: &ForeignCommit, b: &ForeignCommit| match (&a.reason, &b.reason) {
+        (ForeignReason::OrphanedSchema(a), ForeignReason::OrphanedSchema(b)) => a == b,
+        (ForeignReason::UnreferencedField(_), ForeignReason::UnreferencedField(_)) => {
+            a.document_id == b.document_id
+        }
+        _ => false,
+    };
+
+    let mut origins: Vec<(&ForeignCommit, usize)> = Vec::new();
+    for commit in foreign {
+        match origins
+            .iter_mut()
+            .find(|(first, _)| same_origin(first, commit))
+        {
+            Some((_, count)) => *count += 1,
+            None => origins.push((commit, 1)),
+        }
+    }
+
+    origins
+        .into_iter()
+        .map(|(commit, count)| match &commit.reason {
+            ForeignReason::OrphanedSchema(schema_name) => format!(
+                "{} is not defined anymore but still part of the lock file with {count} commits, \
+                remove it with `fishy prune`",
+                style(schema_name).bold()
+            ),
+            ForeignReason::UnreferencedField(field_name) => format!(
+                "Field definition {} is not used by any schema but still part of the lock file \
+                with {count} commits, delete it with `fishy prune --gc`",
+                match field_name {
+                    Some(field_name) => style(field_name.to_string()).bold(),
+                    None => style(commit.document_id.to_string()).bold(),
+                }
+            ),
+        })
+        .collect()
+}
+
+/// Returns one warning for every schema with field definitions which were removed from it but
+/// still get deployed.
+fn unused_field_warnings(unused_fields: &[UnusedField]) -> Vec<String> {
+    let mut schemas: Vec<(Option<&SchemaName>, Vec<String>)> = Vec::new();
+    for field in unused_fields {
+        let name = style(&field.name).bold().to_string();
+        match schemas
+            .iter_mut()
+            .find(|(schema, _)| *schema == field.schema.as_ref())
+        {
+            Some((_, names)) => names.push(name),
+            None => schemas.push((field.schema.as_ref(), vec![name])),
+        }
+    }
+
+    schemas
+        .into_iter()
+        .map(|(schema, names)| {
+            let (fields, were, them) = match names.len() {
+                1 => ("Field", "was", "it"),
+                _ => ("Fields", "were", "them"),
+            };
+
+            match schema {
+                Some(schema) => format!(
+                    "{fields} {} {were} removed from {} but still get deployed with the lock file, \
+                    delete {them} with `fishy prune --gc`",
+                    names.join(", "),
+                    style(schema).bold()
+                ),
+                None => format!(
+                    "{fields} {} {were} never used by any schema but still get deployed with the \
+                    lock file, delete {them} with `fishy prune --gc`",
+                    names.join(", ")
+                ),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,76 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use anyhow::{bail, Context, Result};
+use console::style;
+use fishy::client::Client;
+use fishy::compat::{check_node, compat_matrix, is_compatible};
+
+use crate::terminal::{print_title, print_variable};
+
+/// Print the data versions produced by fishy and the node versions accepting them.
+pub async fn compat(client: Option<(Client, String)>) -> Result<()> {
+    let matrix = compat_matrix();
+
+    print_title("Check compatibility with p2panda nodes");
+    print_variable("fishy", env!("CARGO_PKG_VERSION"));
+    print_variable("p2panda-rs", matrix.p2panda_rs_version);
+    print_variable("operation_version", matrix.operation_version);
+    print_variable("entry_format", matrix.entry_format);
+    for schema_id in &matrix.system_schemas {
+        print_variable("system_schema", schema_id);
+    }
+    println!();
+
+    println!("aquadoggo releases:");
+    for release in &matrix.releases {
+        if release.is_compatible() {
+            println!(
+                "{} {} (p2panda-rs {})",
+                style("✓").green(),
+                release.version,
+                release.p2panda_rs_version
+            );
+        } else {
+            println!(
+                "{} {} (p2panda-rs {})",
+                style("✗").red(),
+                style(release.version).dim(),
+                release.p2panda_rs_version
+            );
+        }
+    }
+
+    let Some((client, endpoint)) = client else {
+        return Ok(());
+    };
+
+    println!();
+    println!("Node at {endpoint}:");
+
+    let requirements = check_node(&client)
+        .await
+        .context("Could not introspect GraphQL API of node")?;
+    for requirement in &requirements {
+        if requirement.satisfied {
+            println!("{} {}", style("✓").green(), requirement.description);
+        } else {
+            println!("{} {}", style("✗").red(), requirement.description);
+        }
+    }
+
+    println!();
+    if !is_compatible(&requirements) {
+        let versions: Vec<&str> = matrix
+            .compatible_releases()
+            .map(|release| release.version)
+            .collect();
+        bail!(
+            "Node does not accept data of this fishy version, run aquadoggo {} instead",
+            versions.join(" or ")
+        );
+    }
+
+    println!("Node accepts data of this fishy version.");
+
+    Ok(())
+}
@@ -0,0 +1,84 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use clap::ValueEnum;
+use fishy::constants::{LOCK_FILE_NAME, SCHEMA_FILE_NAME};
+use fishy::lock_file::LockFile;
+use fishy::project::Project;
+use fishy::schema_file::SchemaFile;
+use fishy::utils::git;
+
+use crate::credentials::Credentials;
+
+/// Default GraphQL endpoint of a local p2panda node.
+const DEFAULT_ENDPOINT: &str = "http://localhost:2020/graphql";
+
+/// Kind of values which can be completed dynamically.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum CompletionKind {
+    /// Schema names defined in the schema file or lock file of the current directory.
+    Schemas,
+
+    /// Endpoints with stored tokens and the default endpoint.
+    Endpoints,
+
+    /// Git tags of the repository the current directory is in.
+    Tags,
+}
+
+/// Print completion candidates starting with the given prefix, one per line.
+///
+/// Shells call this while completing, so errors are ignored and lead to fewer candidates.
+pub fn complete(kind: CompletionKind, prefix: &str) {
+    let candidates = match kind {
+        CompletionKind::Schemas => schema_names(),
+        CompletionKind::Endpoints => endpoints(),
+        CompletionKind::Tags => git::tags(Path::new("."))
+            .unwrap_or_default()
+            .into_iter()
+            .collect(),
+    };
+
+    for candidate in candidates {
+        if candidate.starts_with(prefix) {
+            println!("{candidate}");
+        }
+    }
+}
+
+fn schema_names() -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+
+    if let Ok(schema_file) = SchemaFile::from_path(SCHEMA_FILE_NAME) {
+        names.extend(schema_file.iter().map(|(name, _)| name.to_string()));
+    }
+
+    // Respect a state directory configured in fishy.toml, but never move any files here
+    let lock_path = Project::open(".")
+        .map(|project| project.lock_path)
+        .unwrap_or_else(|_| LOCK_FILE_NAME.into());
+
+    if let Ok(schema_ids) =
+        LockFile::from_path(lock_path).and_then(|lock_file| lock_file.schema_ids())
+    {
+        names.extend(
+            schema_ids
+                .iter()
+                .map(|schema_id| schema_id.name().to_string()),
+        );
+    }
+
+    names
+}
+
+fn endpoints() -> BTreeSet<String> {
+    let mut endpoints = BTreeSet::from([DEFAULT_ENDPOINT.to_owned()]);
+
+    if let Ok(credentials) = Credentials::default_path().and_then(|path| Credentials::load(&path)) {
+        endpoints.extend(credentials.endpoints().map(str::to_owned));
+    }
+
+    endpoints
+}
@@ -1,139 +1,186 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use std::path::PathBuf;
-
-use anyhow::{anyhow, bail, Context, Result};
-use gql_client::Client;
+use anyhow::{bail, Context, Result};
+use console::style;
+use fishy::build::get_previous_schemas;
+use fishy::deploy::{deploy_cancellable, DeployReport, Publisher};
+use fishy::deployments::{self, DeployStamp};
+use fishy::hooks::{call_hooks, DeployHook, HookStatus};
+use fishy::lock_file::LockFile;
+use fishy::project::Project;
+use fishy::utils::files::absolute_path;
 use indicatif::ProgressBar;
 use p2panda_rs::entry::decode::decode_entry;
 use p2panda_rs::entry::traits::AsEntry;
-use p2panda_rs::entry::{LogId, SeqNum};
-use p2panda_rs::hash::Hash;
-use serde::Deserialize;
+use p2panda_rs::identity::PublicKey;
+use p2panda_rs::operation::OperationId;
+use p2panda_rs::schema::{SchemaId, SchemaName};
+use tokio_util::sync::CancellationToken;
 
-use crate::lock_file::LockFile;
-use crate::utils::files::absolute_path;
-use crate::utils::terminal::{print_title, print_variable};
+use crate::terminal::{print_title, print_variable};
 
 /// Deploy created schemas on a node.
-pub async fn deploy(lock_path: PathBuf, endpoint: &str) -> Result<()> {
+pub async fn deploy<P: Publisher>(
+    publisher: P,
+    project: Project,
+    endpoint: &str,
+    hooks: &[DeployHook],
+) -> Result<()> {
     print_title("Deploy created schemas on a node");
-    print_variable("lock_path", absolute_path(&lock_path)?.display());
+    let lock_path = &project.lock_path;
+    print_variable("lock_path", absolute_path(lock_path)?.display());
     print_variable("endpoint", endpoint);
-    println!();
+    for hook in hooks {
+        print_variable("hook", &hook.url);
+    }
 
-    let lock_file = LockFile::from_path(&lock_path).context(format!(
-        "Try reading lock file from path '{}'",
+    // Only commits signed by the trusted keys of the project get deployed
+    let (lock_file, bootstrap_lock) = project.read_lock_files().context(format!(
+        "Try reading lock files from path '{}'",
         lock_path.display()
     ))?;
 
-    let commits = lock_file.commits.unwrap_or(Vec::new());
-    if commits.is_empty() {
-        bail!("No data given to deploy to node. Please run `update` command first.");
+    // Schemas of one project can be signed by different key pairs
+    for (public_key, schema_names) in signing_keys(&lock_file).await? {
+        let schema_names: Vec<String> = schema_names.iter().map(ToString::to_string).collect();
+        print_variable(
+            "public_key",
+            format!("{public_key} ({})", schema_names.join(", ")),
+        );
     }
+    println!();
 
-    // Count how many commits we needed to deploy
-    let mut skipped = 0;
-    let total = commits.len();
-    let progress = ProgressBar::new(total as u64);
-
-    // Publish commits on external node via GraphQL
-    let client = Client::new(endpoint);
-
-    for commit in commits {
-        let entry = decode_entry(&commit.entry).unwrap();
-
-        let query = format!(
-            r#"
-            {{
-                nextArgs(publicKey: "{}", viewId: "{}") {{
-                    logId
-                    seqNum
-                    skiplink
-                    backlink
-                }}
-            }}
-            "#,
-            entry.public_key(),
-            commit.entry_hash,
-        );
+    let mut commits = lock_file.commits.clone().unwrap_or(Vec::new());
 
-        let response = client.query_unwrap::<NextArgsResponse>(&query).await;
+    // Bootstrap documents follow the schemas, they get published right after them
+    commits.extend(bootstrap_lock.commits.unwrap_or_default());
 
-        if let Ok(result) = response {
-            let args = result.next_args;
+    if commits.is_empty() {
+        bail!("No data given to deploy to node. Please run `update` command first.");
+    }
 
-            if entry.log_id() != &args.log_id {
-                bail!("Inconsistency between local commits and node detected");
+    // Stop gracefully after the commit currently being published when pressing Ctrl-C
+    let token = CancellationToken::new();
+    let ctrl_c = tokio::spawn({
+        let token = token.clone();
+        async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                token.cancel();
             }
+        }
+    });
 
-            // Check if node already knows about this commit
-            if entry.seq_num() < &args.seq_num {
-                skipped += 1;
-                progress.inc(1);
-
-                // Skip this one
-                continue;
-            }
+    // Publish commits on node, commits the node already knows about are skipped
+    let total = commits.len();
+    let progress = ProgressBar::new(total as u64);
+    let result = deploy_cancellable(&publisher, &commits, &token, |_, _| progress.inc(1)).await;
+    ctrl_c.abort();
+
+    // Fail with the first rejected commit, suggesting how to resolve it
+    let outcomes = result?;
+    for (commit, outcome) in &outcomes {
+        if let Err(err) = outcome.clone().into_result(commit) {
+            return Err(
+                match err.rejection().and_then(|rejection| rejection.hint()) {
+                    Some(hint) => anyhow::Error::new(err).context(hint),
+                    None => err.into(),
+                },
+            );
         }
+    }
 
-        let query = format!(
-            r#"
-            mutation Publish {{
-                publish(entry: "{}", operation: "{}") {{
-                    logId
-                    seqNum
-                    skiplink
-                    backlink
-                }}
-            }}
-            "#,
-            commit.entry, commit.operation
-        );
+    let report = DeployReport::from_outcomes(&outcomes);
 
-        client
-            .query_unwrap::<PublishResponse>(&query)
-            .await
-            .map_err(|err| anyhow!("GraphQL request to node failed: {err}"))?;
+    println!();
 
-        progress.inc(1);
+    if report.cancelled {
+        println!(
+            "Deployment cancelled after {} of {} commits (ignored {}). Run `deploy` again to continue.",
+            report.published + report.skipped,
+            total,
+            report.skipped,
+        );
+        return Ok(());
     }
 
-    println!();
-
-    if total == skipped {
+    if report.published == 0 {
         println!("Node is already up-to-date with latest schema version. No deployment required.")
     } else {
         println!(
             "Successfully deployed {} commits on node (ignored {}).",
-            total - skipped,
-            skipped,
+            report.published, report.skipped,
         );
     }
 
-    Ok(())
-}
+    // Remember that the node is up-to-date with the lock files
+    let mut deployments = deployments::load(&project.deployments_path)?;
+    deployments.insert(endpoint, DeployStamp::new(&commits));
+    deployments.write(&project.deployments_path)?;
 
-#[derive(Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
-#[allow(dead_code)]
-struct NextArguments {
-    log_id: LogId,
-    seq_num: SeqNum,
-    skiplink: Option<Hash>,
-    backlink: Option<Hash>,
-}
+    if hooks.is_empty() {
+        return Ok(());
+    }
 
-/// GraphQL response for `nextArgs` query.
-#[derive(Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
-struct NextArgsResponse {
-    next_args: NextArguments,
+    // Notify application services about the deployed schema ids
+    let mut schema_ids: Vec<SchemaId> = get_previous_schemas(&lock_file)
+        .await?
+        .values()
+        .map(|schema| schema.schema.id().to_owned())
+        .collect();
+    schema_ids.sort();
+
+    println!();
+
+    for report in call_hooks(hooks, &schema_ids).await {
+        match report.status {
+            HookStatus::Success => println!(
+                "{} {} (attempts: {})",
+                style("✓").green(),
+                report.url,
+                report.attempts
+            ),
+            HookStatus::Failed(err) => println!(
+                "{} {} (attempts: {}): {}",
+                style("✗").red(),
+                report.url,
+                report.attempts,
+                err
+            ),
+        }
+    }
+
+    Ok(())
 }
 
-#[derive(Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
-#[allow(dead_code)]
-struct PublishResponse {
-    publish: NextArguments,
+/// Returns the public keys which signed the latest version of every schema in the lock file,
+/// together with the names of their schemas in alphabetical order.
+async fn signing_keys(lock_file: &LockFile) -> Result<Vec<(PublicKey, Vec<SchemaName>)>> {
+    let commits = lock_file.commits.clone().unwrap_or_default();
+    let previous_schemas = get_previous_schemas(lock_file).await?;
+
+    let mut signing_keys: Vec<(PublicKey, Vec<SchemaName>)> = Vec::new();
+    for (schema_name, previous_schema) in previous_schemas {
+        // The operations of the latest version were signed by the current owner of the schema
+        let commit = previous_schema
+            .schema_view
+            .view_id()
+            .iter()
+            .find_map(|operation_id| {
+                commits
+                    .iter()
+                    .find(|commit| &OperationId::new(&commit.entry_hash) == operation_id)
+            })
+            .context("Lock file is missing the commit of a schema version")?;
+        let public_key = decode_entry(&commit.entry)?.public_key().to_owned();
+
+        match signing_keys
+            .iter_mut()
+            .find(|(other, _)| other == &public_key)
+        {
+            Some((_, schema_names)) => schema_names.push(schema_name),
+            None => signing_keys.push((public_key, vec![schema_name])),
+        }
+    }
+
+    Ok(signing_keys)
 }
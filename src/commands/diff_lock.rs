@@ -0,0 +1,111 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use console::{style, Color};
+use fishy::build::{ChangeStatus, Compatibility};
+use fishy::lock_diff::diff_lock_files;
+use fishy::lock_file::LockFile;
+use fishy::utils::files::absolute_path;
+
+use crate::terminal::{print_title, print_variable};
+
+/// Print which schemas and fields were added, changed or removed between two lock files.
+pub async fn diff_lock(old: &Path, new: &Path) -> Result<()> {
+    print_title("Compare schemas of two lock files");
+    print_variable("old", absolute_path(old)?.display());
+    print_variable("new", absolute_path(new)?.display());
+    println!();
+
+    let read = |path: &Path| {
+        LockFile::from_path(path).context(format!(
+            "Try reading lock file from path '{}'",
+            path.display()
+        ))
+    };
+    let diff = diff_lock_files(&read(old)?, &read(new)?).await?;
+
+    if diff.is_empty() {
+        println!("Both lock files contain the same schemas.");
+        return Ok(());
+    }
+
+    println!(
+        "The following schemas were ({}, {}, {}):\n",
+        style("added").green(),
+        style("changed").yellow(),
+        style("removed").red()
+    );
+
+    for schema in &diff.schemas {
+        let (marker, color) = status_marker(schema.status);
+        print!("{} {}", style(marker).fg(color), style(&schema.name).bold());
+        if schema.status == ChangeStatus::Changed(Compatibility::Breaking) {
+            print!(" {}", style("(breaking)").red());
+        }
+        println!();
+
+        match (&schema.old_schema_id, &schema.new_schema_id) {
+            (Some(old_schema_id), Some(new_schema_id)) => {
+                println!("    {}", style(old_schema_id).dim());
+                println!("  → {new_schema_id}");
+            }
+            (Some(schema_id), None) | (None, Some(schema_id)) => println!("    {schema_id}"),
+            (None, None) => (),
+        }
+
+        if schema.description_changed() {
+            println!(
+                "    description: {} → {}",
+                style(schema.old_description.as_deref().unwrap_or_default()).dim(),
+                schema.new_description.as_deref().unwrap_or_default()
+            );
+        }
+
+        for field in &schema.fields {
+            let (marker, color) = status_marker(field.status);
+            let field_type = match (&field.old, &field.new) {
+                (Some(old), Some(new)) if old.to_string() == new.to_string() => {
+                    format!("{new} (new version)")
+                }
+                (Some(old), Some(new)) => format!("{old} → {new}"),
+                (Some(field), None) | (None, Some(field)) => field.to_string(),
+                (None, None) => String::new(),
+            };
+
+            println!(
+                "    {} {}: {}",
+                style(marker).fg(color),
+                field.name,
+                style(field_type).fg(color)
+            );
+        }
+
+        println!();
+    }
+
+    let count = |status: fn(&ChangeStatus) -> bool| {
+        diff.schemas
+            .iter()
+            .filter(|schema| status(&schema.status))
+            .count()
+    };
+    println!(
+        "{} added, {} changed, {} removed",
+        count(|status| *status == ChangeStatus::Added),
+        count(|status| matches!(status, ChangeStatus::Changed(_))),
+        count(|status| *status == ChangeStatus::Removed)
+    );
+
+    Ok(())
+}
+
+fn status_marker(status: ChangeStatus) -> (&'static str, Color) {
+    match status {
+        ChangeStatus::Added => ("+", Color::Green),
+        ChangeStatus::Changed(_) => ("~", Color::Yellow),
+        ChangeStatus::Removed => ("-", Color::Red),
+        ChangeStatus::Unchanged => (" ", Color::White),
+    }
+}
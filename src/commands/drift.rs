@@ -0,0 +1,79 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use console::style;
+use fishy::client::Client;
+use fishy::drift::{find_drift, DriftStatus};
+use fishy::lock_file::LockFile;
+use fishy::utils::files::absolute_path;
+
+use crate::terminal::{print_title, print_variable};
+
+/// Compare the GraphQL API of a node with the schemas of the lock file.
+pub async fn drift(client: Client, lock_path: PathBuf, endpoint: &str) -> Result<()> {
+    print_title("Compare schemas served by a node with the lock file");
+    print_variable("lock_path", absolute_path(&lock_path)?.display());
+    print_variable("endpoint", endpoint);
+    println!();
+
+    let lock_file = LockFile::from_path(&lock_path).context(format!(
+        "Try reading lock file from path '{}'",
+        lock_path.display()
+    ))?;
+
+    let drifts = find_drift(&client, &lock_file)
+        .await
+        .context("Could not introspect GraphQL API of node")?;
+    if drifts.is_empty() {
+        println!("Lock file does not contain any schemas yet.");
+        return Ok(());
+    }
+
+    let mut drifted = 0;
+    for drift in &drifts {
+        match &drift.status {
+            DriftStatus::InSync => {
+                println!("{} {}", style("✓").green(), drift.schema_id);
+            }
+            DriftStatus::Missing => {
+                drifted += 1;
+                println!(
+                    "{} {} is not served by the node, deploy the lock file or wait until the node materialized it",
+                    style("!").yellow(),
+                    drift.schema_id
+                );
+            }
+            DriftStatus::Divergent {
+                missing_fields,
+                unexpected_fields,
+            } => {
+                drifted += 1;
+                println!(
+                    "{} {} is served with other fields than in the lock file",
+                    style("✗").red(),
+                    drift.schema_id
+                );
+                for field in missing_fields {
+                    println!("  Missing field `{field}`");
+                }
+                for field in unexpected_fields {
+                    println!("  Unexpected field `{field}`");
+                }
+            }
+        }
+    }
+
+    println!();
+    if drifted > 0 {
+        bail!(
+            "{drifted} of {} schemas differ between the node and the lock file",
+            drifts.len()
+        );
+    }
+
+    println!("All {} schemas are served as committed.", drifts.len());
+
+    Ok(())
+}
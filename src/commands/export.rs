@@ -0,0 +1,54 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use fishy::lock_file::LockFile;
+use fishy::utils::files::write_file;
+
+/// Format the commits of the lock file can be exported in.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ExportFormat {
+    /// JSON array of objects with hex-encoded `entry` and `operation`, as used by other p2panda
+    /// tooling and aquadoggo test fixtures.
+    JsonCommits,
+
+    /// Rust module embedding the lock file with typed schema id constants, for applications
+    /// deploying their schemas at first launch.
+    Rust,
+}
+
+/// Export the commits of the lock file in the given format, to stdout or into a file.
+pub fn export(lock_path: &Path, format: ExportFormat, output: Option<&Path>) -> Result<()> {
+    if !lock_path.exists() {
+        bail!(
+            "No lock file found at '{}', nothing to export",
+            lock_path.display()
+        );
+    }
+
+    let lock_file = LockFile::from_path(lock_path).context(format!(
+        "Try reading lock file from path '{}'",
+        lock_path.display()
+    ))?;
+
+    let data = match format {
+        ExportFormat::JsonCommits => lock_file.to_json_commits()?,
+        ExportFormat::Rust => lock_file.to_rust_module()?,
+    };
+
+    match output {
+        Some(path) => {
+            write_file(path, &data)?;
+            println!(
+                "Successfully exported {} commits to {}",
+                lock_file.commits.as_ref().map_or(0, Vec::len),
+                path.display()
+            );
+        }
+        None => println!("{data}"),
+    }
+
+    Ok(())
+}
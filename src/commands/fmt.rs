@@ -0,0 +1,69 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use console::style;
+use fishy::schema_editor::SchemaEditor;
+use fishy::utils::files::absolute_path;
+
+use crate::terminal::{print_title, print_variable};
+
+/// Formats schema files in a canonical way, a directory formats all .toml files inside of it.
+///
+/// With `check` no file gets changed, the command fails if any file is not formatted yet.
+pub fn fmt(schema_path: &Path, check: bool) -> Result<()> {
+    print_title("Format schema files");
+    print_variable("schema_path", absolute_path(schema_path)?.display());
+    println!();
+
+    let mut unformatted: Vec<PathBuf> = Vec::new();
+
+    for path in schema_files(schema_path)? {
+        let mut editor = SchemaEditor::from_path(&path)?;
+        let original = editor.to_string();
+        editor.format();
+
+        if editor.to_string() == original {
+            println!("{} {}", style("✓").green(), path.display());
+            continue;
+        }
+
+        if check {
+            println!("{} {} is not formatted", style("✗").red(), path.display());
+        } else {
+            editor.write(&path)?;
+            println!("{} Formatted {}", style("✓").green(), path.display());
+        }
+
+        unformatted.push(path);
+    }
+
+    println!();
+    match (check, unformatted.len()) {
+        (_, 0) => println!("All schema files are formatted."),
+        (true, count) => {
+            bail!("{count} schema files are not formatted, run `fishy fmt` to fix them")
+        }
+        (false, count) => println!("Formatted {count} schema files."),
+    }
+
+    Ok(())
+}
+
+/// Returns the schema file at the given path or all .toml files of a directory, sorted by name.
+fn schema_files(schema_path: &Path) -> Result<Vec<PathBuf>> {
+    if !schema_path.is_dir() {
+        return Ok(vec![schema_path.to_path_buf()]);
+    }
+
+    let mut paths: Vec<PathBuf> = schema_path
+        .read_dir()
+        .with_context(|| format!("Could not read directory {}", schema_path.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<_>>()?;
+    paths.retain(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "toml"));
+    paths.sort();
+
+    Ok(paths)
+}
@@ -0,0 +1,130 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use console::{style, Color};
+use fishy::build::{ChangeStatus, Compatibility};
+use fishy::history::schema_history;
+use fishy::lock_file::LockFile;
+use fishy::utils::files::absolute_path;
+
+use crate::terminal::{print_title, print_variable};
+
+/// Print every version of the schemas in a lock file with its schema id, changed fields and the
+/// commit introducing it, only of the given schema if a name is set.
+pub async fn history(lock_path: &Path, schema_name: Option<&str>) -> Result<()> {
+    print_title("Show version history of schemas");
+    print_variable("lock_path", absolute_path(lock_path)?.display());
+    println!();
+
+    if !lock_path.exists() {
+        bail!("No lock file found at '{}'", lock_path.display());
+    }
+
+    let lock_file = LockFile::from_path(lock_path).context(format!(
+        "Try reading lock file from path '{}'",
+        lock_path.display()
+    ))?;
+    let mut histories = schema_history(&lock_file).await?;
+
+    if let Some(schema_name) = schema_name {
+        histories.retain(|history| history.name.to_string() == schema_name);
+        if histories.is_empty() {
+            bail!("Lock file does not contain a schema named '{schema_name}'");
+        }
+    }
+
+    if histories.is_empty() {
+        println!("Lock file does not contain any schemas.");
+        return Ok(());
+    }
+
+    for history in &histories {
+        println!("{}", style(&history.name).bold().underlined());
+        println!();
+
+        for (index, version) in history.versions.iter().enumerate() {
+            print!(
+                "{} {}",
+                style(format!("v{}", index + 1)).bold(),
+                version.schema_id
+            );
+            if version.status == ChangeStatus::Changed(Compatibility::Breaking) {
+                print!(" {}", style("(breaking)").red());
+            }
+            println!();
+            println!(
+                "    commit {} signed by {}",
+                style(&version.commit).dim(),
+                style(&version.public_key).dim()
+            );
+
+            if let Some(build) = lock_file.build_of(&version.commit) {
+                print!(
+                    "    built with fishy {} at {}",
+                    build.fishy_version, build.timestamp
+                );
+                match &build.message {
+                    Some(message) => println!(": {}", style(message).italic()),
+                    None => println!(),
+                }
+            }
+
+            let previous = index.checked_sub(1).map(|index| &history.versions[index]);
+            if let Some(previous) = previous {
+                if previous.description != version.description {
+                    println!(
+                        "    description: {} → {}",
+                        style(&previous.description).dim(),
+                        version.description
+                    );
+                }
+            }
+
+            for field in &version.changes {
+                let (marker, color) = match field.status {
+                    ChangeStatus::Added => ("+", Color::Green),
+                    ChangeStatus::Changed(_) => ("~", Color::Yellow),
+                    ChangeStatus::Removed => ("-", Color::Red),
+                    ChangeStatus::Unchanged => (" ", Color::White),
+                };
+                let field_type = match (&field.old, &field.new) {
+                    (Some(old), Some(new)) if old.to_string() == new.to_string() => {
+                        format!("{new} (new version)")
+                    }
+                    (Some(old), Some(new)) => format!("{old} → {new}"),
+                    (Some(field), None) | (None, Some(field)) => field.to_string(),
+                    (None, None) => String::new(),
+                };
+
+                println!(
+                    "    {} {}: {}",
+                    style(marker).fg(color),
+                    field.name,
+                    style(field_type).fg(color)
+                );
+            }
+
+            println!();
+        }
+
+        if let Some(commit) = &history.deleted_in {
+            println!(
+                "{} deleted in commit {}",
+                style("-").red(),
+                style(commit).dim()
+            );
+            println!();
+        }
+    }
+
+    let versions: usize = histories.iter().map(|history| history.versions.len()).sum();
+    println!(
+        "Found {versions} versions of {} schemas in {} file",
+        histories.len(),
+        lock_path.display()
+    );
+
+    Ok(())
+}
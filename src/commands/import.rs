@@ -0,0 +1,69 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use console::style;
+use fishy::lock_file::{AnnotationAction, LockFile};
+use fishy::project::Project;
+use fishy::utils::files::{absolute_path, read_file};
+
+use crate::terminal::{print_title, print_variable};
+
+/// Validate commits produced elsewhere and append them to the lock file.
+pub async fn import(project: Project, commits_path: &Path) -> Result<()> {
+    print_title("Import commits into lock file");
+    print_variable("commits_path", absolute_path(commits_path)?.display());
+    print_variable("lock_path", absolute_path(&project.lock_path)?.display());
+    println!();
+
+    let data = read_file(commits_path)?;
+    let commits = LockFile::from_json_commits(&data)
+        .context(format!(
+            "Try reading commits from path '{}'",
+            commits_path.display()
+        ))?
+        .commits
+        .unwrap_or_default();
+
+    let import = project.import(&commits).await?;
+    if import.imported == 0 {
+        println!("Lock file contains all commits already. Nothing to import.");
+        return Ok(());
+    }
+
+    let all_commits = import.lock_file.commits.as_deref().unwrap_or_default();
+    for commit in &all_commits[all_commits.len() - import.imported..] {
+        let Some(annotation) = &commit.annotation else {
+            continue;
+        };
+
+        let action = match annotation.action {
+            AnnotationAction::Create => "create",
+            AnnotationAction::Update => "update",
+            AnnotationAction::Delete => "delete",
+        };
+        match &annotation.field {
+            Some(field) => println!(
+                "{} {} {action} field {field}",
+                style("✓").green(),
+                style(&annotation.schema).bold()
+            ),
+            None => println!(
+                "{} {} {action}",
+                style("✓").green(),
+                style(&annotation.schema).bold()
+            ),
+        }
+    }
+    println!();
+
+    println!(
+        "Successfully imported {} new commits into {} file, skipped {} existing commits",
+        import.imported,
+        project.lock_path.display(),
+        import.skipped
+    );
+
+    Ok(())
+}
@@ -0,0 +1,289 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use console::style;
+use dialoguer::{Confirm, Input, Select};
+use fishy::constants::{PRIVATE_KEY_FILE_NAME, SCHEMA_FILE_NAME};
+use fishy::project::Project;
+use fishy::schema_editor::SchemaEditor;
+use fishy::schema_file::{
+    Constraints, FieldType, Metadata, RelationId, RelationSchema, RelationType, SchemaField,
+};
+use fishy::utils::files::absolute_path;
+use p2panda_rs::schema::validate::{validate_description, validate_field_name, validate_name};
+use p2panda_rs::schema::{FieldName, SchemaDescription, SchemaName};
+
+use crate::commands::build::TerminalReporter;
+use crate::terminal::{print_title, print_variable};
+
+use super::{init_secret_file, sanity_check};
+
+/// Field types which can be chosen for regular fields, with a short explanation.
+const FIELD_TYPES: [(FieldType, &str, &str); 5] = [
+    (FieldType::String, "str", "Text"),
+    (FieldType::Integer, "int", "Whole number"),
+    (FieldType::Float, "float", "Decimal number"),
+    (FieldType::Boolean, "bool", "Yes or no"),
+    (FieldType::Bytes, "bytes", "Binary data"),
+];
+
+/// Relation types which can be chosen for relation fields, with a short explanation.
+const RELATION_TYPES: [(RelationType, &str, &str); 4] = [
+    (
+        RelationType::Relation,
+        "relation",
+        "Points at the latest version of one document",
+    ),
+    (
+        RelationType::RelationList,
+        "relation_list",
+        "Points at the latest versions of multiple documents",
+    ),
+    (
+        RelationType::PinnedRelation,
+        "pinned_relation",
+        "Points at one fixed version of a document",
+    ),
+    (
+        RelationType::PinnedRelationList,
+        "pinned_relation_list",
+        "Points at fixed versions of multiple documents",
+    ),
+];
+
+/// Schema put together by answering the questions of the guided setup.
+struct GuidedSchema {
+    name: SchemaName,
+    description: SchemaDescription,
+    fields: Vec<(FieldName, SchemaField)>,
+}
+
+/// Walks newcomers through creating two related schemas, shows the plan `fishy build` would commit
+/// and explains how to deploy it.
+pub async fn init_guided(target_dir: PathBuf) -> Result<()> {
+    print_title("Create your first p2panda schemas step by step");
+    print_variable("target_dir", absolute_path(&target_dir)?.display());
+    println!();
+
+    sanity_check(&target_dir)?;
+
+    let schema_path = target_dir.join(SCHEMA_FILE_NAME);
+    if schema_path.exists() {
+        bail!(
+            "{} file exists already, the guided setup only creates new projects",
+            SCHEMA_FILE_NAME
+        );
+    }
+
+    println!(
+        "Schemas describe the documents of your application, similar to tables in a database. \
+        Every document follows one schema and contains its fields."
+    );
+
+    let key_pair_path = target_dir.join(PRIVATE_KEY_FILE_NAME);
+    if !key_pair_path.exists() {
+        init_secret_file(&key_pair_path)?;
+        println!(
+            "Created a new key pair in {PRIVATE_KEY_FILE_NAME}, it signs your schemas and \
+            identifies you as their owner. Keep it secret!"
+        );
+    }
+
+    print_step(1, "Define a schema");
+    println!(
+        "Every schema has a name, a description and one or more fields. For example a cafe with \
+        its name and address."
+    );
+    let first = ask_schema("cafe", "A place serving icecream", &[])?;
+
+    print_step(2, "Relate a second schema to it");
+    println!(
+        "Relation fields point at documents of other schemas. For example an icecream can be \
+        sold in multiple cafes."
+    );
+    let mut second = ask_schema(
+        "icecream",
+        "Flavour of icecream",
+        &[&first.name.to_string()],
+    )?;
+
+    let taken: Vec<&str> = second
+        .fields
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect();
+    let relation_field = ask_field_name(
+        &format!("? Name of the field relating to {}", first.name),
+        &format!("{}s", first.name),
+        &taken,
+    )?;
+    let relation_type = ask_choice("? Type of the relation", &RELATION_TYPES)?;
+    second.fields.push((
+        relation_field,
+        SchemaField::Relation {
+            field_type: relation_type,
+            schema: RelationSchema {
+                id: RelationId::Name(first.name.clone()),
+                external: None,
+                rev: None,
+            },
+            rename_from: None,
+            metadata: Metadata::default(),
+        },
+    ));
+
+    let mut editor = SchemaEditor::new();
+    for schema in [first, second] {
+        editor.add_schema(&schema.name, &schema.description)?;
+        for (field_name, field) in &schema.fields {
+            editor.add_field(&schema.name, field_name, field)?;
+        }
+    }
+    editor.write(&schema_path)?;
+    println!("Written both schemas to {}", schema_path.display());
+
+    print_step(3, "Build");
+    println!(
+        "`fishy build` turns your schemas into p2panda operations, signs them with your key pair \
+        and stores them in schema.lock. Every change to schema.toml creates a new version of the \
+        schema with a new id. This is what it would commit:\n"
+    );
+    Project::open(&target_dir)?
+        .inspect(&mut TerminalReporter::default())
+        .await?;
+
+    print_step(4, "Deploy");
+    println!(
+        "`fishy deploy` publishes the commits of schema.lock on a p2panda node like aquadoggo. \
+        The node then offers a GraphQL API to create and query documents of your schemas."
+    );
+
+    println!();
+    println!("Next steps:");
+    if target_dir != Path::new(".") {
+        println!("- cd {}", target_dir.display());
+    }
+    println!("- Adjust schema.toml and run `fishy build` to commit your schemas");
+    println!("- Run `fishy compat` to find a node version accepting them");
+    println!("- Start a node and run `fishy deploy --endpoint http://localhost:2020/graphql`");
+
+    Ok(())
+}
+
+/// Prints the title of a step of the guided setup.
+fn print_step(number: usize, title: &str) {
+    println!();
+    println!("{}", style(format!("Step {number}: {title}")).bold());
+}
+
+/// Asks for the name, description and fields of a schema.
+fn ask_schema(
+    default_name: &str,
+    default_description: &str,
+    taken: &[&str],
+) -> Result<GuidedSchema> {
+    let taken: Vec<String> = taken.iter().map(|name| name.to_string()).collect();
+    let name: String = Input::new()
+        .with_prompt("? Name of your schema")
+        .default(default_name.to_owned())
+        .validate_with(|input: &String| -> Result<()> {
+            if !validate_name(input) {
+                bail!("This is not a valid p2panda schema name");
+            }
+
+            if taken.contains(input) {
+                bail!("This name is taken by another schema already");
+            }
+
+            Ok(())
+        })
+        .interact_text()?;
+
+    let description: String = Input::new()
+        .with_prompt("? Description")
+        .default(default_description.to_owned())
+        .validate_with(|input: &String| -> Result<()> {
+            if !validate_description(input) {
+                bail!("Description can be at most 256 characters long");
+            }
+
+            Ok(())
+        })
+        .interact_text()?;
+
+    let mut fields: Vec<(FieldName, SchemaField)> = Vec::new();
+    loop {
+        let taken: Vec<&str> = fields.iter().map(|(name, _)| name.as_str()).collect();
+        let default_field = if fields.is_empty() { "name" } else { "" };
+        let field_name = ask_field_name("? Name of the field", default_field, &taken)?;
+        let field_type = ask_choice("? Type of the field", &FIELD_TYPES)?;
+        fields.push((
+            field_name,
+            SchemaField::Field {
+                field_type,
+                rename_from: None,
+                bootstrap: None,
+                constraints: Constraints::default(),
+                metadata: Metadata::default(),
+            },
+        ));
+
+        let add_more = Confirm::new()
+            .with_prompt("Add another field?")
+            .default(false)
+            .interact()?;
+        if !add_more {
+            break;
+        }
+    }
+
+    Ok(GuidedSchema {
+        name: SchemaName::new(&name)?,
+        description: SchemaDescription::new(&description)?,
+        fields,
+    })
+}
+
+/// Asks for the name of a field which is not taken by another field of the same schema.
+fn ask_field_name(prompt: &str, default: &str, taken: &[&str]) -> Result<String> {
+    let taken: Vec<String> = taken.iter().map(|name| name.to_string()).collect();
+    let mut input = Input::new();
+    input.with_prompt(prompt);
+    if !default.is_empty() {
+        input.default(default.to_owned());
+    }
+
+    let field_name = input
+        .validate_with(|input: &String| -> Result<()> {
+            if !validate_field_name(input) {
+                bail!("This is not a valid p2panda field name");
+            }
+
+            if taken.contains(input) {
+                bail!("This schema has a field with this name already");
+            }
+
+            Ok(())
+        })
+        .interact_text()?;
+
+    Ok(field_name)
+}
+
+/// Asks to choose one of the given types and returns it.
+fn ask_choice<T: Clone>(prompt: &str, choices: &[(T, &str, &str)]) -> Result<T> {
+    let items: Vec<String> = choices
+        .iter()
+        .map(|(_, name, explanation)| format!("{name} - {explanation}"))
+        .collect();
+
+    let index = Select::new()
+        .with_prompt(prompt)
+        .items(&items)
+        .default(0)
+        .interact()?;
+
+    Ok(choices[index].0.clone())
+}
@@ -1,19 +1,59 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+mod guided;
+
 use std::path::{Path, PathBuf};
 
 use anyhow::{bail, Result};
+use clap::ValueEnum;
 use dialoguer::Input;
+use fishy::constants::{PRIVATE_KEY_FILE_NAME, SCHEMA_FILE_NAME};
+use fishy::utils::files::{absolute_path, write_file};
+use fishy::utils::key_pair::write_key_pair;
 use p2panda_rs::identity::KeyPair;
 use p2panda_rs::schema::validate::validate_name;
 
-use crate::constants::{PRIVATE_KEY_FILE_NAME, SCHEMA_FILE_NAME};
-use crate::utils::files::{absolute_path, write_file};
-use crate::utils::key_pair::write_key_pair;
-use crate::utils::terminal::{print_title, print_variable};
+use crate::terminal::{print_title, print_variable};
+
+pub use guided::init_guided;
+
+/// Schema files with multiple related schemas to start a new project from.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Template {
+    /// Authors writing posts with tags and comments.
+    Blog,
+
+    /// Users sending messages to channels.
+    Chat,
+
+    /// Books, their authors and loans to library members.
+    Library,
+
+    /// Boards with columns and cards assigned to people.
+    Kanban,
+}
+
+impl Template {
+    /// Returns the content of the schema file of this template.
+    fn schema_file(&self) -> &'static str {
+        match self {
+            Template::Blog => include_str!("templates/blog.toml"),
+            Template::Chat => include_str!("templates/chat.toml"),
+            Template::Library => include_str!("templates/library.toml"),
+            Template::Kanban => include_str!("templates/kanban.toml"),
+        }
+    }
+}
 
 /// Initialises all files for a new fishy project in a given folder.
-pub fn init(target_dir: PathBuf, schema_name: Option<String>) -> Result<()> {
+///
+/// The schema file is created from the given template, otherwise it contains a single schema with
+/// the given name.
+pub fn init(
+    target_dir: PathBuf,
+    schema_name: Option<String>,
+    template: Option<Template>,
+) -> Result<()> {
     print_title("Initialise a new fishy project");
     print_variable("target_dir", absolute_path(&target_dir)?.display());
     println!();
@@ -42,7 +82,15 @@ pub fn init(target_dir: PathBuf, schema_name: Option<String>) -> Result<()> {
         );
     }
 
-    if !schema_path.exists() {
+    if schema_path.exists() {
+        println!(
+            "Do not create {} file as it already exists",
+            SCHEMA_FILE_NAME
+        );
+    } else if let Some(template) = template {
+        print_variable("template", format!("{template:?}").to_lowercase());
+        write_file(&schema_path, template.schema_file())?;
+    } else {
         // Ask user about the schema name when none was given
         let schema_name = match schema_name {
             Some(name) => {
@@ -67,11 +115,6 @@ pub fn init(target_dir: PathBuf, schema_name: Option<String>) -> Result<()> {
         };
 
         init_schema_file(&schema_path, &schema_name)?;
-    } else {
-        println!(
-            "Do not create {} file as it already exists",
-            SCHEMA_FILE_NAME
-        );
     }
 
     println!("Successfully initialised new fishy project in target directory");
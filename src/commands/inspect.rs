@@ -0,0 +1,100 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use comfy_table::modifiers::UTF8_ROUND_CORNERS;
+use comfy_table::presets::UTF8_FULL;
+use comfy_table::Table;
+use console::style;
+use fishy::authors::commit_authors;
+use fishy::inspect::inspect_lock_file;
+use fishy::lock_file::LockFile;
+use fishy::utils::files::absolute_path;
+use p2panda_rs::identity::PublicKey;
+
+use crate::terminal::{print_title, print_variable};
+
+/// List all schemas of a lock file with their fields, without a schema file or key pair.
+///
+/// Authors which are not one of the trusted keys get marked if any are given.
+pub async fn inspect(lock_path: &Path, trusted_keys: &[PublicKey]) -> Result<()> {
+    print_title("Inspect schemas of lock file");
+    print_variable("lock_path", absolute_path(lock_path)?.display());
+    println!();
+
+    if !lock_path.exists() {
+        bail!("No lock file found at '{}'", lock_path.display());
+    }
+
+    let lock_file = LockFile::from_path(lock_path).context(format!(
+        "Try reading lock file from path '{}'",
+        lock_path.display()
+    ))?;
+    let schemas = inspect_lock_file(&lock_file).await?;
+
+    if schemas.is_empty() {
+        println!("Lock file does not contain any schemas.");
+        return Ok(());
+    }
+
+    for schema in &schemas {
+        println!("{}", style(&schema.schema_id).bold().underlined());
+        println!();
+        println!("Name: {}", schema.name);
+        println!("Description: {}", schema.description);
+        println!("Versions: {}", schema.versions);
+        println!("Signed by: {}", style(&schema.public_key).bold());
+        println!("Authors:");
+        for (public_key, commits) in &schema.authors {
+            let marker = match (trusted_keys.is_empty(), trusted_keys.contains(public_key)) {
+                (true, _) => style("-").dim(),
+                (false, true) => style("✓").green(),
+                (false, false) => style("✗").red(),
+            };
+            println!("  {marker} {public_key} ({commits} commits)");
+        }
+
+        let mut table = Table::new();
+        table
+            .load_preset(UTF8_FULL)
+            .apply_modifier(UTF8_ROUND_CORNERS)
+            .set_header(vec!["#", "Field Name", "Field Type"]);
+
+        for (index, (field_name, field)) in schema.fields.iter().enumerate() {
+            table.add_row(vec![
+                (index + 1).to_string(),
+                field_name.to_string(),
+                field.to_string(),
+            ]);
+        }
+
+        println!("{table}\n");
+    }
+
+    let commits = lock_file.commits.as_ref().map_or(0, Vec::len);
+    let untrusted: Vec<String> = commit_authors(lock_file.commits.as_deref().unwrap_or_default())?
+        .into_iter()
+        .filter(|(public_key, _)| !trusted_keys.is_empty() && !trusted_keys.contains(public_key))
+        .map(|(public_key, _)| public_key.to_string())
+        .collect();
+    if !untrusted.is_empty() {
+        println!(
+            "{} {}\n",
+            style("!").yellow(),
+            style(format!(
+                "Lock file contains commits signed by untrusted keys: {}",
+                untrusted.join(", ")
+            ))
+            .yellow()
+        );
+    }
+
+    println!(
+        "Found {} schemas in {commits} commits of {} file",
+        schemas.len(),
+        lock_path.display()
+    );
+
+    Ok(())
+}
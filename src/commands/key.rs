@@ -0,0 +1,82 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::io::stdin;
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use console::{style, user_attended};
+use dialoguer::Password;
+use fishy::utils::files::absolute_path;
+use fishy::utils::key_pair::{from_mnemonic, read_key_pair, to_mnemonic, write_key_pair};
+
+use crate::terminal::{print_title, print_variable};
+
+/// Print the private key of the key pair file as recovery phrase.
+pub fn export_mnemonic(private_key_path: &Path) -> Result<()> {
+    print_title("Export key pair as recovery phrase");
+    print_variable(
+        "private_key_path",
+        absolute_path(private_key_path)?.display(),
+    );
+
+    let key_pair = read_key_pair(private_key_path)?;
+    print_variable("public_key", key_pair.public_key());
+    println!();
+
+    println!(
+        "{} Anyone knowing these words can update your schemas, write them down and keep them \
+        offline:",
+        style("!").yellow()
+    );
+    println!();
+
+    let mnemonic = to_mnemonic(&key_pair);
+    let words: Vec<&str> = mnemonic.split(' ').collect();
+    for (index, chunk) in words.chunks(6).enumerate() {
+        let line: Vec<String> = chunk
+            .iter()
+            .enumerate()
+            .map(|(offset, word)| format!("{:>2}. {word:<8}", index * 6 + offset + 1))
+            .collect();
+        println!("{}", line.join(" ").trim_end());
+    }
+
+    Ok(())
+}
+
+/// Restore the key pair file from a recovery phrase.
+///
+/// The phrase is read from standard input when it is not a terminal, for example in scripts.
+pub fn import_mnemonic(private_key_path: &Path) -> Result<()> {
+    print_title("Restore key pair from recovery phrase");
+    print_variable(
+        "private_key_path",
+        absolute_path(private_key_path)?.display(),
+    );
+    println!();
+
+    if private_key_path.exists() {
+        bail!(
+            "Key pair file {} exists already, move it away before restoring another key pair",
+            private_key_path.display()
+        );
+    }
+
+    let mnemonic = if user_attended() {
+        Password::new()
+            .with_prompt("? Recovery phrase (24 words)")
+            .interact()?
+    } else {
+        let mut line = String::new();
+        stdin().read_line(&mut line)?;
+        line
+    };
+
+    let key_pair = from_mnemonic(&mnemonic)?;
+    write_key_pair(private_key_path, &key_pair)?;
+
+    print_variable("public_key", key_pair.public_key());
+    println!("Restored key pair, check if the public key matches the owner of your schemas.");
+
+    Ok(())
+}
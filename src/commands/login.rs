@@ -0,0 +1,52 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use anyhow::{bail, Result};
+use dialoguer::Password;
+
+use crate::credentials::{read_passphrase, Credentials};
+use crate::terminal::{print_title, print_variable};
+
+/// Store a token to authenticate against a protected node.
+pub fn login(endpoint: &str) -> Result<()> {
+    print_title("Store token to authenticate against a node");
+    print_variable("endpoint", endpoint);
+
+    let path = Credentials::default_path()?;
+    print_variable("credentials_path", path.display());
+    println!();
+
+    let mut credentials = Credentials::load(&path)?;
+
+    let token = Password::new().with_prompt("? Token").interact()?;
+    if token.trim().is_empty() {
+        bail!("Token can not be empty");
+    }
+
+    let passphrase = read_passphrase(true)?;
+    credentials.insert(endpoint, token.trim(), &passphrase)?;
+    credentials.save(&path)?;
+
+    println!();
+    println!("Stored token, it will be sent with every request to this node.");
+
+    Ok(())
+}
+
+/// Remove the stored token of a node.
+pub fn logout(endpoint: &str) -> Result<()> {
+    print_title("Remove stored token of a node");
+    print_variable("endpoint", endpoint);
+    println!();
+
+    let path = Credentials::default_path()?;
+    let mut credentials = Credentials::load(&path)?;
+
+    if !credentials.remove(endpoint) {
+        bail!("No token stored for '{endpoint}'");
+    }
+
+    credentials.save(&path)?;
+    println!("Removed stored token.");
+
+    Ok(())
+}
@@ -0,0 +1,54 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use console::style;
+use fishy::lock_file::LockFile;
+use fishy::merge::merge_lock_files;
+use fishy::utils::files::absolute_path;
+use fishy::Error;
+
+use crate::terminal::{print_title, print_variable};
+
+/// Merge the commits of two diverged lock files into one.
+pub async fn merge_lock(ours: &Path, theirs: &Path, output: Option<&Path>) -> Result<()> {
+    let output = output.unwrap_or(ours);
+
+    print_title("Merge diverged lock files");
+    print_variable("ours", absolute_path(ours)?.display());
+    print_variable("theirs", absolute_path(theirs)?.display());
+    print_variable("output", absolute_path(output)?.display());
+    println!();
+
+    let our_lock_file = LockFile::from_path(ours)?;
+    let their_lock_file = LockFile::from_path(theirs)?;
+
+    let merged = match merge_lock_files(&our_lock_file, &their_lock_file).await {
+        Ok(merged) => merged,
+        Err(Error::LockFileMergeConflicts(conflicts)) => {
+            for conflict in &conflicts {
+                println!("{} {conflict}", style("✗").red());
+            }
+            println!();
+
+            bail!(
+                "Found {} conflicts between lock files, {} was not changed",
+                conflicts.len(),
+                output.display()
+            );
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    let commits = |lock_file: &LockFile| lock_file.commits.as_ref().map_or(0, Vec::len);
+    merged.write(output)?;
+    println!(
+        "Successfully merged {} new commits into {} file, {} commits in total",
+        commits(&merged) - commits(&our_lock_file),
+        output.display(),
+        commits(&merged)
+    );
+
+    Ok(())
+}
@@ -0,0 +1,37 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use anyhow::Result;
+use console::style;
+use fishy::project::Project;
+use fishy::utils::files::absolute_path;
+
+use crate::terminal::{print_title, print_variable};
+
+/// Upgrade lock files written by older versions of fishy to the current format.
+pub fn migrate_lock(project: Project) -> Result<()> {
+    print_title("Migrate lock files to the current format");
+    print_variable("lock_path", absolute_path(&project.lock_path)?.display());
+    print_variable(
+        "bootstrap_lock_path",
+        absolute_path(&project.bootstrap_lock_path)?.display(),
+    );
+    println!();
+
+    let migrated = project.migrate_lock_files()?;
+    if migrated.is_empty() {
+        println!("Lock files are in the current format. Nothing to migrate.");
+        return Ok(());
+    }
+
+    for path in &migrated {
+        println!("{} {}", style("✓").green(), path.display());
+    }
+
+    println!();
+    println!(
+        "Migrated {} lock files, schema ids did not change.",
+        migrated.len()
+    );
+
+    Ok(())
+}
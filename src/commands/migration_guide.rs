@@ -0,0 +1,48 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use fishy::lock_file::LockFile;
+use fishy::migration::migration_guide as generate_migration_guide;
+use fishy::utils::git;
+
+/// Print a Markdown migration guide between two lock file states.
+///
+/// Every state is either a path to a lock file or a git revision of the lock file at `lock_path`.
+pub async fn migration_guide(from: &str, to: Option<&str>, lock_path: &Path) -> Result<()> {
+    let from = load_lock_file(from, lock_path)?;
+    let to = match to {
+        Some(to) => load_lock_file(to, lock_path)?,
+        None => LockFile::from_path(lock_path).context(format!(
+            "Try reading lock file from path '{}'",
+            lock_path.display()
+        ))?,
+    };
+
+    let guide = generate_migration_guide(&from, &to).await?;
+    print!("{}", guide.to_markdown());
+
+    Ok(())
+}
+
+/// Reads a lock file from a path or, if no such file exists, from a git revision.
+fn load_lock_file(state: &str, lock_path: &Path) -> Result<LockFile> {
+    let path = Path::new(state);
+    if path.is_file() {
+        return LockFile::from_path(path)
+            .context(format!("Try reading lock file from path '{state}'"));
+    }
+
+    let data = git::show_file(state, lock_path).context(format!(
+        "'{state}' is neither a lock file nor a git revision of '{}'",
+        lock_path.display()
+    ))?;
+
+    // Segments of the lock file are read from the same revision
+    let dir = lock_path.parent().unwrap_or(Path::new(""));
+    let mut lock_file: LockFile = data.parse()?;
+    lock_file.resolve_segments(|segment| git::show_file(state, &dir.join(segment)))?;
+
+    Ok(lock_file)
+}
@@ -1,9 +1,51 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+mod bench_node;
 mod build;
+mod compat;
+mod complete;
 mod deploy;
+mod diff_lock;
+mod drift;
+mod export;
+mod fmt;
+mod history;
+mod import;
 mod init;
+mod inspect;
+mod key;
+mod login;
+mod merge_lock;
+mod migrate_lock;
+mod migration_guide;
+mod prune;
+mod resign;
+mod signature;
+mod squash;
+mod vendor;
+mod watch_node;
 
+pub use bench_node::bench_node;
 pub use build::build;
+pub use compat::compat;
+pub use complete::{complete, CompletionKind};
 pub use deploy::deploy;
-pub use init::init;
+pub use diff_lock::diff_lock;
+pub use drift::drift;
+pub use export::{export, ExportFormat};
+pub use fmt::fmt;
+pub use history::history;
+pub use import::import;
+pub use init::{init, init_guided, Template};
+pub use inspect::inspect;
+pub use key::{export_mnemonic, import_mnemonic};
+pub use login::{login, logout};
+pub use merge_lock::merge_lock;
+pub use migrate_lock::migrate_lock;
+pub use migration_guide::migration_guide;
+pub use prune::prune;
+pub use resign::resign;
+pub use signature::{sign, verify};
+pub use squash::squash;
+pub use vendor::vendor;
+pub use watch_node::watch_node;
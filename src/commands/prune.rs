@@ -0,0 +1,137 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use anyhow::{bail, Result};
+use console::style;
+use dialoguer::Confirm;
+use fishy::project::Project;
+use fishy::prune::{Orphan, PruneMode, UnusedField};
+use fishy::utils::files::absolute_path;
+
+use crate::terminal::{print_title, print_variable};
+
+/// Delete or remove the documents of schemas which are not defined in the schema file anymore.
+///
+/// With `gc` field definitions the latest version of no schema refers to get deleted as well.
+pub async fn prune(project: Project, mode: PruneMode, gc: bool) -> Result<()> {
+    print_title("Prune schemas which are not defined anymore");
+    print_variable(
+        "schema_path",
+        absolute_path(&project.schema_path)?.display(),
+    );
+    print_variable("lock_path", absolute_path(&project.lock_path)?.display());
+    println!();
+
+    if !project.lock_path.exists() {
+        bail!(
+            "No lock file found at '{}', nothing to prune",
+            project.lock_path.display()
+        );
+    }
+
+    let orphans = project.orphans().await?;
+
+    // Fields of orphaned schemas get pruned together with them
+    let unused_fields: Vec<UnusedField> = project
+        .unused_fields()
+        .await?
+        .into_iter()
+        .filter(|field| {
+            !orphans
+                .iter()
+                .any(|orphan| orphan.document_id() == &field.document_id)
+        })
+        .collect();
+    let kept_fields = if gc { 0 } else { unused_fields.len() };
+    let unused_fields = if gc { unused_fields } else { Vec::new() };
+
+    if orphans.is_empty() && unused_fields.is_empty() {
+        println!("All schemas of the lock file are defined. Nothing to prune.");
+        if kept_fields > 0 {
+            println!("{kept_fields} unused field definitions are kept, add `--gc` to delete them.");
+        }
+        return Ok(());
+    }
+
+    for orphan in &orphans {
+        match orphan {
+            Orphan::Schema { name, view_id, .. } => {
+                println!("{} {} ({view_id})", style("-").red(), style(name).bold())
+            }
+            Orphan::Field { name, .. } => println!("  {} field {name}", style("-").red()),
+        }
+    }
+    for field in &unused_fields {
+        match &field.schema {
+            Some(schema) => println!(
+                "{} field {} removed from {}",
+                style("-").red(),
+                style(&field.name).bold(),
+                style(schema).bold()
+            ),
+            None => println!(
+                "{} field {} not used by any schema",
+                style("-").red(),
+                style(&field.name).bold()
+            ),
+        }
+    }
+    println!();
+
+    if kept_fields > 0 {
+        println!(
+            "{kept_fields} unused field definitions are kept, add `--gc` to delete them as well.\n"
+        );
+    }
+
+    let total = orphans.len() + unused_fields.len();
+    let prompt = match mode {
+        PruneMode::Tombstone => {
+            println!(
+                "Deleting documents adds tombstones to the lock file, nodes delete them after \
+                deploying it."
+            );
+            if !unused_fields.is_empty() {
+                println!(
+                    "{} Older versions of schemas refer to the deleted fields, applications still \
+                    using their schema ids lose these fields.",
+                    style("!").yellow()
+                );
+            }
+            format!("Do you want to delete these {total} documents?")
+        }
+        PruneMode::Remove => {
+            println!(
+                "{} Removing commits is only safe if the lock file was never deployed, nodes keep \
+                the documents and new schemas might use their logs again.",
+                style("!").yellow()
+            );
+            format!("Do you want to remove the commits of these {total} documents?")
+        }
+    };
+    println!();
+
+    if !Confirm::new().with_prompt(prompt).interact()? {
+        println!("Abort. Lock file was not changed.");
+        return Ok(());
+    }
+
+    let mut total = 0;
+    if !orphans.is_empty() {
+        total += project.prune(&orphans, mode).await?;
+    }
+    if !unused_fields.is_empty() {
+        total += project.delete_unused_fields(&unused_fields).await?;
+    }
+    match mode {
+        PruneMode::Tombstone => println!(
+            "Successfully written {total} new commits to {} file",
+            project.lock_path.display()
+        ),
+        PruneMode::Remove => println!(
+            "Successfully removed {total} commits from {} file",
+            project.lock_path.display()
+        ),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,82 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use console::style;
+use dialoguer::Confirm;
+use fishy::project::Project;
+use fishy::utils::files::absolute_path;
+use fishy::utils::key_pair::read_key_pair;
+
+use crate::terminal::{print_title, print_variable};
+
+/// Replay the history of the lock file signed by a new key pair.
+pub async fn resign(project: Project, old_key_path: &Path, new_key_path: &Path) -> Result<()> {
+    print_title("Re-sign history of lock file");
+    print_variable("lock_path", absolute_path(&project.lock_path)?.display());
+    print_variable("old_key_path", absolute_path(old_key_path)?.display());
+    print_variable("new_key_path", absolute_path(new_key_path)?.display());
+    println!();
+
+    if !project.lock_path.exists() {
+        bail!(
+            "No lock file found at '{}', nothing to re-sign",
+            project.lock_path.display()
+        );
+    }
+
+    let old_key_pair = read_key_pair(old_key_path).context("Could not read old key pair")?;
+    let new_key_pair = read_key_pair(new_key_path).context("Could not read new key pair")?;
+    if old_key_pair.public_key() == new_key_pair.public_key() {
+        bail!("Old and new key pair are the same, use another key pair to re-sign the lock file");
+    }
+
+    let resign = project
+        .resign(&old_key_pair.public_key(), &new_key_pair)
+        .await?;
+
+    for (previous_schema_id, schema_id) in &resign.schema_ids {
+        println!("{} {}", style(schema_id.name()).bold(), style("→").dim());
+        println!("  {}", style(previous_schema_id).red());
+        println!("  {}", style(schema_id).green());
+    }
+    println!();
+
+    println!(
+        "{} {}",
+        style("!").yellow(),
+        style("Re-signing changes the ids of all schemas!")
+            .yellow()
+            .bold()
+    );
+    println!(
+        "All commits get signed by {}. Nodes which received the previous commits keep the old \
+        schemas, update all applications to the new schema ids and deploy the lock file again. \
+        Bootstrap documents get removed and are created again by the next build.",
+        style(new_key_pair.public_key()).bold()
+    );
+    println!();
+
+    let commits = resign
+        .lock_file
+        .commits
+        .as_ref()
+        .map_or(0, |commits| commits.len());
+    let confirmed = Confirm::new()
+        .with_prompt(format!("Do you want to re-sign {commits} commits?"))
+        .interact()?;
+    if !confirmed {
+        println!("Abort. Lock file was not changed.");
+        return Ok(());
+    }
+
+    project.write_resign(&resign)?;
+    println!(
+        "Successfully re-signed {commits} commits of {} file, build with {} from now on",
+        project.lock_path.display(),
+        new_key_path.display()
+    );
+
+    Ok(())
+}
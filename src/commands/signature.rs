@@ -0,0 +1,88 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use console::style;
+use fishy::lock_file::LockFile;
+use fishy::lock_signature::{signature_path, LockSignature};
+use fishy::utils::files::absolute_path;
+use fishy::utils::key_pair::read_key_pair;
+use fishy::verify_lock_file;
+use p2panda_rs::identity::PublicKey;
+
+use crate::terminal::{print_title, print_variable};
+
+/// Sign the lock file with the key pair file, writing the signature next to it.
+pub async fn sign(lock_path: &Path, private_key_path: &Path) -> Result<()> {
+    let signature_path = signature_path(lock_path);
+
+    print_title("Sign lock file with key pair");
+    print_variable("lock_path", absolute_path(lock_path)?.display());
+    print_variable(
+        "private_key_path",
+        absolute_path(private_key_path)?.display(),
+    );
+    println!();
+
+    // Make sure we only sign lock files which can be loaded again
+    LockFile::from_path(lock_path).context(format!(
+        "Try reading lock file from path '{}'",
+        lock_path.display()
+    ))?;
+
+    let key_pair = read_key_pair(private_key_path)?;
+    let signature = LockSignature::sign(&fs::read(lock_path)?, &key_pair).await?;
+    signature.write(&signature_path)?;
+
+    print_variable("public_key", signature.public_key);
+    println!(
+        "Successfully written signature to {} file",
+        signature_path.display()
+    );
+
+    Ok(())
+}
+
+/// Check the signature and commits of the lock file.
+///
+/// The signature needs to be made by the given public key, or by the key pair file if none is
+/// given.
+pub async fn verify(
+    lock_path: &Path,
+    public_key: Option<&str>,
+    private_key_path: &Path,
+) -> Result<()> {
+    let signature_path = signature_path(lock_path);
+
+    let public_key = match public_key {
+        Some(public_key) => PublicKey::new(public_key).context("Invalid public key")?,
+        None if private_key_path.exists() => read_key_pair(private_key_path)?.public_key(),
+        None => bail!("No key pair file found, pass the expected signer with --public-key"),
+    };
+
+    print_title("Verify signature and commits of lock file");
+    print_variable("lock_path", absolute_path(lock_path)?.display());
+    print_variable("signature_path", absolute_path(&signature_path)?.display());
+    print_variable("public_key", public_key);
+    println!();
+
+    let signature = LockSignature::from_path(&signature_path)
+        .context("Could not read signature file, create it with `fishy sign`")?;
+    signature.verify(&fs::read(lock_path)?, &public_key)?;
+    println!("{} Lock file is signed by {public_key}", style("✓").green());
+
+    let lock_file = LockFile::from_path(lock_path).context(format!(
+        "Try reading lock file from path '{}'",
+        lock_path.display()
+    ))?;
+    verify_lock_file(&lock_file).await?;
+    println!(
+        "{} All {} commits are valid",
+        style("✓").green(),
+        lock_file.commits.map_or(0, |commits| commits.len())
+    );
+
+    Ok(())
+}
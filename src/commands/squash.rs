@@ -0,0 +1,83 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use anyhow::{bail, Result};
+use console::style;
+use dialoguer::Confirm;
+use fishy::project::Project;
+use fishy::utils::files::absolute_path;
+
+use crate::terminal::{print_title, print_variable};
+
+/// Replace the history of the lock file with the create operations of the current schemas.
+pub async fn squash(project: Project) -> Result<()> {
+    print_title("Squash history of lock file");
+    print_variable("lock_path", absolute_path(&project.lock_path)?.display());
+    print_variable(
+        "private_key_path",
+        absolute_path(&project.private_key_path)?.display(),
+    );
+    println!();
+
+    if !project.lock_path.exists() {
+        bail!(
+            "No lock file found at '{}', nothing to squash",
+            project.lock_path.display()
+        );
+    }
+
+    let squash = project.squash().await?;
+    if squash
+        .schema_ids
+        .iter()
+        .all(|(previous_schema_id, schema_id)| previous_schema_id == schema_id)
+    {
+        println!("Lock file only contains create operations. Nothing to squash.");
+        return Ok(());
+    }
+
+    for (previous_schema_id, schema_id) in &squash.schema_ids {
+        println!("{} {}", style(schema_id.name()).bold(), style("→").dim());
+        println!("  {}", style(previous_schema_id).red());
+        println!("  {}", style(schema_id).green());
+    }
+    println!();
+
+    println!(
+        "{} {}",
+        style("!").yellow(),
+        style("Squashing changes the ids of all schemas!")
+            .yellow()
+            .bold()
+    );
+    println!(
+        "Nodes which received the previous commits keep the old schemas and reject the squashed \
+        ones signed with the same key pair. Deploy the squashed lock file to fresh nodes or use a \
+        new key pair and update all applications to the new schema ids. Bootstrap documents get \
+        removed and are created again by the next build."
+    );
+    println!();
+
+    let commits = squash
+        .lock_file
+        .commits
+        .as_ref()
+        .map_or(0, |commits| commits.len());
+    let confirmed = Confirm::new()
+        .with_prompt(format!(
+            "Do you want to squash {} commits into {commits}?",
+            squash.previous_commits
+        ))
+        .interact()?;
+    if !confirmed {
+        println!("Abort. Lock file was not changed.");
+        return Ok(());
+    }
+
+    project.write_squash(&squash)?;
+    println!(
+        "Successfully squashed {} file into {commits} commits",
+        project.lock_path.display()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,42 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use anyhow::Result;
+use console::style;
+use fishy::project::Project;
+use fishy::utils::files::absolute_path;
+
+use crate::terminal::{print_title, print_variable};
+
+/// Copy the lock files of external projects into the vendor directory.
+pub fn vendor(project: Project) -> Result<()> {
+    print_title("Vendor lock files of external schemas");
+    print_variable(
+        "schema_path",
+        absolute_path(&project.schema_path)?.display(),
+    );
+    print_variable("vendor_dir", absolute_path(&project.vendor_dir)?.display());
+    println!();
+
+    let vendored = project.vendor()?;
+    if vendored.is_empty() {
+        println!("Schema file does not relate to any external schemas. No vendoring required.");
+        return Ok(());
+    }
+
+    for lock_file in &vendored {
+        println!(
+            "{} {} ({})",
+            style("✓").green(),
+            lock_file.source,
+            lock_file.path.display()
+        );
+    }
+
+    println!();
+    println!(
+        "Vendored {} lock files, check them in to build without accessing external projects.",
+        vendored.len()
+    );
+
+    Ok(())
+}
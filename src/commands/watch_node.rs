@@ -0,0 +1,95 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use console::style;
+use fishy::client::Client;
+use fishy::watch::{SchemaEvent, SchemaWatcher};
+
+use crate::terminal::{print_title, print_variable};
+
+/// Print schemas which get created, updated or removed on a node until pressing Ctrl-C.
+pub async fn watch_node(client: Client, endpoint: &str, interval: u64) -> Result<()> {
+    print_title("Watch schema changes on a node");
+    print_variable("endpoint", endpoint);
+    print_variable("interval", format!("{interval}s"));
+    println!();
+
+    if interval == 0 {
+        bail!("Interval needs to be at least one second");
+    }
+
+    let mut watcher = SchemaWatcher::start(client)
+        .await
+        .context("Could not fetch schemas from node")?;
+    println!(
+        "Node knows {} schemas, waiting for changes. Press Ctrl-C to stop.",
+        watcher.schemas().count()
+    );
+    println!();
+
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            _ = tokio::time::sleep(Duration::from_secs(interval)) => (),
+        }
+
+        // Keep watching when the node is not reachable for a moment
+        let events = match watcher.poll().await {
+            Ok(events) => events,
+            Err(err) => {
+                eprintln!(
+                    "{} Could not fetch schemas from node: {err}",
+                    style("!").yellow()
+                );
+                continue;
+            }
+        };
+
+        for event in &events {
+            print_event(event);
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints a schema change with the owner of the schema.
+fn print_event(event: &SchemaEvent) {
+    match event {
+        SchemaEvent::Created(schema) => {
+            println!(
+                "{} Created {} by {}",
+                style("+").green(),
+                schema.schema_id,
+                style(schema.owner).dim()
+            );
+        }
+        SchemaEvent::Updated { previous, current } => {
+            println!(
+                "{} Updated {} to {} by {}",
+                style("~").yellow(),
+                previous.schema_id,
+                current.schema_id,
+                style(current.owner).dim()
+            );
+
+            let (added, removed) = event.field_changes();
+            for field in added {
+                println!("  Added field `{field}`");
+            }
+            for field in removed {
+                println!("  Removed field `{field}`");
+            }
+        }
+        SchemaEvent::Removed(schema) => {
+            println!(
+                "{} Removed {} by {}",
+                style("-").red(),
+                schema.schema_id,
+                style(schema.owner).dim()
+            );
+        }
+    }
+}
@@ -0,0 +1,159 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Check which p2panda nodes accept the data `fishy` produces.
+//!
+//! Entries and operations are encoded with the p2panda-rs version `fishy` was compiled against.
+//! Nodes running an aquadoggo release built on another p2panda-rs version reject them during
+//! deployment, the embedded release table helps to find a matching node before that happens.
+use p2panda_rs::operation::OperationVersion;
+use p2panda_rs::schema::SchemaId;
+use serde::Serialize;
+#[cfg(feature = "client")]
+use tracing::{debug, instrument};
+
+#[cfg(feature = "client")]
+use crate::client::Client;
+#[cfg(feature = "client")]
+use crate::error::Result;
+
+/// Version of p2panda-rs `fishy` was compiled against.
+pub const P2PANDA_RS_VERSION: &str = "0.8.1";
+
+/// Encoding of entries produced by this p2panda-rs version.
+pub const ENTRY_FORMAT: &str = "bamboo (ed25519, yasmf hash)";
+
+/// Released aquadoggo versions and the p2panda-rs version they were built on.
+pub const AQUADOGGO_RELEASES: &[AquadoggoRelease] = &[
+    AquadoggoRelease {
+        version: "0.4",
+        p2panda_rs_version: "0.7",
+    },
+    AquadoggoRelease {
+        version: "0.5",
+        p2panda_rs_version: "0.7",
+    },
+    AquadoggoRelease {
+        version: "0.6",
+        p2panda_rs_version: "0.8",
+    },
+    AquadoggoRelease {
+        version: "0.7",
+        p2panda_rs_version: "0.8",
+    },
+];
+
+/// Release of the aquadoggo node.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct AquadoggoRelease {
+    /// Minor version of aquadoggo, for example `0.7`.
+    pub version: &'static str,
+
+    /// Minor version of p2panda-rs this release was built on.
+    pub p2panda_rs_version: &'static str,
+}
+
+impl AquadoggoRelease {
+    /// Returns true if this release accepts the data produced by `fishy`.
+    ///
+    /// p2panda-rs does not guarantee compatible encodings across minor versions before 1.0.
+    pub fn is_compatible(&self) -> bool {
+        P2PANDA_RS_VERSION
+            .strip_prefix(self.p2panda_rs_version)
+            .is_some_and(|rest| rest.starts_with('.'))
+    }
+}
+
+/// Versions of the data produced by `fishy` and the aquadoggo releases accepting it.
+#[derive(Clone, Debug, Serialize)]
+pub struct CompatMatrix {
+    /// Version of p2panda-rs `fishy` was compiled against.
+    pub p2panda_rs_version: &'static str,
+
+    /// Version of the published operations.
+    pub operation_version: u64,
+
+    /// Encoding of the published entries.
+    pub entry_format: &'static str,
+
+    /// System schemas the published schema and field definitions follow.
+    pub system_schemas: Vec<SchemaId>,
+
+    /// All known aquadoggo releases.
+    pub releases: Vec<AquadoggoRelease>,
+}
+
+impl CompatMatrix {
+    /// Returns the aquadoggo releases which accept the data produced by `fishy`.
+    pub fn compatible_releases(&self) -> impl Iterator<Item = &AquadoggoRelease> {
+        self.releases
+            .iter()
+            .filter(|release| release.is_compatible())
+    }
+}
+
+/// Returns the versions of the data produced by the installed `fishy`.
+pub fn compat_matrix() -> CompatMatrix {
+    CompatMatrix {
+        p2panda_rs_version: P2PANDA_RS_VERSION,
+        operation_version: OperationVersion::V1.as_u64(),
+        entry_format: ENTRY_FORMAT,
+        system_schemas: vec![
+            SchemaId::SchemaDefinition(1),
+            SchemaId::SchemaFieldDefinition(1),
+        ],
+        releases: AQUADOGGO_RELEASES.to_vec(),
+    }
+}
+
+/// Feature of the GraphQL API a node needs to offer to accept the data of `fishy`.
+#[cfg(feature = "client")]
+#[derive(Clone, Debug)]
+pub struct NodeRequirement {
+    /// Human-readable description of the requirement.
+    pub description: &'static str,
+
+    /// True if the node offers this feature.
+    pub satisfied: bool,
+}
+
+/// Introspects the GraphQL API of a node and checks if it offers everything needed to deploy the
+/// data of `fishy`.
+///
+/// Nodes do not expose their version, the blob system schemas introduced with p2panda-rs 0.8 tell
+/// newer nodes apart from older ones.
+#[cfg(feature = "client")]
+#[instrument(skip_all)]
+pub async fn check_node(client: &Client) -> Result<Vec<NodeRequirement>> {
+    let root_fields = client.root_fields().await?;
+    let has_query = |name: &str| root_fields.queries.iter().any(|query| query == name);
+    let has_mutation = |name: &str| {
+        root_fields
+            .mutations
+            .iter()
+            .any(|mutation| mutation == name)
+    };
+
+    let requirements = vec![
+        NodeRequirement {
+            description: "Publishes entries and operations (`nextArgs` and `publish`)",
+            satisfied: has_query("nextArgs") && has_mutation("publish"),
+        },
+        NodeRequirement {
+            description: "Materializes schema definitions (`all_schema_definition_v1`)",
+            satisfied: has_query("all_schema_definition_v1"),
+        },
+        NodeRequirement {
+            description: "Built on p2panda-rs 0.8 (`all_blob_v1`)",
+            satisfied: has_query("all_blob_v1"),
+        },
+    ];
+    debug!(?requirements, "Checked node requirements");
+
+    Ok(requirements)
+}
+
+/// Returns true if all requirements are satisfied by the node.
+#[cfg(feature = "client")]
+pub fn is_compatible(requirements: &[NodeRequirement]) -> bool {
+    requirements.iter().all(|requirement| requirement.satisfied)
+}
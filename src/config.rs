@@ -0,0 +1,91 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Project settings in an optional `fishy.toml` file at the project root.
+//!
+//! ```toml
+//! # Keep generated files like the lock file, aliases and caches in this directory
+//! state_dir = ".fishy"
+//!
+//! # Split the lock file into one segment per schema
+//! lock_segments = true
+//!
+//! # Prefix all schema names with `myapp_` when building
+//! namespace = "myapp"
+//!
+//! # Only accept lock files with commits signed by these public keys
+//! trusted_keys = ["2f8e50c2ede6d936ecc3144187ff1c273808185cfbc5ff3d3748d1ff7353fc96"]
+//!
+//! # Key pair files schemas can be signed with, using `key = "venues"` in the schema file
+//! [keys]
+//! venues = "keys/venues.txt"
+//!
+//! # Schema bundles of other projects, relations can point at their schemas by name
+//! [dependencies]
+//! venues = { git = "https://github.com/example/venues", rev = "v2.0" }
+//! ```
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use p2panda_rs::identity::PublicKey;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::CONFIG_FILE_NAME;
+use crate::dependencies::Dependency;
+use crate::error::{Error, Result};
+use crate::utils::{env, files};
+
+/// Settings of a fishy project.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Directory for generated files, relative to the project root. They are kept next to the
+    /// schema file if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_dir: Option<PathBuf>,
+
+    /// Write the commits of every schema into its own lock file segment, so diffs of the lock file
+    /// only show the schemas which changed. Branches building in parallel still need to rebuild
+    /// on top of each other, the index changes with every build and new documents of the same key
+    /// pair claim the same logs.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub lock_segments: bool,
+
+    /// Namespace of all schemas, their names and relations between them get prefixed with
+    /// `<namespace>_` when building while the schema file keeps the short names.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+
+    /// Public keys of the maintainers allowed to sign commits, lock files with commits of other
+    /// authors are rejected. Every author is accepted if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trusted_keys: Vec<PublicKey>,
+
+    /// Named key pair files, relative to the project root. Schemas set one of these names to be
+    /// signed with it instead of the key pair of the project.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub keys: BTreeMap<String, PathBuf>,
+
+    /// Schema bundles published by other projects, local paths are relative to the project root.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub dependencies: BTreeMap<String, Dependency>,
+}
+
+impl Config {
+    /// Loads the `fishy.toml` file from the given path.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let data = env::interpolate(&files::read_file(&path)?, path.as_ref())?;
+        toml::from_str(&data).map_err(Error::InvalidConfig)
+    }
+
+    /// Loads the `fishy.toml` file of the project in the given directory, falling back to the
+    /// default settings if there is none.
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self> {
+        let path = dir.as_ref().join(CONFIG_FILE_NAME);
+
+        if path.exists() {
+            Self::from_path(path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
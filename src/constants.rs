@@ -1,11 +1,42 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+/// File name of file mapping stable alias names to current schema ids.
+pub const ALIASES_FILE_NAME: &str = "schema-aliases.toml";
+
+/// File name of file containing signed and encoded commits of bootstrap documents, next to the
+/// lock file.
+pub const BOOTSTRAP_LOCK_FILE_NAME: &str = "bootstrap.lock";
+
+/// Name of directory containing cached files fetched from remote sources.
+pub const CACHE_DIR_NAME: &str = ".fishy-cache";
+
+/// File name of file containing the project settings.
+pub const CONFIG_FILE_NAME: &str = "fishy.toml";
+
+/// File name of file recording the last deployment to every node, next to the lock file.
+pub const DEPLOYMENTS_FILE_NAME: &str = "deployments.toml";
+
+/// Comment at the beginning of all files generated by fishy.
+pub const GENERATED_FILE_HEADER: &str =
+    "# This file is automatically generated by fishy.\n# It is not intended for manual editing.";
+
 /// File name of file containing signed and encoded p2panda entries and operations.
-#[allow(dead_code)]
 pub const LOCK_FILE_NAME: &str = "schema.lock";
 
+/// Name of directory containing the lock file segments of every schema, next to the lock file.
+pub const LOCK_SEGMENTS_DIR_NAME: &str = "locks";
+
 /// File name of file containing hex-encoded ed25519 private key.
 pub const PRIVATE_KEY_FILE_NAME: &str = "secret.txt";
 
+/// Name of directory containing cached files inside of a configured state directory.
+pub const STATE_CACHE_DIR_NAME: &str = "cache";
+
 /// File name of file containing schema definition.
 pub const SCHEMA_FILE_NAME: &str = "schema.toml";
+
+/// Path standing for stdin when reading a file and for stdout when writing it.
+pub const STDIO_PATH: &str = "-";
+
+/// Name of directory containing lock files of external projects, checked into the repository.
+pub const VENDOR_DIR_NAME: &str = "vendor";
@@ -0,0 +1,184 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, File, Permissions};
+use std::os::unix::prelude::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use dialoguer::Password;
+use fishy::utils::files::{read_file, write_file};
+use openssl::hash::MessageDigest;
+use openssl::pkcs5::pbkdf2_hmac;
+use openssl::rand::rand_bytes;
+use openssl::symm::{decrypt_aead, encrypt_aead, Cipher};
+use serde::{Deserialize, Serialize};
+
+/// Environment variable holding the passphrase to encrypt and decrypt stored tokens.
+pub const PASSPHRASE_ENV_VAR: &str = "FISHY_PASSPHRASE";
+
+/// Number of PBKDF2 iterations to derive the encryption key from the passphrase.
+const KEY_ITERATIONS: usize = 100_000;
+
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+const TAG_LENGTH: usize = 16;
+
+/// Tokens to authenticate against protected nodes, stored per endpoint.
+///
+/// Every token is encrypted with AES-256-GCM using a key derived from a passphrase and stored
+/// hex-encoded together with salt, nonce and authentication tag:
+///
+/// ```toml
+/// [endpoints]
+/// "https://node.example.org/graphql" = "..."
+/// ```
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Credentials {
+    #[serde(default)]
+    endpoints: BTreeMap<String, String>,
+}
+
+impl Credentials {
+    /// Returns the path of the credentials file in the user's configuration directory.
+    pub fn default_path() -> Result<PathBuf> {
+        let config_dir = match env::var_os("XDG_CONFIG_HOME") {
+            Some(path) if !path.is_empty() => PathBuf::from(path),
+            _ => env::var_os("HOME")
+                .map(|home| PathBuf::from(home).join(".config"))
+                .ok_or_else(|| anyhow!("Could not find home directory to store credentials"))?,
+        };
+
+        Ok(config_dir.join("fishy").join("credentials.toml"))
+    }
+
+    /// Loads credentials from the given path, returns empty credentials if the file does not
+    /// exist yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let data = read_file(path)?;
+        toml::from_str(&data)
+            .with_context(|| format!("Could not parse credentials file '{}'", path.display()))
+    }
+
+    /// Writes credentials to the given path, readable only by the current user.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        write_file(path, &toml::to_string_pretty(&self)?)?;
+
+        let file = File::open(path)?;
+        file.set_permissions(Permissions::from_mode(0o600))?;
+
+        Ok(())
+    }
+
+    /// Returns all endpoints with a stored token.
+    pub fn endpoints(&self) -> impl Iterator<Item = &str> {
+        self.endpoints.keys().map(String::as_str)
+    }
+
+    /// Returns true if a token is stored for the given endpoint.
+    pub fn contains(&self, endpoint: &str) -> bool {
+        self.endpoints.contains_key(normalize(endpoint))
+    }
+
+    /// Encrypts and stores the token for the given endpoint, replacing any previous token.
+    pub fn insert(&mut self, endpoint: &str, token: &str, passphrase: &str) -> Result<()> {
+        let mut salt = [0; SALT_LENGTH];
+        let mut nonce = [0; NONCE_LENGTH];
+        let mut tag = [0; TAG_LENGTH];
+        rand_bytes(&mut salt)?;
+        rand_bytes(&mut nonce)?;
+
+        let key = derive_key(passphrase, &salt)?;
+        let ciphertext = encrypt_aead(
+            Cipher::aes_256_gcm(),
+            &key,
+            Some(&nonce),
+            &[],
+            token.as_bytes(),
+            &mut tag,
+        )?;
+
+        let stored = [&salt[..], &nonce[..], &tag[..], &ciphertext[..]].concat();
+        self.endpoints
+            .insert(normalize(endpoint).to_owned(), hex::encode(stored));
+
+        Ok(())
+    }
+
+    /// Removes the token of the given endpoint, returns false if there was none.
+    pub fn remove(&mut self, endpoint: &str) -> bool {
+        self.endpoints.remove(normalize(endpoint)).is_some()
+    }
+
+    /// Decrypts the token stored for the given endpoint.
+    pub fn token(&self, endpoint: &str, passphrase: &str) -> Result<Option<String>> {
+        let stored = match self.endpoints.get(normalize(endpoint)) {
+            Some(stored) => hex::decode(stored).context("Stored token is not hex-encoded")?,
+            None => return Ok(None),
+        };
+
+        if stored.len() < SALT_LENGTH + NONCE_LENGTH + TAG_LENGTH {
+            bail!("Stored token for '{endpoint}' is too short");
+        }
+
+        let (salt, rest) = stored.split_at(SALT_LENGTH);
+        let (nonce, rest) = rest.split_at(NONCE_LENGTH);
+        let (tag, ciphertext) = rest.split_at(TAG_LENGTH);
+
+        let key = derive_key(passphrase, salt)?;
+        let token = decrypt_aead(
+            Cipher::aes_256_gcm(),
+            &key,
+            Some(nonce),
+            &[],
+            ciphertext,
+            tag,
+        )
+        .map_err(|_| anyhow!("Could not decrypt token for '{endpoint}', wrong passphrase?"))?;
+
+        Ok(Some(String::from_utf8(token)?))
+    }
+}
+
+/// Returns the passphrase from the environment or asks the user for it.
+pub fn read_passphrase(confirm: bool) -> Result<String> {
+    if let Ok(passphrase) = env::var(PASSPHRASE_ENV_VAR) {
+        return Ok(passphrase);
+    }
+
+    let mut prompt = Password::new();
+    prompt.with_prompt("? Passphrase to encrypt stored tokens");
+    if confirm {
+        prompt.with_confirmation("? Repeat passphrase", "Passphrases do not match");
+    }
+
+    Ok(prompt.interact()?)
+}
+
+/// Derives a 256-bit encryption key from the passphrase.
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32]> {
+    let mut key = [0; 32];
+    pbkdf2_hmac(
+        passphrase.as_bytes(),
+        salt,
+        KEY_ITERATIONS,
+        MessageDigest::sha256(),
+        &mut key,
+    )?;
+    Ok(key)
+}
+
+/// Ignores trailing slashes, so both spellings of an endpoint share the same token.
+fn normalize(endpoint: &str) -> &str {
+    endpoint.trim_end_matches('/')
+}
@@ -0,0 +1,267 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Schema bundles published by other projects, declared in the `[dependencies]` section of
+//! `fishy.toml`.
+//!
+//! A bundle is the lock file of another project, published in a git repository, on a registry or
+//! found on the local file system. The ids of the latest schema versions in it are available to
+//! relations by name, as if the schemas were defined in the schema file:
+//!
+//! ```toml
+//! [dependencies]
+//! venues = { git = "https://github.com/example/venues", rev = "v2.0" }
+//! tickets = { registry = "https://schemas.example.org", version = "1.4.0" }
+//! shared = { path = "../shared" }
+//! ```
+//!
+//! Registries serve the lock file of every published version at
+//! `<registry>/<dependency>/<version>/schema.lock`.
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use p2panda_rs::hash::Hash;
+use p2panda_rs::schema::{SchemaId, SchemaName};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::constants::LOCK_FILE_NAME;
+use crate::error::{Error, Result};
+use crate::external::external_schema_ids;
+use crate::lock_file::LockFile;
+use crate::schema_file::{system_schema_id, RelationId, SchemaField, SchemaFile};
+use crate::utils::{files, git};
+
+/// Source of a schema bundle the project depends on, pinned to a revision or version.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum Dependency {
+    /// Lock file in a git repository at the given revision, `schema.lock` at the root of the
+    /// repository if no path is set.
+    Git {
+        git: String,
+        rev: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        path: Option<PathBuf>,
+    },
+
+    /// Lock file of a published version on a registry.
+    Registry { registry: String, version: String },
+
+    /// Project directory or lock file on the local file system, relative to the base directory.
+    Path {
+        path: PathBuf,
+        #[serde(skip)]
+        base_dir: PathBuf,
+    },
+}
+
+impl Dependency {
+    /// Returns the dependency with local paths resolved relative to the given directory.
+    pub(crate) fn relative_to(self, dir: &Path) -> Self {
+        match self {
+            Self::Path { path, .. } => Self::Path {
+                path,
+                base_dir: dir.to_path_buf(),
+            },
+            dependency => dependency,
+        }
+    }
+
+    /// Returns the path to the lock file of this dependency, fetching and caching it from remote
+    /// sources.
+    async fn lock_path(&self, name: &str, cache_dir: &Path) -> Result<PathBuf> {
+        match self {
+            Self::Git { git, rev, path } => git::fetch_lock_file(
+                git,
+                rev,
+                path.as_deref().unwrap_or(Path::new(LOCK_FILE_NAME)),
+                cache_dir,
+            ),
+            Self::Registry { registry, version } => {
+                let url = format!(
+                    "{}/{name}/{version}/{LOCK_FILE_NAME}",
+                    registry.trim_end_matches('/')
+                );
+                fetch_registry_file(name, &url, cache_dir).await
+            }
+            Self::Path { path, base_dir } => {
+                let path = base_dir.join(path);
+                let lock_path = if path.is_dir() {
+                    path.join(LOCK_FILE_NAME)
+                } else {
+                    path
+                };
+
+                if !lock_path.is_file() {
+                    return Err(Error::InvalidDependency(
+                        name.to_owned(),
+                        format!("No lock file found at {}", lock_path.display()),
+                    ));
+                }
+
+                Ok(lock_path)
+            }
+        }
+    }
+}
+
+impl Display for Dependency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Git { git, rev, .. } => write!(f, "{git} ({rev})"),
+            Self::Registry { registry, version } => write!(f, "{registry} ({version})"),
+            Self::Path { path, .. } => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Schemas a dependency resolved to when building, recorded in the lock file.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ResolvedDependency {
+    /// Git repository, registry or local path with revision or version of the dependency.
+    pub source: String,
+
+    /// Hash of the content of the resolved lock file.
+    pub checksum: Hash,
+
+    /// Ids of the latest versions of all schemas in the bundle.
+    pub schemas: BTreeMap<SchemaName, SchemaId>,
+}
+
+/// Resolves all dependencies and replaces relations by name to their schemas with the schema
+/// ids, returning the resolution of every dependency.
+///
+/// Only relations to schemas which are neither defined in the schema file nor a system schema
+/// get resolved. A schema name published by more than one dependency can not be used in
+/// relations, these need to point at the schema id instead.
+#[instrument(skip_all)]
+pub async fn resolve_dependencies(
+    schema_file: &mut SchemaFile,
+    dependencies: &BTreeMap<String, Dependency>,
+    cache_dir: &Path,
+) -> Result<BTreeMap<String, ResolvedDependency>> {
+    let mut resolved = BTreeMap::new();
+
+    for (name, dependency) in dependencies {
+        let lock_path = dependency.lock_path(name, cache_dir).await?;
+        let checksum = Hash::new_from_bytes(files::read_file(&lock_path)?.as_bytes());
+        let schemas = external_schema_ids(&lock_path)
+            .await
+            .map_err(|err| Error::InvalidDependency(name.clone(), err.to_string()))?;
+        debug!("Resolved dependency {name} from {dependency}");
+
+        resolved.insert(
+            name.clone(),
+            ResolvedDependency {
+                source: dependency.to_string(),
+                checksum,
+                schemas,
+            },
+        );
+    }
+
+    let local_names: Vec<SchemaName> = schema_file.iter().map(|(name, _)| name.clone()).collect();
+
+    for (_, schema_definition) in schema_file.iter_mut() {
+        for (_, schema_field) in schema_definition.fields.iter_mut() {
+            let SchemaField::Relation { schema, .. } = schema_field else {
+                continue;
+            };
+
+            let RelationId::Name(schema_name) = &schema.id else {
+                continue;
+            };
+
+            if schema.external.is_some()
+                || local_names.contains(schema_name)
+                || system_schema_id(schema_name).is_some()
+            {
+                continue;
+            }
+
+            let matches: Vec<(&String, &SchemaId)> = resolved
+                .iter()
+                .filter_map(|(name, dependency)| {
+                    dependency
+                        .schemas
+                        .get(schema_name)
+                        .map(|schema_id| (name, schema_id))
+                })
+                .collect();
+
+            match matches.as_slice() {
+                [] => (),
+                [(_, schema_id)] => schema.id = RelationId::Id((*schema_id).clone()),
+                _ => {
+                    return Err(Error::AmbiguousDependencySchema(
+                        schema_name.clone(),
+                        matches
+                            .iter()
+                            .map(|(name, _)| name.as_str())
+                            .collect::<Vec<&str>>()
+                            .join(", "),
+                    ))
+                }
+            }
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// Downloads a lock file from a registry and returns its local path.
+///
+/// Published versions do not change, downloads are cached in `cache_dir` per URL and only fetched
+/// once.
+async fn fetch_registry_file(name: &str, url: &str, cache_dir: &Path) -> Result<PathBuf> {
+    let key = Hash::new_from_bytes(url.as_bytes());
+    let file_path = cache_dir
+        .join("registry")
+        .join(format!("{}.lock", key.as_str()));
+
+    if file_path.exists() {
+        return Ok(file_path);
+    }
+
+    let data = download(url)
+        .await
+        .map_err(|err| Error::InvalidDependency(name.to_owned(), err))?;
+
+    // Make sure we only cache valid lock files
+    data.parse::<LockFile>()
+        .map_err(|err| Error::InvalidDependency(name.to_owned(), err.to_string()))?;
+
+    fs::create_dir_all(
+        file_path
+            .parent()
+            .expect("Cache file is inside of a directory"),
+    )?;
+    fs::write(&file_path, data)?;
+
+    Ok(file_path)
+}
+
+/// Returns the content of the given URL.
+#[cfg(feature = "client")]
+async fn download(url: &str) -> Result<String, String> {
+    let response = reqwest::get(url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| format!("Could not download {url}: {err}"))?;
+
+    response
+        .text()
+        .await
+        .map_err(|err| format!("Could not download {url}: {err}"))
+}
+
+/// Returns the content of the given URL.
+#[cfg(not(feature = "client"))]
+async fn download(url: &str) -> Result<String, String> {
+    Err(format!(
+        "Downloading {url} requires the `client` feature of fishy"
+    ))
+}
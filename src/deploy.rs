@@ -0,0 +1,346 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::fmt::Display;
+
+use async_trait::async_trait;
+use serde::Serialize;
+#[cfg(feature = "client")]
+use tokio_util::sync::CancellationToken;
+use tracing::{info, instrument};
+
+use crate::error::{Error, Result};
+use crate::lock_file::Commit;
+
+/// Transport to publish commits on a p2panda node.
+///
+/// `Client` implements this trait to publish via GraphQL on a remote node. Applications embedding
+/// a node can implement it against their local store directly.
+#[async_trait]
+pub trait Publisher {
+    /// Publishes a single commit.
+    ///
+    /// Commits the node refused to accept are returned as `PublishOutcome::Rejected`, errors are
+    /// reserved for failures to reach the node.
+    async fn publish(&self, commit: &Commit) -> Result<PublishOutcome>;
+}
+
+/// Result of publishing a single commit.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PublishOutcome {
+    /// Commit was published.
+    Published,
+
+    /// Node already knew about this commit, nothing was published.
+    AlreadyKnown,
+
+    /// Commit was not sent to the node.
+    Skipped { reason: SkipReason },
+
+    /// Node refused to accept the commit.
+    Rejected { node_error: String },
+}
+
+impl PublishOutcome {
+    /// Returns the class of validation failure if the node rejected the commit.
+    pub fn rejection(&self) -> Option<Rejection> {
+        match self {
+            Self::Rejected { node_error } => Some(Rejection::from_message(node_error)),
+            _ => None,
+        }
+    }
+
+    /// Converts a rejection into an `Error::CommitRejected`, for callers treating it as failure.
+    pub fn into_result(self, commit: &Commit) -> Result<Self> {
+        match self {
+            Self::Rejected { node_error } => {
+                Err(Error::CommitRejected(commit.entry_hash.clone(), node_error))
+            }
+            outcome => Ok(outcome),
+        }
+    }
+}
+
+/// Reason why a commit was not sent to the node.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SkipReason {
+    /// Deployment was cancelled before this commit.
+    Cancelled,
+
+    /// Node rejected an earlier commit this one depends on.
+    PreviousRejected,
+}
+
+impl Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cancelled => write!(f, "Deployment was cancelled"),
+            Self::PreviousRejected => write!(f, "Node rejected a previous commit"),
+        }
+    }
+}
+
+/// Commits with the outcome of publishing them, in the order of deployment.
+pub type DeployOutcomes = Vec<(Commit, PublishOutcome)>;
+
+/// Class of validation failure reported by a node when it rejected a commit.
+///
+/// Nodes can validate entries more strictly than the in-memory store fishy uses to build commits,
+/// classifying the failure helps to find out if the commits need to be rebuilt.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Rejection {
+    /// Sequence number, backlink or skiplink of the entry do not match the log on the node.
+    LogOrdering,
+
+    /// Log id of the entry is already in use or differs from the log on the node.
+    LogId,
+
+    /// Previous operation or document this commit depends on is unknown to or deleted on the node.
+    MissingDependency,
+
+    /// Any other validation failure.
+    Other,
+}
+
+impl Rejection {
+    /// Classifies a validation error message returned by the node.
+    pub fn from_message(message: &str) -> Self {
+        let message = message.to_lowercase();
+
+        if ["claimed log id", "entry is in log id", "expected log id"]
+            .iter()
+            .any(|pattern| message.contains(pattern))
+        {
+            Self::LogId
+        } else if [
+            "seq num",
+            "backlink",
+            "skiplink",
+            "expected log not found",
+            "sequence number",
+        ]
+        .iter()
+        .any(|pattern| message.contains(pattern))
+        {
+            Self::LogOrdering
+        } else if [
+            "previous operation",
+            "document is deleted",
+            "has been deleted",
+        ]
+        .iter()
+        .any(|pattern| message.contains(pattern))
+        {
+            Self::MissingDependency
+        } else {
+            Self::Other
+        }
+    }
+
+    /// Returns a suggestion on how to resolve this failure.
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            Self::LogOrdering => Some(
+                "The node's log already contains entries the commits were not built on, for example \
+                because other commits were published with the same key. Run `fishy build --endpoint \
+                <url>` to find updates on the node missing in the lock file, then rebuild with \
+                `fishy build --lock <lock file> --key <key file>` using the lock file and key pair \
+                the node's logs were created with",
+            ),
+            Self::LogId => Some(
+                "The node assigned a different log to this document or the log id is already in \
+                use by the same key. Rebuild with `fishy build --lock <lock file> --key <key file>` \
+                using the lock file and key pair the node's logs were created with",
+            ),
+            Self::MissingDependency => Some(
+                "The node is missing previous commits or has deleted documents this commit depends \
+                on. Deploy all commits in order from the same lock file with `fishy deploy --lock \
+                <lock file> --endpoint <url>`",
+            ),
+            Self::Other => None,
+        }
+    }
+}
+
+/// Number of published and skipped commits after a deployment.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct DeployReport {
+    /// Commits which have been published.
+    pub published: usize,
+
+    /// Commits the node already knew about.
+    pub skipped: usize,
+
+    /// True if the deployment was cancelled before all commits were published.
+    pub cancelled: bool,
+}
+
+impl DeployReport {
+    /// Counts the outcomes of a deployment.
+    pub fn from_outcomes(outcomes: &[(Commit, PublishOutcome)]) -> Self {
+        let mut report = Self::default();
+
+        for (_, outcome) in outcomes {
+            match outcome {
+                PublishOutcome::Published => report.published += 1,
+                PublishOutcome::AlreadyKnown => report.skipped += 1,
+                PublishOutcome::Skipped {
+                    reason: SkipReason::Cancelled,
+                } => report.cancelled = true,
+                _ => (),
+            }
+        }
+
+        report
+    }
+}
+
+/// Publishes all commits in the given order and returns the outcome of every commit.
+///
+/// As commits depend on each other, the deployment stops when the node rejected a commit and all
+/// following commits get skipped. The `on_progress` callback gets called after every commit with
+/// the outcome of publishing it.
+pub async fn deploy<P>(
+    publisher: &P,
+    commits: &[Commit],
+    on_progress: impl FnMut(&Commit, &PublishOutcome),
+) -> Result<DeployOutcomes>
+where
+    P: Publisher + ?Sized,
+{
+    deploy_until(publisher, commits, || false, on_progress).await
+}
+
+/// Publishes all commits in the given order until the token gets cancelled.
+///
+/// Cancelling stops the deployment gracefully after the commit currently being published, all
+/// following commits get skipped. As commits are published in order, deploying the same commits
+/// again continues where the cancelled deployment stopped.
+#[cfg(feature = "client")]
+pub async fn deploy_cancellable<P>(
+    publisher: &P,
+    commits: &[Commit],
+    token: &CancellationToken,
+    on_progress: impl FnMut(&Commit, &PublishOutcome),
+) -> Result<DeployOutcomes>
+where
+    P: Publisher + ?Sized,
+{
+    deploy_until(publisher, commits, || token.is_cancelled(), on_progress).await
+}
+
+#[instrument(skip_all, fields(commits = commits.len()))]
+async fn deploy_until<P>(
+    publisher: &P,
+    commits: &[Commit],
+    is_cancelled: impl Fn() -> bool,
+    mut on_progress: impl FnMut(&Commit, &PublishOutcome),
+) -> Result<DeployOutcomes>
+where
+    P: Publisher + ?Sized,
+{
+    let mut outcomes = Vec::with_capacity(commits.len());
+    let mut skip_reason = None;
+
+    for commit in commits {
+        if skip_reason.is_none() && is_cancelled() {
+            skip_reason = Some(SkipReason::Cancelled);
+        }
+
+        let outcome = match skip_reason {
+            Some(reason) => PublishOutcome::Skipped { reason },
+            None => publisher.publish(commit).await?,
+        };
+
+        if matches!(outcome, PublishOutcome::Rejected { .. }) {
+            skip_reason = Some(SkipReason::PreviousRejected);
+        }
+
+        on_progress(commit, &outcome);
+        outcomes.push((commit.clone(), outcome));
+    }
+
+    let report = DeployReport::from_outcomes(&outcomes);
+    info!(
+        published = report.published,
+        skipped = report.skipped,
+        cancelled = report.cancelled,
+        "Deployed commits"
+    );
+
+    Ok(outcomes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rejection;
+
+    #[test]
+    fn classifies_node_validation_messages() {
+        // Validation errors of p2panda-rs 0.8 as returned by aquadoggo, classification depends on
+        // their wording
+        let messages = [
+            (
+                "Entry's claimed seq num of 3 does not match expected seq num of 2 for given public key and log",
+                Rejection::LogOrdering,
+            ),
+            (
+                "Expected skiplink entry not found in store: public key 2f8e, log id 0, seq num 4",
+                Rejection::LogOrdering,
+            ),
+            (
+                "backlink and skiplink not valid for this sequence number",
+                Rejection::LogOrdering,
+            ),
+            (
+                "Expected log not found in store for: public key 2f8e, document id 0020",
+                Rejection::LogOrdering,
+            ),
+            (
+                "Entry's claimed log id of 1 does not match existing log id of 0 for given public key and document id",
+                Rejection::LogId,
+            ),
+            (
+                "Entry's claimed log id of 0 is already in use for given public key",
+                Rejection::LogId,
+            ),
+            (
+                "entry is in log id 1 but backlink entry in log id 0",
+                Rejection::LogId,
+            ),
+            (
+                "Expected log id 2 not found when calculating next args",
+                Rejection::LogId,
+            ),
+            (
+                "Previous operation 0020 not found in store",
+                Rejection::MissingDependency,
+            ),
+            ("Document is deleted", Rejection::MissingDependency),
+            (
+                "You are trying to update or delete a document which has been deleted",
+                Rejection::MissingDependency,
+            ),
+            ("Could not decode payload hash", Rejection::Other),
+        ];
+
+        for (message, rejection) in messages {
+            assert_eq!(Rejection::from_message(message), rejection, "{message}");
+        }
+    }
+
+    #[test]
+    fn hints_name_fishy_flags() {
+        for rejection in [Rejection::LogOrdering, Rejection::LogId] {
+            assert!(rejection
+                .hint()
+                .unwrap()
+                .contains("`fishy build --lock <lock file> --key <key file>`"));
+        }
+
+        assert!(Rejection::LogOrdering
+            .hint()
+            .unwrap()
+            .contains("`fishy build --endpoint <url>`"));
+        assert!(Rejection::Other.hint().is_none());
+    }
+}
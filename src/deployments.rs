@@ -0,0 +1,123 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Record of the last deployment to every node.
+//!
+//! After all commits were deployed, the deployments file next to the lock file notes when that
+//! happened and which commit was the latest one, for every endpoint:
+//!
+//! ```toml
+//! ["http://localhost:2020/graphql"]
+//! timestamp = "2024-03-12T09:41:07Z"
+//! commits = 12
+//! last_commit = "0020c3accb0b0c8822ecc0309190e23de5f7f6c82f660ce08023a1d74e055a3d7c4d"
+//! ```
+//!
+//! The file only describes deployments from this machine, nodes can receive the same commits from
+//! other sources.
+use std::collections::btree_map::Iter;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::SystemTime;
+
+use p2panda_rs::hash::Hash;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::GENERATED_FILE_HEADER;
+use crate::error::{Error, Result};
+use crate::lock_file::Commit;
+use crate::utils::{files, time};
+
+/// Last deployment to every endpoint.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Deployments(BTreeMap<String, DeployStamp>);
+
+impl Deployments {
+    /// Returns the last deployment to the given endpoint.
+    pub fn get(&self, endpoint: &str) -> Option<&DeployStamp> {
+        self.0.get(endpoint)
+    }
+
+    /// Returns an iterator over all endpoints and their last deployment.
+    pub fn iter(&self) -> Iter<'_, String, DeployStamp> {
+        self.0.iter()
+    }
+
+    /// Sets the last deployment to the given endpoint, replacing the previous one.
+    pub fn insert(&mut self, endpoint: &str, stamp: DeployStamp) {
+        self.0.insert(endpoint.to_owned(), stamp);
+    }
+
+    /// Writes deployments to a .toml file at the given path.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let deployments_str = format!(
+            "{}\n\n{}",
+            GENERATED_FILE_HEADER,
+            toml::to_string_pretty(&self)?
+        );
+
+        files::write_file(path, &deployments_str)
+    }
+}
+
+/// Time and state of the lock files when all commits were deployed to a node.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeployStamp {
+    /// Time of the deployment, formatted as RFC 3339.
+    pub timestamp: String,
+
+    /// Number of deployed commits.
+    pub commits: usize,
+
+    /// Entry hash of the latest deployed commit, the node is up-to-date as long as it is the
+    /// latest commit of the lock files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_commit: Option<Hash>,
+}
+
+impl DeployStamp {
+    /// Returns a stamp of deploying the given commits now.
+    pub fn new(commits: &[Commit]) -> Self {
+        Self {
+            timestamp: time::format_rfc3339(SystemTime::now()),
+            commits: commits.len(),
+            last_commit: commits.last().map(|commit| commit.entry_hash.clone()),
+        }
+    }
+}
+
+/// Loads deployments from a .toml file at the given path.
+///
+/// Returns no deployments if the file does not exist yet.
+pub fn load(path: impl AsRef<Path>) -> Result<Deployments> {
+    if !path.as_ref().exists() {
+        return Ok(Deployments::default());
+    }
+
+    let data = files::read_file(&path)?;
+    let deployments: Deployments = toml::from_str(&data).map_err(Error::InvalidDeploymentsFile)?;
+    Ok(deployments)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::temp_dir;
+
+    use super::{load, DeployStamp, Deployments};
+
+    #[test]
+    fn writes_and_loads_deployments() {
+        let path = temp_dir("deployments").join("deployments.toml");
+        assert_eq!(load(&path).unwrap(), Deployments::default());
+
+        let mut deployments = Deployments::default();
+        deployments.insert("http://localhost:2020/graphql", DeployStamp::new(&[]));
+        deployments.write(&path).unwrap();
+
+        let loaded = load(&path).unwrap();
+        assert_eq!(loaded, deployments);
+
+        let stamp = loaded.get("http://localhost:2020/graphql").unwrap();
+        assert_eq!(stamp.commits, 0);
+        assert_eq!(stamp.last_commit, None);
+    }
+}
@@ -0,0 +1,544 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Point at the line and column of invalid parts of a schema file.
+//!
+//! Schema files are deserialized with untagged and flattened types, which loses the location of
+//! errors inside of them. When parsing fails, the file gets parsed again into a tree remembering
+//! the location of every key and value. Walking this tree finds the first invalid key or value and
+//! suggests valid alternatives.
+use std::fmt::Display;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use p2panda_rs::schema::validate::validate_field_name;
+use p2panda_rs::schema::{SchemaDescription, SchemaName};
+use serde::de::{Deserialize, DeserializeOwned, Deserializer, MapAccess, SeqAccess, Visitor};
+use toml::Spanned;
+
+use crate::schema_file::{Constraints, Include, Metadata, RelationSchema, SchemaKey};
+
+/// Location and description of an error in a file.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    /// Path of the file, if it was read from the file system.
+    pub path: Option<PathBuf>,
+
+    /// Line of the error, starting at 1.
+    pub line: usize,
+
+    /// Column of the error, starting at 1.
+    pub column: usize,
+
+    /// What is wrong.
+    pub message: String,
+
+    /// Suggestion how to fix it, for example a list of valid values.
+    pub hint: Option<String>,
+
+    /// Content of the line the error is located in.
+    pub source_line: String,
+}
+
+impl Diagnostic {
+    /// Returns a diagnostic pointing at the start of the given byte range in the data.
+    fn new(data: &str, span: Range<usize>, message: String, hint: Option<String>) -> Self {
+        let offset = span.start.min(data.len());
+        let before = &data[..offset];
+        let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+        let line_end = data[offset..]
+            .find('\n')
+            .map_or(data.len(), |index| offset + index);
+
+        Self {
+            path: None,
+            line: before.matches('\n').count() + 1,
+            column: data[line_start..offset].chars().count() + 1,
+            message,
+            hint,
+            source_line: data[line_start..line_end].trim_end_matches('\r').to_owned(),
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = self
+            .path
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|| "schema file".into());
+        writeln!(f, "{path}:{}:{}: {}", self.line, self.column, self.message)?;
+
+        let number = self.line.to_string();
+        let indent = " ".repeat(number.len());
+        writeln!(f, "{indent} |")?;
+        writeln!(f, "{number} | {}", self.source_line)?;
+        write!(f, "{indent} | {}^", " ".repeat(self.column - 1))?;
+
+        if let Some(hint) = &self.hint {
+            write!(f, "\n{indent} = hint: {hint}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Finds the location of the error which occurred when parsing the given schema file.
+///
+/// Falls back to the location reported by the TOML parser, for example for syntax errors.
+pub(crate) fn diagnose_schema_file(data: &str, error: toml::de::Error) -> Diagnostic {
+    let found = toml::from_str::<Spanned<Node>>(data)
+        .ok()
+        .and_then(|root| check_schema_file(&root));
+
+    match found {
+        Some((span, message, hint)) => Diagnostic::new(data, span, message, hint),
+        None => Diagnostic::new(
+            data,
+            error.span().unwrap_or(0..0),
+            error.message().to_owned(),
+            None,
+        ),
+    }
+}
+
+/// Location, message and optional hint of an error.
+type Finding = (Range<usize>, String, Option<String>);
+
+/// Schema names are validated by p2panda.
+const SCHEMA_NAME_HINT: &str = "schema names start with a letter, contain only letters, digits \
+    and underscores, do not end with an underscore and are at most 64 characters long";
+
+const FIELD_NAME_HINT: &str = "field names start with a letter, contain only letters, digits and \
+    underscores and are at most 64 characters long";
+
+const FIELD_TYPES: [&str; 9] = [
+    "str",
+    "int",
+    "float",
+    "bool",
+    "bytes",
+    "relation",
+    "relation_list",
+    "pinned_relation",
+    "pinned_relation_list",
+];
+
+const SCHEMA_KEYS: [&str; 8] = [
+    "description",
+    "fields",
+    "rename_from",
+    "enabled",
+    "license",
+    "authors",
+    "homepage",
+    "key",
+];
+
+const FIELD_KEYS: [&str; 4] = ["type", "rename_from", "bootstrap", "constraints"];
+
+const RELATION_KEYS: [&str; 3] = ["type", "schema", "rename_from"];
+
+/// Key the TOML deserializer uses to pass datetimes as a map.
+const DATETIME_FIELD: &str = "$__toml_private_datetime";
+
+/// Part of a TOML document which remembers the location of its keys and values.
+#[derive(Debug)]
+enum Node {
+    Table(Vec<(Spanned<String>, Spanned<Node>)>),
+    Array(Vec<Spanned<Node>>),
+    Value(toml::Value),
+}
+
+impl Node {
+    /// Converts the node back into a TOML value, to deserialize it into the actual types.
+    fn to_value(&self) -> toml::Value {
+        match self {
+            Node::Table(entries) => toml::Value::Table(
+                entries
+                    .iter()
+                    .map(|(key, value)| (key.get_ref().clone(), value.get_ref().to_value()))
+                    .collect(),
+            ),
+            Node::Array(items) => {
+                toml::Value::Array(items.iter().map(|item| item.get_ref().to_value()).collect())
+            }
+            Node::Value(value) => value.clone(),
+        }
+    }
+
+    /// Returns the value of the given key if this is a table.
+    fn get(&self, key: &str) -> Option<&Spanned<Node>> {
+        match self {
+            Node::Table(entries) => entries
+                .iter()
+                .find(|(entry_key, _)| entry_key.get_ref() == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(NodeVisitor)
+    }
+}
+
+struct NodeVisitor;
+
+impl<'de> Visitor<'de> for NodeVisitor {
+    type Value = Node;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("TOML value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> std::result::Result<Node, E> {
+        Ok(Node::Value(toml::Value::Boolean(value)))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> std::result::Result<Node, E> {
+        Ok(Node::Value(toml::Value::Integer(value)))
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, value: u64) -> std::result::Result<Node, E> {
+        i64::try_from(value)
+            .map(|value| Node::Value(toml::Value::Integer(value)))
+            .map_err(E::custom)
+    }
+
+    fn visit_f64<E>(self, value: f64) -> std::result::Result<Node, E> {
+        Ok(Node::Value(toml::Value::Float(value)))
+    }
+
+    fn visit_str<E>(self, value: &str) -> std::result::Result<Node, E> {
+        Ok(Node::Value(toml::Value::String(value.to_owned())))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> std::result::Result<Node, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Node::Array(items))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> std::result::Result<Node, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut entries: Vec<(Spanned<String>, Spanned<Node>)> = Vec::new();
+        while let Some(key) = map.next_key()? {
+            entries.push((key, map.next_value()?));
+        }
+
+        // Datetimes are represented as a map with a single private key
+        if let [(key, value)] = entries.as_slice() {
+            if key.get_ref() == DATETIME_FIELD {
+                if let Node::Value(toml::Value::String(datetime)) = value.get_ref() {
+                    if let Ok(datetime) = datetime.parse() {
+                        return Ok(Node::Value(toml::Value::Datetime(datetime)));
+                    }
+                }
+            }
+        }
+
+        Ok(Node::Table(entries))
+    }
+}
+
+/// Checks the top level of a schema file, containing includes, the prefix and all schemas.
+fn check_schema_file(root: &Spanned<Node>) -> Option<Finding> {
+    let Node::Table(entries) = root.get_ref() else {
+        return None;
+    };
+
+    for (key, value) in entries {
+        let finding = match key.get_ref().as_str() {
+            "include" => check_type::<Vec<Include>>(
+                value,
+                "invalid include",
+                r#"use `include = ["schemas/*.toml"]` or `include = [{ git = "...", path = "...", rev = "..." }]`"#,
+            ),
+            "prefix" => check_type::<String>(value, "invalid prefix", "the prefix is a string"),
+            name => check_schema(name, key.span(), value),
+        };
+
+        if finding.is_some() {
+            return finding;
+        }
+    }
+
+    None
+}
+
+/// Checks the name, keys and fields of a schema definition.
+fn check_schema(name: &str, key_span: Range<usize>, value: &Spanned<Node>) -> Option<Finding> {
+    if SchemaName::new(name).is_err() {
+        return Some((
+            key_span,
+            format!("invalid schema name `{name}`"),
+            Some(SCHEMA_NAME_HINT.into()),
+        ));
+    }
+
+    let Node::Table(entries) = value.get_ref() else {
+        return Some((
+            value.span(),
+            format!("schema `{name}` needs to be a table"),
+            Some(format!(
+                "define it with `[{name}]` followed by a description and fields"
+            )),
+        ));
+    };
+
+    for (entry_key, entry_value) in entries {
+        let finding = match entry_key.get_ref().as_str() {
+            "description" => check_description(entry_value),
+            "fields" => check_fields(name, entry_value),
+            "rename_from" => check_schema_name(entry_value),
+            "enabled" => check_type::<bool>(
+                entry_value,
+                "invalid value",
+                "use `enabled = false` to skip this schema",
+            ),
+            "license" | "homepage" => {
+                check_type::<String>(entry_value, "invalid value", "this is a string")
+            }
+            "authors" => check_type::<Vec<String>>(
+                entry_value,
+                "invalid authors",
+                r#"use a list of strings like `authors = ["Jane <jane@example.org>"]`"#,
+            ),
+            "key" => check_type::<SchemaKey>(
+                entry_value,
+                "invalid key",
+                r#"use the name of a key in fishy.toml like `key = "events"` or a key pair file like `key = { path = "keys/events.txt" }`"#,
+            ),
+            other => check_metadata_key(entry_key, other, &SCHEMA_KEYS),
+        };
+
+        if finding.is_some() {
+            return finding;
+        }
+    }
+
+    ["description", "fields"].iter().find_map(|required| {
+        value.get_ref().get(required).is_none().then(|| {
+            (
+                key_span.clone(),
+                format!("schema `{name}` is missing `{required}`"),
+                None,
+            )
+        })
+    })
+}
+
+/// Checks all fields of a schema.
+fn check_fields(schema_name: &str, value: &Spanned<Node>) -> Option<Finding> {
+    let Node::Table(entries) = value.get_ref() else {
+        return Some((
+            value.span(),
+            "fields need to be a table".into(),
+            Some(format!(
+                r#"define them with `[{schema_name}.fields]` followed by `name = {{ type = "str" }}`"#
+            )),
+        ));
+    };
+
+    entries
+        .iter()
+        .find_map(|(key, value)| check_field(key, value))
+}
+
+/// Checks the name, type and keys of a single field.
+fn check_field(key: &Spanned<String>, value: &Spanned<Node>) -> Option<Finding> {
+    let name = key.get_ref();
+    if !validate_field_name(name) {
+        return Some((
+            key.span(),
+            format!("invalid field name `{name}`"),
+            Some(FIELD_NAME_HINT.into()),
+        ));
+    }
+
+    let Node::Table(entries) = value.get_ref() else {
+        return Some((
+            value.span(),
+            format!("field `{name}` needs to be a table"),
+            Some(format!(r#"define it like `{name} = {{ type = "str" }}`"#)),
+        ));
+    };
+
+    let Some(field_type) = value.get_ref().get("type") else {
+        return Some((
+            key.span(),
+            format!("field `{name}` is missing `type`"),
+            Some(format!("valid types are {}", join(&FIELD_TYPES))),
+        ));
+    };
+
+    let is_relation = match field_type.get_ref() {
+        Node::Value(toml::Value::String(field_type))
+            if FIELD_TYPES.contains(&field_type.as_str()) =>
+        {
+            field_type.contains("relation")
+        }
+        _ => {
+            return Some((
+                field_type.span(),
+                format!("invalid field type {}", field_type.get_ref().to_value()),
+                Some(format!("valid types are {}", join(&FIELD_TYPES))),
+            ))
+        }
+    };
+
+    let valid_keys: &[&str] = if is_relation {
+        &RELATION_KEYS
+    } else {
+        &FIELD_KEYS
+    };
+
+    for (entry_key, entry_value) in entries {
+        let finding = match entry_key.get_ref().as_str() {
+            "type" => None,
+            "rename_from" => check_field_name(entry_value),
+            "bootstrap" if !is_relation => None,
+            "constraints" if !is_relation => check_type::<Constraints>(
+                entry_value,
+                "invalid constraints",
+                "valid constraints are `max_length`, `min`, `max` and `regex`",
+            ),
+            "schema" if is_relation => check_relation_schema(entry_value),
+            other => check_metadata_key(entry_key, other, valid_keys),
+        };
+
+        if finding.is_some() {
+            return finding;
+        }
+    }
+
+    if is_relation && value.get_ref().get("schema").is_none() {
+        return Some((
+            key.span(),
+            format!("relation field `{name}` is missing `schema`"),
+            Some(r#"point at another schema with `schema = { name = "..." }`"#.into()),
+        ));
+    }
+
+    None
+}
+
+/// Checks the schema a relation points at, including inline schema definitions.
+fn check_relation_schema(value: &Spanned<Node>) -> Option<Finding> {
+    if let Some(name) = value.get_ref().get("name") {
+        if let Some(finding) = check_schema_name(name) {
+            return Some(finding);
+        }
+    }
+
+    if let Some(inline) = value.get_ref().get("inline") {
+        // Inline schemas are only checked for their own keys and fields, their name is derived
+        // from the relation
+        if let Node::Table(_) = inline.get_ref() {
+            if let Some(finding) = check_schema("inline", inline.span(), inline) {
+                return Some(finding);
+            }
+        }
+    }
+
+    check_type::<RelationSchema>(
+        value,
+        "invalid relation schema",
+        r#"use `{ name = "..." }`, `{ id = "..." }` or `{ inline = { ... } }`, external schemas add `git` and `rev` or `path`"#,
+    )
+}
+
+fn check_description(value: &Spanned<Node>) -> Option<Finding> {
+    match value.get_ref() {
+        Node::Value(toml::Value::String(description)) => {
+            SchemaDescription::new(description).err().map(|_| {
+                (
+                    value.span(),
+                    "invalid schema description".into(),
+                    Some("descriptions are at most 256 characters long".into()),
+                )
+            })
+        }
+        _ => Some((
+            value.span(),
+            "invalid schema description".into(),
+            Some("the description is a string".into()),
+        )),
+    }
+}
+
+fn check_schema_name(value: &Spanned<Node>) -> Option<Finding> {
+    match value.get_ref() {
+        Node::Value(toml::Value::String(name)) if SchemaName::new(name).is_ok() => None,
+        other => Some((
+            value.span(),
+            format!("invalid schema name {}", other.to_value()),
+            Some(SCHEMA_NAME_HINT.into()),
+        )),
+    }
+}
+
+fn check_field_name(value: &Spanned<Node>) -> Option<Finding> {
+    match value.get_ref() {
+        Node::Value(toml::Value::String(name)) if validate_field_name(name) => None,
+        other => Some((
+            value.span(),
+            format!("invalid field name {}", other.to_value()),
+            Some(FIELD_NAME_HINT.into()),
+        )),
+    }
+}
+
+/// Reports unknown keys which are not metadata keys either.
+fn check_metadata_key(key: &Spanned<String>, name: &str, valid_keys: &[&str]) -> Option<Finding> {
+    if name.starts_with(Metadata::PREFIX) {
+        return None;
+    }
+
+    Some((
+        key.span(),
+        format!("unknown key `{name}`"),
+        Some(format!(
+            "valid keys are {} or metadata keys starting with `{}`",
+            join(valid_keys),
+            Metadata::PREFIX
+        )),
+    ))
+}
+
+/// Deserializes the value into the given type, reporting the error with a hint if it fails.
+fn check_type<T: DeserializeOwned>(
+    value: &Spanned<Node>,
+    message: &str,
+    hint: &str,
+) -> Option<Finding> {
+    T::deserialize(value.get_ref().to_value()).err().map(|err| {
+        (
+            value.span(),
+            format!("{message}: {}", err.message()),
+            Some(hint.into()),
+        )
+    })
+}
+
+/// Joins values as a list of inline code.
+fn join(values: &[&str]) -> String {
+    values
+        .iter()
+        .map(|value| format!("`{value}`"))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
@@ -0,0 +1,98 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Detect nodes serving schemas which do not match the lock file.
+//!
+//! Nodes generate a GraphQL API from the schemas they materialized. Introspecting it shows if a
+//! node lags behind the latest schema versions of the lock file or materialized them differently.
+use std::collections::BTreeSet;
+
+use p2panda_rs::schema::{FieldName, SchemaId};
+use tracing::{debug, instrument};
+
+use crate::build::get_previous_schemas;
+use crate::client::Client;
+use crate::error::Result;
+use crate::lock_file::LockFile;
+
+/// State of a schema in the GraphQL API of the node compared to the lock file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DriftStatus {
+    /// Node exposes the schema with the same fields as the lock file.
+    InSync,
+
+    /// Node does not expose the schema, it was not deployed yet or the node still serves an older
+    /// version.
+    Missing,
+
+    /// Node exposes the schema with other fields than the lock file.
+    Divergent {
+        /// Fields of the lock file which are missing in the API.
+        missing_fields: Vec<FieldName>,
+
+        /// Fields of the API which are not part of the lock file.
+        unexpected_fields: Vec<FieldName>,
+    },
+}
+
+/// Latest version of a schema of the lock file and its state on the node.
+#[derive(Clone, Debug)]
+pub struct SchemaDrift {
+    /// Id of the latest version of the schema in the lock file.
+    pub schema_id: SchemaId,
+
+    /// State of the schema in the GraphQL API of the node.
+    pub status: DriftStatus,
+}
+
+/// Compares the types the node exposes for every schema of the lock file with the committed
+/// fields, sorted by schema name.
+///
+/// Application fields of a schema are exposed in a GraphQL type named `<schema_id>Fields`.
+#[instrument(skip_all)]
+pub async fn find_drift(client: &Client, lock_file: &LockFile) -> Result<Vec<SchemaDrift>> {
+    let mut previous_schemas: Vec<_> = get_previous_schemas(lock_file)
+        .await?
+        .into_values()
+        .collect();
+    previous_schemas.sort_by_key(|previous| previous.schema.id().to_string());
+
+    let mut drifts = Vec::new();
+
+    for previous in previous_schemas {
+        let schema_id = previous.schema.id().to_owned();
+        let type_name = format!("{schema_id}Fields");
+
+        let status = match client.type_fields(&type_name).await? {
+            None => DriftStatus::Missing,
+            Some(fields) => {
+                let exposed: BTreeSet<&str> = fields.iter().map(String::as_str).collect();
+                let committed_fields = previous.schema.fields().keys();
+                let committed: BTreeSet<&str> =
+                    committed_fields.iter().map(String::as_str).collect();
+
+                let missing_fields: Vec<FieldName> = committed
+                    .difference(&exposed)
+                    .map(|name| name.to_string())
+                    .collect();
+                let unexpected_fields: Vec<FieldName> = exposed
+                    .difference(&committed)
+                    .map(|name| name.to_string())
+                    .collect();
+
+                if missing_fields.is_empty() && unexpected_fields.is_empty() {
+                    DriftStatus::InSync
+                } else {
+                    DriftStatus::Divergent {
+                        missing_fields,
+                        unexpected_fields,
+                    }
+                }
+            }
+        };
+
+        debug!(%schema_id, ?status, "Compared schema with node");
+        drifts.push(SchemaDrift { schema_id, status });
+    }
+
+    Ok(drifts)
+}
@@ -0,0 +1,64 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Detect new schemas which already exist on a node.
+//!
+//! When a lock file got lost, building the same schema definitions again creates new, parallel
+//! schema documents. Comparing the planned schemas with the ones a node already knows about helps
+//! to reuse the existing schemas instead.
+use p2panda_rs::schema::{SchemaId, SchemaName};
+
+use crate::build::{ChangeStatus, PlanSummary};
+use crate::client::{Client, RemoteSchema};
+use crate::error::Result;
+use crate::schema_file::SchemaFields;
+
+/// New schema which is identical to a schema already existing on the node.
+#[derive(Clone, Debug)]
+pub struct Duplicate {
+    /// Name of the schema.
+    pub name: SchemaName,
+
+    /// Id the new schema would get when committing it.
+    pub planned_schema_id: SchemaId,
+
+    /// Identical schema found on the node.
+    pub existing: RemoteSchema,
+}
+
+/// Returns all schemas which would be newly created although a schema with the same name,
+/// description and fields exists on the node already.
+pub async fn find_duplicates(client: &Client, summaries: &[PlanSummary]) -> Result<Vec<Duplicate>> {
+    let mut duplicates = Vec::new();
+
+    for summary in summaries {
+        if summary.status != ChangeStatus::Added {
+            continue;
+        }
+
+        let mut fields = SchemaFields::new();
+        for field in &summary.fields {
+            if let Some(current) = &field.current {
+                fields.insert(&field.name, current);
+            }
+        }
+
+        let existing = client
+            .schemas_by_name(&summary.name)
+            .await?
+            .into_iter()
+            .find(|remote| {
+                remote.description == summary.current_description.to_string()
+                    && remote.fields == fields
+            });
+
+        if let Some(existing) = existing {
+            duplicates.push(Duplicate {
+                name: summary.name.clone(),
+                planned_schema_id: summary.current_schema_id.clone(),
+                existing,
+            });
+        }
+    }
+
+    Ok(duplicates)
+}
@@ -0,0 +1,414 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use p2panda_rs::api::DomainError;
+use p2panda_rs::document::DocumentViewId;
+use p2panda_rs::entry::error::{DecodeEntryError, ValidateEntryError};
+use p2panda_rs::hash::Hash;
+use p2panda_rs::identity::error::KeyPairError;
+use p2panda_rs::operation::error::{
+    DecodeOperationError, EncodeOperationError, OperationBuilderError,
+};
+use p2panda_rs::schema::error::{
+    SchemaDescriptionError, SchemaError, SchemaIdError, SchemaNameError,
+};
+use p2panda_rs::schema::system::SystemSchemaError;
+use p2panda_rs::schema::{FieldName, SchemaId, SchemaName};
+use thiserror::Error;
+
+use crate::build::RelationCycle;
+use crate::deploy::Rejection;
+use crate::diagnostic::Diagnostic;
+use crate::merge::MergeConflict;
+
+/// Result type used throughout the fishy library.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Errors which can occur while creating, managing or deploying p2panda schemas.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// File could not be opened, for example because it does not exist.
+    #[error("Could not open file '{0}'")]
+    OpenFile(PathBuf, #[source] std::io::Error),
+
+    /// Reading or writing a file failed.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// Private key file does not contain a valid hex-encoded ed25519 private key.
+    #[error("Invalid private key")]
+    InvalidPrivateKey(#[from] KeyPairError),
+
+    /// Key pair file is empty or does not contain exactly one hex-encoded ed25519 private key.
+    #[error("Invalid private key file {}: {1}", .0.display())]
+    InvalidPrivateKeyFile(PathBuf, String),
+
+    /// Recovery phrase is not a valid list of 24 words encoding a private key.
+    #[error("Invalid recovery phrase: {0}")]
+    InvalidMnemonic(String),
+
+    /// Schema file could not be parsed.
+    #[error("Invalid schema file at {0}")]
+    InvalidSchemaFile(Box<Diagnostic>),
+
+    /// Project settings could not be parsed.
+    #[error("Invalid TOML syntax in fishy.toml")]
+    InvalidConfig(#[source] toml::de::Error),
+
+    /// Lock file could not be parsed.
+    #[error("Invalid TOML syntax in lock file")]
+    InvalidLockFile(#[source] toml::de::Error),
+
+    /// Commits of a lock file do not match its checksum.
+    #[error("Lock file checksum {0} does not match its commits ({1}), it was corrupted or edited by hand")]
+    LockFileChecksumMismatch(Hash, Hash),
+
+    /// Signature file of a lock file could not be parsed or does not match the lock file.
+    #[error("Invalid lock file signature: {0}")]
+    InvalidLockSignature(String),
+
+    /// Lock file is signed by another public key than expected.
+    #[error("Lock file is signed by {0} instead of the expected public key {1}")]
+    UntrustedLockSignature(String, String),
+
+    /// Binary lock bundle could not be encoded or decoded.
+    #[error("Invalid lock bundle: {0}")]
+    InvalidBundle(String),
+
+    /// Aliases file could not be parsed.
+    #[error("Invalid TOML syntax in aliases file")]
+    InvalidAliasesFile(#[source] toml::de::Error),
+
+    /// Names of two schemas only differ in case and map to the same alias.
+    #[error("Schemas {1} and {2} both map to alias {0}, rename one of them")]
+    AliasCollision(String, SchemaName, SchemaName),
+
+    /// Deployments file could not be parsed.
+    #[error("Invalid TOML syntax in deployments file")]
+    InvalidDeploymentsFile(#[source] toml::de::Error),
+
+    /// Recorded requests could not be parsed.
+    #[cfg(feature = "cassette")]
+    #[error("Invalid JSON syntax in cassette file")]
+    InvalidCassette(#[source] serde_json::Error),
+
+    /// Lock file could not be serialized.
+    #[error("Could not serialize lock file")]
+    SerializeLockFile(#[from] toml::ser::Error),
+
+    /// Commits could not be serialized as JSON.
+    #[error("Could not serialize commits as JSON")]
+    SerializeJsonCommits(#[source] serde_json::Error),
+
+    /// Commits to import could not be parsed.
+    #[error("Invalid JSON commits, expected an array of objects with `entry` and `operation`")]
+    InvalidJsonCommits(#[source] serde_json::Error),
+
+    /// Schema file and lock file can not both be read from stdin.
+    #[error("Only one of schema file and lock file can be read from stdin")]
+    MultipleStdinInputs,
+
+    /// Schema file does not define any schemas.
+    #[error("Schema file is empty")]
+    EmptySchemaFile,
+
+    /// Untrusted input exceeds a limit of the sandbox.
+    #[error("Sandbox limit exceeded: {0}")]
+    SandboxLimit(String),
+
+    /// Building untrusted input took longer than allowed by the sandbox.
+    #[error("Build exceeded sandbox timeout of {0:?}")]
+    SandboxTimeout(Duration),
+
+    /// Schema with the same name is defined in multiple files.
+    #[error("Schema {0} is defined multiple times, again in {}", .1.display())]
+    DuplicateSchema(SchemaName, PathBuf),
+
+    /// Include pattern of a schema file is not a valid glob pattern or can not be read.
+    #[error("Invalid include '{0}': {1}")]
+    InvalidInclude(String, String),
+
+    /// Include pattern of a schema file does not match any files.
+    #[error("Include '{0}' does not match any schema files")]
+    IncludeNotFound(String),
+
+    /// Schema file includes other files which have not been resolved before building.
+    #[error("Includes of schema file need to be resolved before building")]
+    UnresolvedIncludes,
+
+    /// Schema file relates to schemas of external projects which have not been resolved before
+    /// building.
+    #[error("Relations to external schemas need to be resolved before building")]
+    UnresolvedExternals,
+
+    /// Schema file sets a prefix which has not been applied before building.
+    #[error("Prefix of schema file needs to be applied before building")]
+    UnappliedPrefix,
+
+    /// Relation to a schema of an external project is not defined correctly.
+    #[error("Invalid relation to external schema: {0}")]
+    InvalidExternal(String),
+
+    /// Lock file of an external project does not contain the schema a relation points at.
+    #[error("External schema {0} not found in lock file of {1}")]
+    ExternalSchemaNotFound(SchemaName, String),
+
+    /// Dependency of the project could not be fetched or does not contain a valid lock file.
+    #[error("Could not resolve dependency '{0}': {1}")]
+    InvalidDependency(String, String),
+
+    /// Relation points at a schema by name which is published by more than one dependency.
+    #[error(
+        "Schema {0} is published by the dependencies {1}, relate to it by its schema id instead"
+    )]
+    AmbiguousDependencySchema(SchemaName, String),
+
+    /// Git command to fetch a remote file failed.
+    #[error("Could not fetch from git repository: {0}")]
+    Git(String),
+
+    /// Git revision is not a plain branch, tag or commit name.
+    #[error("Invalid git revision '{0}', only letters, digits and . _ / - are allowed and it can not start with -")]
+    InvalidGitRevision(String),
+
+    /// Git repository url uses a transport other than https, ssh or file.
+    #[error("Invalid git url '{0}', only https://, ssh://, git@ and file:// urls are allowed")]
+    InvalidGitUrl(String),
+
+    /// Name derived for an inline relation schema is not a valid schema name.
+    #[error("Inline schema name '{0}' is invalid, use a shorter schema or field name")]
+    InvalidInlineSchemaName(String),
+
+    /// Schema name with prefix is not a valid schema name.
+    #[error("Prefix '{0}' results in invalid schema name '{1}'")]
+    InvalidSchemaPrefix(String, String),
+
+    /// Name derived for an inline relation schema is already used by another schema.
+    #[error("Inline schema {0} conflicts with an existing schema of the same name")]
+    InlineSchemaConflict(SchemaName),
+
+    /// Schema is signed by a named key which is not configured in the project settings.
+    #[error("Schema {1} is signed by key '{0}' which is not configured in fishy.toml")]
+    UnknownKey(String, SchemaName),
+
+    /// Schema is renamed from a name which is not committed in the lock file.
+    #[error("Schema {0} is renamed from {1} which does not exist in the lock file")]
+    UnknownRenameSource(SchemaName, SchemaName),
+
+    /// Previous name of a renamed schema is still used by another schema.
+    #[error("Schema {0} can not be renamed from {1}, another schema uses this name already")]
+    RenameConflict(SchemaName, SchemaName),
+
+    /// Field is renamed from a name which is not committed for its schema in the lock file.
+    #[error("Field {1} of schema {0} is renamed from {2} which does not exist in the lock file")]
+    UnknownFieldRenameSource(SchemaName, FieldName, FieldName),
+
+    /// Previous name of a renamed field is still used by another field of the same schema.
+    #[error(
+        "Field {1} of schema {0} can not be renamed from {2}, another field uses this name already"
+    )]
+    FieldRenameConflict(SchemaName, FieldName, FieldName),
+
+    /// Schema gives bootstrap values for some of its fields but not for all of them.
+    #[error("Schema {0} defines a bootstrap document, field {1} needs a bootstrap value as well")]
+    IncompleteBootstrap(SchemaName, FieldName),
+
+    /// Bootstrap value does not match the type of its field.
+    #[error("Bootstrap value of field {1} of schema {0} does not match field type {2}")]
+    InvalidBootstrapValue(SchemaName, FieldName, String),
+
+    /// Bootstrap value violates a constraint of its field.
+    #[error("Bootstrap value of field {1} of schema {0} violates its constraints: {2}")]
+    ConstraintViolation(SchemaName, FieldName, String),
+
+    /// Constraints of a field do not apply to its type or are invalid.
+    #[error("Constraints of field {1} of schema {0} are invalid: {2}")]
+    InvalidConstraints(SchemaName, FieldName, String),
+
+    /// Commit in bootstrap lock file follows a schema which is not part of the lock file.
+    #[error("Detected bootstrap commit following unknown schema {0}")]
+    UnknownBootstrapSchema(SchemaId),
+
+    /// Relation points at a schema which is disabled with `enabled = false`.
+    #[error("Field {1} of schema {0} relates to disabled schema {2}")]
+    DisabledRelationSchema(SchemaName, FieldName, SchemaName),
+
+    /// Relation defines its schema inline but was not expanded into a separate schema, this
+    /// happens when schema definitions are deserialized directly instead of parsing a schema file.
+    #[error("Inline schema of field {1} of schema {0} needs to be expanded before building")]
+    UnexpandedInlineSchema(SchemaName, FieldName),
+
+    /// Schema or config file uses an environment variable which is not defined and has no
+    /// default.
+    #[error("Environment variable {0} used in {} is not defined, define it or give a default with ${{{0}:-<default>}}", .1.display())]
+    UndefinedVariable(String, PathBuf),
+
+    /// Edited schema is not defined in the schema file.
+    #[error("Schema {0} is not defined in the schema file")]
+    UnknownSchema(SchemaName),
+
+    /// Edited field is not defined in the schema.
+    #[error("Field {1} is not defined in schema {0}")]
+    UnknownField(SchemaName, FieldName),
+
+    /// Schema file defines a schema with this name already.
+    #[error("Schema {0} is defined in the schema file already")]
+    SchemaExists(SchemaName),
+
+    /// Schema defines a field with this name already.
+    #[error("Field {1} is defined in schema {0} already")]
+    FieldExists(SchemaName, FieldName),
+
+    /// Schema was defined without any fields.
+    #[error("Schema {0} does not contain any fields")]
+    EmptySchema(SchemaName),
+
+    /// Schemas relate to each other in a cycle, a topological order can not be found.
+    #[error("Cyclic dependency detected between relations: {0}")]
+    CyclicDependency(RelationCycle),
+
+    /// Entry hash of a commit does not match the entry.
+    #[error("Entry hash {0} does not match it's content")]
+    InvalidEntryHash(Hash),
+
+    /// Operation of a commit does not match the payload hash or size claimed by its entry.
+    #[error("Operation of commit {0} does not match the payload of its entry")]
+    InvalidPayload(Hash, #[source] ValidateEntryError),
+
+    /// Commit in lock file does not target a schema or field definition.
+    #[error("Detected commit with invalid schema id {0} in lock file")]
+    InvalidCommitSchemaId(SchemaId),
+
+    /// Commit in lock file failed validation.
+    #[error("Invalid commits detected")]
+    InvalidCommit(#[source] Box<DomainError>),
+
+    /// Segment file of a lock file lists segments itself.
+    #[error("Lock file segment {0} can not list other segments")]
+    NestedLockSegments(PathBuf),
+
+    /// Segment listed by a lock file is not a relative path inside of the segments directory.
+    #[error("Lock file segment {0} needs to be a relative path inside of the `locks` directory")]
+    InvalidLockSegmentPath(PathBuf),
+
+    /// Two segments of a lock file contain entries of the same log, usually because they were
+    /// built on different branches with the same key pair.
+    #[error("Lock file segments {0} and {1} both contain log {3} of {2}, they were built on different branches with the same key pair. Keep one of them and run `fishy build` again, `fishy merge-lock` lists all conflicts of two lock files")]
+    ConflictingLockSegments(PathBuf, PathBuf, String, u64),
+
+    /// Lock file lists segments which have not been loaded before building.
+    #[error("Segments of lock file need to be resolved before building")]
+    UnresolvedLockSegments,
+
+    /// Lock files of diverged branches contain changes which conflict with each other.
+    #[error("Lock files can not be merged, found {} conflicts", .0.len())]
+    LockFileMergeConflicts(Vec<MergeConflict>),
+
+    /// Commit at the given index in the lock file failed verification.
+    #[error("Commit at index {0} in lock file is invalid")]
+    InvalidLockFileCommit(usize, #[source] Box<Error>),
+
+    /// Commit at the given index of the imported commits failed validation.
+    #[error("Imported commit at index {0} is invalid")]
+    InvalidImportedCommit(usize, #[source] Box<Error>),
+
+    /// Schema definition in lock file was created without a name.
+    #[error("Schema definition created by commit {0} does not contain a name")]
+    MissingSchemaName(Hash),
+
+    /// Commit of the lock file is signed by another key pair than expected.
+    #[error("Commit {0} is signed by {1} and not by the expected key pair")]
+    UnexpectedCommitAuthor(Hash, String),
+
+    /// Commit is signed by a key pair which is not one of the trusted keys of the project.
+    #[error("Commit {0} is signed by {1} which is not one of the trusted keys")]
+    UntrustedCommitAuthor(Hash, String),
+
+    /// Commit in lock file updates a document which was not created by an earlier commit.
+    #[error("Commit in lock file points at unknown previous operations {0}")]
+    UnknownPreviousOperations(DocumentViewId),
+
+    /// Schema field definition which is referenced by a schema could not be found.
+    #[error("Missing field definition document {0} for schema {1}")]
+    MissingFieldDefinition(DocumentViewId, DocumentViewId),
+
+    /// Schema field definition which is referenced by a schema got deleted.
+    #[error("Can not assign a deleted schema field to a schema")]
+    DeletedFieldDefinition,
+
+    /// Schema could not be assembled from its materialized documents.
+    #[error("Could not assemble schema with view id {0} from given documents")]
+    InvalidSchema(DocumentViewId, #[source] SchemaError),
+
+    /// Document could not be converted into a schema or field definition.
+    #[error(transparent)]
+    SystemSchema(#[from] SystemSchemaError),
+
+    /// Unknown or invalid schema id.
+    #[error(transparent)]
+    SchemaId(#[from] SchemaIdError),
+
+    /// Schema name is invalid.
+    #[error(transparent)]
+    SchemaName(#[from] SchemaNameError),
+
+    /// Schema description is invalid.
+    #[error(transparent)]
+    SchemaDescription(#[from] SchemaDescriptionError),
+
+    /// Entry could not be decoded.
+    #[error(transparent)]
+    DecodeEntry(#[from] DecodeEntryError),
+
+    /// Operation could not be decoded.
+    #[error(transparent)]
+    DecodeOperation(#[from] DecodeOperationError),
+
+    /// Operation could not be encoded.
+    #[error(transparent)]
+    EncodeOperation(#[from] EncodeOperationError),
+
+    /// Operation could not be created.
+    #[error(transparent)]
+    BuildOperation(#[from] OperationBuilderError),
+
+    /// Entry could not be signed.
+    #[error("Could not sign entry: {0}")]
+    Sign(String),
+
+    /// Temporary in-memory store failed.
+    #[error("Critical storage failure: {0}")]
+    Storage(String),
+
+    /// Request to node failed, for example because it could not be reached.
+    #[error("GraphQL request to node failed: {0}")]
+    Request(String),
+
+    /// Node rejected a published commit.
+    #[error("Node rejected commit {0}: {1}")]
+    CommitRejected(Hash, String),
+
+    /// Node holds a different state of a log than the local commits.
+    #[error("Inconsistency between local commits and node detected")]
+    Inconsistency,
+
+    /// Schema is not known to the node.
+    #[error("Schema {0} not found on node")]
+    SchemaNotFound(SchemaId),
+
+    /// Schema on the node does not match the schema an application was compiled against.
+    #[error("Schema {0} on node has checksum {2}, expected {1}")]
+    ChecksumMismatch(SchemaId, Hash, Hash),
+}
+
+impl Error {
+    /// Returns the class of validation failure if a node rejected a commit.
+    pub fn rejection(&self) -> Option<Rejection> {
+        match self {
+            Error::CommitRejected(_, message) => Some(Rejection::from_message(message)),
+            _ => None,
+        }
+    }
+}
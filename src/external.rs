@@ -0,0 +1,262 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Resolve relations to schemas of other projects.
+//!
+//! Relations can point at a schema by name in another project, either a git repository of a
+//! shared schema library or a fishy project on the local file system. The lock file of that
+//! project gets loaded and the relation is pinned to the schema id of the latest version committed
+//! in it.
+//!
+//! Lock files of external projects can be vendored into the project, so builds do not need to
+//! access them anymore.
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use p2panda_rs::hash::Hash;
+use p2panda_rs::schema::{SchemaId, SchemaName};
+use serde::Serialize;
+use tracing::{debug, instrument};
+
+use crate::build::get_previous_schemas;
+use crate::constants::LOCK_FILE_NAME;
+use crate::error::{Error, Result};
+use crate::lock_file::LockFile;
+use crate::schema_file::{RelationId, RelationSchema, RelationSource, SchemaField, SchemaFile};
+use crate::utils::git;
+
+/// Schema of an external project the schema file depends on.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct ExternalSchema {
+    /// Name of the schema.
+    pub name: SchemaName,
+
+    /// Id of the latest version of the schema in the lock file of the external project.
+    pub schema_id: SchemaId,
+
+    /// Git repository with revision or local path of the external project.
+    pub source: String,
+}
+
+/// Lock file of an external project which was copied into the vendor directory.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct VendoredLockFile {
+    /// Git repository with revision or local path of the external project.
+    pub source: String,
+
+    /// Path of the copied lock file.
+    pub path: PathBuf,
+}
+
+/// Replaces all relations to external schemas with the schema ids they currently resolve to and
+/// returns these external dependencies.
+///
+/// Lock files found in the vendor directory are used directly, others get fetched and cached in
+/// the given cache directory, pinned to the revision of the relation. Local paths are relative to
+/// the schema file and point at a project directory or its lock file.
+#[instrument(skip_all)]
+pub async fn resolve_externals(
+    schema_file: &mut SchemaFile,
+    cache_dir: &Path,
+    vendor_dir: &Path,
+) -> Result<Vec<ExternalSchema>> {
+    let base_dir = schema_file.base_dir();
+    let mut externals: Vec<ExternalSchema> = Vec::new();
+
+    // Schema ids of every external lock file, so each of them is only read once
+    let mut resolved: HashMap<PathBuf, BTreeMap<SchemaName, SchemaId>> = HashMap::new();
+
+    for (_, schema_definition) in schema_file.iter_mut() {
+        for (_, schema_field) in schema_definition.fields.iter_mut() {
+            let SchemaField::Relation { schema, .. } = schema_field else {
+                continue;
+            };
+
+            let Some(project) = ExternalProject::from_relation(schema, &base_dir)? else {
+                continue;
+            };
+
+            let RelationId::Name(schema_name) = &schema.id else {
+                return Err(Error::InvalidExternal(
+                    "Relations to external schemas need to refer to them by name".into(),
+                ));
+            };
+
+            let vendored_path = vendor_dir.join(project.vendor_file_name());
+            let lock_path = if vendored_path.exists() {
+                vendored_path
+            } else {
+                project.lock_path(cache_dir)?
+            };
+
+            if !resolved.contains_key(&lock_path) {
+                let schema_ids = external_schema_ids(&lock_path).await?;
+                resolved.insert(lock_path.clone(), schema_ids);
+            }
+
+            let schema_id = resolved[&lock_path]
+                .get(schema_name)
+                .ok_or_else(|| {
+                    Error::ExternalSchemaNotFound(schema_name.clone(), project.to_string())
+                })?
+                .clone();
+            debug!(%schema_id, "Resolved external schema {schema_name} from {project}");
+
+            let external = ExternalSchema {
+                name: schema_name.clone(),
+                schema_id: schema_id.clone(),
+                source: project.to_string(),
+            };
+            if !externals.contains(&external) {
+                externals.push(external);
+            }
+
+            schema.id = RelationId::Id(schema_id);
+            schema.external = None;
+            schema.rev = None;
+        }
+    }
+
+    Ok(externals)
+}
+
+/// Copies the lock files of all external projects the schema file relates to into the vendor
+/// directory, replacing previously vendored versions.
+///
+/// Lock files split into segments are vendored as a single file with the commits of all segments.
+#[instrument(skip_all)]
+pub fn vendor_externals(
+    schema_file: &SchemaFile,
+    cache_dir: &Path,
+    vendor_dir: &Path,
+) -> Result<Vec<VendoredLockFile>> {
+    let base_dir = schema_file.base_dir();
+    let mut vendored: Vec<VendoredLockFile> = Vec::new();
+
+    for (_, schema_definition) in schema_file.iter() {
+        for (_, schema_field) in schema_definition.fields.iter() {
+            let SchemaField::Relation { schema, .. } = schema_field else {
+                continue;
+            };
+
+            let Some(project) = ExternalProject::from_relation(schema, &base_dir)? else {
+                continue;
+            };
+
+            let path = vendor_dir.join(project.vendor_file_name());
+            if vendored.iter().any(|lock_file| lock_file.path == path) {
+                continue;
+            }
+
+            // Segments get merged into a single file, which also makes sure we only vendor valid
+            // lock files
+            let lock_file = LockFile::from_path(project.lock_path(cache_dir)?)?;
+
+            fs::create_dir_all(vendor_dir)?;
+            lock_file.write(&path)?;
+            debug!("Vendored lock file of {project} to {}", path.display());
+
+            vendored.push(VendoredLockFile {
+                source: project.to_string(),
+                path,
+            });
+        }
+    }
+
+    Ok(vendored)
+}
+
+/// External project a relation points at.
+enum ExternalProject {
+    /// Git repository, pinned to a revision.
+    Git { url: String, rev: String },
+
+    /// Local project directory or lock file, as written in the schema file and resolved relative
+    /// to it.
+    Path { path: String, resolved: PathBuf },
+}
+
+impl ExternalProject {
+    /// Returns the external project of this relation, `None` if it is not pointing at one.
+    fn from_relation(schema: &RelationSchema, base_dir: &Path) -> Result<Option<Self>> {
+        let project = match &schema.external {
+            Some(RelationSource::Git(url)) => {
+                let rev = schema.rev.as_ref().ok_or_else(|| {
+                    Error::InvalidExternal(format!(
+                        "Relation to external schema in {url} needs to be pinned to a `rev`"
+                    ))
+                })?;
+
+                Self::Git {
+                    url: url.clone(),
+                    rev: rev.clone(),
+                }
+            }
+            Some(RelationSource::Path(path)) => Self::Path {
+                path: path.clone(),
+                resolved: base_dir.join(path),
+            },
+            None => return Ok(None),
+        };
+
+        Ok(Some(project))
+    }
+
+    /// Returns the path to the lock file of this project, fetching it from git repositories.
+    fn lock_path(&self, cache_dir: &Path) -> Result<PathBuf> {
+        match self {
+            Self::Git { url, rev } => {
+                git::fetch_lock_file(url, rev, Path::new(LOCK_FILE_NAME), cache_dir)
+            }
+            Self::Path { resolved, .. } => {
+                let lock_path = if resolved.is_dir() {
+                    resolved.join(LOCK_FILE_NAME)
+                } else {
+                    resolved.clone()
+                };
+
+                if !lock_path.is_file() {
+                    return Err(Error::InvalidExternal(format!(
+                        "No lock file found at {}",
+                        lock_path.display()
+                    )));
+                }
+
+                Ok(lock_path)
+            }
+        }
+    }
+
+    /// Returns the name of the lock file of this project inside of the vendor directory.
+    fn vendor_file_name(&self) -> String {
+        let key = match self {
+            Self::Git { url, rev } => format!("{url}\n{rev}"),
+            Self::Path { path, .. } => path.clone(),
+        };
+
+        format!("{}.lock", Hash::new_from_bytes(key.as_bytes()).as_str())
+    }
+}
+
+impl Display for ExternalProject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Git { url, rev } => write!(f, "{url} ({rev})"),
+            Self::Path { path, .. } => write!(f, "{path}"),
+        }
+    }
+}
+
+/// Returns the ids of the latest versions of all schemas committed in a lock file.
+pub(crate) async fn external_schema_ids(
+    lock_path: &Path,
+) -> Result<BTreeMap<SchemaName, SchemaId>> {
+    let lock_file = LockFile::from_path(lock_path)?;
+    let previous_schemas = get_previous_schemas(&lock_file).await?;
+
+    Ok(previous_schemas
+        .into_iter()
+        .map(|(schema_name, previous_schema)| (schema_name, previous_schema.schema.id().to_owned()))
+        .collect())
+}
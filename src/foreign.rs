@@ -0,0 +1,132 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Find commits of the lock file which can not be derived from the schema file.
+//!
+//! Building only ever appends commits, everything which once got into the lock file is replayed
+//! and deployed again with every build. Leftovers of renamed or removed schemas and field
+//! definitions no schema refers to, for example appended manually, are flagged so they do not go
+//! unnoticed.
+use std::collections::{HashMap, HashSet};
+
+use p2panda_rs::document::DocumentId;
+use p2panda_rs::hash::Hash;
+use p2panda_rs::operation::decode::decode_operation;
+use p2panda_rs::operation::plain::PlainValue;
+use p2panda_rs::operation::traits::{Actionable, Schematic};
+use p2panda_rs::operation::{OperationAction, OperationId};
+use p2panda_rs::schema::{FieldName, SchemaId, SchemaName};
+use serde::Serialize;
+use tracing::{debug, instrument};
+
+use crate::error::{Error, Result};
+use crate::lock_file::LockFile;
+use crate::prune::find_orphans;
+use crate::schema_file::SchemaFile;
+
+/// Commit of the lock file which can not be derived from any schema of the schema file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ForeignCommit {
+    /// Hash of the entry of the commit.
+    pub entry_hash: Hash,
+
+    /// Id of the document the commit belongs to.
+    pub document_id: DocumentId,
+
+    /// Why the commit can not be derived from the schema file.
+    pub reason: ForeignReason,
+}
+
+/// Reason why a commit can not be derived from the schema file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", content = "name", rename_all = "snake_case")]
+pub enum ForeignReason {
+    /// Commit belongs to a schema which is not defined in the schema file anymore, for example
+    /// because it was renamed without `rename_from` or removed.
+    OrphanedSchema(SchemaName),
+
+    /// Commit belongs to a field definition with the given name which no schema definition refers
+    /// to.
+    UnreferencedField(Option<FieldName>),
+}
+
+/// Returns all commits of the lock file which can not be derived from the schema file, in the
+/// order of the lock file.
+///
+/// Documents which were deleted are not foreign, their commits are needed to delete them on nodes
+/// as well.
+#[instrument(skip_all)]
+pub async fn find_foreign_commits(
+    schema_file: &SchemaFile,
+    lock_file: &LockFile,
+) -> Result<Vec<ForeignCommit>> {
+    let orphans: HashMap<DocumentId, SchemaName> = find_orphans(schema_file, lock_file)
+        .await?
+        .into_iter()
+        .map(|orphan| (orphan.document_id().clone(), orphan.schema().clone()))
+        .collect();
+    let document_schemas = lock_file.document_schemas()?;
+
+    let mut roots: HashMap<OperationId, OperationId> = HashMap::new();
+    let mut field_names: HashMap<DocumentId, FieldName> = HashMap::new();
+    let mut deleted: HashSet<DocumentId> = HashSet::new();
+    let mut candidates = Vec::new();
+
+    for commit in lock_file.commits.iter().flatten() {
+        let operation = decode_operation(&commit.operation)?;
+        let operation_id = OperationId::new(&commit.entry_hash);
+
+        let root = match operation.previous() {
+            None => operation_id.clone(),
+            Some(previous) => previous
+                .iter()
+                .find_map(|previous_id| roots.get(previous_id))
+                .cloned()
+                .ok_or_else(|| Error::UnknownPreviousOperations(previous.clone()))?,
+        };
+        roots.insert(operation_id, root.clone());
+        let document_id = DocumentId::new(&root);
+
+        if operation.action() == OperationAction::Delete {
+            deleted.insert(document_id.clone());
+        }
+
+        let reason = match (orphans.get(&document_id), operation.schema_id()) {
+            (Some(schema), _) => ForeignReason::OrphanedSchema(schema.clone()),
+            (None, SchemaId::SchemaFieldDefinition(_))
+                if !document_schemas.contains_key(&document_id) =>
+            {
+                let fields = operation.fields();
+                if let Some(PlainValue::String(name)) =
+                    fields.as_ref().and_then(|fields| fields.get("name"))
+                {
+                    field_names.insert(document_id.clone(), name.clone());
+                }
+                ForeignReason::UnreferencedField(None)
+            }
+            _ => continue,
+        };
+
+        candidates.push(ForeignCommit {
+            entry_hash: commit.entry_hash.clone(),
+            document_id,
+            reason,
+        });
+    }
+
+    let foreign: Vec<ForeignCommit> = candidates
+        .into_iter()
+        .filter(|commit| !deleted.contains(&commit.document_id))
+        .map(|commit| match commit.reason {
+            ForeignReason::UnreferencedField(_) => ForeignCommit {
+                reason: ForeignReason::UnreferencedField(
+                    field_names.get(&commit.document_id).cloned(),
+                ),
+                ..commit
+            },
+            ForeignReason::OrphanedSchema(_) => commit,
+        })
+        .collect();
+    debug!("Found {} foreign commits", foreign.len());
+
+    Ok(foreign)
+}
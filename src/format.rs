@@ -0,0 +1,336 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Render plans, project states and deploy reports in different output formats.
+//!
+//! Applications embedding fishy can implement `Formatter` to render the results of the library
+//! themselves and register it under a name next to the built-in `human`, `json` and `markdown`
+//! formatters, instead of parsing the output of the command-line-tool.
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use p2panda_rs::identity::PublicKey;
+use serde::Serialize;
+
+use crate::build::{render_plan, render_plan_items, ChangeStatus, PlanSummary};
+use crate::deploy::DeployReport;
+use crate::deployments::DeployStamp;
+use crate::project::{ProjectSnapshot, SchemaStatus};
+
+/// Renders the results of the library into text.
+pub trait Formatter: Send + Sync {
+    /// Renders the changes of all schemas.
+    ///
+    /// If `only_changes` is set, schemas without any changes can be omitted.
+    fn plan(&self, summaries: &[PlanSummary], public_key: &PublicKey, only_changes: bool)
+        -> String;
+
+    /// Renders the state of all schemas of a project.
+    fn status(&self, snapshot: &ProjectSnapshot) -> String;
+
+    /// Renders the number of published and skipped commits after a deployment.
+    fn deploy_report(&self, report: &DeployReport) -> String;
+}
+
+/// Plain text output for humans, the same as used by `render_plan`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HumanFormatter;
+
+impl Formatter for HumanFormatter {
+    fn plan(
+        &self,
+        summaries: &[PlanSummary],
+        public_key: &PublicKey,
+        only_changes: bool,
+    ) -> String {
+        render_plan(summaries, public_key, only_changes)
+    }
+
+    fn status(&self, snapshot: &ProjectSnapshot) -> String {
+        let mut output = String::new();
+
+        for schema in &snapshot.schemas {
+            output.push_str(&format!(
+                "{} ({}): {}\n",
+                schema.name,
+                schema_status_label(&schema.status),
+                schema.current_schema_id
+            ));
+        }
+
+        output.push_str(&format!(
+            "{} committed, {} pending commits\n",
+            snapshot.committed_commits, snapshot.pending_commits
+        ));
+
+        for (endpoint, stamp) in snapshot.deployments.iter() {
+            output.push_str(&format!(
+                "Deployed to {endpoint} at {} ({})\n",
+                stamp.timestamp,
+                deployment_label(snapshot, stamp)
+            ));
+        }
+
+        output
+    }
+
+    fn deploy_report(&self, report: &DeployReport) -> String {
+        let mut output = format!(
+            "Published {} commits, skipped {} commits the node already knew about\n",
+            report.published, report.skipped
+        );
+
+        if report.cancelled {
+            output.push_str("Deployment was cancelled before all commits were published\n");
+        }
+
+        output
+    }
+}
+
+/// Pretty-printed JSON of the serialized results.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonFormatter;
+
+impl JsonFormatter {
+    fn to_json(value: &impl Serialize) -> String {
+        let mut output =
+            serde_json::to_string_pretty(value).expect("Results can always be serialized as JSON");
+        output.push('\n');
+        output
+    }
+}
+
+impl Formatter for JsonFormatter {
+    fn plan(
+        &self,
+        summaries: &[PlanSummary],
+        public_key: &PublicKey,
+        only_changes: bool,
+    ) -> String {
+        #[derive(Serialize)]
+        struct Plan<'a> {
+            public_key: &'a PublicKey,
+            schemas: Vec<&'a PlanSummary>,
+        }
+
+        Self::to_json(&Plan {
+            public_key,
+            schemas: summaries
+                .iter()
+                .filter(|summary| !only_changes || summary.has_changes())
+                .collect(),
+        })
+    }
+
+    fn status(&self, snapshot: &ProjectSnapshot) -> String {
+        Self::to_json(snapshot)
+    }
+
+    fn deploy_report(&self, report: &DeployReport) -> String {
+        Self::to_json(report)
+    }
+}
+
+/// Markdown tables, for example to post them as a pull request comment.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MarkdownFormatter;
+
+impl MarkdownFormatter {
+    fn write_plan(
+        output: &mut String,
+        summaries: &[PlanSummary],
+        public_key: &PublicKey,
+        only_changes: bool,
+    ) -> std::fmt::Result {
+        writeln!(output, "# Plan")?;
+
+        for schema in render_plan_items(summaries, only_changes) {
+            writeln!(output)?;
+            writeln!(
+                output,
+                "## {} ({})",
+                schema.name,
+                status_label(schema.status)
+            )?;
+            writeln!(output)?;
+            writeln!(output, "* Schema id: `{}`", schema.schema_id)?;
+
+            if let Some(previous_schema_id) = &schema.previous_schema_id {
+                writeln!(output, "* Previously: `{previous_schema_id}`")?;
+            }
+
+            writeln!(output, "* Description: {}", schema.description)?;
+
+            if schema.public_key != public_key.to_string() {
+                writeln!(output, "* Signed by: `{}`", schema.public_key)?;
+            }
+
+            if schema.breaking {
+                writeln!(
+                    output,
+                    "* **Breaking change:** fields were removed, renamed or changed their type"
+                )?;
+            }
+
+            writeln!(output)?;
+            writeln!(output, "| # | Field Name | Field Type | Status |")?;
+            writeln!(output, "| --- | --- | --- | --- |")?;
+
+            for field in &schema.fields {
+                writeln!(
+                    output,
+                    "| {} | `{}` | `{}` | {} |",
+                    field.index,
+                    field.name,
+                    field.field_type,
+                    status_label(field.status)
+                )?;
+            }
+        }
+
+        writeln!(output)?;
+        writeln!(output, "Public key used for signing: `{public_key}`")
+    }
+
+    fn write_status(output: &mut String, snapshot: &ProjectSnapshot) -> std::fmt::Result {
+        writeln!(output, "# Status")?;
+        writeln!(output)?;
+        writeln!(output, "| Schema | Status | Schema id |")?;
+        writeln!(output, "| --- | --- | --- |")?;
+
+        for schema in &snapshot.schemas {
+            writeln!(
+                output,
+                "| {} | {} | `{}` |",
+                schema.name,
+                schema_status_label(&schema.status),
+                schema.current_schema_id
+            )?;
+        }
+
+        writeln!(output)?;
+        writeln!(
+            output,
+            "{} committed, {} pending commits",
+            snapshot.committed_commits, snapshot.pending_commits
+        )?;
+
+        if snapshot.deployments.iter().next().is_none() {
+            return Ok(());
+        }
+
+        writeln!(output)?;
+        writeln!(output, "| Node | Deployed at | State |")?;
+        writeln!(output, "| --- | --- | --- |")?;
+
+        for (endpoint, stamp) in snapshot.deployments.iter() {
+            writeln!(
+                output,
+                "| {endpoint} | {} | {} |",
+                stamp.timestamp,
+                deployment_label(snapshot, stamp)
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Formatter for MarkdownFormatter {
+    fn plan(
+        &self,
+        summaries: &[PlanSummary],
+        public_key: &PublicKey,
+        only_changes: bool,
+    ) -> String {
+        let mut output = String::new();
+        Self::write_plan(&mut output, summaries, public_key, only_changes)
+            .expect("Writing into a string never fails");
+        output
+    }
+
+    fn status(&self, snapshot: &ProjectSnapshot) -> String {
+        let mut output = String::new();
+        Self::write_status(&mut output, snapshot).expect("Writing into a string never fails");
+        output
+    }
+
+    fn deploy_report(&self, report: &DeployReport) -> String {
+        format!(
+            "# Deployment\n\n| Published | Skipped | Cancelled |\n| --- | --- | --- |\n| {} | {} | {} |\n",
+            report.published,
+            report.skipped,
+            if report.cancelled { "yes" } else { "no" }
+        )
+    }
+}
+
+/// Returns the label of a schema status.
+fn schema_status_label(status: &SchemaStatus) -> &'static str {
+    match status {
+        SchemaStatus::New => "new",
+        SchemaStatus::Changed => "changed",
+        SchemaStatus::Unchanged => "unchanged",
+    }
+}
+
+/// Returns whether a node received all commits of the lock files with its last deployment.
+fn deployment_label(snapshot: &ProjectSnapshot, stamp: &DeployStamp) -> &'static str {
+    if stamp.last_commit == snapshot.last_commit {
+        "up-to-date"
+    } else {
+        "outdated"
+    }
+}
+
+/// Returns the label of a change status in Markdown output.
+fn status_label(status: ChangeStatus) -> &'static str {
+    match status {
+        ChangeStatus::Added => "added",
+        ChangeStatus::Changed(_) => "changed",
+        ChangeStatus::Removed => "removed",
+        ChangeStatus::Unchanged => "unchanged",
+    }
+}
+
+/// Formatters registered by name.
+///
+/// The default registry contains the built-in `human`, `json` and `markdown` formatters.
+pub struct Formatters(BTreeMap<String, Box<dyn Formatter>>);
+
+impl Formatters {
+    /// Returns a registry without any formatters.
+    pub fn empty() -> Self {
+        Self(BTreeMap::new())
+    }
+
+    /// Registers a formatter under the given name, replacing and returning any formatter which
+    /// was registered under this name before.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        formatter: impl Formatter + 'static,
+    ) -> Option<Box<dyn Formatter>> {
+        self.0.insert(name.into(), Box::new(formatter))
+    }
+
+    /// Returns the formatter registered under the given name.
+    pub fn get(&self, name: &str) -> Option<&dyn Formatter> {
+        self.0.get(name).map(|formatter| formatter.as_ref())
+    }
+
+    /// Returns the names of all registered formatters, sorted alphabetically.
+    pub fn names(&self) -> Vec<&str> {
+        self.0.keys().map(String::as_str).collect()
+    }
+}
+
+impl Default for Formatters {
+    fn default() -> Self {
+        let mut formatters = Self::empty();
+        formatters.register("human", HumanFormatter);
+        formatters.register("json", JsonFormatter);
+        formatters.register("markdown", MarkdownFormatter);
+        formatters
+    }
+}
@@ -0,0 +1,184 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Order items which depend on each other.
+//!
+//! `fishy` uses this to commit schemas after the schemas they relate to, applications can use it
+//! the same way to publish their own documents after the documents they point at.
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Display};
+use std::hash::Hash;
+
+/// Items depend on each other in a cycle, a topological order can not be found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CyclicDependency<T> {
+    /// Members of the cycle, every item depends on the one following it and the last one depends
+    /// on the first one again.
+    pub cycle: Vec<T>,
+}
+
+impl<T: Display> Display for CyclicDependency<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for item in &self.cycle {
+            write!(f, "{item} -> ")?;
+        }
+
+        match self.cycle.first() {
+            Some(first) => write!(f, "{first}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: Debug + Display> std::error::Error for CyclicDependency<T> {}
+
+/// Sorts items in topological order, every item comes after all items it depends on.
+///
+/// Every item is given together with its dependencies. Dependencies which are not given as items
+/// themselves are part of the result as well. Items are sorted in rounds: every round takes all
+/// items whose dependencies were taken in earlier rounds, in the order they were first seen.
+///
+/// ```
+/// use fishy::graph::sort_dependencies;
+///
+/// let sorted = sort_dependencies([
+///     ("comment", vec!["post", "author"]),
+///     ("post", vec!["author"]),
+///     ("author", vec![]),
+/// ])
+/// .unwrap();
+///
+/// assert_eq!(sorted, vec!["author", "post", "comment"]);
+/// ```
+pub fn sort_dependencies<T, I, D>(items: I) -> Result<Vec<T>, CyclicDependency<T>>
+where
+    T: Clone + Eq + Hash,
+    I: IntoIterator<Item = (T, D)>,
+    D: IntoIterator<Item = T>,
+{
+    // Collect all nodes in the order they were seen first, together with their dependencies
+    let mut nodes: Vec<T> = Vec::new();
+    let mut dependencies: Vec<Vec<usize>> = Vec::new();
+    let mut indices: HashMap<T, usize> = HashMap::new();
+
+    let mut index_of = |item: T, nodes: &mut Vec<T>, dependencies: &mut Vec<Vec<usize>>| {
+        *indices.entry(item.clone()).or_insert_with(|| {
+            nodes.push(item);
+            dependencies.push(Vec::new());
+            nodes.len() - 1
+        })
+    };
+
+    for (item, item_dependencies) in items {
+        let index = index_of(item, &mut nodes, &mut dependencies);
+
+        for dependency in item_dependencies {
+            let dependency_index = index_of(dependency, &mut nodes, &mut dependencies);
+            if !dependencies[index].contains(&dependency_index) {
+                dependencies[index].push(dependency_index);
+            }
+        }
+    }
+
+    // Repeatedly take all nodes whose dependencies are sorted already
+    let mut sorted = vec![false; nodes.len()];
+    let mut order: Vec<usize> = Vec::with_capacity(nodes.len());
+
+    while order.len() < nodes.len() {
+        let next: Vec<usize> = (0..nodes.len())
+            .filter(|&index| {
+                !sorted[index]
+                    && dependencies[index]
+                        .iter()
+                        .all(|&dependency| sorted[dependency])
+            })
+            .collect();
+
+        if next.is_empty() {
+            return Err(CyclicDependency {
+                cycle: find_cycle(&dependencies, &sorted)
+                    .into_iter()
+                    .map(|index| nodes[index].clone())
+                    .collect(),
+            });
+        }
+
+        for index in next {
+            sorted[index] = true;
+            order.push(index);
+        }
+    }
+
+    Ok(order
+        .into_iter()
+        .map(|index| nodes[index].clone())
+        .collect())
+}
+
+/// Returns the indices of the members of one cycle between the nodes which could not be sorted.
+///
+/// Every unsorted node has at least one unsorted dependency, following them from any unsorted
+/// node eventually leads back to a node visited before.
+fn find_cycle(dependencies: &[Vec<usize>], sorted: &[bool]) -> Vec<usize> {
+    let mut path: Vec<usize> = Vec::new();
+    let mut current = sorted
+        .iter()
+        .position(|is_sorted| !is_sorted)
+        .expect("At least one node is not sorted");
+
+    loop {
+        if let Some(start) = path.iter().position(|&index| index == current) {
+            return path.split_off(start);
+        }
+
+        path.push(current);
+        current = *dependencies[current]
+            .iter()
+            .find(|&&dependency| !sorted[dependency])
+            .expect("Unsorted node has unsorted dependencies");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sort_dependencies, CyclicDependency};
+
+    #[test]
+    fn sorts_in_rounds() {
+        let sorted = sort_dependencies([
+            ("venue", vec![]),
+            ("event", vec!["venue", "artist"]),
+            ("ticket", vec![]),
+        ])
+        .unwrap();
+
+        // `event` waits for `artist` which was only seen as its dependency
+        assert_eq!(sorted, vec!["venue", "artist", "ticket", "event"]);
+    }
+
+    #[test]
+    fn reports_cycle() {
+        let err = sort_dependencies([
+            ("venue", vec![]),
+            ("event", vec!["venue", "artist"]),
+            ("artist", vec!["tour"]),
+            ("tour", vec!["event"]),
+        ])
+        .unwrap_err();
+
+        assert_eq!(
+            err,
+            CyclicDependency {
+                cycle: vec!["event", "artist", "tour"]
+            }
+        );
+        assert_eq!(err.to_string(), "event -> artist -> tour -> event");
+    }
+
+    #[test]
+    fn reports_item_depending_on_itself() {
+        let err = sort_dependencies([("venue", vec!["venue"])]).unwrap_err();
+
+        assert_eq!(err.cycle, vec!["venue"]);
+        assert_eq!(err.to_string(), "venue -> venue");
+    }
+}
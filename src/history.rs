@@ -0,0 +1,166 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Version history of the schemas committed in a lock file.
+//!
+//! Every update of a schema gets a new schema id, the lock file only keeps the signed and encoded
+//! commits which lead to them. Replaying the commits one after another materializes every version
+//! of a schema again, together with the fields which changed and the commit introducing it.
+use std::collections::HashMap;
+
+use p2panda_rs::document::traits::AsDocument;
+use p2panda_rs::document::DocumentId;
+use p2panda_rs::entry::decode::decode_entry;
+use p2panda_rs::entry::traits::AsEntry;
+use p2panda_rs::hash::Hash;
+use p2panda_rs::identity::PublicKey;
+use p2panda_rs::operation::decode::decode_operation;
+use p2panda_rs::operation::traits::{Actionable, Schematic};
+use p2panda_rs::operation::OperationId;
+use p2panda_rs::schema::system::SchemaView;
+use p2panda_rs::schema::{FieldName, SchemaId, SchemaName};
+use p2panda_rs::storage_provider::traits::DocumentStore;
+use p2panda_rs::test_utils::memory_store::MemoryStore;
+use serde::Serialize;
+use tracing::instrument;
+
+use crate::build::{assemble_schema, publish_commit, ChangeStatus, PreviousSchema};
+use crate::error::{Error, Result};
+use crate::lock_diff::{diff_fields, fields, fields_compatibility, FieldChange};
+use crate::lock_file::LockFile;
+use crate::schema_file::SchemaField;
+
+/// All versions of a schema committed in a lock file.
+#[derive(Clone, Debug, Serialize)]
+pub struct SchemaHistory {
+    /// Name of the schema.
+    pub name: SchemaName,
+
+    /// Versions of the schema, starting with the one creating it.
+    pub versions: Vec<SchemaVersion>,
+
+    /// Hash of the commit deleting the schema, `None` if it was not deleted.
+    pub deleted_in: Option<Hash>,
+}
+
+/// Single version of a schema committed in a lock file.
+#[derive(Clone, Debug, Serialize)]
+pub struct SchemaVersion {
+    /// Id of the schema in this version, containing its document view id.
+    pub schema_id: SchemaId,
+
+    /// Description of the schema in this version.
+    pub description: String,
+
+    /// Fields of the schema in this version, sorted by name. Relations point at schema ids.
+    pub fields: Vec<(FieldName, SchemaField)>,
+
+    /// `Added` for the first version, otherwise if this version is compatible to the previous one.
+    pub status: ChangeStatus,
+
+    /// Fields which were added, changed or removed compared to the previous version.
+    pub changes: Vec<FieldChange>,
+
+    /// Hash of the commit introducing this version.
+    pub commit: Hash,
+
+    /// Public key of the key pair which signed this version.
+    pub public_key: PublicKey,
+}
+
+/// Returns every version of all schemas committed in the lock file, sorted by name.
+///
+/// Only the lock file is needed, no schema file or key pair.
+#[instrument(skip_all)]
+pub async fn schema_history(lock_file: &LockFile) -> Result<Vec<SchemaHistory>> {
+    if !lock_file.segments.is_empty() {
+        return Err(Error::UnresolvedLockSegments);
+    }
+
+    let store = MemoryStore::default();
+    let mut roots: HashMap<OperationId, OperationId> = HashMap::new();
+    let mut histories: Vec<(OperationId, SchemaHistory)> = Vec::new();
+
+    for commit in lock_file.commits.iter().flatten() {
+        publish_commit(&store, commit).await?;
+
+        let operation = decode_operation(&commit.operation)?;
+        if operation.schema_id() != &SchemaId::SchemaDefinition(1) {
+            continue;
+        }
+
+        // Follow the operation back to the operation creating the schema definition document
+        let operation_id = OperationId::new(&commit.entry_hash);
+        let root = match operation.previous() {
+            None => operation_id.clone(),
+            Some(previous) => previous
+                .iter()
+                .find_map(|previous_id| roots.get(previous_id))
+                .cloned()
+                .ok_or_else(|| Error::UnknownPreviousOperations(previous.clone()))?,
+        };
+        roots.insert(operation_id, root.clone());
+
+        let document = store
+            .get_document(&DocumentId::new(&root))
+            .await
+            .map_err(|err| Error::Storage(err.to_string()))?;
+        let Some(document_view) = document.as_ref().and_then(|document| document.view()) else {
+            if let Some((_, history)) = histories.iter_mut().find(|(id, _)| id == &root) {
+                history.deleted_in = Some(commit.entry_hash.clone());
+            }
+            continue;
+        };
+
+        let schema_view = SchemaView::try_from(document_view)?;
+        let (schema, schema_field_views) = assemble_schema(&store, &schema_view).await?;
+        let version_fields = fields(Some(&PreviousSchema::new(
+            &schema,
+            &schema_view,
+            &schema_field_views,
+        )));
+
+        let position = match histories.iter().position(|(id, _)| id == &root) {
+            Some(position) => position,
+            None => {
+                histories.push((
+                    root,
+                    SchemaHistory {
+                        name: schema.id().name(),
+                        versions: Vec::new(),
+                        deleted_in: None,
+                    },
+                ));
+                histories.len() - 1
+            }
+        };
+        let history = &mut histories[position].1;
+
+        let (status, changes) = match history.versions.last() {
+            None => (ChangeStatus::Added, diff_fields(&[], &version_fields)),
+            Some(previous) => {
+                let changes = diff_fields(&previous.fields, &version_fields);
+                (
+                    ChangeStatus::Changed(fields_compatibility(&changes)),
+                    changes,
+                )
+            }
+        };
+
+        history.name = schema.id().name();
+        history.versions.push(SchemaVersion {
+            schema_id: schema.id().to_owned(),
+            description: schema_view.description().to_owned(),
+            fields: version_fields,
+            status,
+            changes,
+            commit: commit.entry_hash.clone(),
+            public_key: decode_entry(&commit.entry)?.public_key().to_owned(),
+        });
+    }
+
+    let mut histories: Vec<SchemaHistory> =
+        histories.into_iter().map(|(_, history)| history).collect();
+    histories.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(histories)
+}
@@ -0,0 +1,159 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::time::Duration;
+
+use p2panda_rs::schema::SchemaId;
+use serde::Serialize;
+use tracing::{debug, instrument, warn};
+
+/// Default timeout for requests to the service.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Time to wait before retrying a failed hook request, doubled after every attempt.
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Application service which gets notified about the deployed schema ids.
+#[derive(Clone, Debug)]
+pub struct DeployHook {
+    /// URL the schema ids get sent to via HTTP POST.
+    pub url: String,
+
+    /// How often a failed request gets retried.
+    pub retries: usize,
+
+    /// Timeout for every request, services which do not answer in time count as failed.
+    pub timeout: Duration,
+
+    client: reqwest::Client,
+}
+
+impl DeployHook {
+    /// Returns a new instance of `DeployHook` with the default timeout of 5 seconds.
+    pub fn new(url: &str, retries: usize) -> Self {
+        Self {
+            url: url.to_owned(),
+            retries,
+            timeout: DEFAULT_TIMEOUT,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Sets the timeout for every request.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sends the schema ids to the service, retrying failed requests.
+    #[instrument(skip_all, fields(url = %self.url))]
+    pub async fn call(&self, schema_ids: &[SchemaId]) -> HookReport {
+        let body = HookPayload { schema_ids };
+
+        let mut attempts = 0;
+        let mut delay = RETRY_DELAY;
+
+        loop {
+            attempts += 1;
+
+            let result = self
+                .client
+                .post(&self.url)
+                .timeout(self.timeout)
+                .json(&body)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+
+            match result {
+                Ok(_) => {
+                    debug!("Notified application service");
+                    return HookReport {
+                        url: self.url.clone(),
+                        attempts,
+                        status: HookStatus::Success,
+                    };
+                }
+                Err(err) if attempts > self.retries => {
+                    warn!("Could not notify application service: {}", err);
+                    return HookReport {
+                        url: self.url.clone(),
+                        attempts,
+                        status: HookStatus::Failed(err.to_string()),
+                    };
+                }
+                Err(_) => {
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                }
+            }
+        }
+    }
+}
+
+/// Calls all given hooks one after another and returns a report for each of them.
+pub async fn call_hooks(hooks: &[DeployHook], schema_ids: &[SchemaId]) -> Vec<HookReport> {
+    let mut reports = Vec::new();
+
+    for hook in hooks {
+        reports.push(hook.call(schema_ids).await);
+    }
+
+    reports
+}
+
+/// JSON body sent to every application service.
+#[derive(Serialize)]
+struct HookPayload<'a> {
+    schema_ids: &'a [SchemaId],
+}
+
+/// Result of notifying a single application service.
+#[derive(Clone, Debug)]
+pub struct HookReport {
+    /// URL of the service.
+    pub url: String,
+
+    /// Number of requests it took until the service accepted the schema ids or we gave up.
+    pub attempts: usize,
+
+    /// Final status of this hook.
+    pub status: HookStatus,
+}
+
+/// Status of a called hook.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum HookStatus {
+    /// Service accepted the schema ids.
+    Success,
+
+    /// Service could not be reached or responded with an error after all retries.
+    Failed(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::{DeployHook, HookStatus};
+
+    #[tokio::test]
+    async fn gives_up_on_services_which_never_respond() {
+        // Accepts connections but never answers
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let connections: Vec<_> = listener.incoming().collect();
+            drop(connections);
+        });
+
+        let hook = DeployHook::new(&url, 1).with_timeout(Duration::from_millis(100));
+        let report = tokio::time::timeout(Duration::from_secs(10), hook.call(&[]))
+            .await
+            .expect("Hook request timed out");
+
+        assert_eq!(report.attempts, 2);
+        assert!(matches!(report.status, HookStatus::Failed(_)));
+    }
+}
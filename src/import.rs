@@ -0,0 +1,86 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Import commits produced by other tools into a lock file.
+//!
+//! Commits exported from another fishy project or created with p2panda-js tooling can be appended
+//! to a lock file. They go through the same checks as the commits of the lock file itself and
+//! have to create or change schema or field definitions.
+use std::collections::HashSet;
+
+use p2panda_rs::hash::Hash;
+use p2panda_rs::test_utils::memory_store::MemoryStore;
+use tracing::{debug, instrument};
+
+use crate::build::publish_commit;
+use crate::error::{Error, Result};
+use crate::lock_file::{Commit, LockFile};
+
+/// Lock file with imported commits.
+#[derive(Clone, Debug)]
+pub struct Import {
+    /// Lock file with the new commits appended and annotated.
+    pub lock_file: LockFile,
+
+    /// Number of commits which were appended.
+    pub imported: usize,
+
+    /// Number of commits which were already part of the lock file.
+    pub skipped: usize,
+}
+
+/// Validates the given commits on top of the lock file and appends the ones it does not contain
+/// yet.
+///
+/// Every commit gets verified and published on an in-memory node after the commits of the lock
+/// file, commits which do not target the schema or field definition system schemas are refused.
+/// Appended commits are annotated with their schema like all other commits. Segments need to be
+/// resolved before.
+#[instrument(skip_all)]
+pub async fn import_commits(lock_file: &LockFile, commits: &[Commit]) -> Result<Import> {
+    if !lock_file.segments.is_empty() {
+        return Err(Error::UnresolvedLockSegments);
+    }
+
+    let store = MemoryStore::default();
+    let mut entry_hashes: HashSet<Hash> = HashSet::new();
+    for commit in lock_file.commits.iter().flatten() {
+        publish_commit(&store, commit).await?;
+        entry_hashes.insert(commit.entry_hash.clone());
+    }
+
+    let mut new_commits = Vec::new();
+    for (index, commit) in commits.iter().enumerate() {
+        if entry_hashes.contains(&commit.entry_hash) {
+            continue;
+        }
+
+        publish_commit(&store, commit)
+            .await
+            .map_err(|err| Error::InvalidImportedCommit(index, Box::new(err)))?;
+        entry_hashes.insert(commit.entry_hash.clone());
+        new_commits.push(commit.clone());
+    }
+    debug!(
+        "Importing {} of {} commits",
+        new_commits.len(),
+        commits.len()
+    );
+
+    let imported = new_commits.len();
+    let all_commits: Vec<Commit> = lock_file
+        .commits
+        .iter()
+        .flatten()
+        .cloned()
+        .chain(new_commits)
+        .collect();
+
+    Ok(Import {
+        lock_file: LockFile::new(&all_commits)
+            .with_builds(&lock_file.builds)
+            .with_tooling(lock_file.tooling.clone())
+            .migrate()?,
+        imported,
+        skipped: commits.len() - imported,
+    })
+}
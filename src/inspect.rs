@@ -0,0 +1,145 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! List the schemas committed in a lock file.
+//!
+//! Consumers of a schema bundle often only receive the lock file, without the schema file or the
+//! key pair it was built with. Inspecting materializes the commits and shows the latest version of
+//! every schema with its fields and all authors who signed it.
+use std::collections::HashMap;
+
+use p2panda_rs::entry::decode::decode_entry;
+use p2panda_rs::entry::traits::AsEntry;
+use p2panda_rs::identity::PublicKey;
+use p2panda_rs::operation::decode::decode_operation;
+use p2panda_rs::operation::plain::PlainValue;
+use p2panda_rs::operation::traits::{Actionable, Schematic};
+use p2panda_rs::operation::OperationId;
+use p2panda_rs::schema::{FieldName, SchemaId, SchemaName};
+use serde::Serialize;
+use tracing::instrument;
+
+use crate::build::get_previous_schemas;
+use crate::error::{Error, Result};
+use crate::lock_file::LockFile;
+use crate::schema_file::SchemaField;
+
+/// Latest version of a schema committed in a lock file.
+#[derive(Clone, Debug, Serialize)]
+pub struct LockedSchema {
+    /// Name of the schema.
+    pub name: SchemaName,
+
+    /// Id of the latest version of the schema.
+    pub schema_id: SchemaId,
+
+    /// Description of the latest version of the schema.
+    pub description: String,
+
+    /// Number of versions committed for this schema, including the latest one.
+    pub versions: usize,
+
+    /// Public key of the key pair which signed the latest version.
+    pub public_key: PublicKey,
+
+    /// Public keys of all key pairs which signed commits of the schema or its field definitions,
+    /// with their number of commits, in the order they signed their first commit.
+    pub authors: Vec<(PublicKey, usize)>,
+
+    /// Fields of the latest version, sorted by name. Relations point at schema ids.
+    pub fields: Vec<(FieldName, SchemaField)>,
+}
+
+/// Returns the latest version of every schema committed in the lock file, sorted by name.
+///
+/// Only the lock file is needed, no schema file or key pair.
+#[instrument(skip_all)]
+pub async fn inspect_lock_file(lock_file: &LockFile) -> Result<Vec<LockedSchema>> {
+    let previous_schemas = get_previous_schemas(lock_file).await?;
+
+    // Follow every operation back to the operation creating the document
+    let mut roots: HashMap<OperationId, OperationId> = HashMap::new();
+    let mut versions: HashMap<OperationId, usize> = HashMap::new();
+    let mut public_keys: HashMap<OperationId, PublicKey> = HashMap::new();
+    let mut field_schemas: HashMap<OperationId, OperationId> = HashMap::new();
+    let mut commit_authors: Vec<(OperationId, PublicKey)> = Vec::new();
+
+    for commit in lock_file.commits.iter().flatten() {
+        let operation = decode_operation(&commit.operation)?;
+        let operation_id = OperationId::new(&commit.entry_hash);
+        let root = match operation.previous() {
+            None => operation_id.clone(),
+            Some(previous) => previous
+                .iter()
+                .find_map(|previous_id| roots.get(previous_id))
+                .cloned()
+                .ok_or_else(|| Error::UnknownPreviousOperations(previous.clone()))?,
+        };
+        roots.insert(operation_id.clone(), root.clone());
+
+        let public_key = decode_entry(&commit.entry)?.public_key().to_owned();
+        commit_authors.push((root.clone(), public_key));
+
+        if operation.schema_id() != &SchemaId::SchemaDefinition(1) {
+            continue;
+        }
+
+        // Field definitions belong to the schema referring to them
+        let field_hashes = match operation
+            .fields()
+            .and_then(|fields| fields.get("fields").cloned())
+        {
+            Some(PlainValue::AmbiguousRelation(hashes)) => hashes,
+            Some(PlainValue::PinnedRelationList(view_ids)) => view_ids.concat(),
+            _ => Vec::new(),
+        };
+        for hash in &field_hashes {
+            if let Some(field_root) = roots.get(&OperationId::new(hash)) {
+                field_schemas.insert(field_root.clone(), root.clone());
+            }
+        }
+
+        *versions.entry(root).or_default() += 1;
+        public_keys.insert(operation_id, public_key);
+    }
+
+    let schemas = previous_schemas
+        .into_values()
+        .map(|previous_schema| {
+            let latest = previous_schema
+                .schema_view
+                .view_id()
+                .iter()
+                .next()
+                .expect("Document views have at least one operation")
+                .clone();
+            let root = &roots[&latest];
+
+            let mut authors: Vec<(PublicKey, usize)> = Vec::new();
+            for (_, public_key) in commit_authors.iter().filter(|(document, _)| {
+                document == root || field_schemas.get(document) == Some(root)
+            }) {
+                match authors.iter_mut().find(|(author, _)| author == public_key) {
+                    Some((_, count)) => *count += 1,
+                    None => authors.push((*public_key, 1)),
+                }
+            }
+
+            LockedSchema {
+                name: previous_schema.schema.id().name(),
+                schema_id: previous_schema.schema.id().to_owned(),
+                description: previous_schema.schema_view.description().to_owned(),
+                versions: versions[root],
+                public_key: public_keys[&latest],
+                authors,
+                fields: previous_schema
+                    .schema
+                    .fields()
+                    .iter()
+                    .map(|(name, field_type)| (name.to_owned(), SchemaField::from(field_type)))
+                    .collect(),
+            }
+        })
+        .collect();
+
+    Ok(schemas)
+}
@@ -0,0 +1,82 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Create, manage and deploy p2panda schemas.
+//!
+//! This library contains the core logic of the `fishy` command-line-tool: Parsing schema and lock
+//! files, calculating the difference between the current and previous schema versions and
+//! generating signed p2panda commits from them.
+pub mod aliases;
+pub mod authors;
+pub mod bench;
+#[cfg(feature = "bindings")]
+pub mod bindings;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod build;
+#[cfg(feature = "cassette")]
+pub mod cassette;
+pub mod checksum;
+#[cfg(feature = "client")]
+pub mod client;
+pub mod compat;
+pub mod config;
+pub mod constants;
+pub mod dependencies;
+pub mod deploy;
+pub mod deployments;
+pub mod diagnostic;
+#[cfg(feature = "client")]
+pub mod drift;
+#[cfg(feature = "client")]
+pub mod duplicates;
+mod error;
+pub mod external;
+pub mod foreign;
+pub mod format;
+pub mod graph;
+pub mod history;
+#[cfg(feature = "client")]
+pub mod hooks;
+pub mod import;
+pub mod inspect;
+pub mod lock_diff;
+pub mod lock_file;
+pub mod lock_signature;
+pub mod merge;
+pub mod migration;
+pub mod p2panda;
+#[cfg(feature = "client")]
+pub mod pinned;
+pub mod project;
+pub mod prune;
+#[cfg(feature = "client")]
+pub mod remote_signer;
+pub mod reporter;
+pub mod resign;
+pub mod sandbox;
+pub mod schema_editor;
+pub mod schema_file;
+pub mod signer;
+pub mod squash;
+#[cfg(feature = "client")]
+pub mod stale;
+#[cfg(test)]
+mod test_utils;
+#[cfg(feature = "client")]
+pub mod transport;
+pub mod utils;
+pub mod verify;
+#[cfg(feature = "client")]
+pub mod watch;
+
+pub use build::{build, build_with_signers, BuildResult, ToP2pandaSchema};
+#[cfg(feature = "client")]
+pub use checksum::verify_binding;
+pub use error::{Error, Result};
+#[cfg(feature = "macros")]
+pub use fishy_macros::{schema, ToP2pandaSchema};
+pub use signer::{Signer, Signers};
+pub use verify::verify_lock_file;
+
+#[cfg(feature = "bindings")]
+uniffi::setup_scaffolding!();
@@ -0,0 +1,206 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Differences between the schemas of two lock files.
+//!
+//! Lock files only contain signed and encoded commits, reviewing a dependency bump or another
+//! branch by looking at them is not possible. Comparing the schemas materialized from both lock
+//! files shows which schemas and fields were added, changed or removed instead.
+use std::collections::BTreeSet;
+
+use p2panda_rs::schema::{FieldName, SchemaId, SchemaName};
+use serde::Serialize;
+
+use crate::build::{
+    field_compatibility, get_previous_schemas, ChangeStatus, Compatibility, PreviousSchema,
+};
+use crate::error::Result;
+use crate::lock_file::LockFile;
+use crate::schema_file::SchemaField;
+
+/// Changed schemas between two lock files.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct LockDiff {
+    /// Schemas which were added, changed or removed, sorted by name.
+    pub schemas: Vec<SchemaChange>,
+}
+
+impl LockDiff {
+    /// Returns true if both lock files contain the same schemas.
+    pub fn is_empty(&self) -> bool {
+        self.schemas.is_empty()
+    }
+}
+
+/// Changes of a single schema between two lock files.
+#[derive(Clone, Debug, Serialize)]
+pub struct SchemaChange {
+    /// Name of the schema.
+    pub name: SchemaName,
+
+    /// Status of the schema in the new lock file compared to the old one.
+    pub status: ChangeStatus,
+
+    /// Id of the schema in the old lock file, `None` if it was added.
+    pub old_schema_id: Option<SchemaId>,
+
+    /// Id of the schema in the new lock file, `None` if it was removed.
+    pub new_schema_id: Option<SchemaId>,
+
+    /// Description of the schema in the old lock file, `None` if it was added.
+    pub old_description: Option<String>,
+
+    /// Description of the schema in the new lock file, `None` if it was removed.
+    pub new_description: Option<String>,
+
+    /// Fields which were added, changed or removed, sorted by name.
+    pub fields: Vec<FieldChange>,
+}
+
+impl SchemaChange {
+    /// Returns true if the description of a schema in both lock files is different.
+    pub fn description_changed(&self) -> bool {
+        self.old_description.is_some()
+            && self.new_description.is_some()
+            && self.old_description != self.new_description
+    }
+}
+
+/// Changes of a single field between two lock files.
+#[derive(Clone, Debug, Serialize)]
+pub struct FieldChange {
+    /// Name of the field.
+    pub name: FieldName,
+
+    /// Status of the field in the new lock file compared to the old one.
+    pub status: ChangeStatus,
+
+    /// Field type in the old lock file, `None` if it was added.
+    pub old: Option<SchemaField>,
+
+    /// Field type in the new lock file, `None` if it was removed.
+    pub new: Option<SchemaField>,
+}
+
+/// Compares the latest schema versions of two lock files.
+///
+/// Relations pointing at another version of the same schema are compatible changes, renamed
+/// fields show up as a removed and an added field.
+pub async fn diff_lock_files(old: &LockFile, new: &LockFile) -> Result<LockDiff> {
+    let old_schemas = get_previous_schemas(old).await?;
+    let new_schemas = get_previous_schemas(new).await?;
+
+    let names: BTreeSet<&SchemaName> = old_schemas.keys().chain(new_schemas.keys()).collect();
+
+    let schemas = names
+        .into_iter()
+        .map(|name| compare_schema(name, old_schemas.get(name), new_schemas.get(name)))
+        .filter(|schema| schema.status != ChangeStatus::Unchanged)
+        .collect();
+
+    Ok(LockDiff { schemas })
+}
+
+fn compare_schema(
+    name: &SchemaName,
+    old: Option<&PreviousSchema>,
+    new: Option<&PreviousSchema>,
+) -> SchemaChange {
+    let fields = diff_fields(&fields(old), &fields(new));
+
+    let old_schema_id = old.map(|schema| schema.schema.id().to_owned());
+    let new_schema_id = new.map(|schema| schema.schema.id().to_owned());
+
+    let status = match (&old_schema_id, &new_schema_id) {
+        (None, _) => ChangeStatus::Added,
+        (_, None) => ChangeStatus::Removed,
+        (Some(old_id), Some(new_id)) if old_id == new_id => ChangeStatus::Unchanged,
+        (Some(_), Some(_)) => ChangeStatus::Changed(fields_compatibility(&fields)),
+    };
+
+    SchemaChange {
+        name: name.clone(),
+        status,
+        old_schema_id,
+        new_schema_id,
+        old_description: old.map(|schema| schema.schema_view.description().to_owned()),
+        new_description: new.map(|schema| schema.schema_view.description().to_owned()),
+        fields,
+    }
+}
+
+/// Returns the fields which were added, changed or removed between two versions of a schema,
+/// sorted by name.
+pub(crate) fn diff_fields(
+    old_fields: &[(FieldName, SchemaField)],
+    new_fields: &[(FieldName, SchemaField)],
+) -> Vec<FieldChange> {
+    let field_names: BTreeSet<&FieldName> = old_fields
+        .iter()
+        .chain(new_fields.iter())
+        .map(|(field_name, _)| field_name)
+        .collect();
+
+    field_names
+        .into_iter()
+        .filter_map(|field_name| {
+            let find = |fields: &[(FieldName, SchemaField)]| {
+                fields
+                    .iter()
+                    .find(|(name, _)| name == field_name)
+                    .map(|(_, field)| field.clone())
+            };
+            let old_field = find(old_fields);
+            let new_field = find(new_fields);
+
+            let status = match (&old_field, &new_field) {
+                (None, _) => ChangeStatus::Added,
+                (_, None) => ChangeStatus::Removed,
+                (Some(old_field), Some(new_field)) if old_field == new_field => {
+                    return None;
+                }
+                (Some(old_field), Some(new_field)) => {
+                    ChangeStatus::Changed(field_compatibility(old_field, new_field))
+                }
+            };
+
+            Some(FieldChange {
+                name: field_name.clone(),
+                status,
+                old: old_field,
+                new: new_field,
+            })
+        })
+        .collect()
+}
+
+/// Returns the compatibility of a schema change with the given field changes.
+///
+/// Like in the build plan, only removed fields and breaking field changes are breaking.
+pub(crate) fn fields_compatibility(fields: &[FieldChange]) -> Compatibility {
+    let breaking = fields.iter().any(|field| {
+        matches!(
+            field.status,
+            ChangeStatus::Removed | ChangeStatus::Changed(Compatibility::Breaking)
+        )
+    });
+
+    if breaking {
+        Compatibility::Breaking
+    } else {
+        Compatibility::Compatible
+    }
+}
+
+/// Returns the fields of a schema with relations pointing at schema ids.
+pub(crate) fn fields(schema: Option<&PreviousSchema>) -> Vec<(FieldName, SchemaField)> {
+    schema
+        .map(|schema| {
+            schema
+                .schema
+                .fields()
+                .iter()
+                .map(|(name, field_type)| (name.to_owned(), SchemaField::from(field_type)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
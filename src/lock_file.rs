@@ -1,56 +1,1075 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
 
-use anyhow::{Context, Result};
-use p2panda_rs::entry::traits::AsEncodedEntry;
+use p2panda_rs::document::{DocumentId, DocumentViewId};
+use p2panda_rs::entry::decode::decode_entry;
+use p2panda_rs::entry::traits::{AsEncodedEntry, AsEntry};
+use p2panda_rs::entry::validate::validate_payload;
 use p2panda_rs::entry::EncodedEntry;
 use p2panda_rs::hash::Hash;
-use p2panda_rs::operation::EncodedOperation;
+use p2panda_rs::identity::PublicKey;
+use p2panda_rs::operation::decode::decode_operation;
+use p2panda_rs::operation::plain::PlainValue;
+use p2panda_rs::operation::traits::{Actionable, Schematic};
+use p2panda_rs::operation::{EncodedOperation, OperationAction, OperationId};
+use p2panda_rs::schema::{FieldName, FieldType, SchemaId, SchemaName};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::utils::files;
+use crate::aliases::{self, Aliases};
+use crate::constants::{GENERATED_FILE_HEADER, LOCK_SEGMENTS_DIR_NAME};
+use crate::dependencies::ResolvedDependency;
+use crate::error::{Error, Result};
+use crate::graph::sort_dependencies;
+use crate::schema_file::{Constraints, SchemaFile};
+use crate::utils::{files, time};
 
 /// Serializable format holding encoded and signed p2panda operations and entries.
 ///
 /// ```toml
-/// version = 1
+/// version = 2
 ///
 /// [[commits]]
 /// entry_hash = "..."
 /// entry = "..."
 /// operation = "..."
 ///
+/// [commits.annotation]
+/// schema = "event"
+/// action = "create"
+/// field = "title"
+///
 /// [[commits]]
 /// entry_hash = "..."
 /// entry = "..."
 /// operation = "..."
 ///
+/// [commits.annotation]
+/// schema = "event"
+/// action = "create"
+///
 /// # ...
 /// ```
-#[derive(Debug, Serialize, Deserialize)]
+///
+/// Commits written by fishy carry an annotation naming the schema, action and field they change,
+/// so diffs of the lock file can be reviewed. Annotations are not part of the signed data. Lock
+/// files of version 1 do not contain them and can still be read.
+///
+/// The commits can also be split into one segment file per schema, the lock file then serves as
+/// an index listing the segments in the `locks` directory next to itself. Changes to different
+/// schemas only touch different segments, which keeps diffs small. Branches building in parallel
+/// still claim the same logs for new documents of the same key pair, see `resolve_segments`.
+/// Segments are listed after the segments of the schemas their relations point at.
+///
+/// ```toml
+/// version = 2
+/// segments = ["locks/cafe.lock", "locks/icecream.lock"]
+/// ```
+///
+/// Metadata only used by tooling, like the constraints of fields or the resolved dependencies, is
+/// kept in a `tooling` section. It is not part of any operation and does not change the schema
+/// ids.
+///
+/// ```toml
+/// [tooling.constraints.event.title]
+/// max_length = 120
+/// ```
+///
+/// Every `fishy build` adds a `builds` entry with the fishy version, the time and an optional
+/// message, listing the commits it added. Like annotations it is not part of the signed data, it
+/// only helps to audit how the schemas came to be.
+///
+/// ```toml
+/// [[builds]]
+/// fishy_version = "0.2.1"
+/// timestamp = "2023-08-01T12:30:00Z"
+/// message = "add venue relation"
+/// commits = ["...", "..."]
+/// ```
+///
+/// Written lock files contain a `checksum` over all commits, in case of an index over the commits
+/// of all segments. It gets verified when loading the lock file, so corrupted or edited commits
+/// are detected before using them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct LockFile {
     pub version: LockFileVersion,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<Hash>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub segments: Vec<PathBuf>,
     pub commits: Option<Vec<Commit>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub builds: Vec<BuildMetadata>,
+    #[serde(default, skip_serializing_if = "ToolingMetadata::is_empty")]
+    pub tooling: ToolingMetadata,
 }
 
 impl LockFile {
     /// Returns a new, empty instance of `LockFile`.
     pub fn new(commits: &[Commit]) -> Self {
         Self {
-            version: LockFileVersion::V1,
+            version: LockFileVersion::V2,
+            checksum: None,
+            segments: Vec::new(),
             commits: Some(commits.to_vec()),
+            builds: Vec::new(),
+            tooling: ToolingMetadata::default(),
         }
     }
 
+    /// Sets the tooling metadata of this lock file.
+    pub fn with_tooling(mut self, tooling: ToolingMetadata) -> Self {
+        self.tooling = tooling;
+        self
+    }
+
+    /// Sets the metadata of the builds which added the commits of this lock file.
+    ///
+    /// Commits which are not part of this lock file get removed from the builds, builds without
+    /// any commits left are dropped.
+    pub fn with_builds(mut self, builds: &[BuildMetadata]) -> Self {
+        let entry_hashes: HashSet<&Hash> = self
+            .commits
+            .iter()
+            .flatten()
+            .map(|commit| &commit.entry_hash)
+            .collect();
+
+        self.builds = builds
+            .iter()
+            .map(|build| BuildMetadata {
+                commits: build
+                    .commits
+                    .iter()
+                    .filter(|entry_hash| entry_hashes.contains(entry_hash))
+                    .cloned()
+                    .collect(),
+                ..build.clone()
+            })
+            .filter(|build| !build.commits.is_empty())
+            .collect();
+        self
+    }
+
+    /// Returns the metadata of the build which added the commit with the given entry hash.
+    pub fn build_of(&self, entry_hash: &Hash) -> Option<&BuildMetadata> {
+        self.builds
+            .iter()
+            .find(|build| build.commits.contains(entry_hash))
+    }
+
     /// Loads a .toml file from the given path and serialises its content into a new `LockFile`
     /// instance.
+    ///
+    /// If the lock file is an index of segments, the commits of all segment files get loaded as
+    /// well.
     pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
         let data = files::read_file(&path)?;
-        let schema_file: Self =
-            toml::from_str(&data).with_context(|| "Invalid TOML syntax in lock file")?;
-        Ok(schema_file)
+        let mut lock_file: Self = data.parse()?;
+
+        let dir = parent_dir(path.as_ref());
+        lock_file.resolve_segments(|segment| files::read_file(dir.join(segment)))?;
+
+        Ok(lock_file)
+    }
+
+    /// Loads the segments listed by this lock file with the given function and merges their
+    /// commits, in the order of the index and followed by the commits of the index itself.
+    ///
+    /// The function receives the path of the segment relative to the lock file and returns its
+    /// content. Afterwards the lock file lists no segments anymore. Segments outside of the
+    /// `locks` directory fail with `Error::InvalidLockSegmentPath` before reading any of them.
+    ///
+    /// Every document has its own log, so segments never share one. Segments built on different
+    /// branches with the same key pair claim the same next log for their new documents though,
+    /// which fails with `Error::ConflictingLockSegments`.
+    pub fn resolve_segments(
+        &mut self,
+        mut read: impl FnMut(&Path) -> Result<String>,
+    ) -> Result<()> {
+        let mut commits = Vec::new();
+        let mut logs: HashMap<(PublicKey, u64), PathBuf> = HashMap::new();
+
+        for segment in &self.segments {
+            validate_segment_path(segment)?;
+        }
+
+        for segment in self.segments.drain(..) {
+            let segment_file: LockFile = read(&segment)?.parse()?;
+            if !segment_file.segments.is_empty() {
+                return Err(Error::NestedLockSegments(segment));
+            }
+
+            for commit in segment_file.commits.iter().flatten() {
+                let entry = decode_entry(&commit.entry)?;
+                let log = (*entry.public_key(), entry.log_id().as_u64());
+                match logs.get(&log) {
+                    Some(other) if other != &segment => {
+                        return Err(Error::ConflictingLockSegments(
+                            other.clone(),
+                            segment,
+                            log.0.to_string(),
+                            log.1,
+                        ));
+                    }
+                    Some(_) => (),
+                    None => {
+                        logs.insert(log, segment.clone());
+                    }
+                }
+            }
+
+            commits.extend(segment_file.commits.unwrap_or_default());
+        }
+
+        if !commits.is_empty() {
+            commits.extend(self.commits.take().unwrap_or_default());
+            self.commits = Some(commits);
+        }
+
+        self.verify_checksum()
+    }
+
+    /// Checks that the commits match the checksum of the lock file, if it has one.
+    ///
+    /// Lock files listing segments are only checked after resolving them.
+    pub fn verify_checksum(&self) -> Result<()> {
+        let Some(expected) = &self.checksum else {
+            return Ok(());
+        };
+
+        if !self.segments.is_empty() {
+            return Ok(());
+        }
+
+        let actual = commits_checksum(self.commits.iter().flatten());
+        if &actual != expected {
+            return Err(Error::LockFileChecksumMismatch(expected.clone(), actual));
+        }
+
+        Ok(())
+    }
+
+    /// Encodes the commits of this lock file into a compact CBOR bundle, for example to embed them
+    /// into an application.
+    ///
+    /// Entries and operations are kept as raw bytes, annotations and tooling metadata are left
+    /// out. Segments need to be resolved before.
+    pub fn to_cbor(&self) -> Result<Vec<u8>> {
+        if !self.segments.is_empty() {
+            return Err(Error::InvalidBundle(
+                "Segments of the lock file need to be resolved first".into(),
+            ));
+        }
+
+        let bundle = Bundle {
+            version: BUNDLE_VERSION,
+            commits: self
+                .commits
+                .iter()
+                .flatten()
+                .map(|commit| BundleCommit {
+                    entry: commit.entry.into_bytes(),
+                    operation: commit.operation.into_bytes(),
+                })
+                .collect(),
+        };
+
+        let mut data = Vec::new();
+        ciborium::ser::into_writer(&bundle, &mut data)
+            .map_err(|err| Error::InvalidBundle(err.to_string()))?;
+
+        Ok(data)
+    }
+
+    /// Encodes the commits of this lock file as JSON array of objects with hex-encoded `entry`
+    /// and `operation`, the format other p2panda tooling and aquadoggo test fixtures use.
+    ///
+    /// Annotations and tooling metadata are left out. Segments need to be resolved before.
+    pub fn to_json_commits(&self) -> Result<String> {
+        if !self.segments.is_empty() {
+            return Err(Error::UnresolvedLockSegments);
+        }
+
+        let commits: Vec<JsonCommit> = self
+            .commits
+            .iter()
+            .flatten()
+            .map(|commit| JsonCommit {
+                entry: commit.entry.clone(),
+                operation: commit.operation.clone(),
+            })
+            .collect();
+
+        serde_json::to_string_pretty(&commits).map_err(Error::SerializeJsonCommits)
+    }
+
+    /// Generates a Rust module embedding this lock file, for applications deploying their schemas
+    /// without access to the filesystem.
+    ///
+    /// The module contains the lock file as `SCHEMA_LOCK`, a `<NAME>_SCHEMA_ID` constant and a
+    /// `<name>_schema_id()` function returning the typed `SchemaId` of every schema and a
+    /// `lock_file()` function returning the parsed lock file. It only depends on `fishy`. Segments
+    /// need to be resolved before.
+    pub fn to_rust_module(&self) -> Result<String> {
+        if !self.segments.is_empty() {
+            return Err(Error::UnresolvedLockSegments);
+        }
+
+        let lock_str = self.to_toml_string()?;
+
+        // Use enough hashes to close the raw string only at its very end
+        let mut hashes = "#".to_string();
+        while lock_str.contains(&format!("\"{hashes}")) {
+            hashes.push('#');
+        }
+
+        let mut module = String::new();
+        for line in GENERATED_FILE_HEADER.lines() {
+            module.push_str(&format!("{}\n", line.replacen('#', "//", 1)));
+        }
+        module.push_str(
+            "\nuse fishy::lock_file::LockFile;\nuse fishy::p2panda::SchemaId;\n\n\
+            /// Lock file with the commits of all schemas.\n",
+        );
+        module.push_str(&format!(
+            "pub const SCHEMA_LOCK: &str = r{hashes}\"{lock_str}\"{hashes};\n"
+        ));
+
+        // Constant names are derived like aliases, schemas whose names only differ in case would
+        // get the same constant
+        let schema_ids = self.schema_ids()?;
+        Aliases::new(&schema_ids)?;
+
+        for schema_id in schema_ids {
+            let name = schema_id.name();
+            let const_name = format!("{}_SCHEMA_ID", aliases::alias_name(&name));
+            let fn_name = format!("{}_schema_id", name.to_string().to_lowercase());
+
+            module.push_str(&format!(
+                "\n/// Id of the latest version of the `{name}` schema.\n\
+                pub const {const_name}: &str = \"{schema_id}\";\n\n\
+                /// Returns the id of the latest version of the `{name}` schema.\n\
+                pub fn {fn_name}() -> SchemaId {{\n    \
+                    {const_name}.parse().expect(\"Embedded schema id is valid\")\n\
+                }}\n"
+            ));
+        }
+
+        module.push_str(
+            "\n/// Returns the embedded lock file, ready to be deployed.\n\
+            pub fn lock_file() -> LockFile {\n    \
+                SCHEMA_LOCK.parse().expect(\"Embedded lock file is valid\")\n\
+            }\n",
+        );
+
+        Ok(module)
+    }
+
+    /// Decodes a JSON array of commits in the format of `to_json_commits` into a lock file.
+    ///
+    /// Entries and operations are only decoded from hex, use `import_commits` to validate them.
+    pub fn from_json_commits(data: &str) -> Result<Self> {
+        let commits: Vec<JsonCommit> =
+            serde_json::from_str(data).map_err(Error::InvalidJsonCommits)?;
+
+        let commits: Vec<Commit> = commits
+            .iter()
+            .map(|commit| Commit::new(&commit.entry, &commit.operation))
+            .collect();
+
+        Ok(Self::new(&commits))
+    }
+
+    /// Decodes a CBOR bundle created with `to_cbor` into a lock file.
+    pub fn from_cbor(data: &[u8]) -> Result<Self> {
+        let bundle: Bundle =
+            ciborium::de::from_reader(data).map_err(|err| Error::InvalidBundle(err.to_string()))?;
+
+        if bundle.version != BUNDLE_VERSION {
+            return Err(Error::InvalidBundle(format!(
+                "unsupported bundle version {}",
+                bundle.version
+            )));
+        }
+
+        let commits: Vec<Commit> = bundle
+            .commits
+            .iter()
+            .map(|commit| {
+                Commit::new(
+                    &EncodedEntry::from_bytes(&commit.entry),
+                    &EncodedOperation::from_bytes(&commit.operation),
+                )
+            })
+            .collect();
+
+        Ok(Self::new(&commits))
+    }
+
+    /// Returns the ids of all schemas defined by the commits of this lock file, sorted by name.
+    ///
+    /// Only the operations get decoded to follow every schema definition to its latest version,
+    /// entries are not validated. Use `verify_lock_file` to check the integrity of the commits.
+    pub fn schema_ids(&self) -> Result<Vec<SchemaId>> {
+        let mut schema_ids: Vec<SchemaId> = self
+            .documents()?
+            .definitions
+            .into_iter()
+            .filter(|definition| !definition.deleted)
+            .map(|definition| {
+                SchemaId::new_application(
+                    &definition.name,
+                    &DocumentViewId::from(definition.latest),
+                )
+            })
+            .collect();
+        schema_ids.sort_by_key(|schema_id| schema_id.to_string());
+
+        Ok(schema_ids)
+    }
+
+    /// Returns true if the lock file was written in an older format, `migrate` upgrades it.
+    pub fn is_outdated(&self) -> bool {
+        !matches!(self.version, LockFileVersion::V2)
+            || self
+                .commits
+                .iter()
+                .flatten()
+                .any(|commit| commit.annotation.is_none())
+    }
+
+    /// Upgrades a lock file written in an older format to the current one.
+    ///
+    /// Commits without annotation get annotated with the latest name of their schema, the action
+    /// and the field they change. Entries and operations are kept as they are, so schema ids do
+    /// not change. Segments need to be resolved before.
+    pub fn migrate(&self) -> Result<Self> {
+        if !self.segments.is_empty() {
+            return Err(Error::UnresolvedLockSegments);
+        }
+
+        let documents = self.documents()?;
+
+        // First operation of the document and name of every field definition document
+        let mut roots: HashMap<OperationId, OperationId> = HashMap::new();
+        let mut field_names: HashMap<OperationId, FieldName> = HashMap::new();
+
+        let mut commits = Vec::new();
+        for (commit, owner) in self.commits.iter().flatten().zip(&documents.owners) {
+            let operation = decode_operation(&commit.operation)?;
+            let operation_id = OperationId::new(&commit.entry_hash);
+
+            let root = match operation.previous() {
+                None => Some(operation_id.clone()),
+                Some(previous) => previous
+                    .iter()
+                    .find_map(|previous_id| roots.get(previous_id))
+                    .cloned(),
+            };
+
+            let field = match (operation.schema_id(), &root) {
+                (SchemaId::SchemaFieldDefinition(_), Some(root)) => {
+                    let fields = operation.fields();
+                    if let Some(PlainValue::String(name)) =
+                        fields.as_ref().and_then(|fields| fields.get("name"))
+                    {
+                        field_names.insert(root.clone(), name.clone());
+                    }
+                    field_names.get(root).cloned()
+                }
+                _ => None,
+            };
+
+            if let Some(root) = root {
+                roots.insert(operation_id, root);
+            }
+
+            // Bootstrap documents belong to the schema of their operation
+            let schema = match (owner, operation.schema_id()) {
+                (Some(index), _) => Some(documents.definitions[*index].name.clone()),
+                (None, SchemaId::Application(name, _)) => Some(name.clone()),
+                (None, _) => None,
+            };
+
+            let annotation = match (&commit.annotation, schema) {
+                (Some(annotation), _) => Some(annotation.clone()),
+                (None, Some(schema)) => {
+                    Some(Annotation::new(&schema, operation.action(), field.as_ref()))
+                }
+                (None, None) => None,
+            };
+
+            commits.push(Commit {
+                annotation,
+                ..Commit::new(&commit.entry, &commit.operation)
+            });
+        }
+
+        Ok(Self::new(&commits)
+            .with_builds(&self.builds)
+            .with_tooling(self.tooling.clone()))
+    }
+
+    /// Returns the name of the schema every schema and field definition document of this lock file
+    /// belongs to.
+    ///
+    /// Field definitions belong to the first schema definition referring to any of their versions.
+    pub(crate) fn document_schemas(&self) -> Result<HashMap<DocumentId, SchemaName>> {
+        let documents = self.documents()?;
+        let mut document_schemas = HashMap::new();
+
+        for (commit, owner) in self.commits.iter().flatten().zip(&documents.owners) {
+            let Some(index) = owner else {
+                continue;
+            };
+
+            if decode_operation(&commit.operation)?.previous().is_none() {
+                document_schemas.insert(
+                    DocumentId::new(&OperationId::new(&commit.entry_hash)),
+                    documents.definitions[*index].name.clone(),
+                );
+            }
+        }
+
+        Ok(document_schemas)
+    }
+
+    /// Returns a copy of this lock file without the commits of the given documents.
+    ///
+    /// Every document is written into its own log, the remaining commits stay valid. Nodes which
+    /// received the removed commits keep them though.
+    pub fn without_documents(&self, document_ids: &[DocumentId]) -> Result<Self> {
+        let mut roots: HashMap<OperationId, OperationId> = HashMap::new();
+        let mut commits = Vec::new();
+
+        for commit in self.commits.iter().flatten() {
+            let operation = decode_operation(&commit.operation)?;
+            let operation_id = OperationId::new(&commit.entry_hash);
+
+            let root = match operation.previous() {
+                None => operation_id.clone(),
+                Some(previous) => previous
+                    .iter()
+                    .find_map(|previous_id| roots.get(previous_id))
+                    .cloned()
+                    .ok_or_else(|| Error::UnknownPreviousOperations(previous.clone()))?,
+            };
+            roots.insert(operation_id, root.clone());
+
+            if !document_ids.contains(&DocumentId::new(&root)) {
+                commits.push(commit.clone());
+            }
+        }
+
+        Ok(Self::new(&commits)
+            .with_builds(&self.builds)
+            .with_tooling(self.tooling.clone()))
+    }
+
+    /// Follows all schema definition documents of this lock file and the field definitions they
+    /// refer to.
+    fn documents(&self) -> Result<Documents> {
+        // Name and latest operation of every schema definition document
+        let mut definitions: Vec<Definition> = Vec::new();
+
+        // Position of the schema definition in `definitions` for every commit, field definitions
+        // are assigned after all commits were seen
+        let mut owners: Vec<Option<usize>> = Vec::new();
+
+        // First operation of the document for every known operation
+        let mut roots: HashMap<OperationId, OperationId> = HashMap::new();
+
+        // Position of the schema definition in `definitions` for every document
+        let mut documents: HashMap<OperationId, usize> = HashMap::new();
+
+        // Field definition operations referred to by schema definitions
+        let mut field_references: Vec<(OperationId, usize)> = Vec::new();
+
+        for commit in self.commits.iter().flatten() {
+            let operation = decode_operation(&commit.operation)?;
+            let operation_id = OperationId::new(&commit.entry_hash);
+
+            let root = match operation.previous() {
+                None => Some(operation_id.clone()),
+                Some(previous) => previous
+                    .iter()
+                    .find_map(|previous_id| roots.get(previous_id))
+                    .cloned(),
+            };
+
+            if !matches!(operation.schema_id(), SchemaId::SchemaDefinition(_)) {
+                if let Some(root) = root {
+                    roots.insert(operation_id, root);
+                }
+                owners.push(None);
+                continue;
+            }
+
+            let root = match (root, operation.previous()) {
+                (Some(root), _) => root,
+                (None, previous) => {
+                    let previous = previous.expect("Create operations are their own root");
+                    return Err(Error::UnknownPreviousOperations(previous.clone()));
+                }
+            };
+            roots.insert(operation_id.clone(), root.clone());
+
+            let fields = operation.fields();
+            let name = match fields.as_ref().and_then(|fields| fields.get("name")) {
+                Some(PlainValue::String(name)) => Some(SchemaName::new(name)?),
+                _ => None,
+            };
+
+            let index = match documents.get(&root) {
+                None => {
+                    let name = name.ok_or(Error::MissingSchemaName(commit.entry_hash.clone()))?;
+                    definitions.push(Definition {
+                        name,
+                        latest: operation_id.clone(),
+                        deleted: false,
+                    });
+                    documents.insert(root, definitions.len() - 1);
+                    definitions.len() - 1
+                }
+                Some(index) => {
+                    let definition = &mut definitions[*index];
+                    if let Some(name) = name {
+                        definition.name = name;
+                    }
+                    definition.latest = operation_id.clone();
+                    definition.deleted = operation.action() == OperationAction::Delete;
+                    *index
+                }
+            };
+
+            if let Some(PlainValue::PinnedRelationList(view_ids)) =
+                fields.as_ref().and_then(|fields| fields.get("fields"))
+            {
+                for hash in view_ids.iter().flatten() {
+                    field_references.push((OperationId::new(hash), index));
+                }
+            }
+
+            owners.push(Some(index));
+        }
+
+        // Field definitions belong to the schema definition referring to any of their versions
+        let mut field_owners: HashMap<OperationId, usize> = HashMap::new();
+        for (operation_id, index) in field_references {
+            if let Some(root) = roots.get(&operation_id) {
+                field_owners.entry(root.clone()).or_insert(index);
+            }
+        }
+
+        for (commit, owner) in self.commits.iter().flatten().zip(owners.iter_mut()) {
+            if owner.is_none() {
+                *owner = roots
+                    .get(&OperationId::new(&commit.entry_hash))
+                    .and_then(|root| field_owners.get(root))
+                    .copied();
+            }
+        }
+
+        Ok(Documents {
+            definitions,
+            owners,
+        })
+    }
+
+    /// Splits the commits into one segment file per schema inside of the `locks` directory next to
+    /// the lock file, and writes the lock file at the given path as an index of them.
+    ///
+    /// Commits which do not belong to any schema stay in the index. Segment files listed by a
+    /// previous version of the index which are not needed anymore get removed, an index listing
+    /// files outside of the `locks` directory fails with `Error::InvalidLockSegmentPath`.
+    pub fn write_segments(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let dir = parent_dir(path);
+        let documents = self.documents()?;
+
+        // Segments in the order their schemas first appear in
+        let mut segments: Vec<Segment> = Vec::new();
+        let mut remaining: Vec<Commit> = Vec::new();
+
+        for (commit, owner) in self.commits.iter().flatten().zip(&documents.owners) {
+            let Some(index) = owner else {
+                remaining.push(commit.clone());
+                continue;
+            };
+
+            let name = &documents.definitions[*index].name;
+            let position = match segments.iter().position(|segment| &segment.name == name) {
+                Some(position) => position,
+                None => {
+                    segments.push(Segment {
+                        name: name.clone(),
+                        commits: Vec::new(),
+                        relations: Vec::new(),
+                    });
+                    segments.len() - 1
+                }
+            };
+
+            let segment = &mut segments[position];
+            if let Some(relation) = relation_schema_name(commit)? {
+                if !segment.relations.contains(&relation) {
+                    segment.relations.push(relation);
+                }
+            }
+            segment.commits.push(commit.clone());
+        }
+
+        let segments: Vec<(PathBuf, Vec<Commit>)> = sort_segments(segments)
+            .into_iter()
+            .map(|segment| {
+                let file_name = format!("{}.lock", segment.name);
+                (
+                    Path::new(LOCK_SEGMENTS_DIR_NAME).join(file_name),
+                    segment.commits,
+                )
+            })
+            .collect();
+
+        let previous_segments = match path.exists() {
+            true => files::read_file(path)?.parse::<LockFile>()?.segments,
+            false => Vec::new(),
+        };
+
+        for segment in &previous_segments {
+            validate_segment_path(segment)?;
+        }
+
+        fs::create_dir_all(dir.join(LOCK_SEGMENTS_DIR_NAME))?;
+        for (segment, commits) in &segments {
+            LockFile::new(commits).write(dir.join(segment))?;
+        }
+
+        for segment in previous_segments {
+            let needed = segments.iter().any(|(path, _)| path == &segment);
+            if !needed && dir.join(&segment).exists() {
+                fs::remove_file(dir.join(segment))?;
+            }
+        }
+
+        // Commits of the segments come first when resolving them again
+        let checksum = commits_checksum(
+            segments
+                .iter()
+                .flat_map(|(_, commits)| commits)
+                .chain(&remaining),
+        );
+
+        let index = LockFile {
+            version: LockFileVersion::V2,
+            checksum: Some(checksum),
+            segments: segments.into_iter().map(|(path, _)| path).collect(),
+            commits: (!remaining.is_empty()).then_some(remaining),
+            builds: self.builds.clone(),
+            tooling: self.tooling.clone(),
+        };
+        index.write(path)
+    }
+
+    /// Serializes the lock file into .toml format, starting with a comment marking it as
+    /// generated.
+    ///
+    /// The checksum gets calculated over the current commits, indices of segments keep theirs.
+    pub fn to_toml_string(&self) -> Result<String> {
+        let lock_file = match self.segments.is_empty() {
+            true => Self {
+                checksum: Some(commits_checksum(self.commits.iter().flatten())),
+                ..self.clone()
+            },
+            false => self.clone(),
+        };
+
+        Ok(format!(
+            "{}\n\n{}",
+            GENERATED_FILE_HEADER,
+            toml::to_string_pretty(&lock_file)?
+        ))
+    }
+
+    /// Writes the serialized lock file into the given writer, for example a database blob or
+    /// network stream.
+    pub fn write_to(&self, mut writer: impl Write) -> Result<()> {
+        writer.write_all(self.to_toml_string()?.as_bytes())?;
+        Ok(())
+    }
+
+    /// Writes the lock file to a .toml file at the given path.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        files::write_file(path, &self.to_toml_string()?)
+    }
+}
+
+/// Returns the BLAKE3 hash over the entry hashes, entries and operations of the given commits.
+fn commits_checksum<'a>(commits: impl IntoIterator<Item = &'a Commit>) -> Hash {
+    let mut content = String::new();
+    for commit in commits {
+        content.push_str(&format!(
+            "{}\n{}\n{}\n",
+            commit.entry_hash, commit.entry, commit.operation
+        ));
+    }
+
+    Hash::new_from_bytes(content.as_bytes())
+}
+
+/// Returns the name of the schema a relation field definition points at.
+fn relation_schema_name(commit: &Commit) -> Result<Option<SchemaName>> {
+    let operation = decode_operation(&commit.operation)?;
+    if !matches!(operation.schema_id(), SchemaId::SchemaFieldDefinition(_)) {
+        return Ok(None);
+    }
+
+    let field_type = match operation
+        .fields()
+        .as_ref()
+        .and_then(|fields| fields.get("type"))
+    {
+        Some(PlainValue::String(field_type)) => field_type.parse::<FieldType>().ok(),
+        _ => None,
+    };
+
+    Ok(match field_type {
+        Some(FieldType::Relation(schema_id))
+        | Some(FieldType::RelationList(schema_id))
+        | Some(FieldType::PinnedRelation(schema_id))
+        | Some(FieldType::PinnedRelationList(schema_id)) => Some(schema_id.name()),
+        _ => None,
+    })
+}
+
+/// Orders segments so every schema comes after the schemas its relations point at, otherwise
+/// keeping their order.
+///
+/// Deploying the commits of the resolved segments this way never refers to a schema before it was
+/// created.
+///
+/// Relations to committed versions of a schema can form cycles between segments. Only relations
+/// to segments appearing earlier are taken into account then, they follow the order the commits
+/// were built in and never form a cycle.
+fn sort_segments(mut segments: Vec<Segment>) -> Vec<Segment> {
+    let names: Vec<SchemaName> = segments
+        .iter()
+        .map(|segment| segment.name.clone())
+        .collect();
+
+    let graph = |only_earlier: bool| {
+        segments
+            .iter()
+            .enumerate()
+            .map(|(index, segment)| {
+                let relations: Vec<SchemaName> = segment
+                    .relations
+                    .iter()
+                    .filter(
+                        |relation| match names.iter().position(|name| name == *relation) {
+                            Some(position) => {
+                                position != index && (!only_earlier || position < index)
+                            }
+                            None => false,
+                        },
+                    )
+                    .cloned()
+                    .collect();
+                (segment.name.clone(), relations)
+            })
+            .collect::<Vec<(SchemaName, Vec<SchemaName>)>>()
+    };
+
+    let order = sort_dependencies(graph(false)).unwrap_or_else(|_| {
+        sort_dependencies(graph(true)).expect("Relations to earlier segments are acyclic")
+    });
+    segments.sort_by_key(|segment| order.iter().position(|name| name == &segment.name));
+    segments
+}
+
+/// Checks that a segment listed by a lock file is a file inside of the `locks` directory.
+///
+/// Segments get read and removed relative to the lock file, other paths could point at any file
+/// on the system.
+fn validate_segment_path(path: &Path) -> Result<()> {
+    let mut components = path.components();
+    let in_segments_dir = components.next()
+        == Some(Component::Normal(LOCK_SEGMENTS_DIR_NAME.as_ref()))
+        && components.next().is_some()
+        && components.all(|component| matches!(component, Component::Normal(_)));
+
+    if !in_segments_dir {
+        return Err(Error::InvalidLockSegmentPath(path.to_path_buf()));
+    }
+
+    Ok(())
+}
+
+/// Returns the directory of the given lock file path, paths of segments are relative to it.
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+/// Schema definition documents of a lock file.
+struct Documents {
+    definitions: Vec<Definition>,
+
+    /// Position of the schema definition in `definitions` every commit belongs to, in the order
+    /// of the commits.
+    owners: Vec<Option<usize>>,
+}
+
+/// Commits of one schema written into its own segment file.
+struct Segment {
+    name: SchemaName,
+    commits: Vec<Commit>,
+
+    /// Names of the schemas relation fields of this schema point at.
+    relations: Vec<SchemaName>,
+}
+
+/// Name and latest operation of a schema definition document.
+struct Definition {
+    name: SchemaName,
+    latest: OperationId,
+    deleted: bool,
+}
+
+impl FromStr for LockFile {
+    type Err = Error;
+
+    /// Parses the content of a .toml lock file and verifies its checksum.
+    fn from_str(data: &str) -> Result<Self> {
+        let lock_file: Self = toml::from_str(data).map_err(Error::InvalidLockFile)?;
+        lock_file.verify_checksum()?;
+        Ok(lock_file)
+    }
+}
+
+/// Version of the binary lock bundle format.
+const BUNDLE_VERSION: u64 = 1;
+
+/// Commits of a lock file in compact binary form.
+#[derive(Serialize, Deserialize)]
+struct Bundle {
+    version: u64,
+    commits: Vec<BundleCommit>,
+}
+
+/// Encoded entry and operation of a commit as raw bytes.
+#[derive(Serialize, Deserialize)]
+struct BundleCommit {
+    #[serde(with = "serde_bytes")]
+    entry: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    operation: Vec<u8>,
+}
+
+/// Encoded entry and operation of a commit, hex-encoded when serialized as JSON.
+#[derive(Serialize, Deserialize)]
+struct JsonCommit {
+    entry: EncodedEntry,
+    operation: EncodedOperation,
+}
+
+/// Metadata of the schemas which is only relevant for tooling and not part of p2panda itself.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolingMetadata {
+    /// Constraints of fields, by schema and field name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub constraints: BTreeMap<SchemaName, BTreeMap<FieldName, Constraints>>,
+
+    /// Schema bundles of other projects which were resolved when building, by dependency name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub dependencies: BTreeMap<String, ResolvedDependency>,
+}
+
+impl ToolingMetadata {
+    /// Collects the tooling metadata of all schemas in the schema file.
+    pub fn new(schema_file: &SchemaFile) -> Self {
+        let mut constraints = BTreeMap::new();
+
+        for (schema_name, schema_definition) in schema_file.iter() {
+            let fields: BTreeMap<FieldName, Constraints> = schema_definition
+                .fields
+                .iter()
+                .filter_map(|(field_name, field)| {
+                    field
+                        .constraints()
+                        .filter(|constraints| !constraints.is_empty())
+                        .map(|constraints| (field_name.clone(), constraints.clone()))
+                })
+                .collect();
+
+            if !fields.is_empty() {
+                constraints.insert(schema_name.clone(), fields);
+            }
+        }
+
+        Self {
+            constraints,
+            dependencies: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the resolved dependencies of the project.
+    pub fn with_dependencies(mut self, dependencies: BTreeMap<String, ResolvedDependency>) -> Self {
+        self.dependencies = dependencies;
+        self
+    }
+
+    /// Returns true if there is no tooling metadata.
+    pub fn is_empty(&self) -> bool {
+        self.constraints.is_empty() && self.dependencies.is_empty()
+    }
+}
+
+/// Metadata of a build which added commits to the lock file, only used to audit its history.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BuildMetadata {
+    /// Version of fishy which created the commits.
+    pub fishy_version: String,
+
+    /// Time of the build as RFC 3339 timestamp in UTC.
+    pub timestamp: String,
+
+    /// Message describing the changes, as given with `fishy build --message`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+
+    /// Entry hashes of the commits added by the build.
+    pub commits: Vec<Hash>,
+}
+
+impl BuildMetadata {
+    /// Returns the metadata of a build adding the given commits with this version of fishy, now.
+    pub fn new(commits: &[Commit]) -> Self {
+        Self {
+            fishy_version: env!("CARGO_PKG_VERSION").to_string(),
+            timestamp: time::format_rfc3339(SystemTime::now()),
+            message: None,
+            commits: commits
+                .iter()
+                .map(|commit| commit.entry_hash.clone())
+                .collect(),
+        }
+    }
+
+    /// Sets the message describing the changes of the build.
+    pub fn with_message(mut self, message: Option<&str>) -> Self {
+        self.message = message.map(str::to_string);
+        self
     }
 }
 
@@ -58,6 +1077,9 @@ impl LockFile {
 #[derive(Debug, Clone)]
 pub enum LockFileVersion {
     V1,
+
+    /// Commits can carry annotations.
+    V2,
 }
 
 impl LockFileVersion {
@@ -65,12 +1087,13 @@ impl LockFileVersion {
     pub fn as_u64(&self) -> u64 {
         match self {
             LockFileVersion::V1 => 1,
+            LockFileVersion::V2 => 2,
         }
     }
 }
 
 impl Serialize for LockFileVersion {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
@@ -79,7 +1102,7 @@ impl Serialize for LockFileVersion {
 }
 
 impl<'de> Deserialize<'de> for LockFileVersion {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
@@ -87,6 +1110,7 @@ impl<'de> Deserialize<'de> for LockFileVersion {
 
         match version {
             1 => Ok(LockFileVersion::V1),
+            2 => Ok(LockFileVersion::V2),
             _ => Err(serde::de::Error::custom(format!(
                 "unsupported lock file version {}",
                 version
@@ -107,6 +1131,10 @@ pub struct Commit {
 
     /// Encoded p2panda operation.
     pub operation: EncodedOperation,
+
+    /// Human-readable description of the change, only used to review lock files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotation: Option<Annotation>,
 }
 
 impl Commit {
@@ -116,6 +1144,341 @@ impl Commit {
             entry_hash: entry.hash(),
             entry: entry.clone(),
             operation: operation.clone(),
+            annotation: None,
+        }
+    }
+
+    /// Sets the annotation describing this commit.
+    pub fn with_annotation(mut self, annotation: Annotation) -> Self {
+        self.annotation = Some(annotation);
+        self
+    }
+
+    /// Checks the integrity of this commit on its own.
+    ///
+    /// Verifies that the entry hash matches the entry, that the entry is correctly encoded and
+    /// signed by the public key it contains and that the operation matches the payload hash and
+    /// size of the entry. Checking the log integrity requires the other commits, use
+    /// `verify_lock_file` for this.
+    pub fn verify(&self) -> Result<()> {
+        if self.entry_hash != self.entry.hash() {
+            return Err(Error::InvalidEntryHash(self.entry_hash.clone()));
         }
+
+        let entry = decode_entry(&self.entry)?;
+        validate_payload(&entry, &self.operation)
+            .map_err(|err| Error::InvalidPayload(self.entry_hash.clone(), err))?;
+
+        Ok(())
+    }
+}
+
+/// Human-readable description of a commit, naming the schema and field it changes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Annotation {
+    /// Name of the schema the commit belongs to.
+    pub schema: SchemaName,
+
+    /// Whether the commit creates, updates or deletes a document.
+    pub action: AnnotationAction,
+
+    /// Name of the field, if the commit changes a field definition.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field: Option<FieldName>,
+}
+
+impl Annotation {
+    /// Returns a new annotation of a commit with the given operation action.
+    pub fn new(schema: &SchemaName, action: OperationAction, field: Option<&FieldName>) -> Self {
+        Self {
+            schema: schema.clone(),
+            action: action.into(),
+            field: field.cloned(),
+        }
+    }
+}
+
+/// Action of an annotated commit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnotationAction {
+    Create,
+    Update,
+    Delete,
+}
+
+impl From<OperationAction> for AnnotationAction {
+    fn from(action: OperationAction) -> Self {
+        match action {
+            OperationAction::Create => Self::Create,
+            OperationAction::Update => Self::Update,
+            OperationAction::Delete => Self::Delete,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::document::DocumentId;
+    use p2panda_rs::hash::Hash;
+    use p2panda_rs::operation::OperationId;
+
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crate::error::Error;
+    use crate::test_utils::{build_commits, key_pair, temp_dir};
+    use crate::verify::verify_lock_file;
+
+    use super::{AnnotationAction, Commit, LockFile};
+
+    const VENUES: &str = r#"
+        [venues]
+        description = "Places to meet"
+        fields = { name = { type = "str" } }
+    "#;
+
+    const VENUES_AND_EVENTS: &str = r#"
+        [venues]
+        description = "Places to meet"
+        fields = { name = { type = "str" }, city = { type = "str" } }
+
+        [events]
+        description = "Things happening"
+        fields = { title = { type = "str" } }
+    "#;
+
+    fn entry_hashes(commits: &[Commit]) -> Vec<Hash> {
+        commits
+            .iter()
+            .map(|commit| commit.entry_hash.clone())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn verifies_checksum() {
+        let commits = build_commits(VENUES, &[], &key_pair(1)).await;
+        let data = LockFile::new(&commits).to_toml_string().unwrap();
+
+        let mut lock_file: LockFile = data.parse().unwrap();
+        assert!(lock_file.checksum.is_some());
+
+        lock_file.commits.as_mut().unwrap().pop();
+        assert!(matches!(
+            lock_file.verify_checksum(),
+            Err(Error::LockFileChecksumMismatch(_, _))
+        ));
+    }
+
+    #[tokio::test]
+    async fn builds_identical_lock_files() {
+        let dir = temp_dir("reproducible-builds");
+        let mut lock_files = Vec::new();
+
+        // Updating several schemas depends on the order of the previous schemas
+        for run in 0..2 {
+            let mut commits = build_commits(VENUES, &[], &key_pair(1)).await;
+            commits.extend(build_commits(VENUES_AND_EVENTS, &commits, &key_pair(1)).await);
+
+            let lock_path = dir.join(format!("schema-{run}.lock"));
+            LockFile::new(&commits).write(&lock_path).unwrap();
+            lock_files.push(fs::read(&lock_path).unwrap());
+        }
+
+        assert_eq!(lock_files[0], lock_files[1]);
+    }
+
+    #[tokio::test]
+    async fn generates_rust_module() {
+        // The module is compiled by the `rust_module` integration test
+        let commits = build_commits(VENUES, &[], &key_pair(1)).await;
+        let module = LockFile::new(&commits).to_rust_module().unwrap();
+        assert_eq!(module, include_str!("../tests/fixtures/schema_lock.rs"));
+    }
+
+    /// Replaces the last character of the given hex string in the lock file with another one.
+    fn tamper(data: &str, hex: &str) -> String {
+        let last = if hex.ends_with('0') { "1" } else { "0" };
+        data.replace(hex, &format!("{}{last}", &hex[..hex.len() - 1]))
+    }
+
+    #[tokio::test]
+    async fn rejects_tampered_commits() {
+        let commits = build_commits(VENUES, &[], &key_pair(1)).await;
+        let data = LockFile::new(&commits).to_toml_string().unwrap();
+
+        for hex in [
+            commits[0].entry.to_string(),
+            commits[0].operation.to_string(),
+        ] {
+            assert!(matches!(
+                tamper(&data, &hex).parse::<LockFile>(),
+                Err(Error::LockFileChecksumMismatch(_, _))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn loads_lock_file_without_checksum() {
+        let commits = build_commits(VENUES, &[], &key_pair(1)).await;
+        let data = LockFile::new(&commits).to_toml_string().unwrap();
+
+        // Lock files written before checksums were introduced
+        let data: String = data
+            .lines()
+            .filter(|line| !line.starts_with("checksum ="))
+            .map(|line| format!("{line}\n"))
+            .collect();
+
+        let lock_file: LockFile = data.parse().unwrap();
+        assert!(lock_file.checksum.is_none());
+        assert_eq!(
+            entry_hashes(&lock_file.commits.unwrap()),
+            entry_hashes(&commits)
+        );
+    }
+
+    #[tokio::test]
+    async fn round_trips_cbor_bundle() {
+        let commits = build_commits(VENUES, &[], &key_pair(1)).await;
+        let lock_file = LockFile::new(&commits);
+
+        let bundle = LockFile::from_cbor(&lock_file.to_cbor().unwrap()).unwrap();
+        let bundle_commits = bundle.commits.unwrap();
+
+        assert_eq!(entry_hashes(&bundle_commits), entry_hashes(&commits));
+        for (bundle_commit, commit) in bundle_commits.iter().zip(&commits) {
+            assert_eq!(bundle_commit.entry, commit.entry);
+            assert_eq!(bundle_commit.operation, commit.operation);
+        }
+    }
+
+    #[test]
+    fn rejects_cbor_bundle_with_segments() {
+        let lock_file = LockFile {
+            segments: vec!["locks/venues.lock".into()],
+            ..LockFile::new(&[])
+        };
+
+        assert!(matches!(lock_file.to_cbor(), Err(Error::InvalidBundle(_))));
+        assert!(matches!(
+            LockFile::from_cbor(b"not a bundle"),
+            Err(Error::InvalidBundle(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn removes_all_commits_of_documents() {
+        let key_pair = key_pair(1);
+        let mut commits = build_commits(VENUES, &[], &key_pair).await;
+        commits.extend(build_commits(VENUES_AND_EVENTS, &commits, &key_pair).await);
+
+        // Schema definition of venues got created and updated when adding a field
+        let venues: Vec<&Commit> = commits
+            .iter()
+            .filter(|commit| {
+                let annotation = commit.annotation.as_ref().unwrap();
+                annotation.schema.to_string() == "venues" && annotation.field.is_none()
+            })
+            .collect();
+        assert_eq!(venues.len(), 2);
+        assert_eq!(
+            venues[0].annotation.as_ref().unwrap().action,
+            AnnotationAction::Create
+        );
+        let venues_id = DocumentId::new(&OperationId::new(&venues[0].entry_hash));
+
+        let lock_file = LockFile::new(&commits)
+            .without_documents(&[venues_id])
+            .unwrap();
+        let remaining = lock_file.commits.unwrap();
+
+        assert_eq!(remaining.len(), commits.len() - 2);
+        for commit in venues {
+            assert!(!entry_hashes(&remaining).contains(&commit.entry_hash));
+        }
+    }
+
+    #[test]
+    fn rejects_segments_outside_of_segments_dir() {
+        for segment in [
+            "/etc/passwd",
+            "../outside.lock",
+            "locks/../../outside.lock",
+            "outside.lock",
+            "locks",
+        ] {
+            let mut lock_file = LockFile {
+                segments: vec![segment.into()],
+                ..LockFile::new(&[])
+            };
+
+            let result = lock_file.resolve_segments(|_| panic!("Read segment {segment}"));
+            assert!(
+                matches!(result, Err(Error::InvalidLockSegmentPath(_))),
+                "{segment}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn keeps_files_outside_of_segments_dir() {
+        let dir = temp_dir("lock-segments-outside");
+        let outside = dir.join("outside.lock");
+        fs::write(&outside, "").unwrap();
+
+        let lock_path = dir.join("project").join("schema.lock");
+        fs::create_dir_all(lock_path.parent().unwrap()).unwrap();
+        LockFile {
+            segments: vec![PathBuf::from("../outside.lock"), outside.clone()],
+            ..LockFile::new(&[])
+        }
+        .write(&lock_path)
+        .unwrap();
+
+        let commits = build_commits(VENUES, &[], &key_pair(1)).await;
+        let result = LockFile::new(&commits).write_segments(&lock_path);
+
+        assert!(matches!(result, Err(Error::InvalidLockSegmentPath(_))));
+        assert!(outside.exists());
+    }
+
+    #[tokio::test]
+    async fn round_trips_segments_with_cyclic_relations() {
+        let key_pair = key_pair(1);
+        let mut commits = build_commits(VENUES, &[], &key_pair).await;
+        let venues_id = LockFile::new(&commits).schema_ids().unwrap()[0].clone();
+
+        // Events relate to the committed version of venues, the new version of venues relates
+        // back to events
+        let schema_file = format!(
+            r#"
+            [events]
+            description = "Things happening"
+            fields = {{ venue = {{ type = "relation", schema = {{ id = "{venues_id}" }} }} }}
+
+            [venues]
+            description = "Places to meet"
+            fields = {{ name = {{ type = "str" }}, events = {{ type = "relation_list", schema = {{ name = "events" }} }} }}
+            "#
+        );
+        commits.extend(build_commits(&schema_file, &commits, &key_pair).await);
+
+        let lock_path = temp_dir("lock-segments-cyclic").join("schema.lock");
+        LockFile::new(&commits).write_segments(&lock_path).unwrap();
+        let lock_file = LockFile::from_path(&lock_path).unwrap();
+
+        let mut expected = entry_hashes(&commits);
+        let mut resolved = entry_hashes(lock_file.commits.as_ref().unwrap());
+        expected.sort();
+        resolved.sort();
+        assert_eq!(resolved, expected);
+
+        let verification = verify_lock_file(&lock_file).await.unwrap();
+        assert_eq!(
+            verification.schema_ids,
+            LockFile::new(&commits).schema_ids().unwrap()
+        );
     }
 }
@@ -0,0 +1,107 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Detached signatures of lock files.
+//!
+//! Applications downloading lock files at runtime can check who published them before deploying
+//! the commits on their node. The signature is kept next to the lock file in `schema.lock.sig`:
+//!
+//! ```toml
+//! public_key = "2f8e50c2ede6d936ecc3144187ff1c273808185cfbc5ff3d3748d1ff7353fc96"
+//! signature = "..."
+//! ```
+//!
+//! The signature covers the exact content of the lock file. Indices of lock file segments contain
+//! a checksum over the commits of all segments, which gets verified when loading them.
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use ed25519_dalek::Signature;
+use p2panda_rs::identity::{KeyPair, PublicKey};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::signer::Signer;
+use crate::utils::files;
+
+/// Prefix of the signed bytes, so lock file signatures can not be mistaken for signatures of
+/// entries made with the same key.
+const SIGNATURE_CONTEXT: &[u8] = b"fishy lock file signature v1\n";
+
+/// Detached signature of a lock file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LockSignature {
+    /// Public key of the author who signed the lock file.
+    pub public_key: PublicKey,
+
+    /// Hex-encoded ed25519 signature over the content of the lock file.
+    pub signature: String,
+}
+
+impl LockSignature {
+    /// Signs the content of a lock file with the given signer.
+    pub async fn sign(data: &[u8], signer: &dyn Signer) -> Result<Self> {
+        let signature = signer.sign(&signed_bytes(data)).await?;
+
+        Ok(Self {
+            public_key: signer.public_key(),
+            signature: hex::encode(signature),
+        })
+    }
+
+    /// Checks that the content of a lock file was signed by the given public key.
+    pub fn verify(&self, data: &[u8], public_key: &PublicKey) -> Result<()> {
+        if &self.public_key != public_key {
+            return Err(Error::UntrustedLockSignature(
+                self.public_key.to_string(),
+                public_key.to_string(),
+            ));
+        }
+
+        let signature = hex::decode(&self.signature)
+            .ok()
+            .and_then(|bytes| Signature::from_bytes(&bytes).ok())
+            .ok_or_else(|| {
+                Error::InvalidLockSignature(
+                    "signature is not a hex-encoded ed25519 signature".into(),
+                )
+            })?;
+
+        KeyPair::verify(public_key, &signed_bytes(data), &signature).map_err(|_| {
+            Error::InvalidLockSignature(
+                "signature does not match the lock file, sign it again after changing it".into(),
+            )
+        })
+    }
+
+    /// Loads a signature file from the given path.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        files::read_file(path)?.parse()
+    }
+
+    /// Writes the signature to a .toml file at the given path.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        files::write_file(path, &toml::to_string_pretty(&self)?)
+    }
+}
+
+impl FromStr for LockSignature {
+    type Err = Error;
+
+    /// Parses the content of a .toml signature file.
+    fn from_str(data: &str) -> Result<Self> {
+        toml::from_str(data).map_err(|err| Error::InvalidLockSignature(err.to_string()))
+    }
+}
+
+/// Returns the path of the signature file of a lock file, `schema.lock.sig` for `schema.lock`.
+pub fn signature_path(lock_path: impl AsRef<Path>) -> PathBuf {
+    let mut path = lock_path.as_ref().as_os_str().to_owned();
+    path.push(".sig");
+    PathBuf::from(path)
+}
+
+/// Returns the bytes which get signed for the given lock file content.
+fn signed_bytes(data: &[u8]) -> Vec<u8> {
+    [SIGNATURE_CONTEXT, data].concat()
+}
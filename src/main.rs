@@ -1,16 +1,23 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 mod commands;
-mod constants;
-mod lock_file;
-mod schema_file;
-mod utils;
+mod credentials;
+mod terminal;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use clap::{Parser, Subcommand};
-use p2panda_rs::test_utils::memory_store::MemoryStore;
+use fishy::client::{Client, ClientBuilder};
+use fishy::hooks::DeployHook;
+use fishy::project::Project;
+use fishy::prune::PruneMode;
+use fishy::sandbox::SandboxOptions;
+use tracing_subscriber::EnvFilter;
+
+use crate::commands::{CompletionKind, ExportFormat, Template};
+use crate::credentials::{read_passphrase, Credentials};
 
 /// Command line arguments to configure fishy.
 #[derive(Debug, Parser)]
@@ -19,6 +26,10 @@ use p2panda_rs::test_utils::memory_store::MemoryStore;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Log what is happening to stderr, use `-vv` to show every commit.
+    #[arg(short = 'v', long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
 }
 
 /// Subcommands with extra arguments defining the features of fishy.
@@ -33,25 +44,154 @@ enum Commands {
         /// Name of the schema which will be created.
         #[arg(short = 'n', default_value = None)]
         schema_name: Option<String>,
+
+        /// Create multiple related schemas from a template instead of a single schema.
+        #[arg(short = 't', long, conflicts_with = "schema_name")]
+        template: Option<Template>,
+
+        /// Walk through creating two related schemas step by step, explaining what building and
+        /// deploying them does.
+        #[arg(long, action=clap::ArgAction::SetTrue, conflicts_with_all = ["schema_name", "template"])]
+        guided: bool,
     },
 
     /// Automatically creates and signs p2panda data from a key pair and the defined schemas.
     Build {
-        /// Path to the schema definition file.
+        /// Path to the schema definition file, `-` reads it from stdin.
         #[arg(short = 's', long = "schema", default_value = "schema.toml")]
         schema_path: PathBuf,
 
-        /// Path to the lock file with signed and encoded p2panda data.
-        #[arg(short = 'l', long = "lock", default_value = "schema.lock")]
-        lock_path: PathBuf,
+        /// Path to the lock file with signed and encoded p2panda data, `-` reads it from stdin and
+        /// writes it to stdout [default: schema.lock, inside of the state directory if configured
+        /// in fishy.toml].
+        #[arg(short = 'l', long = "lock")]
+        lock_path: Option<PathBuf>,
 
         /// Path to the key pair file, storing a hex-encoded ed25519 private key.
         #[arg(short = 'k', long = "key", default_value = "secret.txt")]
         private_key_path: PathBuf,
 
+        /// Path to the file mapping stable alias names to current schema ids [default:
+        /// schema-aliases.toml, next to the lock file or the schema file if the lock file is `-`].
+        #[arg(short = 'a', long = "aliases")]
+        aliases_path: Option<PathBuf>,
+
+        /// Prefix prepended to all schema names, overriding the namespace set in fishy.toml and the
+        /// prefix set in the schema file.
+        #[arg(long = "prefix")]
+        prefix: Option<String>,
+
+        /// Message describing the changes, kept with the new commits in the lock file together
+        /// with the fishy version and time of the build.
+        #[arg(short = 'm', long, conflicts_with = "only_show_plan_and_exit")]
+        message: Option<String>,
+
         /// Show current state without committing any changes.
         #[arg(short = 'i', long = "inspect", action=clap::ArgAction::SetTrue)]
         only_show_plan_and_exit: bool,
+
+        /// Print only the plan in the given format, `human`, `json` or `markdown`.
+        #[arg(long, requires = "only_show_plan_and_exit")]
+        format: Option<String>,
+
+        /// GraphQL endpoint of a p2panda node to check for identical, already existing schemas
+        /// before creating new ones and for newer updates missing in the lock file.
+        #[arg(short = 'e', long)]
+        endpoint: Option<String>,
+
+        /// GraphQL endpoint of a p2panda node to check that schemas which relations point at with
+        /// their id exist, their names and fields are shown with the plan.
+        #[arg(long, value_name = "ENDPOINT")]
+        resolve_external: Option<String>,
+
+        /// Custom HTTP header sent with every request to the nodes, for example
+        /// "Authorization: Bearer <token>".
+        #[arg(long = "header", value_parser = parse_header)]
+        headers: Vec<(String, String)>,
+
+        /// Build untrusted schema files with limited file sizes, number of schemas and fields and
+        /// a timeout.
+        #[arg(long, action=clap::ArgAction::SetTrue)]
+        sandbox: bool,
+
+        /// URL of a remote signing service which signs commits instead of the key pair file, a
+        /// token stored with `fishy login <URL>` gets sent with every request.
+        #[arg(long)]
+        signer: Option<String>,
+
+        /// Replace an empty or invalid key pair file with a newly generated key pair without
+        /// asking, the previous file is kept with a `.bak` extension.
+        #[arg(long, action=clap::ArgAction::SetTrue, conflicts_with = "signer")]
+        regenerate_key: bool,
+
+        /// Write all commits of the lock file into a compact CBOR bundle at the given path, for
+        /// example to embed them into an app.
+        #[arg(long, value_name = "PATH", conflicts_with = "only_show_plan_and_exit")]
+        emit_bundle: Option<PathBuf>,
+    },
+
+    /// Replace the history of the lock file with the create operations of the current schemas.
+    /// Changes all schema ids, nodes need to be deployed again.
+    Squash {
+        /// Path to the schema definition file.
+        #[arg(short = 's', long = "schema", default_value = "schema.toml")]
+        schema_path: PathBuf,
+
+        /// Path to the lock file with signed and encoded p2panda data [default: schema.lock, inside
+        /// of the state directory if configured in fishy.toml].
+        #[arg(short = 'l', long = "lock")]
+        lock_path: Option<PathBuf>,
+
+        /// Path to the key pair file, storing a hex-encoded ed25519 private key.
+        #[arg(short = 'k', long = "key", default_value = "secret.txt")]
+        private_key_path: PathBuf,
+    },
+
+    /// Replay the history of the lock file signed by a new key pair, changing all schema ids.
+    Resign {
+        /// Path to the schema definition file.
+        #[arg(short = 's', long = "schema", default_value = "schema.toml")]
+        schema_path: PathBuf,
+
+        /// Path to the lock file with signed and encoded p2panda data [default: schema.lock, inside
+        /// of the state directory if configured in fishy.toml].
+        #[arg(short = 'l', long = "lock")]
+        lock_path: Option<PathBuf>,
+
+        /// Path to the key pair file which signed all commits of the lock file.
+        #[arg(long = "old-key", default_value = "secret.txt")]
+        old_key_path: PathBuf,
+
+        /// Path to the key pair file signing the commits from now on.
+        #[arg(long = "new-key")]
+        new_key_path: PathBuf,
+    },
+
+    /// Delete schemas which are not defined in the schema file anymore, together with their
+    /// fields.
+    Prune {
+        /// Path to the schema definition file.
+        #[arg(short = 's', long = "schema", default_value = "schema.toml")]
+        schema_path: PathBuf,
+
+        /// Path to the lock file with signed and encoded p2panda data [default: schema.lock, inside
+        /// of the state directory if configured in fishy.toml].
+        #[arg(short = 'l', long = "lock")]
+        lock_path: Option<PathBuf>,
+
+        /// Path to the key pair file, storing a hex-encoded ed25519 private key.
+        #[arg(short = 'k', long = "key", default_value = "secret.txt")]
+        private_key_path: PathBuf,
+
+        /// Remove the commits of the schemas from the lock file instead of deleting them, only if
+        /// the lock file was never deployed.
+        #[arg(long, action=clap::ArgAction::SetTrue)]
+        remove: bool,
+
+        /// Delete field definitions which were removed from their schemas as well. Older versions
+        /// of these schemas refer to them, which is why it can not be combined with `--remove`.
+        #[arg(long, action=clap::ArgAction::SetTrue, conflicts_with = "remove")]
+        gc: bool,
     },
 
     /// Deploy created schemas on a node.
@@ -60,47 +200,738 @@ enum Commands {
         #[arg(short = 'e', long, default_value = "http://localhost:2020/graphql")]
         endpoint: String,
 
-        /// Path to the lock file with signed and encoded p2panda data.
-        #[arg(short = 'l', long = "lock", default_value = "schema.lock")]
-        lock_path: PathBuf,
+        /// Path to the lock file with signed and encoded p2panda data [default: schema.lock, inside
+        /// of the state directory if configured in fishy.toml].
+        #[arg(short = 'l', long = "lock")]
+        lock_path: Option<PathBuf>,
+
+        /// Timeout for every request to the node and to the hooks in seconds.
+        #[arg(long, default_value_t = 5)]
+        timeout: u64,
+
+        /// Custom HTTP header sent with every request to the node, for example
+        /// "Authorization: Bearer <token>".
+        #[arg(long = "header", value_parser = parse_header)]
+        headers: Vec<(String, String)>,
+
+        /// How often a failed request to the node gets retried.
+        #[arg(long, default_value_t = 0)]
+        retries: usize,
+
+        /// User agent sent with every request to the node.
+        #[arg(long)]
+        user_agent: Option<String>,
+
+        /// URL of an application service which gets notified about the deployed schema ids.
+        #[arg(long = "hook")]
+        hooks: Vec<String>,
+
+        /// How often a failed hook request gets retried.
+        #[arg(long, default_value_t = 3)]
+        hook_retries: usize,
+    },
+
+    /// Sign the lock file with the key pair, writing the signature into `<lock>.sig`.
+    Sign {
+        /// Path to the lock file with signed and encoded p2panda data [default: schema.lock, inside
+        /// of the state directory if configured in fishy.toml].
+        #[arg(short = 'l', long = "lock")]
+        lock_path: Option<PathBuf>,
+
+        /// Path to the key pair file, storing a hex-encoded ed25519 private key.
+        #[arg(short = 'k', long = "key", default_value = "secret.txt")]
+        private_key_path: PathBuf,
+    },
+
+    /// Check that the lock file is signed by the expected public key and all its commits are
+    /// valid.
+    Verify {
+        /// Path to the lock file with signed and encoded p2panda data [default: schema.lock, inside
+        /// of the state directory if configured in fishy.toml].
+        #[arg(short = 'l', long = "lock")]
+        lock_path: Option<PathBuf>,
+
+        /// Public key the lock file needs to be signed by [default: public key of the key pair
+        /// file].
+        #[arg(long)]
+        public_key: Option<String>,
+
+        /// Path to the key pair file, storing a hex-encoded ed25519 private key.
+        #[arg(short = 'k', long = "key", default_value = "secret.txt")]
+        private_key_path: PathBuf,
+    },
+
+    /// Compare the schemas a node serves in its GraphQL API with the lock file.
+    Drift {
+        /// GraphQL endpoint of p2panda node which gets compared.
+        #[arg(short = 'e', long, default_value = "http://localhost:2020/graphql")]
+        endpoint: String,
+
+        /// Path to the lock file with signed and encoded p2panda data [default: schema.lock, inside
+        /// of the state directory if configured in fishy.toml].
+        #[arg(short = 'l', long = "lock")]
+        lock_path: Option<PathBuf>,
+    },
+
+    /// Print which p2panda data versions fishy produces and which node versions accept them.
+    Compat {
+        /// GraphQL endpoint of p2panda node which gets checked for compatibility.
+        #[arg(short = 'e', long)]
+        endpoint: Option<String>,
+    },
+
+    /// Measure publish latency and throughput of a node with synthetic commits.
+    BenchNode {
+        /// GraphQL endpoint of p2panda node which gets benchmarked.
+        #[arg(short = 'e', long, default_value = "http://localhost:2020/graphql")]
+        endpoint: String,
+
+        /// Number of commits to publish.
+        #[arg(short = 'n', long, default_value_t = 100)]
+        count: usize,
+    },
+
+    /// Print schemas which get created, updated or removed on a node, until pressing Ctrl-C.
+    WatchNode {
+        /// GraphQL endpoint of p2panda node which gets watched.
+        #[arg(short = 'e', long, default_value = "http://localhost:2020/graphql")]
+        endpoint: String,
+
+        /// Seconds to wait between asking the node for its schemas.
+        #[arg(long, default_value_t = 5)]
+        interval: u64,
+    },
+
+    /// Print a Markdown guide for app developers listing schema id and field changes between two
+    /// lock file states.
+    MigrationGuide {
+        /// Previous state, either a path to a lock file or a git revision, for example a tag.
+        from: String,
+
+        /// New state, either a path to a lock file or a git revision. Defaults to the current
+        /// lock file.
+        to: Option<String>,
+
+        /// Path to the lock file, used to look it up in git revisions [default: schema.lock,
+        /// inside of the state directory if configured in fishy.toml].
+        #[arg(short = 'l', long = "lock")]
+        lock_path: Option<PathBuf>,
+    },
+
+    /// Upgrade lock files written by older versions of fishy to the current format, annotating
+    /// all commits.
+    MigrateLock {
+        /// Path to the lock file with signed and encoded p2panda data [default: schema.lock, inside
+        /// of the state directory if configured in fishy.toml].
+        #[arg(short = 'l', long = "lock")]
+        lock_path: Option<PathBuf>,
+    },
+
+    /// Export the commits of the lock file in another format, for example to inject them into a
+    /// node through other channels than GraphQL.
+    Export {
+        /// Format to export the commits in.
+        #[arg(short = 'f', long = "format")]
+        format: ExportFormat,
+
+        /// Path to the lock file with signed and encoded p2panda data [default: schema.lock, inside
+        /// of the state directory if configured in fishy.toml].
+        #[arg(short = 'l', long = "lock")]
+        lock_path: Option<PathBuf>,
+
+        /// Path to write the export to [default: stdout].
+        #[arg(short = 'o', long = "output")]
+        output: Option<PathBuf>,
+    },
+
+    /// Validate commits produced elsewhere, as exported with `fishy export --format
+    /// json-commits`, and append them to the lock file.
+    Import {
+        /// Path to a JSON file with an array of objects with hex-encoded `entry` and `operation`.
+        commits_path: PathBuf,
+
+        /// Path to the lock file with signed and encoded p2panda data [default: schema.lock, inside
+        /// of the state directory if configured in fishy.toml].
+        #[arg(short = 'l', long = "lock")]
+        lock_path: Option<PathBuf>,
+    },
+
+    /// List every schema of a lock file with its schema id, number of versions, fields and
+    /// signing public key, without a schema file or key pair.
+    Inspect {
+        /// Path to the lock file with signed and encoded p2panda data [default: schema.lock, inside
+        /// of the state directory if configured in fishy.toml].
+        #[arg(short = 'l', long = "lock")]
+        lock_path: Option<PathBuf>,
+    },
+
+    /// Show every version of a schema in the lock file with its schema id, changed fields and the
+    /// commit introducing it.
+    History {
+        /// Name of the schema, all schemas if not set.
+        schema_name: Option<String>,
+
+        /// Path to the lock file with signed and encoded p2panda data [default: schema.lock, inside
+        /// of the state directory if configured in fishy.toml].
+        #[arg(short = 'l', long = "lock")]
+        lock_path: Option<PathBuf>,
+    },
+
+    /// Show which schemas and fields were added, changed or removed between two lock files.
+    DiffLock {
+        /// Path to the old lock file.
+        old: PathBuf,
+
+        /// Path to the new lock file.
+        new: PathBuf,
+    },
+
+    /// Merge the commits of two diverged lock files, for example as a git merge driver.
+    MergeLock {
+        /// Path to our lock file.
+        ours: PathBuf,
+
+        /// Path to their lock file.
+        theirs: PathBuf,
+
+        /// Path to write the merged lock file to [default: our lock file].
+        #[arg(short = 'o', long = "output")]
+        output: Option<PathBuf>,
+    },
+
+    /// Copy the lock files of external schema dependencies into the vendor directory, to build
+    /// without accessing external projects.
+    Vendor {
+        /// Path to the schema definition file.
+        #[arg(short = 's', long = "schema", default_value = "schema.toml")]
+        schema_path: PathBuf,
+    },
+
+    /// Format schema files in one canonical way, sorting schemas and fields by name.
+    Fmt {
+        /// Path to the schema definition file, or a directory to format all .toml files in it.
+        #[arg(short = 's', long = "schema", default_value = "schema.toml")]
+        schema_path: PathBuf,
+
+        /// Do not change any files, fail if a file is not formatted yet. Useful for CI.
+        #[arg(long, action=clap::ArgAction::SetTrue)]
+        check: bool,
+    },
+
+    /// Back up and restore the key pair signing the schemas.
+    Key {
+        #[command(subcommand)]
+        command: KeyCommands,
+    },
+
+    /// Store an encrypted token which gets sent with every request to a protected node.
+    Login {
+        /// GraphQL endpoint of p2panda node the token is for.
+        #[arg(default_value = "http://localhost:2020/graphql")]
+        endpoint: String,
+    },
+
+    /// Remove the stored token of a node.
+    Logout {
+        /// GraphQL endpoint of p2panda node the token is for.
+        #[arg(default_value = "http://localhost:2020/graphql")]
+        endpoint: String,
+    },
+
+    /// Print completion candidates for shells, one per line.
+    #[command(name = "__complete", hide = true)]
+    Complete {
+        /// Kind of value to complete.
+        kind: CompletionKind,
+
+        /// Only print candidates starting with this prefix.
+        #[arg(default_value = "")]
+        prefix: String,
+    },
+}
+
+/// Subcommands to manage the key pair.
+#[derive(Debug, Subcommand)]
+enum KeyCommands {
+    /// Print the private key as recovery phrase of 24 words, to back it up offline.
+    ExportMnemonic {
+        /// Path to the key pair file, storing a hex-encoded ed25519 private key.
+        #[arg(short = 'k', long = "key", default_value = "secret.txt")]
+        private_key_path: PathBuf,
+    },
+
+    /// Restore the key pair file from a recovery phrase.
+    ImportMnemonic {
+        /// Path of the key pair file to create.
+        #[arg(short = 'k', long = "key", default_value = "secret.txt")]
+        private_key_path: PathBuf,
     },
 }
 
+/// Parses a HTTP header in the format "Name: value".
+fn parse_header(value: &str) -> Result<(String, String)> {
+    let (name, value) = value
+        .split_once(':')
+        .ok_or_else(|| anyhow!("Header needs to be in the format \"Name: value\""))?;
+
+    Ok((name.trim().to_owned(), value.trim().to_owned()))
+}
+
+/// Attaches the token stored with `fishy login` for this endpoint, unless an `Authorization` header
+/// was given explicitly.
+fn authenticate(
+    builder: ClientBuilder,
+    endpoint: &str,
+    headers: &[(String, String)],
+) -> Result<ClientBuilder> {
+    if headers
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case("Authorization"))
+    {
+        return Ok(builder);
+    }
+
+    match stored_token(endpoint)? {
+        Some(token) => Ok(builder.bearer_token(&token)),
+        None => Ok(builder),
+    }
+}
+
+/// Returns the token stored with `fishy login` for this endpoint, asking for the passphrase only
+/// if there is one.
+fn stored_token(endpoint: &str) -> Result<Option<String>> {
+    let credentials = Credentials::load(&Credentials::default_path()?)?;
+    if !credentials.contains(endpoint) {
+        return Ok(None);
+    }
+
+    credentials.token(endpoint, &read_passphrase(false)?)
+}
+
+/// Returns the project directory containing the schema file.
+fn project_dir(schema_path: &Path) -> &Path {
+    match schema_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+/// Opens the project in the given directory with the settings of its `fishy.toml` file.
+fn open_project(dir: &Path) -> Result<Project> {
+    Project::open(dir).context("Could not read project settings")
+}
+
+/// Opens the project in the given directory like `open_project`, moving generated files into a
+/// newly configured state directory.
+///
+/// Only commands which write generated files anyhow use this, all others leave the working tree
+/// untouched.
+fn open_and_migrate_project(dir: &Path) -> Result<Project> {
+    let project = open_project(dir)?;
+
+    for (from, to) in project.migrate(dir)? {
+        eprintln!("Moved {} to {}", from.display(), to.display());
+    }
+
+    Ok(project)
+}
+
+/// Opens the project of the given schema file with the given lock and key pair file, keeping the
+/// other settings of its `fishy.toml` file.
+fn project_with_lock(
+    schema_path: &Path,
+    lock_path: Option<PathBuf>,
+    private_key_path: &Path,
+) -> Result<Project> {
+    let defaults = open_project(project_dir(schema_path))?;
+
+    let lock_path = lock_path.unwrap_or(defaults.lock_path);
+    let mut project = Project::new(schema_path, &lock_path, private_key_path)
+        .with_cache_dir(defaults.cache_dir)
+        .with_lock_segments(defaults.lock_segments);
+    for (name, path) in defaults.keys {
+        project = project.with_key(&name, path);
+    }
+    for public_key in defaults.trusted_keys {
+        project = project.with_trusted_key(public_key);
+    }
+    for (name, dependency) in defaults.dependencies {
+        project = project.with_dependency(&name, dependency);
+    }
+    if let Some(prefix) = defaults.prefix {
+        project = project.with_prefix(&prefix);
+    }
+
+    Ok(project)
+}
+
+/// Logs events of the library to stderr, `RUST_LOG` overrides the verbosity.
+fn init_tracing(verbose: u8) {
+    let filter = match (EnvFilter::try_from_default_env(), verbose) {
+        (Ok(filter), _) => filter,
+        (Err(_), 0) => return,
+        (Err(_), 1) => EnvFilter::new("fishy=debug"),
+        (Err(_), _) => EnvFilter::new("fishy=trace"),
+    };
+
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .init();
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Cli::parse();
-    let store = MemoryStore::default();
+    init_tracing(args.verbose);
 
     match args.command {
         Commands::Init {
             target_dir,
             schema_name,
+            template,
+            guided,
         } => {
-            commands::init(target_dir, schema_name)
-                .with_context(|| "Could not initialise new fishy project")?;
+            if guided {
+                commands::init_guided(target_dir)
+                    .await
+                    .with_context(|| "Could not initialise new fishy project")?;
+            } else {
+                commands::init(target_dir, schema_name, template)
+                    .with_context(|| "Could not initialise new fishy project")?;
+            }
         }
         Commands::Build {
             schema_path,
             lock_path,
             private_key_path,
+            aliases_path,
+            prefix,
+            message,
             only_show_plan_and_exit,
+            format,
+            endpoint,
+            resolve_external,
+            headers,
+            sandbox,
+            signer,
+            regenerate_key,
+            emit_bundle,
         } => {
+            let defaults = open_and_migrate_project(project_dir(&schema_path))?;
+
+            let lock_path = lock_path.unwrap_or(defaults.lock_path);
+            let mut project = Project::new(&schema_path, &lock_path, &private_key_path)
+                .with_cache_dir(defaults.cache_dir)
+                .with_lock_segments(defaults.lock_segments);
+            if let Some(aliases_path) = aliases_path {
+                project = project.with_aliases_path(&aliases_path);
+            }
+            for (name, path) in defaults.keys {
+                project = project.with_key(&name, path);
+            }
+            for public_key in defaults.trusted_keys {
+                project = project.with_trusted_key(public_key);
+            }
+            for (name, dependency) in defaults.dependencies {
+                project = project.with_dependency(&name, dependency);
+            }
+            if let Some(prefix) = prefix.or(defaults.prefix) {
+                project = project.with_prefix(&prefix);
+            }
+            if let Some(message) = message {
+                project = project.with_message(&message);
+            }
+            if sandbox {
+                project = project.with_sandbox(SandboxOptions::default());
+            }
+
+            let client = |endpoint: String| -> Result<(Client, String)> {
+                let mut builder = Client::builder(&endpoint);
+                for (name, value) in &headers {
+                    builder = builder.header(name, value);
+                }
+
+                Ok((
+                    authenticate(builder, &endpoint, &headers)?.build(),
+                    endpoint,
+                ))
+            };
+
             commands::build(
-                store,
-                schema_path,
-                lock_path,
-                private_key_path,
+                project,
                 only_show_plan_and_exit,
+                endpoint.map(client).transpose()?,
+                resolve_external.map(client).transpose()?,
+                signer.as_deref(),
+                format.as_deref(),
+                regenerate_key,
+                emit_bundle.as_deref(),
             )
             .await
             .with_context(|| "Could not create or update schema")?;
         }
+        Commands::Squash {
+            schema_path,
+            lock_path,
+            private_key_path,
+        } => {
+            let project = project_with_lock(&schema_path, lock_path, &private_key_path)?;
+
+            commands::squash(project)
+                .await
+                .with_context(|| "Could not squash lock file")?
+        }
+        Commands::Resign {
+            schema_path,
+            lock_path,
+            old_key_path,
+            new_key_path,
+        } => {
+            let project = project_with_lock(&schema_path, lock_path, &new_key_path)?;
+
+            commands::resign(project, &old_key_path, &new_key_path)
+                .await
+                .with_context(|| "Could not re-sign lock file")?
+        }
+        Commands::Prune {
+            schema_path,
+            lock_path,
+            private_key_path,
+            remove,
+            gc,
+        } => {
+            let project = project_with_lock(&schema_path, lock_path, &private_key_path)?;
+            let mode = if remove {
+                PruneMode::Remove
+            } else {
+                PruneMode::Tombstone
+            };
+
+            commands::prune(project, mode, gc)
+                .await
+                .with_context(|| "Could not prune lock file")?
+        }
         Commands::Deploy {
             lock_path,
             endpoint,
-        } => commands::deploy(lock_path, &endpoint)
+            timeout,
+            headers,
+            retries,
+            user_agent,
+            hooks,
+            hook_retries,
+        } => {
+            let hooks: Vec<DeployHook> = hooks
+                .iter()
+                .map(|url| {
+                    DeployHook::new(url, hook_retries).with_timeout(Duration::from_secs(timeout))
+                })
+                .collect();
+
+            let mut builder = Client::builder(&endpoint)
+                .timeout(Duration::from_secs(timeout))
+                .retries(retries);
+
+            for (name, value) in &headers {
+                builder = builder.header(name, value);
+            }
+
+            if let Some(user_agent) = &user_agent {
+                builder = builder.user_agent(user_agent);
+            }
+
+            let client = authenticate(builder, &endpoint, &headers)?.build();
+            let defaults = open_project(Path::new("."))?;
+            let project =
+                project_with_lock(&defaults.schema_path, lock_path, &defaults.private_key_path)?;
+
+            commands::deploy(client, project, &endpoint, &hooks)
+                .await
+                .with_context(|| "Could not publish schemas to node")?
+        }
+        Commands::Sign {
+            lock_path,
+            private_key_path,
+        } => {
+            let lock_path = match lock_path {
+                Some(lock_path) => lock_path,
+                None => open_project(Path::new("."))?.lock_path,
+            };
+
+            commands::sign(&lock_path, &private_key_path)
+                .await
+                .with_context(|| "Could not sign lock file")?
+        }
+        Commands::Verify {
+            lock_path,
+            public_key,
+            private_key_path,
+        } => {
+            let lock_path = match lock_path {
+                Some(lock_path) => lock_path,
+                None => open_project(Path::new("."))?.lock_path,
+            };
+
+            commands::verify(&lock_path, public_key.as_deref(), &private_key_path)
+                .await
+                .with_context(|| "Could not verify lock file")?
+        }
+        Commands::Drift {
+            endpoint,
+            lock_path,
+        } => {
+            let client = authenticate(Client::builder(&endpoint), &endpoint, &[])?.build();
+            let lock_path = match lock_path {
+                Some(lock_path) => lock_path,
+                None => open_project(Path::new("."))?.lock_path,
+            };
+
+            commands::drift(client, lock_path, &endpoint)
+                .await
+                .with_context(|| "Could not detect schema drift")?
+        }
+        Commands::Compat { endpoint } => {
+            let client = match endpoint {
+                Some(endpoint) => Some((
+                    authenticate(Client::builder(&endpoint), &endpoint, &[])?.build(),
+                    endpoint,
+                )),
+                None => None,
+            };
+
+            commands::compat(client)
+                .await
+                .with_context(|| "Could not check compatibility")?
+        }
+        Commands::BenchNode { endpoint, count } => {
+            let client = authenticate(Client::builder(&endpoint), &endpoint, &[])?.build();
+
+            commands::bench_node(client, &endpoint, count)
+                .await
+                .with_context(|| "Could not benchmark node")?
+        }
+        Commands::WatchNode { endpoint, interval } => {
+            let client = authenticate(Client::builder(&endpoint), &endpoint, &[])?.build();
+
+            commands::watch_node(client, &endpoint, interval)
+                .await
+                .with_context(|| "Could not watch node")?
+        }
+        Commands::MigrationGuide {
+            from,
+            to,
+            lock_path,
+        } => {
+            let lock_path = match lock_path {
+                Some(lock_path) => lock_path,
+                None => open_project(Path::new("."))?.lock_path,
+            };
+
+            commands::migration_guide(&from, to.as_deref(), &lock_path)
+                .await
+                .with_context(|| "Could not generate migration guide")?
+        }
+        Commands::MigrateLock { lock_path } => {
+            let defaults = open_and_migrate_project(Path::new("."))?;
+            let project = match lock_path {
+                Some(lock_path) => {
+                    Project::new(&defaults.schema_path, lock_path, &defaults.private_key_path)
+                        .with_lock_segments(defaults.lock_segments)
+                }
+                None => defaults,
+            };
+
+            commands::migrate_lock(project).with_context(|| "Could not migrate lock files")?
+        }
+        Commands::Export {
+            format,
+            lock_path,
+            output,
+        } => {
+            let lock_path = match lock_path {
+                Some(lock_path) => lock_path,
+                None => open_project(Path::new("."))?.lock_path,
+            };
+
+            commands::export(&lock_path, format, output.as_deref())
+                .with_context(|| "Could not export lock file")?
+        }
+        Commands::Import {
+            commits_path,
+            lock_path,
+        } => {
+            let defaults = open_project(Path::new("."))?;
+            let project = match lock_path {
+                Some(lock_path) => defaults.trusted_keys.iter().fold(
+                    Project::new(&defaults.schema_path, lock_path, &defaults.private_key_path)
+                        .with_lock_segments(defaults.lock_segments),
+                    |project, public_key| project.with_trusted_key(*public_key),
+                ),
+                None => defaults,
+            };
+
+            commands::import(project, &commits_path)
+                .await
+                .with_context(|| "Could not import commits")?
+        }
+        Commands::Inspect { lock_path } => {
+            let defaults = open_project(Path::new("."))?;
+            let lock_path = lock_path.unwrap_or(defaults.lock_path);
+
+            commands::inspect(&lock_path, &defaults.trusted_keys)
+                .await
+                .with_context(|| "Could not inspect lock file")?
+        }
+        Commands::History {
+            schema_name,
+            lock_path,
+        } => {
+            let lock_path = match lock_path {
+                Some(lock_path) => lock_path,
+                None => open_project(Path::new("."))?.lock_path,
+            };
+
+            commands::history(&lock_path, schema_name.as_deref())
+                .await
+                .with_context(|| "Could not show schema history")?
+        }
+        Commands::DiffLock { old, new } => commands::diff_lock(&old, &new)
+            .await
+            .with_context(|| "Could not compare lock files")?,
+        Commands::MergeLock {
+            ours,
+            theirs,
+            output,
+        } => commands::merge_lock(&ours, &theirs, output.as_deref())
             .await
-            .with_context(|| "Could not publish schemas to node")?,
+            .with_context(|| "Could not merge lock files")?,
+        Commands::Vendor { schema_path } => {
+            let mut project = open_project(project_dir(&schema_path))?;
+            project.schema_path = schema_path;
+
+            commands::vendor(project).with_context(|| "Could not vendor external schemas")?
+        }
+        Commands::Fmt { schema_path, check } => {
+            commands::fmt(&schema_path, check).with_context(|| "Could not format schema files")?
+        }
+        Commands::Key { command } => match command {
+            KeyCommands::ExportMnemonic { private_key_path } => {
+                commands::export_mnemonic(&private_key_path)
+                    .with_context(|| "Could not export key pair")?
+            }
+            KeyCommands::ImportMnemonic { private_key_path } => {
+                commands::import_mnemonic(&private_key_path)
+                    .with_context(|| "Could not restore key pair")?
+            }
+        },
+        Commands::Login { endpoint } => {
+            commands::login(&endpoint).with_context(|| "Could not store token")?
+        }
+        Commands::Logout { endpoint } => {
+            commands::logout(&endpoint).with_context(|| "Could not remove token")?
+        }
+        Commands::Complete { kind, prefix } => commands::complete(kind, &prefix),
     }
 
     Ok(())
@@ -0,0 +1,449 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Merge lock files of diverged branches.
+//!
+//! Two branches which both ran `fishy build` append different commits to the same lock file,
+//! git can not merge these in a meaningful way. Merging replays the commits of both lock files on
+//! an in-memory node instead and reports the changes which really conflict with each other.
+//!
+//! The merge can be used as git merge driver for lock files:
+//!
+//! ```text
+//! # .gitattributes
+//! schema.lock merge=fishy
+//!
+//! # .git/config
+//! [merge "fishy"]
+//!     name = fishy lock file merge
+//!     driver = fishy merge-lock %A %B --output %A
+//! ```
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display};
+
+use p2panda_rs::document::traits::AsDocument;
+use p2panda_rs::document::DocumentId;
+use p2panda_rs::entry::decode::decode_entry;
+use p2panda_rs::entry::traits::AsEntry;
+use p2panda_rs::hash::Hash;
+use p2panda_rs::operation::decode::decode_operation;
+use p2panda_rs::operation::traits::{Actionable, Schematic};
+use p2panda_rs::operation::OperationId;
+use p2panda_rs::schema::system::SchemaView;
+use p2panda_rs::schema::{SchemaId, SchemaName};
+use p2panda_rs::storage_provider::traits::DocumentStore;
+use p2panda_rs::test_utils::memory_store::MemoryStore;
+use tracing::{debug, instrument};
+
+use crate::build::publish_commit;
+use crate::error::{Error, Result};
+use crate::lock_file::{BuildMetadata, Commit, LockFile};
+
+/// Changes of two lock files which can not be merged.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MergeConflict {
+    /// Both lock files contain a different entry at the same position in the log of an author,
+    /// usually because both branches were built with the same key pair.
+    ForkedLog {
+        public_key: String,
+        log_id: u64,
+        seq_num: u64,
+    },
+
+    /// Both lock files change the same document.
+    ConcurrentChanges {
+        schema: SchemaName,
+        document_id: DocumentId,
+    },
+
+    /// Both lock files create a schema with the same name.
+    DuplicateSchema(SchemaName),
+}
+
+impl Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeConflict::ForkedLog {
+                public_key,
+                log_id,
+                seq_num,
+            } => write!(
+                f,
+                "Both lock files contain entry {seq_num} of log {log_id} by {public_key}, keep \
+                one lock file and run `fishy build` again to add the changes of the other branch"
+            ),
+            MergeConflict::ConcurrentChanges {
+                schema,
+                document_id,
+            } => write!(
+                f,
+                "Both lock files change document {document_id} of schema {schema}, keep one lock \
+                file and run `fishy build` again to add the changes of the other branch"
+            ),
+            MergeConflict::DuplicateSchema(schema) => write!(
+                f,
+                "Both lock files create a schema named {schema}, rename one of them in the schema \
+                file or keep only one"
+            ),
+        }
+    }
+}
+
+/// Merges the commits of two lock files which diverged from a common history.
+///
+/// The result contains all commits of `ours`, followed by the commits only `theirs` contains.
+/// All commits get replayed to make sure they can be deployed together. Forked logs, documents
+/// changed in both lock files and schemas created in both lock files with the same name fail with
+/// `Error::LockFileMergeConflicts`.
+///
+/// Tooling metadata of `ours` is kept, schemas and dependencies only `theirs` knows are added.
+/// Builds of both lock files are kept, `ours` first.
+/// Segments need to be resolved before.
+#[instrument(skip_all)]
+pub async fn merge_lock_files(ours: &LockFile, theirs: &LockFile) -> Result<LockFile> {
+    if !ours.segments.is_empty() || !theirs.segments.is_empty() {
+        return Err(Error::UnresolvedLockSegments);
+    }
+
+    let our_hashes: HashSet<&Hash> = ours
+        .commits
+        .iter()
+        .flatten()
+        .map(|commit| &commit.entry_hash)
+        .collect();
+    let commits: Vec<Commit> = ours
+        .commits
+        .iter()
+        .flatten()
+        .chain(
+            theirs
+                .commits
+                .iter()
+                .flatten()
+                .filter(|commit| !our_hashes.contains(&commit.entry_hash)),
+        )
+        .cloned()
+        .collect();
+    debug!(
+        "Merging {} commits, {} only in their lock file",
+        commits.len(),
+        commits.len() - our_hashes.len()
+    );
+
+    let merged = LockFile::new(&commits);
+
+    let mut conflicts = forked_logs(&commits)?;
+    if conflicts.is_empty() {
+        let store = MemoryStore::default();
+        for commit in &commits {
+            publish_commit(&store, commit).await?;
+        }
+
+        conflicts.extend(concurrent_changes(&merged)?);
+        conflicts.extend(duplicate_schemas(&store).await?);
+    }
+
+    if !conflicts.is_empty() {
+        return Err(Error::LockFileMergeConflicts(conflicts));
+    }
+
+    let mut tooling = ours.tooling.clone();
+    for (schema_name, constraints) in &theirs.tooling.constraints {
+        tooling
+            .constraints
+            .entry(schema_name.clone())
+            .or_insert_with(|| constraints.clone());
+    }
+    for (name, dependency) in &theirs.tooling.dependencies {
+        tooling
+            .dependencies
+            .entry(name.clone())
+            .or_insert_with(|| dependency.clone());
+    }
+
+    let builds: Vec<BuildMetadata> = ours
+        .builds
+        .iter()
+        .chain(
+            theirs
+                .builds
+                .iter()
+                .filter(|build| !ours.builds.contains(build)),
+        )
+        .cloned()
+        .collect();
+
+    Ok(merged.with_builds(&builds).with_tooling(tooling))
+}
+
+/// Finds different entries at the same position of a log.
+fn forked_logs(commits: &[Commit]) -> Result<Vec<MergeConflict>> {
+    let mut positions: HashMap<(String, u64, u64), &Hash> = HashMap::new();
+    let mut conflicts = Vec::new();
+
+    for commit in commits {
+        let entry = decode_entry(&commit.entry)?;
+        let position = (
+            entry.public_key().to_string(),
+            entry.log_id().as_u64(),
+            entry.seq_num().as_u64(),
+        );
+
+        match positions.get(&position) {
+            Some(entry_hash) if *entry_hash != &commit.entry_hash => {
+                let (public_key, log_id, seq_num) = position;
+                conflicts.push(MergeConflict::ForkedLog {
+                    public_key,
+                    log_id,
+                    seq_num,
+                });
+            }
+            Some(_) => (),
+            None => {
+                positions.insert(position, &commit.entry_hash);
+            }
+        }
+    }
+
+    Ok(conflicts)
+}
+
+/// Finds documents with more than one latest operation, changed by operations which do not know
+/// about each other.
+fn concurrent_changes(lock_file: &LockFile) -> Result<Vec<MergeConflict>> {
+    let document_schemas = lock_file.document_schemas()?;
+
+    let mut roots: HashMap<OperationId, OperationId> = HashMap::new();
+    let mut tips: Vec<(OperationId, HashSet<OperationId>)> = Vec::new();
+    let mut schemas: HashMap<OperationId, SchemaName> = HashMap::new();
+
+    for commit in lock_file.commits.iter().flatten() {
+        let operation = decode_operation(&commit.operation)?;
+        let operation_id = OperationId::new(&commit.entry_hash);
+
+        let root = match operation.previous() {
+            None => operation_id.clone(),
+            Some(previous) => previous
+                .iter()
+                .find_map(|previous_id| roots.get(previous_id))
+                .cloned()
+                .ok_or_else(|| Error::UnknownPreviousOperations(previous.clone()))?,
+        };
+        roots.insert(operation_id.clone(), root.clone());
+
+        let position = match tips.iter().position(|(document, _)| document == &root) {
+            Some(position) => position,
+            None => {
+                tips.push((root.clone(), HashSet::new()));
+                schemas.insert(root.clone(), operation.schema_id().name());
+                tips.len() - 1
+            }
+        };
+
+        let document_tips = &mut tips[position].1;
+        if let Some(previous) = operation.previous() {
+            for previous_id in previous.iter() {
+                document_tips.remove(previous_id);
+            }
+        }
+        document_tips.insert(operation_id);
+    }
+
+    Ok(tips
+        .into_iter()
+        .filter(|(_, document_tips)| document_tips.len() > 1)
+        .map(|(root, _)| {
+            let document_id = DocumentId::new(&root);
+            let schema = document_schemas
+                .get(&document_id)
+                .cloned()
+                .unwrap_or_else(|| schemas[&root].clone());
+
+            MergeConflict::ConcurrentChanges {
+                schema,
+                document_id,
+            }
+        })
+        .collect())
+}
+
+/// Finds schema definitions with the same name.
+async fn duplicate_schemas(store: &MemoryStore) -> Result<Vec<MergeConflict>> {
+    let definitions = store
+        .get_documents_by_schema(&SchemaId::SchemaDefinition(1))
+        .await
+        .map_err(|err| Error::Storage(err.to_string()))?;
+
+    let mut names: Vec<SchemaName> = Vec::new();
+    for definition in definitions {
+        // Skip over deleted documents
+        let Some(document_view) = definition.view() else {
+            continue;
+        };
+
+        names.push(SchemaName::new(
+            SchemaView::try_from(document_view)?.name(),
+        )?);
+    }
+    names.sort();
+
+    let mut conflicts: Vec<MergeConflict> = names
+        .windows(2)
+        .filter(|pair| pair[0] == pair[1])
+        .map(|pair| MergeConflict::DuplicateSchema(pair[0].clone()))
+        .collect();
+    conflicts.dedup();
+
+    Ok(conflicts)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::lock_file::{BuildMetadata, Commit, LockFile};
+    use crate::test_utils::{build_commits, key_pair};
+
+    use super::{merge_lock_files, MergeConflict};
+
+    const VENUES: &str = r#"
+        [venues]
+        description = "Places to meet"
+        fields = { name = { type = "str" } }
+    "#;
+
+    /// Builds the schema file on top of the base commits and returns all commits as lock file.
+    async fn branch(base: &[Commit], schema_file: &str, seed: u8) -> LockFile {
+        let mut commits = base.to_vec();
+        commits.extend(build_commits(schema_file, base, &key_pair(seed)).await);
+        LockFile::new(&commits)
+    }
+
+    async fn conflicts(ours: &LockFile, theirs: &LockFile) -> Vec<MergeConflict> {
+        match merge_lock_files(ours, theirs).await {
+            Err(Error::LockFileMergeConflicts(conflicts)) => conflicts,
+            result => panic!("Expected merge conflicts, got {result:?}"),
+        }
+    }
+
+    fn with_schema(name: &str) -> String {
+        format!(
+            r#"{VENUES}
+            [{name}]
+            description = "Added on a branch"
+            fields = {{ title = {{ type = "str" }} }}
+            "#
+        )
+    }
+
+    #[tokio::test]
+    async fn merges_schemas_of_different_authors() {
+        let base = build_commits(VENUES, &[], &key_pair(1)).await;
+        let ours = branch(&base, &with_schema("events"), 2).await;
+        let theirs = branch(&base, &with_schema("artists"), 3).await;
+
+        let merged = merge_lock_files(&ours, &theirs).await.unwrap();
+        let commits = merged.commits.unwrap();
+
+        let ours = ours.commits.unwrap();
+        let theirs = theirs.commits.unwrap();
+        assert_eq!(commits.len(), ours.len() + theirs.len() - base.len());
+        for (merged, ours) in commits.iter().zip(&ours) {
+            assert_eq!(merged.entry_hash, ours.entry_hash);
+        }
+    }
+
+    #[tokio::test]
+    async fn reports_forked_logs() {
+        let base = build_commits(VENUES, &[], &key_pair(1)).await;
+        let ours = branch(&base, &with_schema("events"), 1).await;
+        let theirs = branch(&base, &with_schema("artists"), 1).await;
+
+        let conflicts = conflicts(&ours, &theirs).await;
+        assert!(!conflicts.is_empty());
+        assert!(conflicts
+            .iter()
+            .all(|conflict| matches!(conflict, MergeConflict::ForkedLog { .. })));
+    }
+
+    #[tokio::test]
+    async fn reports_concurrent_changes() {
+        let base = build_commits(VENUES, &[], &key_pair(1)).await;
+        let ours = branch(&base, &VENUES.replace("Places to meet", "Ours"), 2).await;
+        let theirs = branch(&base, &VENUES.replace("Places to meet", "Theirs"), 3).await;
+
+        let conflicts = conflicts(&ours, &theirs).await;
+        assert_eq!(conflicts.len(), 1);
+        assert!(matches!(
+            &conflicts[0],
+            MergeConflict::ConcurrentChanges { schema, .. } if schema.to_string() == "venues"
+        ));
+    }
+
+    #[tokio::test]
+    async fn reports_duplicate_schemas() {
+        let base = build_commits(VENUES, &[], &key_pair(1)).await;
+        let ours = branch(&base, &with_schema("events"), 2).await;
+        let theirs = branch(&base, &with_schema("events"), 3).await;
+
+        let conflicts = conflicts(&ours, &theirs).await;
+        assert_eq!(conflicts.len(), 1);
+        assert!(matches!(
+            &conflicts[0],
+            MergeConflict::DuplicateSchema(schema) if schema.to_string() == "events"
+        ));
+    }
+
+    #[tokio::test]
+    async fn takes_commits_of_lock_file_ahead() {
+        let base = build_commits(VENUES, &[], &key_pair(1)).await;
+        let theirs = branch(&base, &with_schema("events"), 1).await;
+
+        let merged = merge_lock_files(&LockFile::new(&base), &theirs)
+            .await
+            .unwrap();
+
+        let entry_hashes = |lock_file: LockFile| -> Vec<_> {
+            lock_file
+                .commits
+                .unwrap_or_default()
+                .into_iter()
+                .map(|commit| commit.entry_hash)
+                .collect()
+        };
+        assert_eq!(entry_hashes(merged), entry_hashes(theirs));
+    }
+
+    #[tokio::test]
+    async fn keeps_builds_of_both_lock_files() {
+        let base = build_commits(VENUES, &[], &key_pair(1)).await;
+        let base_build = BuildMetadata::new(&base);
+
+        let mut builds = Vec::new();
+        let mut branches = Vec::new();
+        for (name, seed, message) in [("events", 2, "add events"), ("artists", 3, "add artists")] {
+            let lock_file = branch(&base, &with_schema(name), seed).await;
+            let added = &lock_file.commits.as_ref().unwrap()[base.len()..];
+            let build = BuildMetadata::new(added).with_message(Some(message));
+
+            builds.push(build.clone());
+            branches.push(lock_file.with_builds(&[base_build.clone(), build]));
+        }
+
+        let merged = merge_lock_files(&branches[0], &branches[1]).await.unwrap();
+
+        assert_eq!(
+            merged.builds,
+            vec![base_build, builds[0].clone(), builds[1].clone()]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_unresolved_segments() {
+        let segmented = LockFile {
+            segments: vec!["locks/venues.lock".into()],
+            ..LockFile::new(&[])
+        };
+
+        let result = merge_lock_files(&segmented, &LockFile::new(&[])).await;
+        assert!(matches!(result, Err(Error::UnresolvedLockSegments)));
+    }
+}
@@ -0,0 +1,235 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Migration guides between two lock file states.
+//!
+//! App developers upgrading their clients to new schema versions need to know which schema ids
+//! changed and which fields they have to adjust. The guide compares the schemas materialized from
+//! two lock files and renders these changes as Markdown.
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use p2panda_rs::schema::{FieldName, SchemaId, SchemaName};
+
+use crate::build::{get_previous_schemas, PreviousSchema};
+use crate::error::Result;
+use crate::lock_file::LockFile;
+use crate::schema_file::SchemaField;
+
+/// Changes of all schemas between two lock file states.
+#[derive(Clone, Debug, Default)]
+pub struct MigrationGuide {
+    /// Schemas which were added, removed or changed, sorted by name.
+    pub schemas: Vec<SchemaMigration>,
+}
+
+/// Changes of a single schema between two lock file states.
+#[derive(Clone, Debug)]
+pub struct SchemaMigration {
+    /// Name of the schema.
+    pub name: SchemaName,
+
+    /// Id of the schema in the old lock file, `None` if it was added.
+    pub old_schema_id: Option<SchemaId>,
+
+    /// Id of the schema in the new lock file, `None` if it was removed.
+    pub new_schema_id: Option<SchemaId>,
+
+    /// Fields which only exist in the new version.
+    pub added_fields: Vec<(FieldName, SchemaField)>,
+
+    /// Fields which only exist in the old version.
+    pub removed_fields: Vec<(FieldName, SchemaField)>,
+
+    /// Fields with their old and new type.
+    pub retyped_fields: Vec<(FieldName, SchemaField, SchemaField)>,
+
+    /// Old and new name of fields which were removed and added again under a different name.
+    ///
+    /// Renames are detected by a removed and an added field sharing a type which no other
+    /// removed or added field of this schema has.
+    pub renamed_fields: Vec<(FieldName, FieldName, SchemaField)>,
+}
+
+impl SchemaMigration {
+    /// Returns true if applications need to adjust their fields for this schema.
+    pub fn has_field_changes(&self) -> bool {
+        !self.added_fields.is_empty()
+            || !self.removed_fields.is_empty()
+            || !self.retyped_fields.is_empty()
+            || !self.renamed_fields.is_empty()
+    }
+}
+
+/// Compares the schemas of two lock file states.
+///
+/// Relation fields are compared by the name of the schema they point at, so new versions of a
+/// related schema show up in the schema id mapping and not as changed field types.
+pub async fn migration_guide(from: &LockFile, to: &LockFile) -> Result<MigrationGuide> {
+    let old_schemas = get_previous_schemas(from).await?;
+    let new_schemas = get_previous_schemas(to).await?;
+
+    let names: BTreeSet<&SchemaName> = old_schemas.keys().chain(new_schemas.keys()).collect();
+
+    let schemas = names
+        .into_iter()
+        .map(|name| compare_schema(name, old_schemas.get(name), new_schemas.get(name)))
+        .filter(|migration| {
+            migration.old_schema_id != migration.new_schema_id || migration.has_field_changes()
+        })
+        .collect();
+
+    Ok(MigrationGuide { schemas })
+}
+
+fn compare_schema(
+    name: &SchemaName,
+    old: Option<&PreviousSchema>,
+    new: Option<&PreviousSchema>,
+) -> SchemaMigration {
+    let old_fields = fields(old);
+    let new_fields = fields(new);
+
+    let mut added_fields = Vec::new();
+    let mut retyped_fields = Vec::new();
+    for (field_name, new_field) in &new_fields {
+        match old_fields
+            .iter()
+            .find(|(old_name, _)| old_name == field_name)
+        {
+            Some((_, old_field)) if old_field.to_string() != new_field.to_string() => {
+                retyped_fields.push((field_name.clone(), old_field.clone(), new_field.clone()))
+            }
+            Some(_) => (),
+            None => added_fields.push((field_name.clone(), new_field.clone())),
+        }
+    }
+
+    let mut removed_fields: Vec<(FieldName, SchemaField)> = old_fields
+        .into_iter()
+        .filter(|(old_name, _)| !new_fields.iter().any(|(new_name, _)| new_name == old_name))
+        .collect();
+
+    // Pair removed and added fields sharing a type no other removed or added field has
+    let mut renamed_fields = Vec::new();
+    let field_type = |field: &SchemaField| field.to_string();
+    let unique = |fields: &[(FieldName, SchemaField)], field: &SchemaField| {
+        fields
+            .iter()
+            .filter(|(_, other)| field_type(other) == field_type(field))
+            .count()
+            == 1
+    };
+
+    for (old_name, old_field) in removed_fields.clone() {
+        let added = added_fields
+            .iter()
+            .position(|(_, new_field)| field_type(new_field) == field_type(&old_field));
+
+        if let Some(index) = added {
+            if unique(&removed_fields, &old_field) && unique(&added_fields, &old_field) {
+                let (new_name, _) = added_fields.remove(index);
+                removed_fields.retain(|(name, _)| name != &old_name);
+                renamed_fields.push((old_name, new_name, old_field));
+            }
+        }
+    }
+
+    SchemaMigration {
+        name: name.clone(),
+        old_schema_id: old.map(|schema| schema.schema.id().to_owned()),
+        new_schema_id: new.map(|schema| schema.schema.id().to_owned()),
+        added_fields,
+        removed_fields,
+        retyped_fields,
+        renamed_fields,
+    }
+}
+
+/// Returns the fields of a schema with relations pointing at schema names.
+fn fields(schema: Option<&PreviousSchema>) -> Vec<(FieldName, SchemaField)> {
+    schema
+        .map(|schema| {
+            schema
+                .schema
+                .fields()
+                .iter()
+                .map(|(name, field_type)| (name.to_owned(), SchemaField::from(field_type)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+impl MigrationGuide {
+    /// Renders the guide as Markdown, with a table mapping old to new schema ids followed by the
+    /// field changes of every schema.
+    pub fn to_markdown(&self) -> String {
+        let mut output = String::new();
+        self.write_markdown(&mut output)
+            .expect("Writing into a string never fails");
+        output
+    }
+
+    fn write_markdown(&self, output: &mut String) -> std::fmt::Result {
+        writeln!(output, "# Migration guide")?;
+        writeln!(output)?;
+
+        if self.schemas.is_empty() {
+            writeln!(output, "No schemas changed.")?;
+            return Ok(());
+        }
+
+        writeln!(output, "## Schema ids")?;
+        writeln!(output)?;
+        writeln!(output, "| Schema | Old schema id | New schema id |")?;
+        writeln!(output, "| --- | --- | --- |")?;
+
+        for schema in &self.schemas {
+            let format_id = |schema_id: &Option<SchemaId>, missing: &str| match schema_id {
+                Some(schema_id) => format!("`{schema_id}`"),
+                None => missing.to_owned(),
+            };
+
+            writeln!(
+                output,
+                "| {} | {} | {} |",
+                schema.name,
+                format_id(&schema.old_schema_id, "(added)"),
+                format_id(&schema.new_schema_id, "(removed)")
+            )?;
+        }
+
+        for schema in self
+            .schemas
+            .iter()
+            .filter(|schema| schema.has_field_changes())
+        {
+            writeln!(output)?;
+            writeln!(output, "## {}", schema.name)?;
+            writeln!(output)?;
+
+            for (old_name, new_name, field) in &schema.renamed_fields {
+                writeln!(
+                    output,
+                    "* Renamed field `{old_name}` to `{new_name}` (`{field}`)"
+                )?;
+            }
+
+            for (name, old_field, new_field) in &schema.retyped_fields {
+                writeln!(
+                    output,
+                    "* Changed type of field `{name}` from `{old_field}` to `{new_field}`"
+                )?;
+            }
+
+            for (name, field) in &schema.removed_fields {
+                writeln!(output, "* Removed field `{name}` (`{field}`)")?;
+            }
+
+            for (name, field) in &schema.added_fields {
+                writeln!(output, "* Added field `{name}` (`{field}`)")?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,12 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! p2panda types used in the public API of fishy.
+//!
+//! Applications can use these re-exports instead of their own `p2panda_rs` dependency, so they
+//! always compile against the same version as fishy.
+pub use p2panda_rs::document::DocumentViewId;
+pub use p2panda_rs::entry::EncodedEntry;
+pub use p2panda_rs::hash::Hash;
+pub use p2panda_rs::identity::{KeyPair, PublicKey};
+pub use p2panda_rs::operation::EncodedOperation;
+pub use p2panda_rs::schema::{FieldType, Schema, SchemaDescription, SchemaId, SchemaName};
@@ -0,0 +1,80 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Check relations to pinned schema ids against a node.
+//!
+//! Relations defined with `schema = { id = "..." }` point at schemas of other projects. Building
+//! trusts these ids, a typo or a schema which was never deployed only shows up when the node
+//! rejects documents relating to it. Looking them up on a node finds such ids before committing.
+use p2panda_rs::schema::{FieldName, SchemaId, SchemaName};
+
+use crate::build::PlanSummary;
+use crate::client::{Client, RemoteSchema};
+use crate::error::Result;
+use crate::schema_file::{RelationId, SchemaField};
+
+/// Schema outside of the project which relations point at with its schema id.
+#[derive(Clone, Debug)]
+pub struct PinnedSchema {
+    /// Id the relations point at.
+    pub schema_id: SchemaId,
+
+    /// Schemas and fields relating to this schema.
+    pub relations: Vec<(SchemaName, FieldName)>,
+
+    /// Schema found on the node, `None` if the node does not know this schema id.
+    pub remote: Option<RemoteSchema>,
+}
+
+impl PinnedSchema {
+    /// Returns true if the node knows this schema.
+    pub fn exists(&self) -> bool {
+        self.remote.is_some()
+    }
+}
+
+/// Looks up all application schemas outside of the planned ones which the current fields relate
+/// to, in the order they are first related to.
+pub async fn resolve_pinned_schemas(
+    client: &Client,
+    summaries: &[PlanSummary],
+) -> Result<Vec<PinnedSchema>> {
+    let mut pinned_schemas: Vec<PinnedSchema> = Vec::new();
+
+    for summary in summaries {
+        for field in &summary.fields {
+            let Some(SchemaField::Relation { schema, .. }) = &field.current else {
+                continue;
+            };
+
+            // Relations to schemas of this project and to system schemas are known already
+            let RelationId::Id(schema_id @ SchemaId::Application(..)) = &schema.id else {
+                continue;
+            };
+            if summaries
+                .iter()
+                .any(|other| &other.current_schema_id == schema_id)
+            {
+                continue;
+            }
+
+            let relation = (summary.name.clone(), field.name.clone());
+            match pinned_schemas
+                .iter_mut()
+                .find(|pinned| &pinned.schema_id == schema_id)
+            {
+                Some(pinned) => pinned.relations.push(relation),
+                None => pinned_schemas.push(PinnedSchema {
+                    schema_id: schema_id.clone(),
+                    relations: vec![relation],
+                    remote: None,
+                }),
+            }
+        }
+    }
+
+    for pinned in &mut pinned_schemas {
+        pinned.remote = client.schema_by_id(&pinned.schema_id).await?;
+    }
+
+    Ok(pinned_schemas)
+}
@@ -0,0 +1,1214 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use p2panda_rs::document::DocumentId;
+use p2panda_rs::hash::Hash;
+use p2panda_rs::identity::PublicKey;
+use p2panda_rs::schema::{SchemaDescription, SchemaId, SchemaName};
+use serde::Serialize;
+use tracing::info;
+
+use crate::aliases::{self, Aliases};
+use crate::authors::verify_commit_authors;
+use crate::build::{build_with_signers, BuildResult, PlanSummary};
+use crate::config::Config;
+use crate::constants::{
+    ALIASES_FILE_NAME, BOOTSTRAP_LOCK_FILE_NAME, CACHE_DIR_NAME, DEPLOYMENTS_FILE_NAME,
+    LOCK_FILE_NAME, LOCK_SEGMENTS_DIR_NAME, PRIVATE_KEY_FILE_NAME, SCHEMA_FILE_NAME,
+    STATE_CACHE_DIR_NAME, VENDOR_DIR_NAME,
+};
+use crate::dependencies::{resolve_dependencies, Dependency, ResolvedDependency};
+use crate::deployments::{self, Deployments};
+use crate::error::{Error, Result};
+use crate::external::{resolve_externals, vendor_externals, ExternalSchema, VendoredLockFile};
+use crate::foreign::find_foreign_commits;
+use crate::import::{import_commits, Import};
+use crate::lock_file::{BuildMetadata, Commit, LockFile, ToolingMetadata};
+use crate::prune::{
+    bootstrap_documents, find_orphans, find_unused_fields, tombstone_orphans,
+    tombstone_unused_fields, Orphan, PruneMode, UnusedField,
+};
+use crate::reporter::{Progress, Reporter};
+use crate::resign::{resign_lock_file, Resign};
+use crate::sandbox::SandboxOptions;
+use crate::schema_file::{Metadata, SchemaFields, SchemaFile, SchemaKey};
+use crate::signer::{Signer, Signers};
+use crate::squash::{squash_lock_file, Squash};
+use crate::utils::{files, key_pair};
+
+/// Paths to all files belonging to a fishy project.
+#[derive(Clone, Debug)]
+pub struct Project {
+    /// Path to the schema definition file.
+    pub schema_path: PathBuf,
+
+    /// Path to the lock file with signed and encoded p2panda data.
+    pub lock_path: PathBuf,
+
+    /// Path to the key pair file, storing a hex-encoded ed25519 private key.
+    pub private_key_path: PathBuf,
+
+    /// Path to the file mapping stable alias names to current schema ids.
+    pub aliases_path: PathBuf,
+
+    /// Path to the lock file with signed and encoded commits of bootstrap documents.
+    pub bootstrap_lock_path: PathBuf,
+
+    /// Path to the file recording the last deployment to every node.
+    pub deployments_path: PathBuf,
+
+    /// Directory where files fetched from remote sources are cached.
+    pub cache_dir: PathBuf,
+
+    /// Directory containing vendored lock files of external projects.
+    pub vendor_dir: PathBuf,
+
+    /// Writes the commits of every schema into its own segment file next to the lock file, which
+    /// then serves as an index.
+    pub lock_segments: bool,
+
+    /// Prefix prepended to all schema names, overriding the prefix of the schema file.
+    pub prefix: Option<String>,
+
+    /// Message describing the changes, kept with the commits of the next build in the lock file.
+    pub message: Option<String>,
+
+    /// Resource limits when building untrusted schema files.
+    pub sandbox: Option<SandboxOptions>,
+
+    /// Signs commits instead of the key pair file, for example a remote signing service.
+    pub signer: Option<Arc<dyn Signer>>,
+
+    /// Named key pair files schemas can be signed with instead of the key pair of the project.
+    pub keys: BTreeMap<String, PathBuf>,
+
+    /// Public keys allowed to sign commits of the lock files, every author is accepted if empty.
+    pub trusted_keys: Vec<PublicKey>,
+
+    /// Schema bundles of other projects which relations can point at by name.
+    pub dependencies: BTreeMap<String, Dependency>,
+}
+
+impl Project {
+    /// Returns a new instance of `Project`.
+    ///
+    /// The aliases, bootstrap lock and deployments files are expected next to the lock file, or
+    /// next to the schema file if the lock file is read from stdin and written to stdout. The cache
+    /// and vendor directories are expected next to the schema file.
+    pub fn new(
+        schema_path: impl AsRef<Path>,
+        lock_path: impl AsRef<Path>,
+        private_key_path: impl AsRef<Path>,
+    ) -> Self {
+        let schema_path = schema_path.as_ref().to_path_buf();
+        let lock_path = lock_path.as_ref().to_path_buf();
+        let files_path = match files::is_stdio(&lock_path) {
+            true => &schema_path,
+            false => &lock_path,
+        };
+        let aliases_path = files_path.with_file_name(ALIASES_FILE_NAME);
+        let bootstrap_lock_path = files_path.with_file_name(BOOTSTRAP_LOCK_FILE_NAME);
+        let deployments_path = files_path.with_file_name(DEPLOYMENTS_FILE_NAME);
+        let cache_dir = schema_path.with_file_name(CACHE_DIR_NAME);
+        let vendor_dir = schema_path.with_file_name(VENDOR_DIR_NAME);
+
+        Self {
+            schema_path,
+            lock_path,
+            private_key_path: private_key_path.as_ref().to_path_buf(),
+            aliases_path,
+            bootstrap_lock_path,
+            deployments_path,
+            cache_dir,
+            vendor_dir,
+            lock_segments: false,
+            prefix: None,
+            message: None,
+            sandbox: None,
+            signer: None,
+            keys: BTreeMap::new(),
+            trusted_keys: Vec::new(),
+            dependencies: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the path to the aliases file.
+    pub fn with_aliases_path(mut self, aliases_path: impl AsRef<Path>) -> Self {
+        self.aliases_path = aliases_path.as_ref().to_path_buf();
+        self
+    }
+
+    /// Sets the path to the bootstrap lock file.
+    pub fn with_bootstrap_lock_path(mut self, bootstrap_lock_path: impl AsRef<Path>) -> Self {
+        self.bootstrap_lock_path = bootstrap_lock_path.as_ref().to_path_buf();
+        self
+    }
+
+    /// Sets the path to the deployments file.
+    pub fn with_deployments_path(mut self, deployments_path: impl AsRef<Path>) -> Self {
+        self.deployments_path = deployments_path.as_ref().to_path_buf();
+        self
+    }
+
+    /// Sets the directory where files fetched from remote sources are cached.
+    pub fn with_cache_dir(mut self, cache_dir: impl AsRef<Path>) -> Self {
+        self.cache_dir = cache_dir.as_ref().to_path_buf();
+        self
+    }
+
+    /// Splits the lock file into one segment file per schema when writing it.
+    pub fn with_lock_segments(mut self, lock_segments: bool) -> Self {
+        self.lock_segments = lock_segments;
+        self
+    }
+
+    /// Prepends the given prefix to all schema names, instead of the prefix set in the schema
+    /// file.
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = Some(prefix.to_owned());
+        self
+    }
+
+    /// Sets the message describing the changes of the next build.
+    pub fn with_message(mut self, message: &str) -> Self {
+        self.message = Some(message.to_owned());
+        self
+    }
+
+    /// Prepends `<namespace>_` to all schema names, instead of the prefix set in the schema file.
+    pub fn with_namespace(self, namespace: &str) -> Self {
+        self.with_prefix(&format!("{namespace}_"))
+    }
+
+    /// Builds the schema and lock files within the given resource limits.
+    pub fn with_sandbox(mut self, options: SandboxOptions) -> Self {
+        self.sandbox = Some(options);
+        self
+    }
+
+    /// Signs commits with the given signer, the key pair file is not read in this case.
+    pub fn with_signer(mut self, signer: impl Signer + 'static) -> Self {
+        self.signer = Some(Arc::new(signer));
+        self
+    }
+
+    /// Adds a named key pair file, schemas with `key = "<name>"` are signed with it.
+    pub fn with_key(mut self, name: &str, path: impl AsRef<Path>) -> Self {
+        self.keys
+            .insert(name.to_owned(), path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Adds a public key which is allowed to sign commits of the lock files.
+    ///
+    /// Once a trusted key is set, lock files with commits of other authors are rejected when
+    /// loading them and no new commits are signed by other key pairs.
+    pub fn with_trusted_key(mut self, public_key: PublicKey) -> Self {
+        if !self.trusted_keys.contains(&public_key) {
+            self.trusted_keys.push(public_key);
+        }
+        self
+    }
+
+    /// Adds a schema bundle of another project, relations can point at its schemas by name.
+    pub fn with_dependency(mut self, name: &str, dependency: Dependency) -> Self {
+        self.dependencies.insert(name.to_owned(), dependency);
+        self
+    }
+
+    /// Returns a project with the default file names inside of the given directory.
+    pub fn from_dir(dir: impl AsRef<Path>) -> Self {
+        let dir = dir.as_ref();
+
+        Self::new(
+            dir.join(SCHEMA_FILE_NAME),
+            dir.join(LOCK_FILE_NAME),
+            dir.join(PRIVATE_KEY_FILE_NAME),
+        )
+    }
+
+    /// Returns the project inside of the given directory with the settings of its `fishy.toml`
+    /// file.
+    ///
+    /// If a state directory is configured, the lock files, aliases and deployments files and cache
+    /// directory are expected inside of it. Use `migrate` to move existing files there. A
+    /// configured namespace is used as prefix of all schema names, configured keys and local
+    /// dependencies are relative to the directory.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let config = Config::load(dir)?;
+
+        let project = match config.state_dir {
+            Some(state_dir) => {
+                let state_dir = dir.join(state_dir);
+
+                Self::new(
+                    dir.join(SCHEMA_FILE_NAME),
+                    state_dir.join(LOCK_FILE_NAME),
+                    dir.join(PRIVATE_KEY_FILE_NAME),
+                )
+                .with_cache_dir(state_dir.join(STATE_CACHE_DIR_NAME))
+            }
+            None => Self::from_dir(dir),
+        };
+
+        let project = config.keys.iter().fold(
+            project.with_lock_segments(config.lock_segments),
+            |project, (name, path)| project.with_key(name, dir.join(path)),
+        );
+        let project = config
+            .trusted_keys
+            .into_iter()
+            .fold(project, Project::with_trusted_key);
+        let project =
+            config
+                .dependencies
+                .into_iter()
+                .fold(project, |project, (name, dependency)| {
+                    project.with_dependency(&name, dependency.relative_to(dir))
+                });
+
+        Ok(match config.namespace {
+            Some(namespace) => project.with_namespace(&namespace),
+            None => project,
+        })
+    }
+
+    /// Moves generated files from their default location inside of the given project directory to
+    /// the configured paths of this project, for example after setting a state directory.
+    ///
+    /// Files are only moved if they do not exist at the new location yet. Returns the old and new
+    /// path of every moved file.
+    pub fn migrate(&self, dir: impl AsRef<Path>) -> Result<Vec<(PathBuf, PathBuf)>> {
+        let dir = dir.as_ref();
+        let mut moved = Vec::new();
+        let segments_dir = self.lock_path.with_file_name(LOCK_SEGMENTS_DIR_NAME);
+
+        for (from, to) in [
+            (dir.join(LOCK_FILE_NAME), &self.lock_path),
+            (dir.join(LOCK_SEGMENTS_DIR_NAME), &segments_dir),
+            (dir.join(ALIASES_FILE_NAME), &self.aliases_path),
+            (
+                dir.join(BOOTSTRAP_LOCK_FILE_NAME),
+                &self.bootstrap_lock_path,
+            ),
+            (dir.join(DEPLOYMENTS_FILE_NAME), &self.deployments_path),
+            (dir.join(CACHE_DIR_NAME), &self.cache_dir),
+        ] {
+            if from == *to || !from.exists() || to.exists() {
+                continue;
+            }
+
+            if let Some(parent) = to.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::rename(&from, to)?;
+            info!("Moved {} to {}", from.display(), to.display());
+
+            moved.push((from, to.clone()));
+        }
+
+        Ok(moved)
+    }
+
+    /// Validates commits produced elsewhere on top of the lock file and writes the ones it does
+    /// not contain yet into it, see `import_commits`.
+    pub async fn import(&self, commits: &[Commit]) -> Result<Import> {
+        let lock_file = self.read_lock_file(&self.lock_path)?;
+        verify_commit_authors(commits, &self.trusted_keys)?;
+        let import = import_commits(&lock_file, commits).await?;
+
+        if import.imported > 0 {
+            self.write_lock_file(import.lock_file.clone(), import.lock_file.tooling.clone())?;
+            info!(
+                "Imported {} commits into {}",
+                import.imported,
+                self.lock_path.display()
+            );
+        }
+
+        Ok(import)
+    }
+
+    /// Upgrades the lock file and bootstrap lock file to the current format if they were written
+    /// by an older version of fishy, see `LockFile::migrate`. Returns the paths of all migrated
+    /// files.
+    pub fn migrate_lock_files(&self) -> Result<Vec<PathBuf>> {
+        let mut migrated = Vec::new();
+
+        for path in [&self.lock_path, &self.bootstrap_lock_path] {
+            if !path.exists() {
+                continue;
+            }
+
+            let lock_file = LockFile::from_path(path)?;
+            if !lock_file.is_outdated() {
+                continue;
+            }
+
+            let lock_file = lock_file.migrate()?;
+            if path == &self.lock_path {
+                let tooling = lock_file.tooling.clone();
+                self.write_lock_file(lock_file, tooling)?;
+            } else {
+                lock_file.write(path)?;
+            }
+            info!("Migrated {} to the current format", path.display());
+
+            migrated.push(path.clone());
+        }
+
+        Ok(migrated)
+    }
+
+    /// Copies the lock files of all external projects the schema file relates to into the vendor
+    /// directory, so builds do not need to fetch them anymore.
+    pub fn vendor(&self) -> Result<Vec<VendoredLockFile>> {
+        let mut schema_file = SchemaFile::from_path(&self.schema_path)?;
+        schema_file.resolve_includes(&self.cache_dir)?;
+
+        vendor_externals(&schema_file, &self.cache_dir, &self.vendor_dir)
+    }
+
+    /// Loads all project files and returns a serializable snapshot of the full project state.
+    ///
+    /// The snapshot contains all defined schemas with their committed and current schema ids,
+    /// the pending changes, the last deployment to every node and the public key used for
+    /// signing. No files get written.
+    pub async fn snapshot(&self) -> Result<ProjectSnapshot> {
+        let (schema_file, lock_file, bootstrap_lock, signers, externals, _) = self.load().await?;
+        let public_key = signers.default.public_key();
+
+        // Calculate the changes we would need to commit to get to the current version
+        let result = self
+            .build(&schema_file, &lock_file, &bootstrap_lock, &signers)
+            .await?;
+
+        let schemas = result
+            .plans
+            .iter()
+            .map(|plan| {
+                let schema_diff = plan.schema_diff();
+                let current_schema_id = plan.schema_id();
+
+                let committed_schema_id = schema_diff.previous_schema_id()?;
+
+                let status = match &committed_schema_id {
+                    Some(schema_id) if schema_id == &current_schema_id => SchemaStatus::Unchanged,
+                    Some(_) => SchemaStatus::Changed,
+                    None => SchemaStatus::New,
+                };
+
+                let schema_definition = schema_file
+                    .iter()
+                    .find(|(schema_name, _)| *schema_name == &schema_diff.name)
+                    .map(|(_, schema_definition)| schema_definition)
+                    .expect("Current schema needs to be given in schema file");
+
+                Ok(SchemaSnapshot {
+                    name: schema_diff.name,
+                    public_key: plan.public_key(),
+                    description: schema_diff.current_description,
+                    committed_schema_id,
+                    current_schema_id,
+                    status,
+                    fields: schema_definition.fields.clone(),
+                    license: schema_definition.license.clone(),
+                    authors: schema_definition.authors.clone(),
+                    homepage: schema_definition.homepage.clone(),
+                    metadata: schema_definition.metadata.clone(),
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        // Bootstrap commits get deployed after the schemas
+        let last_commit = bootstrap_lock
+            .commits
+            .iter()
+            .flatten()
+            .last()
+            .or_else(|| lock_file.commits.iter().flatten().last())
+            .map(|commit| commit.entry_hash.clone());
+
+        Ok(ProjectSnapshot {
+            public_key,
+            committed_commits: lock_file.commits.map_or(0, |commits| commits.len()),
+            pending_commits: result.commits.len() + result.bootstrap_commits.len(),
+            pending: result
+                .summary()?
+                .into_iter()
+                .filter(|summary| summary.has_changes())
+                .collect(),
+            last_commit,
+            deployments: deployments::load(&self.deployments_path)?,
+            schemas,
+            externals,
+        })
+    }
+
+    /// Returns a summary of the changes required to get to the current schema versions, without
+    /// committing them.
+    pub async fn plan(&self) -> Result<Vec<PlanSummary>> {
+        let (schema_file, lock_file, bootstrap_lock, signers, _, _) = self.load().await?;
+        self.build(&schema_file, &lock_file, &bootstrap_lock, &signers)
+            .await?
+            .summary()
+    }
+
+    /// Reads the lock file and bootstrap lock file, for example to deploy their commits.
+    ///
+    /// Missing files count as empty lock files. All commits need to be signed by one of the
+    /// trusted keys if any are set.
+    pub fn read_lock_files(&self) -> Result<(LockFile, LockFile)> {
+        Ok((
+            self.read_lock_file(&self.lock_path)?,
+            self.read_lock_file(&self.bootstrap_lock_path)?,
+        ))
+    }
+
+    /// Shows the current state of all schemas to the reporter without committing any changes.
+    pub async fn inspect<R: Reporter + ?Sized>(&self, reporter: &mut R) -> Result<()> {
+        let (schema_file, lock_file, bootstrap_lock, signers, _, _) = self.load().await?;
+        let result = self
+            .build(&schema_file, &lock_file, &bootstrap_lock, &signers)
+            .await?;
+
+        if !result.skipped.is_empty() {
+            reporter.progress(&Progress::SchemasSkipped(result.skipped.clone()));
+        }
+        report_leftovers(&schema_file, &lock_file, reporter).await?;
+        reporter.plan(&result.summary()?, &signers.default.public_key(), false);
+
+        Ok(())
+    }
+
+    /// Calculates the changes to get to the current schema versions, shows them to the reporter
+    /// and writes the new commits to the lock files after confirmation.
+    ///
+    /// The aliases file gets updated whenever the schema ids changed. Returns the number of
+    /// written commits.
+    pub async fn commit<R: Reporter + ?Sized>(&self, reporter: &mut R) -> Result<usize> {
+        let (schema_file, lock_file, bootstrap_lock, signers, _, dependencies) =
+            self.load().await?;
+        let result = self
+            .build(&schema_file, &lock_file, &bootstrap_lock, &signers)
+            .await?;
+        let schema_ids: Vec<SchemaId> = result.plans.iter().map(|plan| plan.schema_id()).collect();
+        // Check aliases before writing anything, schemas with colliding names can not be committed
+        let aliases = Aliases::new(&schema_ids)?;
+        let tooling = ToolingMetadata::new(&schema_file).with_dependencies(dependencies);
+        // Consumers reading the lock file from stdout expect it even when nothing changed
+        let tooling_changed = tooling != lock_file.tooling || files::is_stdio(&self.lock_path);
+
+        if !result.skipped.is_empty() {
+            reporter.progress(&Progress::SchemasSkipped(result.skipped.clone()));
+        }
+        report_leftovers(&schema_file, &lock_file, reporter).await?;
+
+        if result.commits.is_empty() && result.bootstrap_commits.is_empty() {
+            reporter.progress(&Progress::NoChanges);
+            if tooling_changed {
+                self.write_lock_file(
+                    LockFile::new(&lock_file.commits.unwrap_or_default())
+                        .with_builds(&lock_file.builds),
+                    tooling,
+                )?;
+                reporter.progress(&Progress::ToolingWritten(self.lock_path.clone()));
+            }
+            self.write_aliases(&aliases, reporter)?;
+            return Ok(0);
+        }
+
+        verify_commit_authors(&result.commits, &self.trusted_keys)?;
+        verify_commit_authors(&result.bootstrap_commits, &self.trusted_keys)?;
+        reporter.plan(&result.summary()?, &signers.default.public_key(), true);
+
+        let total = result.commits.len() + result.bootstrap_commits.len();
+        if !reporter.confirm(total)? {
+            reporter.progress(&Progress::Aborted);
+            return Ok(0);
+        }
+
+        if !result.commits.is_empty() || tooling_changed {
+            let written = result.commits.len();
+            let mut builds = lock_file.builds.clone();
+            if written > 0 {
+                builds.push(
+                    BuildMetadata::new(&result.commits).with_message(self.message.as_deref()),
+                );
+            }
+
+            let commits: Vec<Commit> = lock_file
+                .commits
+                .unwrap_or_default()
+                .into_iter()
+                .chain(result.commits)
+                .collect();
+
+            self.write_lock_file(LockFile::new(&commits).with_builds(&builds), tooling)?;
+            reporter.progress(&match written {
+                0 => Progress::ToolingWritten(self.lock_path.clone()),
+                _ => Progress::CommitsWritten(written, self.lock_path.clone()),
+            });
+        }
+
+        if !result.bootstrap_commits.is_empty() {
+            let written = result.bootstrap_commits.len();
+            let commits: Vec<Commit> = bootstrap_lock
+                .commits
+                .unwrap_or_default()
+                .into_iter()
+                .chain(result.bootstrap_commits)
+                .collect();
+
+            LockFile::new(&commits).write(&self.bootstrap_lock_path)?;
+            reporter.progress(&Progress::BootstrapCommitsWritten(
+                written,
+                self.bootstrap_lock_path.clone(),
+            ));
+        }
+
+        self.write_aliases(&aliases, reporter)?;
+
+        Ok(total)
+    }
+
+    /// Squashes the history of the lock file into the create operations of its current schemas,
+    /// signed with the signers of the project.
+    ///
+    /// Nothing gets written, use `write_squash` to replace the lock file afterwards.
+    pub async fn squash(&self) -> Result<Squash> {
+        let (_, lock_file, _, signers, _, _) = self.load().await?;
+        squash_lock_file(&lock_file, &signers.signers()).await
+    }
+
+    /// Replaces the lock file with the squashed one and updates the aliases file.
+    ///
+    /// Bootstrap documents belong to the previous schema ids, the bootstrap lock file gets removed
+    /// and the next build creates them again.
+    pub fn write_squash(&self, squash: &Squash) -> Result<()> {
+        self.write_lock_file(squash.lock_file.clone(), squash.lock_file.tooling.clone())?;
+
+        if self.bootstrap_lock_path.exists() {
+            fs::remove_file(&self.bootstrap_lock_path)?;
+        }
+
+        let schema_ids: Vec<SchemaId> = squash
+            .schema_ids
+            .iter()
+            .map(|(_, schema_id)| schema_id.clone())
+            .collect();
+        Aliases::new(&schema_ids)?.write(&self.aliases_path)
+    }
+
+    /// Re-signs all commits of the lock file, signed by the old public key, with the new signer.
+    ///
+    /// Only the lock file is read, the schema file and key pair file of the project are not
+    /// needed.
+    pub async fn resign(&self, old_key: &PublicKey, signer: &dyn Signer) -> Result<Resign> {
+        let lock_file = self.read_lock_file(&self.lock_path)?;
+        resign_lock_file(&lock_file, old_key, signer).await
+    }
+
+    /// Writes the re-signed lock file and updates the aliases file with the new schema ids.
+    ///
+    /// The bootstrap lock file gets removed, its documents are created again by the next build
+    /// with the new schema ids.
+    pub fn write_resign(&self, resign: &Resign) -> Result<()> {
+        self.write_lock_file(resign.lock_file.clone(), resign.lock_file.tooling.clone())?;
+
+        if self.bootstrap_lock_path.exists() {
+            fs::remove_file(&self.bootstrap_lock_path)?;
+        }
+
+        let schema_ids: Vec<SchemaId> = resign
+            .schema_ids
+            .iter()
+            .map(|(_, schema_id)| schema_id.clone())
+            .collect();
+        Aliases::new(&schema_ids)?.write(&self.aliases_path)
+    }
+
+    /// Finds the documents of schemas which are not defined in the schema file anymore.
+    pub async fn orphans(&self) -> Result<Vec<Orphan>> {
+        let (schema_file, lock_file, _, _, _, _) = self.load().await?;
+        find_orphans(&schema_file, &lock_file).await
+    }
+
+    /// Prunes the given orphaned documents from the lock file, by deleting them with tombstones or
+    /// removing their commits. Returns the number of written or removed commits.
+    ///
+    /// Bootstrap documents of orphaned schemas are removed from the bootstrap lock file in both
+    /// cases, they can not be created without their schema.
+    pub async fn prune(&self, orphans: &[Orphan], mode: PruneMode) -> Result<usize> {
+        let (_, lock_file, bootstrap_lock, signers, _, _) = self.load().await?;
+        let commits = lock_file.commits.clone().unwrap_or_default();
+
+        let (pruned, total) = match mode {
+            PruneMode::Tombstone => {
+                let tombstones = tombstone_orphans(&lock_file, orphans, &signers.signers()).await?;
+                let total = tombstones.len();
+                let commits: Vec<Commit> = commits.into_iter().chain(tombstones).collect();
+                (
+                    LockFile::new(&commits).with_builds(&lock_file.builds),
+                    total,
+                )
+            }
+            PruneMode::Remove => {
+                let document_ids: Vec<DocumentId> = orphans
+                    .iter()
+                    .map(|orphan| orphan.document_id().clone())
+                    .collect();
+                let pruned = lock_file.without_documents(&document_ids)?;
+                let total = commits.len() - pruned.commits.as_ref().map_or(0, Vec::len);
+                (pruned, total)
+            }
+        };
+        self.write_lock_file(pruned, lock_file.tooling.clone())?;
+
+        let schema_names: Vec<SchemaName> = orphans
+            .iter()
+            .filter_map(|orphan| match orphan {
+                Orphan::Schema { name, .. } => Some(name.clone()),
+                Orphan::Field { .. } => None,
+            })
+            .collect();
+        let bootstrap_documents = bootstrap_documents(&bootstrap_lock, &schema_names)?;
+        if !bootstrap_documents.is_empty() {
+            bootstrap_lock
+                .without_documents(&bootstrap_documents)?
+                .write(&self.bootstrap_lock_path)?;
+        }
+
+        Ok(total)
+    }
+
+    /// Finds the field definitions of the lock file which the latest version of no schema refers
+    /// to anymore.
+    pub async fn unused_fields(&self) -> Result<Vec<UnusedField>> {
+        let lock_file = self.read_lock_file(&self.lock_path)?;
+        find_unused_fields(&lock_file).await
+    }
+
+    /// Deletes the given unused field definitions with tombstones. Returns the number of written
+    /// commits.
+    ///
+    /// Their commits are not removed, older versions of schemas refer to them and would become
+    /// invalid. Nodes delete the field definitions after deploying the lock file, applications
+    /// still using these versions lose the fields.
+    pub async fn delete_unused_fields(&self, unused_fields: &[UnusedField]) -> Result<usize> {
+        let (_, lock_file, _, signers, _, _) = self.load().await?;
+
+        let tombstones =
+            tombstone_unused_fields(&lock_file, unused_fields, &signers.signers()).await?;
+        let total = tombstones.len();
+        let commits: Vec<Commit> = lock_file
+            .commits
+            .clone()
+            .unwrap_or_default()
+            .into_iter()
+            .chain(tombstones)
+            .collect();
+        self.write_lock_file(
+            LockFile::new(&commits).with_builds(&lock_file.builds),
+            lock_file.tooling.clone(),
+        )?;
+
+        Ok(total)
+    }
+
+    /// Loads the schema file, the lock files (if they exist already) and the signers, reading the
+    /// key pair file if no other signer was set and the key pair files of schemas with their own
+    /// key.
+    ///
+    /// Relations to external schemas and schemas of dependencies get resolved and are returned as
+    /// well, afterwards the prefix is prepended to all schema names.
+    #[allow(clippy::type_complexity)]
+    async fn load(
+        &self,
+    ) -> Result<(
+        SchemaFile,
+        LockFile,
+        LockFile,
+        ProjectSigners,
+        Vec<ExternalSchema>,
+        BTreeMap<String, ResolvedDependency>,
+    )> {
+        if files::is_stdio(&self.schema_path) && files::is_stdio(&self.lock_path) {
+            return Err(Error::MultipleStdinInputs);
+        }
+
+        let (mut schema_file, externals, dependencies) = match &self.sandbox {
+            // Size of stdin is not known before reading it
+            Some(sandbox) if files::is_stdio(&self.schema_path) => (
+                sandbox.parse_schema_file(&files::read_file(&self.schema_path)?)?,
+                Vec::new(),
+                BTreeMap::new(),
+            ),
+            Some(sandbox) => (
+                sandbox.read_schema_file(&self.schema_path)?,
+                Vec::new(),
+                BTreeMap::new(),
+            ),
+            None => {
+                let mut schema_file = SchemaFile::from_path(&self.schema_path)?;
+                schema_file.resolve_includes(&self.cache_dir)?;
+                let externals =
+                    resolve_externals(&mut schema_file, &self.cache_dir, &self.vendor_dir).await?;
+                let dependencies =
+                    resolve_dependencies(&mut schema_file, &self.dependencies, &self.cache_dir)
+                        .await?;
+                (schema_file, externals, dependencies)
+            }
+        };
+        schema_file.apply_prefix(self.prefix.as_deref())?;
+        if schema_file.iter().len() == 0 {
+            return Err(Error::EmptySchemaFile);
+        }
+
+        let lock_file = self.read_lock_file(&self.lock_path)?;
+        let bootstrap_lock = self.read_lock_file(&self.bootstrap_lock_path)?;
+
+        let signers = self.load_signers(&schema_file)?;
+
+        Ok((
+            schema_file,
+            lock_file,
+            bootstrap_lock,
+            signers,
+            externals,
+            dependencies,
+        ))
+    }
+
+    /// Loads the signer of the project and of all schemas with their own key, every key pair file
+    /// is read once.
+    fn load_signers(&self, schema_file: &SchemaFile) -> Result<ProjectSigners> {
+        let default: Arc<dyn Signer> = match &self.signer {
+            Some(signer) => signer.clone(),
+            None => Arc::new(key_pair::read_key_pair(&self.private_key_path)?),
+        };
+
+        let mut key_pairs: BTreeMap<PathBuf, Arc<dyn Signer>> = BTreeMap::new();
+        let mut schemas = BTreeMap::new();
+
+        for (schema_name, schema_definition) in schema_file.iter() {
+            let path = match &schema_definition.key {
+                None => continue,
+                Some(SchemaKey::Path { path }) => path,
+                Some(SchemaKey::Name(name)) => self
+                    .keys
+                    .get(name)
+                    .ok_or_else(|| Error::UnknownKey(name.clone(), schema_name.clone()))?,
+            };
+
+            let signer = match key_pairs.get(path) {
+                Some(signer) => signer.clone(),
+                None => {
+                    let signer: Arc<dyn Signer> = Arc::new(key_pair::read_key_pair(path)?);
+                    key_pairs.insert(path.clone(), signer.clone());
+                    signer
+                }
+            };
+            schemas.insert(schema_name.clone(), signer);
+        }
+
+        Ok(ProjectSigners { default, schemas })
+    }
+
+    /// Reads a lock file within the limits of the sandbox if one is set, returns an empty lock
+    /// file if it does not exist yet.
+    ///
+    /// The path `-` reads the lock file from stdin, nothing piped into fishy counts as an empty
+    /// lock file. All commits need to be signed by one of the trusted keys if any are set.
+    fn read_lock_file(&self, path: &Path) -> Result<LockFile> {
+        let lock_file = if files::is_stdio(path) {
+            if io::stdin().is_terminal() {
+                return Ok(LockFile::new(&[]));
+            }
+
+            let data = files::read_file(path)?;
+            match (&self.sandbox, data.trim().is_empty()) {
+                (_, true) => LockFile::new(&[]),
+                (Some(sandbox), false) => sandbox.parse_lock_file(&data)?,
+                (None, false) => data.parse()?,
+            }
+        } else {
+            match (&self.sandbox, path.exists()) {
+                (Some(sandbox), true) => sandbox.read_lock_file(path)?,
+                (None, true) => LockFile::from_path(path)?,
+                (_, false) => LockFile::new(&[]),
+            }
+        };
+
+        verify_commit_authors(
+            lock_file.commits.as_deref().unwrap_or_default(),
+            &self.trusted_keys,
+        )?;
+
+        Ok(lock_file)
+    }
+
+    /// Builds the project, within the resource limits of the sandbox if one is set.
+    async fn build(
+        &self,
+        schema_file: &SchemaFile,
+        lock_file: &LockFile,
+        bootstrap_lock: &LockFile,
+        signers: &ProjectSigners,
+    ) -> Result<BuildResult> {
+        match &self.sandbox {
+            Some(sandbox) => {
+                sandbox
+                    .build(
+                        schema_file,
+                        lock_file,
+                        bootstrap_lock,
+                        signers.default.as_ref(),
+                    )
+                    .await
+            }
+            None => {
+                build_with_signers(schema_file, lock_file, bootstrap_lock, &signers.signers()).await
+            }
+        }
+    }
+
+    /// Writes the lock file with the given tooling metadata, split into segments if configured.
+    ///
+    /// The path `-` writes the lock file to stdout, always as a single file.
+    fn write_lock_file(&self, lock_file: LockFile, tooling: ToolingMetadata) -> Result<()> {
+        let lock_file = lock_file.with_tooling(tooling);
+        if self.lock_segments && !files::is_stdio(&self.lock_path) {
+            lock_file.write_segments(&self.lock_path)
+        } else {
+            lock_file.write(&self.lock_path)
+        }
+    }
+
+    /// Keeps the aliases file in sync with the current schema ids.
+    fn write_aliases<R: Reporter + ?Sized>(
+        &self,
+        aliases: &Aliases,
+        reporter: &mut R,
+    ) -> Result<()> {
+        // Do not touch the file when nothing changed
+        if self.aliases_path.exists() && &aliases::load(&self.aliases_path)? == aliases {
+            return Ok(());
+        }
+
+        aliases.write(&self.aliases_path)?;
+        reporter.progress(&Progress::AliasesWritten(self.aliases_path.clone()));
+
+        Ok(())
+    }
+}
+
+/// Warns the reporter about commits of the lock file which can not be derived from the schema
+/// file and about field definitions the latest version of no schema uses.
+async fn report_leftovers<R: Reporter + ?Sized>(
+    schema_file: &SchemaFile,
+    lock_file: &LockFile,
+    reporter: &mut R,
+) -> Result<()> {
+    let foreign = find_foreign_commits(schema_file, lock_file).await?;
+
+    // Fields no schema ever referred to or of orphaned schemas were reported as foreign already
+    let unused_fields: Vec<UnusedField> = find_unused_fields(lock_file)
+        .await?
+        .into_iter()
+        .filter(|field| {
+            !foreign
+                .iter()
+                .any(|commit| commit.document_id == field.document_id)
+        })
+        .collect();
+
+    if !foreign.is_empty() {
+        reporter.progress(&Progress::ForeignCommits(foreign));
+    }
+    if !unused_fields.is_empty() {
+        reporter.progress(&Progress::UnusedFields(unused_fields));
+    }
+
+    Ok(())
+}
+
+/// Signer of the project and the signers of schemas with their own key.
+#[derive(Debug)]
+struct ProjectSigners {
+    default: Arc<dyn Signer>,
+    schemas: BTreeMap<SchemaName, Arc<dyn Signer>>,
+}
+
+impl ProjectSigners {
+    /// Returns the signers to build with.
+    fn signers(&self) -> Signers<'_> {
+        self.schemas.iter().fold(
+            Signers::new(self.default.as_ref()),
+            |acc, (name, signer)| acc.with_schema_signer(name, signer.as_ref()),
+        )
+    }
+}
+
+/// Serializable state of a fishy project, for example to render it in a dashboard.
+#[derive(Clone, Debug, Serialize)]
+pub struct ProjectSnapshot {
+    /// Public key of the key pair used for signing, schemas with their own key are signed by
+    /// another one.
+    pub public_key: PublicKey,
+
+    /// Number of commits already written to the lock file.
+    pub committed_commits: usize,
+
+    /// Number of commits required to get to the current version of all schemas.
+    pub pending_commits: usize,
+
+    /// Changes of all new or changed schemas which have not been committed yet.
+    pub pending: Vec<PlanSummary>,
+
+    /// Entry hash of the latest commit of the lock files, nodes whose last deployment ended with
+    /// it are up-to-date.
+    pub last_commit: Option<Hash>,
+
+    /// Last deployment from this machine to every node.
+    pub deployments: Deployments,
+
+    /// All schemas defined in the schema file, in topological order.
+    pub schemas: Vec<SchemaSnapshot>,
+
+    /// Schemas of other projects which relations point at.
+    pub externals: Vec<ExternalSchema>,
+}
+
+/// State of a single schema inside of a project snapshot.
+#[derive(Clone, Debug, Serialize)]
+pub struct SchemaSnapshot {
+    /// Name of the schema.
+    pub name: SchemaName,
+
+    /// Public key of the key pair signing this schema.
+    pub public_key: PublicKey,
+
+    /// Current version of the schema description.
+    pub description: SchemaDescription,
+
+    /// Id of the last committed version of this schema (if it existed).
+    pub committed_schema_id: Option<SchemaId>,
+
+    /// Id of this schema after all pending changes have been committed.
+    pub current_schema_id: SchemaId,
+
+    /// Status of this schema compared to the last committed version.
+    pub status: SchemaStatus,
+
+    /// Current version of the schema fields, including their metadata.
+    pub fields: SchemaFields,
+
+    /// License of the schema design.
+    pub license: Option<String>,
+
+    /// Authors of the schema design.
+    pub authors: Vec<String>,
+
+    /// Website documenting the schema.
+    pub homepage: Option<String>,
+
+    /// `x-` prefixed metadata of the schema.
+    pub metadata: Metadata,
+}
+
+/// Status of a schema compared to its last committed version.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaStatus {
+    /// Schema was never committed before.
+    New,
+
+    /// Schema was committed before but has pending changes.
+    Changed,
+
+    /// Schema was committed before and has no pending changes.
+    Unchanged,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use std::process::Command;
+
+    use crate::deployments::{DeployStamp, Deployments};
+    use crate::error::Error;
+    use crate::lock_file::LockFile;
+    use crate::test_utils::{build_commits, key_pair, temp_dir};
+
+    use super::Project;
+
+    const VENUES: &str = r#"
+        [venues]
+        description = "Places to meet"
+        fields = { name = { type = "str" } }
+    "#;
+
+    #[tokio::test]
+    async fn snapshot_contains_pending_changes_and_deployments() {
+        let dir = temp_dir("snapshot");
+        let project = Project::from_dir(&dir);
+
+        // Venues were committed and deployed, events are not committed yet
+        let commits = build_commits(VENUES, &[], &key_pair(1)).await;
+        LockFile::new(&commits).write(&project.lock_path).unwrap();
+        fs::write(&project.private_key_path, hex::encode([1; 32])).unwrap();
+        fs::write(
+            &project.schema_path,
+            format!(
+                r#"{VENUES}
+                [events]
+                description = "Things happening"
+                fields = {{ title = {{ type = "str" }} }}
+                "#
+            ),
+        )
+        .unwrap();
+
+        let mut deployments = Deployments::default();
+        deployments.insert("http://localhost:2020/graphql", DeployStamp::new(&commits));
+        deployments.write(&project.deployments_path).unwrap();
+
+        let snapshot = project.snapshot().await.unwrap();
+
+        assert_eq!(snapshot.committed_commits, commits.len());
+        assert_eq!(snapshot.pending.len(), 1);
+        assert_eq!(snapshot.pending[0].name.to_string(), "events");
+        assert_eq!(
+            snapshot.last_commit,
+            commits.last().map(|commit| commit.entry_hash.clone())
+        );
+
+        let stamp = snapshot
+            .deployments
+            .get("http://localhost:2020/graphql")
+            .unwrap();
+        assert_eq!(stamp.last_commit, snapshot.last_commit);
+    }
+
+    #[test]
+    fn keeps_files_next_to_schema_file_when_streaming_lock_file() {
+        let project = Project::new("app/schema.toml", "-", "app/secret.txt");
+
+        assert_eq!(project.aliases_path, Path::new("app/schema-aliases.toml"));
+        assert_eq!(project.bootstrap_lock_path.parent(), Some(Path::new("app")));
+        assert_eq!(project.deployments_path.parent(), Some(Path::new("app")));
+    }
+
+    #[tokio::test]
+    async fn rejects_lock_files_with_untrusted_authors() {
+        let dir = temp_dir("untrusted-authors");
+        let project = Project::from_dir(&dir).with_trusted_key(key_pair(1).public_key());
+
+        let commits = build_commits(VENUES, &[], &key_pair(1)).await;
+        LockFile::new(&commits).write(&project.lock_path).unwrap();
+        let (lock_file, _) = project.read_lock_files().unwrap();
+        assert_eq!(
+            lock_file.commits.map(|commits| commits.len()),
+            Some(commits.len())
+        );
+
+        // Commits of another author are not deployed, even if they are in the bootstrap lock file
+        let untrusted = build_commits(VENUES, &[], &key_pair(2)).await;
+        LockFile::new(&untrusted)
+            .write(&project.bootstrap_lock_path)
+            .unwrap();
+        assert!(matches!(
+            project.read_lock_files(),
+            Err(Error::UntrustedCommitAuthor(entry_hash, _)) if entry_hash == untrusted[0].entry_hash
+        ));
+    }
+
+    /// Writes a project with the venues schema as segmented lock file into the given directory.
+    async fn segmented_upstream(dir: &Path) {
+        fs::create_dir_all(dir).unwrap();
+        let commits = build_commits(VENUES, &[], &key_pair(1)).await;
+        LockFile::new(&commits)
+            .write_segments(dir.join("schema.lock"))
+            .unwrap();
+        assert!(dir.join("locks").join("venues.lock").exists());
+    }
+
+    /// Returns a project relating to the venues schema of an external project.
+    fn project_relating_to(dir: &Path, source: &str) -> Project {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join("secret.txt"), hex::encode([2; 32])).unwrap();
+        fs::write(
+            dir.join("schema.toml"),
+            format!(
+                r#"
+                [events]
+                description = "Things happening"
+                fields = {{ venue = {{ type = "relation", schema = {{ name = "venues", {source} }} }} }}
+                "#
+            ),
+        )
+        .unwrap();
+
+        Project::from_dir(dir)
+    }
+
+    #[tokio::test]
+    async fn builds_with_vendored_segmented_lock_file() {
+        let dir = temp_dir("vendor-segments");
+        segmented_upstream(&dir.join("upstream")).await;
+        let project = project_relating_to(&dir.join("app"), r#"path = "../upstream""#);
+
+        let vendored = project.vendor().unwrap();
+        assert_eq!(vendored.len(), 1);
+
+        // Builds only read the vendored lock file from now on
+        fs::remove_dir_all(dir.join("upstream")).unwrap();
+        let plan = project.plan().await.unwrap();
+        assert_eq!(plan.len(), 1);
+        assert_eq!(plan[0].name.to_string(), "events");
+    }
+
+    #[tokio::test]
+    async fn builds_with_segmented_lock_file_from_git() {
+        let dir = temp_dir("git-segments");
+        let upstream = dir.join("upstream");
+        segmented_upstream(&upstream).await;
+
+        for args in [
+            vec!["init", "--quiet"],
+            vec!["add", "."],
+            vec![
+                "-c",
+                "user.name=fishy",
+                "-c",
+                "user.email=fishy@example.org",
+                "commit",
+                "--quiet",
+                "-m",
+                "Add venues",
+            ],
+        ] {
+            let status = Command::new("git")
+                .args(args)
+                .current_dir(&upstream)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        }
+
+        let source = format!(r#"git = "file://{}", rev = "HEAD""#, upstream.display());
+        let project = project_relating_to(&dir.join("app"), &source);
+
+        let plan = project.plan().await.unwrap();
+        assert_eq!(plan.len(), 1);
+        assert_eq!(plan[0].name.to_string(), "events");
+    }
+}
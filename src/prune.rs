@@ -0,0 +1,387 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Find and prune documents of schemas which are not defined anymore.
+//!
+//! Removing a schema from the schema file does not touch its commits, they stay in the lock file
+//! and keep getting deployed. The schema definitions of these orphaned schemas and the field
+//! definitions only they refer to can be deleted with tombstones or removed from the lock file.
+//!
+//! Fields removed from schemas which are still defined are kept, older versions of these schemas
+//! refer to them. They keep getting deployed as well, `find_unused_fields` finds them to delete
+//! them separately.
+use std::collections::HashSet;
+
+use p2panda_rs::document::traits::AsDocument;
+use p2panda_rs::document::{DocumentId, DocumentViewId};
+use p2panda_rs::operation::decode::decode_operation;
+use p2panda_rs::operation::traits::{Actionable, Schematic};
+use p2panda_rs::operation::{OperationAction, OperationBuilder, OperationId};
+use p2panda_rs::schema::system::SchemaFieldView;
+use p2panda_rs::schema::{FieldName, FieldType, SchemaId, SchemaName};
+use p2panda_rs::storage_provider::traits::DocumentStore;
+use p2panda_rs::test_utils::memory_store::MemoryStore;
+use tracing::{debug, instrument};
+
+use crate::build::materialize_previous_schemas;
+use crate::error::{Error, Result};
+use crate::lock_file::{Annotation, Commit, LockFile};
+use crate::schema_file::{RelationId, SchemaField, SchemaFile};
+use crate::signer::{sign_and_publish, Signer, Signers};
+
+/// Document in the lock file belonging to a schema which is not defined anymore.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Orphan {
+    /// Definition of a schema which is neither defined in the schema file nor related to by a
+    /// defined schema.
+    Schema {
+        name: SchemaName,
+        document_id: DocumentId,
+        view_id: DocumentViewId,
+    },
+
+    /// Definition of a field of an orphaned schema which no defined schema refers to.
+    Field {
+        name: FieldName,
+        schema: SchemaName,
+        document_id: DocumentId,
+        view_id: DocumentViewId,
+    },
+}
+
+impl Orphan {
+    /// Returns the name of the orphaned schema this document belongs to.
+    pub fn schema(&self) -> &SchemaName {
+        match self {
+            Orphan::Schema { name, .. } => name,
+            Orphan::Field { schema, .. } => schema,
+        }
+    }
+
+    /// Returns the id of the orphaned document.
+    pub fn document_id(&self) -> &DocumentId {
+        match self {
+            Orphan::Schema { document_id, .. } | Orphan::Field { document_id, .. } => document_id,
+        }
+    }
+
+    /// Returns the latest view of the orphaned document.
+    pub fn view_id(&self) -> &DocumentViewId {
+        match self {
+            Orphan::Schema { view_id, .. } | Orphan::Field { view_id, .. } => view_id,
+        }
+    }
+}
+
+/// Field definition in the lock file which the latest version of no schema refers to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnusedField {
+    /// Name of the field.
+    pub name: FieldName,
+
+    /// Schema which referred to the field in an earlier version, `None` if no schema ever did.
+    pub schema: Option<SchemaName>,
+
+    /// Id of the field definition document.
+    pub document_id: DocumentId,
+
+    /// Latest view of the field definition document.
+    pub view_id: DocumentViewId,
+}
+
+/// How orphaned documents get pruned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PruneMode {
+    /// Append commits deleting the orphaned documents, nodes which received them delete them as
+    /// well.
+    Tombstone,
+
+    /// Remove all commits of the orphaned documents from the lock file. Only use this if the lock
+    /// file was never deployed, nodes keep the documents and new documents might reuse their logs.
+    Remove,
+}
+
+/// Finds the documents of the lock file belonging to schemas which are not defined in the schema
+/// file anymore, sorted by schema with the schema definition first.
+///
+/// Schemas renamed with `rename_from` and schemas which defined schemas relate to, directly or
+/// through other schemas of the lock file, are not orphaned.
+#[instrument(skip_all)]
+pub async fn find_orphans(schema_file: &SchemaFile, lock_file: &LockFile) -> Result<Vec<Orphan>> {
+    let store = MemoryStore::default();
+    let previous_schemas = materialize_previous_schemas(&store, lock_file).await?;
+
+    // Names of schemas of the schema file, their previous names and the schemas they relate to
+    // with their ids
+    let mut pending: Vec<SchemaName> = Vec::new();
+    for (schema_name, schema_definition) in schema_file.iter() {
+        pending.push(schema_name.clone());
+        pending.extend(schema_definition.rename_from.clone());
+
+        for (_, schema_field) in schema_definition.fields.iter() {
+            if let SchemaField::Relation { schema, .. } = schema_field {
+                if let RelationId::Id(schema_id) = &schema.id {
+                    pending.push(schema_id.name());
+                }
+            }
+        }
+    }
+
+    // Follow relations of committed schemas which are still in use
+    let mut kept: HashSet<SchemaName> = HashSet::new();
+    while let Some(schema_name) = pending.pop() {
+        let Some(previous_schema) = previous_schemas.get(&schema_name) else {
+            continue;
+        };
+
+        if !kept.insert(schema_name) {
+            continue;
+        }
+
+        for (_, field_type) in previous_schema.schema.fields().iter() {
+            if let Some(schema_id) = relation_schema_id(field_type) {
+                pending.push(schema_id.name());
+            }
+        }
+    }
+
+    // Field definitions used by schemas which are still in use
+    let mut kept_fields: HashSet<DocumentId> = HashSet::new();
+    for schema_name in &kept {
+        for view_id in previous_schemas[schema_name].schema_view.fields().iter() {
+            kept_fields.insert(document_id(&store, view_id).await?);
+        }
+    }
+
+    let mut orphans = Vec::new();
+    let mut orphaned_schemas: HashSet<SchemaName> = HashSet::new();
+
+    for (schema_name, previous_schema) in &previous_schemas {
+        if kept.contains(schema_name) {
+            continue;
+        }
+
+        let view_id = previous_schema.schema_view.view_id().clone();
+        orphans.push(Orphan::Schema {
+            name: schema_name.clone(),
+            document_id: document_id(&store, &view_id).await?,
+            view_id,
+        });
+        orphaned_schemas.insert(schema_name.clone());
+    }
+
+    let document_schemas = lock_file.document_schemas()?;
+    let field_definitions = store
+        .get_documents_by_schema(&SchemaId::SchemaFieldDefinition(1))
+        .await
+        .map_err(|err| Error::Storage(err.to_string()))?;
+
+    for document in field_definitions {
+        // Skip over deleted documents
+        let Some(document_view) = document.view() else {
+            continue;
+        };
+
+        let Some(schema_name) = document_schemas.get(document.id()) else {
+            continue;
+        };
+
+        if !orphaned_schemas.contains(schema_name) || kept_fields.contains(document.id()) {
+            continue;
+        }
+
+        let field_view = SchemaFieldView::try_from(document_view)?;
+        orphans.push(Orphan::Field {
+            name: field_view.name().to_owned(),
+            schema: schema_name.clone(),
+            document_id: document.id().clone(),
+            view_id: document.view_id().clone(),
+        });
+    }
+
+    orphans.sort_by_key(|orphan| match orphan {
+        Orphan::Schema { name, .. } => (name.to_string(), None),
+        Orphan::Field { name, schema, .. } => (schema.to_string(), Some(name.clone())),
+    });
+    debug!("Found {} orphaned documents", orphans.len());
+
+    Ok(orphans)
+}
+
+/// Finds the field definitions of the lock file which the latest version of no schema refers to,
+/// sorted by schema and name.
+///
+/// These are fields which were removed from a schema or which no schema ever referred to. Field
+/// definitions which were already deleted are skipped.
+#[instrument(skip_all)]
+pub async fn find_unused_fields(lock_file: &LockFile) -> Result<Vec<UnusedField>> {
+    let store = MemoryStore::default();
+    let previous_schemas = materialize_previous_schemas(&store, lock_file).await?;
+
+    let mut used_fields: HashSet<DocumentId> = HashSet::new();
+    for previous_schema in previous_schemas.values() {
+        for view_id in previous_schema.schema_view.fields().iter() {
+            used_fields.insert(document_id(&store, view_id).await?);
+        }
+    }
+
+    let document_schemas = lock_file.document_schemas()?;
+    let field_definitions = store
+        .get_documents_by_schema(&SchemaId::SchemaFieldDefinition(1))
+        .await
+        .map_err(|err| Error::Storage(err.to_string()))?;
+
+    let mut unused_fields = Vec::new();
+    for document in field_definitions {
+        // Skip over deleted documents
+        let Some(document_view) = document.view() else {
+            continue;
+        };
+
+        if used_fields.contains(document.id()) {
+            continue;
+        }
+
+        let field_view = SchemaFieldView::try_from(document_view)?;
+        unused_fields.push(UnusedField {
+            name: field_view.name().to_owned(),
+            schema: document_schemas.get(document.id()).cloned(),
+            document_id: document.id().clone(),
+            view_id: document.view_id().clone(),
+        });
+    }
+
+    unused_fields.sort_by_key(|field| {
+        (
+            field.schema.as_ref().map(|schema| schema.to_string()),
+            field.name.clone(),
+        )
+    });
+    debug!("Found {} unused field definitions", unused_fields.len());
+
+    Ok(unused_fields)
+}
+
+/// Signs commits deleting the given orphaned documents, signed by the signer of their schema.
+pub async fn tombstone_orphans(
+    lock_file: &LockFile,
+    orphans: &[Orphan],
+    signers: &Signers<'_>,
+) -> Result<Vec<Commit>> {
+    let store = MemoryStore::default();
+    materialize_previous_schemas(&store, lock_file).await?;
+
+    let mut commits = Vec::new();
+    for orphan in orphans {
+        let (schema_id, field) = match orphan {
+            Orphan::Schema { .. } => (SchemaId::SchemaDefinition(1), None),
+            Orphan::Field { name, .. } => (SchemaId::SchemaFieldDefinition(1), Some(name)),
+        };
+
+        let commit = tombstone(
+            &store,
+            &schema_id,
+            orphan.view_id(),
+            signers.get(orphan.schema()),
+        )
+        .await?;
+        commits.push(commit.with_annotation(Annotation::new(
+            orphan.schema(),
+            OperationAction::Delete,
+            field,
+        )));
+    }
+
+    Ok(commits)
+}
+
+/// Signs commits deleting the given unused field definitions, signed by the signer of the schema
+/// which referred to them.
+pub async fn tombstone_unused_fields(
+    lock_file: &LockFile,
+    unused_fields: &[UnusedField],
+    signers: &Signers<'_>,
+) -> Result<Vec<Commit>> {
+    let store = MemoryStore::default();
+    materialize_previous_schemas(&store, lock_file).await?;
+
+    let mut commits = Vec::new();
+    for field in unused_fields {
+        let signer = match &field.schema {
+            Some(schema) => signers.get(schema),
+            None => signers.default_signer(),
+        };
+
+        let commit = tombstone(
+            &store,
+            &SchemaId::SchemaFieldDefinition(1),
+            &field.view_id,
+            signer,
+        )
+        .await?;
+        commits.push(match &field.schema {
+            Some(schema) => commit.with_annotation(Annotation::new(
+                schema,
+                OperationAction::Delete,
+                Some(&field.name),
+            )),
+            None => commit,
+        });
+    }
+
+    Ok(commits)
+}
+
+/// Signs a commit deleting the document with the given view.
+async fn tombstone(
+    store: &MemoryStore,
+    schema_id: &SchemaId,
+    view_id: &DocumentViewId,
+    signer: &dyn Signer,
+) -> Result<Commit> {
+    let operation = OperationBuilder::new(schema_id)
+        .action(OperationAction::Delete)
+        .previous(view_id)
+        .build()?;
+    let (encoded_entry, encoded_operation) = sign_and_publish(store, signer, &operation).await?;
+
+    Ok(Commit::new(&encoded_entry, &encoded_operation))
+}
+
+/// Returns the ids of all bootstrap documents of the given schemas, at any of their versions.
+pub fn bootstrap_documents(
+    bootstrap_lock: &LockFile,
+    schema_names: &[SchemaName],
+) -> Result<Vec<DocumentId>> {
+    let mut document_ids = Vec::new();
+
+    for commit in bootstrap_lock.commits.iter().flatten() {
+        let operation = decode_operation(&commit.operation)?;
+        if operation.previous().is_none() && schema_names.contains(&operation.schema_id().name()) {
+            document_ids.push(DocumentId::new(&OperationId::new(&commit.entry_hash)));
+        }
+    }
+
+    Ok(document_ids)
+}
+
+/// Returns the schema id a relation field points at.
+fn relation_schema_id(field_type: &FieldType) -> Option<&SchemaId> {
+    match field_type {
+        FieldType::Relation(schema_id)
+        | FieldType::RelationList(schema_id)
+        | FieldType::PinnedRelation(schema_id)
+        | FieldType::PinnedRelationList(schema_id) => Some(schema_id),
+        _ => None,
+    }
+}
+
+/// Returns the id of the document with the given view.
+async fn document_id(store: &MemoryStore, view_id: &DocumentViewId) -> Result<DocumentId> {
+    let document = store
+        .get_document_by_view_id(view_id)
+        .await
+        .map_err(|err| Error::Storage(err.to_string()))?
+        .ok_or_else(|| Error::Storage(format!("document view {view_id} not found")))?;
+
+    Ok(document.id().clone())
+}
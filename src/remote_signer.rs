@@ -0,0 +1,166 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Sign entries with a remote signing service over HTTP.
+//!
+//! Production schema keys can live in a dedicated signing service while developers run `build`
+//! locally. The service needs to provide two endpoints below its base URL:
+//!
+//! * `GET <url>/public-key` responding with `{ "public_key": "<hex>" }`
+//! * `POST <url>/sign` with `{ "public_key": "<hex>", "bytes": "<hex>" }`, responding with
+//!   `{ "signature": "<hex>" }` containing the ed25519 signature of the given bytes
+use std::collections::HashMap;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use p2panda_rs::identity::PublicKey;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::error::{Error, Result};
+use crate::signer::Signer;
+
+/// Default timeout for requests to the signing service.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Signer sending every entry to a remote signing service.
+#[derive(Clone, Debug)]
+pub struct RemoteSigner {
+    client: reqwest::Client,
+    url: String,
+    timeout: Duration,
+    headers: HashMap<String, String>,
+    public_key: PublicKey,
+}
+
+impl RemoteSigner {
+    /// Returns a builder to configure a `RemoteSigner` for the service at the given base URL.
+    pub fn builder(url: &str) -> RemoteSignerBuilder {
+        RemoteSignerBuilder::new(url)
+    }
+
+    /// Returns the base URL of the signing service.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+#[async_trait]
+impl Signer for RemoteSigner {
+    fn public_key(&self) -> PublicKey {
+        self.public_key
+    }
+
+    #[instrument(skip_all, fields(url = %self.url))]
+    async fn sign(&self, bytes: &[u8]) -> Result<Vec<u8>> {
+        let body = SignRequest {
+            public_key: self.public_key.to_string(),
+            bytes: hex::encode(bytes),
+        };
+
+        let request = self.client.post(format!("{}/sign", self.url)).json(&body);
+        let response: SignResponse = send(request, self.timeout, &self.headers).await?;
+        debug!("Signed entry with signing service");
+
+        hex::decode(response.signature)
+            .map_err(|err| Error::Sign(format!("Invalid signature from signing service: {err}")))
+    }
+}
+
+/// Builder to configure a `RemoteSigner`.
+#[derive(Clone, Debug)]
+pub struct RemoteSignerBuilder {
+    url: String,
+    timeout: Duration,
+    headers: HashMap<String, String>,
+}
+
+impl RemoteSignerBuilder {
+    /// Returns a new builder with default settings.
+    fn new(url: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_owned(),
+            timeout: DEFAULT_TIMEOUT,
+            headers: HashMap::new(),
+        }
+    }
+
+    /// Sets the timeout for every request.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Adds a custom HTTP header which gets sent with every request, for example `Authorization`.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.insert(name.to_owned(), value.to_owned());
+        self
+    }
+
+    /// Authenticates every request with the given token in an `Authorization: Bearer` header.
+    pub fn bearer_token(self, token: &str) -> Self {
+        self.header("Authorization", &format!("Bearer {token}"))
+    }
+
+    /// Asks the signing service for its public key and returns the configured `RemoteSigner`.
+    pub async fn connect(self) -> Result<RemoteSigner> {
+        let client = reqwest::Client::new();
+
+        let request = client.get(format!("{}/public-key", self.url));
+        let response: PublicKeyResponse = send(request, self.timeout, &self.headers).await?;
+        let public_key = PublicKey::new(&response.public_key).map_err(|err| {
+            Error::Sign(format!("Invalid public key from signing service: {err}"))
+        })?;
+        debug!(%public_key, "Connected to signing service");
+
+        Ok(RemoteSigner {
+            client,
+            url: self.url,
+            timeout: self.timeout,
+            headers: self.headers,
+            public_key,
+        })
+    }
+}
+
+/// Sends a request to the signing service and parses the JSON response.
+async fn send<T: DeserializeOwned>(
+    request: reqwest::RequestBuilder,
+    timeout: Duration,
+    headers: &HashMap<String, String>,
+) -> Result<T> {
+    let mut request = request.timeout(timeout);
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+
+    let response = request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| Error::Sign(format!("Signing service failed: {err}")))?;
+
+    response
+        .json()
+        .await
+        .map_err(|err| Error::Sign(format!("Invalid response from signing service: {err}")))
+}
+
+/// Request to sign bytes.
+#[derive(Serialize)]
+struct SignRequest {
+    public_key: String,
+    bytes: String,
+}
+
+/// Signature returned by the signing service.
+#[derive(Deserialize)]
+struct SignResponse {
+    signature: String,
+}
+
+/// Public key returned by the signing service.
+#[derive(Deserialize)]
+struct PublicKeyResponse {
+    public_key: String,
+}
@@ -0,0 +1,67 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Hooks to present the build process to the user.
+//!
+//! The library does not print anything or ask for input itself. Instead it calls a `Reporter` to
+//! display the plan, report progress and confirm changes, which allows the command-line-tool and
+//! graphical applications to provide their own interface.
+use std::path::PathBuf;
+
+use p2panda_rs::identity::PublicKey;
+use p2panda_rs::schema::SchemaName;
+
+use crate::build::PlanSummary;
+use crate::error::Result;
+use crate::foreign::ForeignCommit;
+use crate::prune::UnusedField;
+
+/// Presents the build process to the user.
+pub trait Reporter {
+    /// Displays the changes of all schemas.
+    ///
+    /// If `only_changes` is set, schemas without any changes can be omitted.
+    fn plan(&mut self, summaries: &[PlanSummary], public_key: &PublicKey, only_changes: bool);
+
+    /// Asks the user if the given number of new commits should be written to the lock files.
+    ///
+    /// Returning `false` aborts without writing any files.
+    fn confirm(&mut self, total: usize) -> Result<bool>;
+
+    /// Informs the user about the progress of the build process.
+    fn progress(&mut self, event: &Progress);
+}
+
+/// Events reported during the build process.
+#[derive(Clone, Debug)]
+pub enum Progress {
+    /// Schemas disabled with `enabled = false` were skipped.
+    SchemasSkipped(Vec<SchemaName>),
+
+    /// Commits of the lock file can not be derived from the schema file, for example because
+    /// their schema is not defined anymore. They still get deployed.
+    ForeignCommits(Vec<ForeignCommit>),
+
+    /// Field definitions were removed from their schemas but still get deployed, they can be
+    /// deleted with `Project::delete_unused_fields`.
+    UnusedFields(Vec<UnusedField>),
+
+    /// All schemas are already committed, there is nothing to write.
+    NoChanges,
+
+    /// User did not confirm the changes, nothing was written.
+    Aborted,
+
+    /// New commits have been added to the lock file at the given path.
+    CommitsWritten(usize, PathBuf),
+
+    /// New commits of bootstrap documents have been added to the bootstrap lock file at the given
+    /// path.
+    BootstrapCommitsWritten(usize, PathBuf),
+
+    /// Aliases file at the given path was updated.
+    AliasesWritten(PathBuf),
+
+    /// Tooling metadata, like field constraints, was updated in the lock file at the given path
+    /// without any new commits.
+    ToolingWritten(PathBuf),
+}
@@ -0,0 +1,376 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Re-sign the history of lock files with another key pair.
+//!
+//! Leaked key pairs or projects changing ownership require signing the schemas with a new key
+//! pair. Re-signing replays every commit of the lock file with the same action and fields, signed
+//! by the new key pair into its own logs, so the whole history of all schemas is kept.
+//!
+//! Operation ids are derived from the signed entries, all re-signed documents get new view ids
+//! and with them all schemas new schema ids. Nodes which received the previous commits still know
+//! the old schemas, applications need to be updated to the new schema ids.
+use std::collections::HashMap;
+
+use p2panda_rs::document::DocumentViewId;
+use p2panda_rs::entry::decode::decode_entry;
+use p2panda_rs::entry::traits::AsEntry;
+use p2panda_rs::hash::HashId;
+use p2panda_rs::identity::PublicKey;
+use p2panda_rs::operation::decode::decode_operation;
+use p2panda_rs::operation::plain::PlainValue;
+use p2panda_rs::operation::traits::{Actionable, Schematic};
+use p2panda_rs::operation::{OperationBuilder, OperationId, OperationValue, PinnedRelationList};
+use p2panda_rs::schema::{FieldType, SchemaId};
+use p2panda_rs::test_utils::memory_store::MemoryStore;
+use tracing::{debug, instrument};
+
+use crate::build::get_previous_schemas;
+use crate::error::{Error, Result};
+use crate::lock_file::{BuildMetadata, Commit, LockFile};
+use crate::signer::{sign_and_publish, Signer};
+
+/// Lock file with the re-signed history and the resulting schema ids.
+#[derive(Clone, Debug)]
+pub struct Resign {
+    /// Lock file with the same commits signed by the new key pair, with the tooling metadata and
+    /// builds of the original lock file.
+    pub lock_file: LockFile,
+
+    /// Previous and new id of every schema, sorted by name.
+    pub schema_ids: Vec<(SchemaId, SchemaId)>,
+}
+
+/// Replays all commits of the lock file signed by the new signer.
+///
+/// All commits need to be signed by the old public key. Relations between schemas of the lock
+/// file point at the re-signed versions of them, relations to other schemas are kept as they are.
+#[instrument(skip_all, fields(old_key = %old_key, new_key = %signer.public_key()))]
+pub async fn resign_lock_file(
+    lock_file: &LockFile,
+    old_key: &PublicKey,
+    signer: &dyn Signer,
+) -> Result<Resign> {
+    // Make sure the history is valid before replaying it
+    let previous_schemas = get_previous_schemas(lock_file).await?;
+
+    for commit in lock_file.commits.iter().flatten() {
+        let public_key = decode_entry(&commit.entry)?.public_key().to_owned();
+        if &public_key != old_key {
+            return Err(Error::UnexpectedCommitAuthor(
+                commit.entry_hash.clone(),
+                public_key.to_string(),
+            ));
+        }
+    }
+
+    let store = MemoryStore::default();
+    let mut operation_ids: HashMap<OperationId, OperationId> = HashMap::new();
+    let mut commits = Vec::new();
+
+    for commit in lock_file.commits.iter().flatten() {
+        let plain_operation = decode_operation(&commit.operation)?;
+
+        let mut fields: Vec<(String, OperationValue)> = Vec::new();
+        for (name, value) in plain_operation
+            .fields()
+            .iter()
+            .flat_map(|fields| fields.iter())
+        {
+            let value = match value {
+                PlainValue::String(field_type)
+                    if name == "type"
+                        && matches!(
+                            plain_operation.schema_id(),
+                            SchemaId::SchemaFieldDefinition(_)
+                        ) =>
+                {
+                    match field_type.parse::<FieldType>() {
+                        Ok(field_type) => remap_field_type(field_type, &operation_ids).into(),
+                        Err(_) => field_type.clone().into(),
+                    }
+                }
+                PlainValue::String(value) => value.clone().into(),
+                PlainValue::Boolean(value) => (*value).into(),
+                PlainValue::Integer(value) => (*value).into(),
+                PlainValue::Float(value) => (*value).into(),
+                PlainValue::BytesOrRelation(value) => value.as_slice().into(),
+                PlainValue::AmbiguousRelation(hashes) => {
+                    // Only pinned relation lists, like the fields of schema definitions, occur in
+                    // system schemas. Empty lists can not be told apart from other relations
+                    OperationValue::PinnedRelationList(PinnedRelationList::new(
+                        hashes
+                            .iter()
+                            .map(|hash| {
+                                remap_view_id(
+                                    &DocumentViewId::new(&[OperationId::new(hash)]),
+                                    &operation_ids,
+                                )
+                            })
+                            .collect(),
+                    ))
+                }
+                PlainValue::PinnedRelationList(view_ids) => {
+                    OperationValue::PinnedRelationList(PinnedRelationList::new(
+                        view_ids
+                            .iter()
+                            .map(|hashes| {
+                                let operation_ids_of_view: Vec<OperationId> =
+                                    hashes.iter().map(OperationId::new).collect();
+                                remap_view_id(
+                                    &DocumentViewId::new(&operation_ids_of_view),
+                                    &operation_ids,
+                                )
+                            })
+                            .collect(),
+                    ))
+                }
+            };
+            fields.push((name.clone(), value));
+        }
+        let fields: Vec<(&str, OperationValue)> = fields
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.clone()))
+            .collect();
+
+        let mut builder =
+            OperationBuilder::new(plain_operation.schema_id()).action(plain_operation.action());
+        if let Some(previous) = plain_operation.previous() {
+            builder = builder.previous(&remap_view_id(previous, &operation_ids));
+        }
+        if !fields.is_empty() {
+            builder = builder.fields(&fields);
+        }
+        let operation = builder.build()?;
+
+        let (encoded_entry, encoded_operation) =
+            sign_and_publish(&store, signer, &operation).await?;
+        let resigned = Commit {
+            annotation: commit.annotation.clone(),
+            ..Commit::new(&encoded_entry, &encoded_operation)
+        };
+
+        operation_ids.insert(
+            OperationId::new(&commit.entry_hash),
+            OperationId::new(&resigned.entry_hash),
+        );
+        commits.push(resigned);
+    }
+    debug!("Re-signed {} commits", commits.len());
+
+    let builds: Vec<BuildMetadata> = lock_file
+        .builds
+        .iter()
+        .map(|build| BuildMetadata {
+            commits: build
+                .commits
+                .iter()
+                .map(|entry_hash| {
+                    operation_ids
+                        .get(&OperationId::new(entry_hash))
+                        .map(|operation_id| operation_id.as_hash().clone())
+                        .unwrap_or_else(|| entry_hash.clone())
+                })
+                .collect(),
+            ..build.clone()
+        })
+        .collect();
+
+    let lock_file = LockFile::new(&commits)
+        .with_builds(&builds)
+        .with_tooling(lock_file.tooling.clone());
+
+    let resigned_schemas = get_previous_schemas(&lock_file).await?;
+    let schema_ids = previous_schemas
+        .iter()
+        .filter_map(|(schema_name, previous_schema)| {
+            resigned_schemas.get(schema_name).map(|resigned_schema| {
+                (
+                    previous_schema.schema.id().to_owned(),
+                    resigned_schema.schema.id().to_owned(),
+                )
+            })
+        })
+        .collect();
+
+    Ok(Resign {
+        lock_file,
+        schema_ids,
+    })
+}
+
+/// Replaces the re-signed operations of a document view with their new ids.
+fn remap_view_id(
+    view_id: &DocumentViewId,
+    operation_ids: &HashMap<OperationId, OperationId>,
+) -> DocumentViewId {
+    let remapped: Vec<OperationId> = view_id
+        .iter()
+        .map(|operation_id| {
+            operation_ids
+                .get(operation_id)
+                .cloned()
+                .unwrap_or_else(|| operation_id.clone())
+        })
+        .collect();
+
+    DocumentViewId::new(&remapped)
+}
+
+/// Points relations at the re-signed versions of schemas of the lock file.
+fn remap_field_type(
+    field_type: FieldType,
+    operation_ids: &HashMap<OperationId, OperationId>,
+) -> FieldType {
+    let remap = |schema_id: SchemaId| match schema_id {
+        SchemaId::Application(name, view_id) => {
+            SchemaId::Application(name, remap_view_id(&view_id, operation_ids))
+        }
+        schema_id => schema_id,
+    };
+
+    match field_type {
+        FieldType::Relation(schema_id) => FieldType::Relation(remap(schema_id)),
+        FieldType::RelationList(schema_id) => FieldType::RelationList(remap(schema_id)),
+        FieldType::PinnedRelation(schema_id) => FieldType::PinnedRelation(remap(schema_id)),
+        FieldType::PinnedRelationList(schema_id) => FieldType::PinnedRelationList(remap(schema_id)),
+        field_type => field_type,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::document::DocumentViewId;
+    use p2panda_rs::entry::decode::decode_entry;
+    use p2panda_rs::entry::traits::AsEntry;
+    use p2panda_rs::hash::{Hash, HashId};
+    use p2panda_rs::operation::decode::decode_operation;
+    use p2panda_rs::operation::traits::Actionable;
+    use p2panda_rs::operation::OperationId;
+    use p2panda_rs::schema::{FieldType, SchemaId};
+
+    use crate::build::get_previous_schemas;
+    use crate::error::Error;
+    use crate::lock_file::LockFile;
+    use crate::test_utils::{build_commits, key_pair};
+
+    use super::resign_lock_file;
+
+    const VENUES: &str = r#"
+        [venues]
+        description = "Places to meet"
+        fields = { name = { type = "str" } }
+    "#;
+
+    #[tokio::test]
+    async fn rejects_commits_of_other_authors() {
+        let commits = build_commits(VENUES, &[], &key_pair(1)).await;
+
+        let result = resign_lock_file(
+            &LockFile::new(&commits),
+            &key_pair(2).public_key(),
+            &key_pair(3),
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(Error::UnexpectedCommitAuthor(entry_hash, _)) if entry_hash == commits[0].entry_hash
+        ));
+    }
+
+    #[tokio::test]
+    async fn remaps_relations_and_previous_operations() {
+        let old_key_pair = key_pair(1);
+        let new_key_pair = key_pair(2);
+
+        let mut commits = build_commits(VENUES, &[], &old_key_pair).await;
+        let venues_v1 = LockFile::new(&commits).schema_ids().unwrap()[0].clone();
+
+        // Venues get updated while events still relate to their first version
+        let schema_file = format!(
+            r#"
+            [venues]
+            description = "Places to meet"
+            fields = {{ name = {{ type = "str" }}, city = {{ type = "str" }} }}
+
+            [events]
+            description = "Things happening"
+            fields = {{ venue = {{ type = "relation", schema = {{ id = "{venues_v1}" }} }} }}
+            "#
+        );
+        commits.extend(build_commits(&schema_file, &commits, &old_key_pair).await);
+
+        let resign = resign_lock_file(
+            &LockFile::new(&commits),
+            &old_key_pair.public_key(),
+            &new_key_pair,
+        )
+        .await
+        .unwrap();
+        let resigned_commits = resign.lock_file.commits.clone().unwrap();
+        assert_eq!(resigned_commits.len(), commits.len());
+
+        // Commits are replayed in the same order, with the new id of every operation
+        let resigned_hash = |hash: &Hash| -> Hash {
+            let index = commits
+                .iter()
+                .position(|commit| &commit.entry_hash == hash)
+                .expect("Operation of the lock file");
+            resigned_commits[index].entry_hash.clone()
+        };
+
+        let mut updates = 0;
+        for (commit, resigned) in commits.iter().zip(&resigned_commits) {
+            let entry = decode_entry(&resigned.entry).unwrap();
+            assert_eq!(entry.public_key(), &new_key_pair.public_key());
+
+            let previous = decode_operation(&commit.operation)
+                .unwrap()
+                .previous()
+                .cloned();
+            let resigned_previous = decode_operation(&resigned.operation)
+                .unwrap()
+                .previous()
+                .cloned();
+            if let (Some(previous), Some(resigned_previous)) = (previous, resigned_previous) {
+                updates += 1;
+                for (operation_id, resigned_operation_id) in
+                    previous.iter().zip(resigned_previous.iter())
+                {
+                    assert_eq!(
+                        resigned_operation_id.as_hash(),
+                        &resigned_hash(operation_id.as_hash())
+                    );
+                }
+            }
+        }
+        assert!(updates > 0);
+
+        // Events relate to the re-signed first version of venues
+        let SchemaId::Application(_, venues_v1_view) = &venues_v1 else {
+            panic!("Application schema");
+        };
+        let operation_ids: Vec<OperationId> = venues_v1_view
+            .iter()
+            .map(|operation_id| OperationId::new(&resigned_hash(operation_id.as_hash())))
+            .collect();
+        let resigned_venues_v1 =
+            SchemaId::new_application(&venues_v1.name(), &DocumentViewId::new(&operation_ids));
+
+        let resigned_schemas = get_previous_schemas(&resign.lock_file).await.unwrap();
+        let events = resigned_schemas
+            .iter()
+            .find(|(name, _)| name.to_string() == "events")
+            .map(|(_, previous_schema)| &previous_schema.schema)
+            .unwrap();
+        assert_eq!(
+            events.fields().get("venue"),
+            Some(&FieldType::Relation(resigned_venues_v1))
+        );
+
+        for (previous_id, resigned_id) in &resign.schema_ids {
+            assert_ne!(previous_id, resigned_id);
+        }
+        assert_eq!(resign.schema_ids.len(), 2);
+    }
+}
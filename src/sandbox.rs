@@ -0,0 +1,278 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Build untrusted schema and lock files with strict resource limits.
+//!
+//! CI runners which automatically build schema files of third parties (templates, contributions)
+//! can bound the size of the input and the time spent on building it. Schema files including other
+//! files are rejected, the sandbox never accesses the network.
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use p2panda_rs::test_utils::memory_store::MemoryStore;
+
+use crate::build::{
+    assemble_previous_schemas, execute_bootstrap, execute_plan_with_abort, get_current_schemas,
+    get_diff, publish_bootstrap_commit, publish_commit, BuildResult,
+};
+use crate::error::{Error, Result};
+use crate::lock_file::LockFile;
+use crate::schema_file::SchemaFile;
+use crate::signer::{Signer, Signers};
+use crate::utils::files;
+
+/// Resource limits for building untrusted input.
+#[derive(Clone, Debug)]
+pub struct SandboxOptions {
+    /// Maximum size of the schema file in bytes.
+    pub max_schema_file_size: usize,
+
+    /// Maximum size of the lock file in bytes.
+    pub max_lock_file_size: usize,
+
+    /// Maximum number of schemas, including expanded inline schemas.
+    pub max_schemas: usize,
+
+    /// Maximum number of fields per schema.
+    pub max_fields: usize,
+
+    /// Maximum number of commits in the lock file.
+    pub max_commits: usize,
+
+    /// Maximum time the build may take after the files have been parsed.
+    pub timeout: Duration,
+}
+
+impl Default for SandboxOptions {
+    fn default() -> Self {
+        Self {
+            max_schema_file_size: 256 * 1024,
+            max_lock_file_size: 16 * 1024 * 1024,
+            max_schemas: 100,
+            max_fields: 100,
+            max_commits: 10_000,
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl SandboxOptions {
+    /// Parses a schema file after checking its size, then checks the number of schemas and
+    /// fields.
+    pub fn parse_schema_file(&self, data: &str) -> Result<SchemaFile> {
+        check_size("Schema file", data.len(), self.max_schema_file_size)?;
+
+        let schema_file: SchemaFile = data.parse()?;
+        self.check_schema_file(&schema_file)?;
+
+        Ok(schema_file)
+    }
+
+    /// Parses a lock file after checking its size, then checks the number of commits.
+    pub fn parse_lock_file(&self, data: &str) -> Result<LockFile> {
+        check_size("Lock file", data.len(), self.max_lock_file_size)?;
+
+        let lock_file: LockFile = data.parse()?;
+        self.check_lock_file(&lock_file)?;
+
+        Ok(lock_file)
+    }
+
+    /// Reads and parses a schema file, the size is checked before reading it.
+    pub fn read_schema_file(&self, path: impl AsRef<Path>) -> Result<SchemaFile> {
+        check_size("Schema file", file_size(&path)?, self.max_schema_file_size)?;
+        self.parse_schema_file(&files::read_file(path)?)
+    }
+
+    /// Reads and parses a lock file, the size is checked before reading it.
+    pub fn read_lock_file(&self, path: impl AsRef<Path>) -> Result<LockFile> {
+        check_size("Lock file", file_size(&path)?, self.max_lock_file_size)?;
+        self.parse_lock_file(&files::read_file(path)?)
+    }
+
+    /// Checks the number of schemas and fields of a parsed schema file.
+    ///
+    /// Includes and external schemas are not allowed, since they would require fetching files from
+    /// remote sources. Schemas with their own key are not allowed either, untrusted input must not
+    /// point at key pair files.
+    pub fn check_schema_file(&self, schema_file: &SchemaFile) -> Result<()> {
+        if !schema_file.includes().is_empty() {
+            return Err(Error::SandboxLimit(
+                "Schema file includes other files, they are not fetched in the sandbox".into(),
+            ));
+        }
+
+        if schema_file.has_externals() {
+            return Err(Error::SandboxLimit(
+                "Schema file relates to external schemas, they are not fetched in the sandbox"
+                    .into(),
+            ));
+        }
+
+        let schemas = schema_file.iter().len();
+        if schemas > self.max_schemas {
+            return Err(Error::SandboxLimit(format!(
+                "Schema file defines {schemas} schemas, allowed are {}",
+                self.max_schemas
+            )));
+        }
+
+        for (schema_name, schema_definition) in schema_file.iter() {
+            if schema_definition.key.is_some() {
+                return Err(Error::SandboxLimit(format!(
+                    "Schema {schema_name} sets its own key, key pair files are not read in the sandbox"
+                )));
+            }
+
+            let fields = schema_definition.fields.len();
+            if fields > self.max_fields {
+                return Err(Error::SandboxLimit(format!(
+                    "Schema {schema_name} defines {fields} fields, allowed are {}",
+                    self.max_fields
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the number of commits of a parsed lock file.
+    ///
+    /// Lock files split into segments are not allowed, since they would require reading further
+    /// files.
+    pub fn check_lock_file(&self, lock_file: &LockFile) -> Result<()> {
+        if !lock_file.segments.is_empty() {
+            return Err(Error::SandboxLimit(
+                "Lock file is split into segments, they are not read in the sandbox".into(),
+            ));
+        }
+
+        let commits = lock_file
+            .commits
+            .as_ref()
+            .map_or(0, |commits| commits.len());
+        if commits > self.max_commits {
+            return Err(Error::SandboxLimit(format!(
+                "Lock file contains {commits} commits, allowed are {}",
+                self.max_commits
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Like `build`, but checks the limits of the given files and aborts when the build takes
+    /// longer than the timeout.
+    ///
+    /// The timeout is checked after every validated commit, before signing every new commit and
+    /// between all build steps.
+    pub async fn build(
+        &self,
+        schema_file: &SchemaFile,
+        lock_file: &LockFile,
+        bootstrap_lock: &LockFile,
+        signer: &dyn Signer,
+    ) -> Result<BuildResult> {
+        let deadline = Deadline::new(self.timeout);
+
+        self.check_schema_file(schema_file)?;
+        self.check_lock_file(lock_file)?;
+        self.check_lock_file(bootstrap_lock)?;
+
+        let store = MemoryStore::default();
+
+        for commit in lock_file.commits.iter().flatten() {
+            publish_commit(&store, commit).await?;
+            deadline.check()?;
+        }
+
+        let previous_schemas = assemble_previous_schemas(&store).await?;
+        deadline.check()?;
+
+        for commit in bootstrap_lock.commits.iter().flatten() {
+            publish_bootstrap_commit(&store, commit).await?;
+            deadline.check()?;
+        }
+
+        let current_schemas = get_current_schemas(schema_file)?;
+        let diff = get_diff(previous_schemas.clone(), current_schemas.clone()).await?;
+        deadline.check()?;
+
+        let signers = Signers::new(signer);
+        let (commits, plans) =
+            execute_plan_with_abort(store.clone(), &signers, diff, &|| deadline.check()).await?;
+
+        let (bootstrap_commits, bootstraps) =
+            execute_bootstrap(&store, &signers, &current_schemas, &plans).await?;
+        deadline.check()?;
+
+        Ok(BuildResult {
+            commits,
+            plans,
+            previous_schemas,
+            bootstrap_commits,
+            bootstraps,
+            skipped: schema_file.disabled_schemas(),
+        })
+    }
+}
+
+/// Parses untrusted schema, lock and bootstrap lock file contents and builds them within the
+/// given limits.
+///
+/// Without a bootstrap lock file all bootstrap documents get created.
+pub async fn build_sandboxed(
+    schema_file: &str,
+    lock_file: &str,
+    bootstrap_lock: Option<&str>,
+    signer: &dyn Signer,
+    options: &SandboxOptions,
+) -> Result<BuildResult> {
+    let schema_file = options.parse_schema_file(schema_file)?;
+    let lock_file = options.parse_lock_file(lock_file)?;
+    let bootstrap_lock = match bootstrap_lock {
+        Some(bootstrap_lock) => options.parse_lock_file(bootstrap_lock)?,
+        None => LockFile::new(&[]),
+    };
+    options
+        .build(&schema_file, &lock_file, &bootstrap_lock, signer)
+        .await
+}
+
+/// Point in time after which the build gets aborted.
+struct Deadline {
+    started: Instant,
+    timeout: Duration,
+}
+
+impl Deadline {
+    fn new(timeout: Duration) -> Self {
+        Self {
+            started: Instant::now(),
+            timeout,
+        }
+    }
+
+    fn check(&self) -> Result<()> {
+        if self.started.elapsed() > self.timeout {
+            return Err(Error::SandboxTimeout(self.timeout));
+        }
+
+        Ok(())
+    }
+}
+
+fn check_size(name: &str, size: usize, max: usize) -> Result<()> {
+    if size > max {
+        return Err(Error::SandboxLimit(format!(
+            "{name} is {size} bytes large, allowed are {max} bytes"
+        )));
+    }
+
+    Ok(())
+}
+
+fn file_size(path: impl AsRef<Path>) -> Result<usize> {
+    let metadata = std::fs::metadata(&path)
+        .map_err(|err| Error::OpenFile(path.as_ref().to_path_buf(), err))?;
+    Ok(usize::try_from(metadata.len()).unwrap_or(usize::MAX))
+}
@@ -0,0 +1,637 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Edit schema files while keeping their comments and formatting.
+//!
+//! `SchemaFile` is a parsed representation which loses everything not needed for building.
+//! Commands changing `schema.toml` for the user edit the TOML document itself instead, every
+//! change only touches the affected lines and the result gets validated before it is written.
+//! `fishy fmt` uses the same editor to rewrite whole files in one canonical formatting.
+//!
+//! ```
+//! use fishy::p2panda::SchemaName;
+//! use fishy::schema_editor::SchemaEditor;
+//! use fishy::schema_file::{Constraints, FieldType, Metadata, SchemaField};
+//!
+//! let mut editor: SchemaEditor = r#"
+//! ## Cafes selling icecream
+//! [cafe]
+//! description = "A place serving icecream"
+//!
+//! [cafe.fields]
+//! name = { type = "str" }
+//! "#
+//! .parse()
+//! .unwrap();
+//!
+//! let field = SchemaField::Field {
+//!     field_type: FieldType::String,
+//!     rename_from: None,
+//!     bootstrap: None,
+//!     constraints: Constraints::default(),
+//!     metadata: Metadata::default(),
+//! };
+//! editor
+//!     .add_field(
+//!         &SchemaName::new("cafe").unwrap(),
+//!         &"address".to_string(),
+//!         &field,
+//!     )
+//!     .unwrap();
+//!
+//! assert!(editor.to_string().contains("# Cafes selling icecream"));
+//! assert!(editor.to_string().contains("address = { type = \"str\" }"));
+//! ```
+use std::fmt::{self, Display};
+use std::path::Path;
+use std::str::FromStr;
+
+use p2panda_rs::schema::{FieldName, SchemaDescription, SchemaName};
+use serde::Serialize;
+use toml_edit::ser::ValueSerializer;
+use toml_edit::{
+    Array, Decor, Document, InlineTable, Item, Key, RawString, Table, TableLike, Value,
+};
+
+use crate::error::{Error, Result};
+use crate::schema_file::{SchemaField, SchemaFile};
+use crate::utils::files;
+
+/// Keys allowed at the top of a schema file, in the order they are formatted in. Schemas follow
+/// them sorted by name.
+const ROOT_KEY_ORDER: [&str; 2] = ["include", "prefix"];
+
+/// Order of the keys of schemas, fields, relations and includes when formatting. Other keys, like
+/// metadata, follow sorted by name.
+const KEY_ORDER: [&str; 17] = [
+    "type",
+    "id",
+    "name",
+    "schema",
+    "git",
+    "path",
+    "rev",
+    "description",
+    "rename_from",
+    "enabled",
+    "license",
+    "authors",
+    "homepage",
+    "key",
+    "bootstrap",
+    "constraints",
+    "fields",
+];
+
+/// Indentation of values in arrays spanning multiple lines.
+const INDENT: &str = "    ";
+
+/// Schema file which can be edited without losing comments and formatting.
+#[derive(Clone, Debug, Default)]
+pub struct SchemaEditor {
+    document: Document,
+}
+
+impl SchemaEditor {
+    /// Returns an editor for a new, empty schema file.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the schema file at the given path.
+    ///
+    /// Environment variables and includes are kept as they are, the editor only changes schemas
+    /// defined in this file.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+
+        files::read_file(path)?.parse().map_err(|err| match err {
+            Error::InvalidSchemaFile(mut diagnostic) => {
+                diagnostic.path = Some(path.to_path_buf());
+                Error::InvalidSchemaFile(diagnostic)
+            }
+            err => err,
+        })
+    }
+
+    /// Adds a new schema without any fields at the end of the file.
+    pub fn add_schema(&mut self, name: &SchemaName, description: &SchemaDescription) -> Result<()> {
+        if self.document.contains_key(&name.to_string()) {
+            return Err(Error::SchemaExists(name.clone()));
+        }
+
+        let mut schema = Table::new();
+        schema.insert("description", toml_edit::value(description.to_string()));
+
+        let mut fields = Table::new();
+        fields.decor_mut().set_prefix("\n");
+        schema.insert("fields", Item::Table(fields));
+
+        // Separate the new schema from the previous content with an empty line
+        if !self.document.is_empty() {
+            schema.decor_mut().set_prefix("\n");
+        }
+
+        self.document.insert(&name.to_string(), Item::Table(schema));
+
+        Ok(())
+    }
+
+    /// Removes a schema with all its fields.
+    pub fn remove_schema(&mut self, name: &SchemaName) -> Result<()> {
+        self.document
+            .remove(&name.to_string())
+            .ok_or_else(|| Error::UnknownSchema(name.clone()))?;
+
+        Ok(())
+    }
+
+    /// Renames a schema in place, relations of other schemas pointing at it by name are updated
+    /// as well.
+    ///
+    /// This does not set `rename_from`, without it the renamed schema is committed as a new
+    /// schema.
+    pub fn rename_schema(&mut self, from: &SchemaName, to: &SchemaName) -> Result<()> {
+        if self.document.contains_key(&to.to_string()) {
+            return Err(Error::SchemaExists(to.clone()));
+        }
+
+        self.schema_mut(from)?;
+        rename_key(
+            self.document.as_table_mut(),
+            &from.to_string(),
+            &to.to_string(),
+        );
+
+        for (_, schema) in self.document.iter_mut() {
+            let Some(fields) = schema
+                .as_table_like_mut()
+                .and_then(|schema| schema.get_mut("fields"))
+                .and_then(|fields| fields.as_table_like_mut())
+            else {
+                continue;
+            };
+
+            for (_, field) in fields.iter_mut() {
+                let Some(name) = field
+                    .as_table_like_mut()
+                    .and_then(|field| field.get_mut("schema"))
+                    .and_then(|schema| schema.as_table_like_mut())
+                    .and_then(|schema| schema.get_mut("name"))
+                    .and_then(|name| name.as_value_mut())
+                else {
+                    continue;
+                };
+
+                if name.as_str() == Some(&from.to_string()) {
+                    replace_value(name, Value::from(to.to_string()));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Changes the description of a schema.
+    pub fn set_description(
+        &mut self,
+        name: &SchemaName,
+        description: &SchemaDescription,
+    ) -> Result<()> {
+        let schema = self.schema_mut(name)?;
+
+        match schema
+            .get_mut("description")
+            .and_then(|item| item.as_value_mut())
+        {
+            Some(value) => replace_value(value, Value::from(description.to_string())),
+            None => {
+                schema.insert("description", toml_edit::value(description.to_string()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds a field after the existing fields of a schema.
+    pub fn add_field(
+        &mut self,
+        schema_name: &SchemaName,
+        field_name: &FieldName,
+        field: &SchemaField,
+    ) -> Result<()> {
+        let value = field
+            .serialize(ValueSerializer::new())
+            .expect("Schema fields can always be serialized");
+
+        let fields = self.fields_mut(schema_name)?;
+        if fields.contains_key(field_name) {
+            return Err(Error::FieldExists(schema_name.clone(), field_name.clone()));
+        }
+
+        fields.insert(field_name, Item::Value(value));
+
+        Ok(())
+    }
+
+    /// Removes a field of a schema.
+    pub fn remove_field(&mut self, schema_name: &SchemaName, field_name: &FieldName) -> Result<()> {
+        self.fields_mut(schema_name)?
+            .remove(field_name)
+            .ok_or_else(|| Error::UnknownField(schema_name.clone(), field_name.clone()))?;
+
+        Ok(())
+    }
+
+    /// Renames a field of a schema in place.
+    ///
+    /// This does not set `rename_from`, without it the renamed field is committed as a new field.
+    pub fn rename_field(
+        &mut self,
+        schema_name: &SchemaName,
+        from: &FieldName,
+        to: &FieldName,
+    ) -> Result<()> {
+        let fields = self.fields_mut(schema_name)?;
+
+        if !fields.contains_key(from) {
+            return Err(Error::UnknownField(schema_name.clone(), from.clone()));
+        }
+
+        if fields.contains_key(to) {
+            return Err(Error::FieldExists(schema_name.clone(), to.clone()));
+        }
+
+        rename_key(fields, from, to);
+
+        Ok(())
+    }
+
+    /// Formats the whole document in one canonical way, schema files with the same content look
+    /// the same after formatting no matter how they were written.
+    ///
+    /// Schemas and fields are sorted by name and their keys follow a fixed order. Every schema
+    /// gets a `[<schema>.fields]` table with one inline table per field, values are written with
+    /// double quotes and single spaces. Comments are kept and move together with the schema, field
+    /// or value they are written above.
+    pub fn format(&mut self) {
+        let root = self.document.as_table_mut();
+        root.set_position(0);
+
+        let entries = take_sorted(root, &ROOT_KEY_ORDER);
+        let mut layout = Layout {
+            position: 1,
+            separate: entries.iter().any(|(_, item)| item.is_value()),
+        };
+
+        for (mut key, item) in entries {
+            let item = match item {
+                Item::Value(mut value) => {
+                    format_entry(&mut key, &mut value);
+                    Item::Value(value)
+                }
+                Item::ArrayOfTables(mut tables) => {
+                    let comments = take_comments(&mut key);
+                    for (index, table) in tables.iter_mut().enumerate() {
+                        let comments = if index == 0 { comments.as_str() } else { "" };
+                        layout.place(table, comments);
+                        format_values(table);
+                    }
+                    Item::ArrayOfTables(tables)
+                }
+                Item::Table(mut schema) => {
+                    let comments = take_comments(&mut key);
+                    layout.place(&mut schema, &comments);
+                    format_schema(&mut schema, &mut layout);
+                    Item::Table(schema)
+                }
+                Item::None => continue,
+            };
+
+            root.insert_formatted(&key, item);
+        }
+
+        let trailing = comments(Some(self.document.trailing()));
+        self.document.set_trailing(if trailing.is_empty() {
+            String::new()
+        } else {
+            format!("\n{trailing}")
+        });
+    }
+
+    /// Parses the edited document, to check if it is still a valid schema file.
+    pub fn schema_file(&self) -> Result<SchemaFile> {
+        self.to_string().parse()
+    }
+
+    /// Validates the edited document and writes it to the given path.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.schema_file()?;
+        files::write_file(path, &self.to_string())?;
+
+        Ok(())
+    }
+
+    fn schema_mut(&mut self, name: &SchemaName) -> Result<&mut dyn TableLike> {
+        self.document
+            .get_mut(&name.to_string())
+            .and_then(|schema| schema.as_table_like_mut())
+            .ok_or_else(|| Error::UnknownSchema(name.clone()))
+    }
+
+    fn fields_mut(&mut self, schema_name: &SchemaName) -> Result<&mut dyn TableLike> {
+        let schema = self.schema_mut(schema_name)?;
+
+        if !schema.contains_key("fields") {
+            schema.insert("fields", Item::Table(Table::new()));
+        }
+
+        Ok(schema
+            .get_mut("fields")
+            .and_then(|fields| fields.as_table_like_mut())
+            .expect("Valid schemas contain a fields table"))
+    }
+}
+
+impl FromStr for SchemaEditor {
+    type Err = Error;
+
+    /// Parses the content of a .toml schema file.
+    fn from_str(data: &str) -> Result<Self> {
+        // Report invalid schema files with the same diagnostics as when building
+        SchemaFile::from_str(data)?;
+
+        let document = data
+            .parse()
+            .expect("Valid schema files are valid TOML documents");
+
+        Ok(Self { document })
+    }
+}
+
+impl Display for SchemaEditor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.document)
+    }
+}
+
+/// Renames a key without changing its position or the comments around it.
+fn rename_key(table: &mut dyn TableLike, from: &str, to: &str) {
+    let keys: Vec<String> = table.iter().map(|(key, _)| key.to_owned()).collect();
+
+    let entries: Vec<(Key, Item)> = keys
+        .iter()
+        .map(|key| {
+            let (formatted_key, _) = table.get_key_value(key).expect("Key exists");
+            let formatted_key = formatted_key.clone();
+            let item = table.remove(key).expect("Key exists");
+            (formatted_key, item)
+        })
+        .collect();
+
+    for (key, item) in entries {
+        let key = if key.get() == from {
+            Key::new(to).with_decor(key.decor().clone())
+        } else {
+            key
+        };
+
+        table.entry_format(&key).or_insert(item);
+    }
+}
+
+/// Replaces a value, keeping the whitespace and comments around it.
+fn replace_value(value: &mut Value, replacement: Value) {
+    let decor = value.decor().clone();
+    *value = replacement;
+    *value.decor_mut() = decor;
+}
+
+/// Position and spacing of the next table header when formatting.
+struct Layout {
+    /// Position of the next table in the document.
+    position: usize,
+
+    /// If the next table header needs to be separated from the content before it.
+    separate: bool,
+}
+
+impl Layout {
+    /// Places a table after the previous one, separated by an empty line and keeping the comments
+    /// above its header.
+    fn place(&mut self, table: &mut Table, key_comments: &str) {
+        let comments = key_comments.to_owned() + &comments(table.decor().prefix());
+        let suffix = trailing_comment(table.decor().suffix());
+        let separator = if self.separate { "\n" } else { "" };
+
+        table.set_dotted(false);
+        table.set_implicit(false);
+        table.set_position(self.position);
+        *table.decor_mut() = Decor::new(format!("{separator}{comments}"), suffix);
+
+        self.position += 1;
+        self.separate = true;
+    }
+}
+
+/// Formats the values of a schema and turns its fields into a `[<schema>.fields]` table.
+fn format_schema(schema: &mut Table, layout: &mut Layout) {
+    for (mut key, item) in take_sorted(schema, &KEY_ORDER) {
+        let item = match (key.get() == "fields", item) {
+            (true, Item::Value(Value::InlineTable(fields))) => Item::Table(fields.into_table()),
+            (false, Item::Table(table)) => {
+                Item::Value(Value::InlineTable(table.into_inline_table()))
+            }
+            (_, item) => item,
+        };
+
+        let item = match item {
+            Item::Table(mut fields) => {
+                let comments = take_comments(&mut key);
+                layout.place(&mut fields, &comments);
+                format_fields(&mut fields);
+                Item::Table(fields)
+            }
+            Item::Value(mut value) => {
+                format_entry(&mut key, &mut value);
+                Item::Value(value)
+            }
+            item => item,
+        };
+
+        schema.insert_formatted(&key, item);
+    }
+}
+
+/// Sorts fields by name and writes every field as inline table.
+fn format_fields(fields: &mut Table) {
+    for (mut key, item) in take_sorted(fields, &[]) {
+        let mut value = match item {
+            Item::Table(table) => {
+                let comments = take_comments(&mut key) + &comments(table.decor().prefix());
+                key.decor_mut().set_prefix(comments);
+                Value::InlineTable(table.into_inline_table())
+            }
+            Item::Value(value) => value,
+            _ => continue,
+        };
+
+        format_entry(&mut key, &mut value);
+        fields.insert_formatted(&key, Item::Value(value));
+    }
+}
+
+/// Sorts the values of a table and formats them.
+fn format_values(table: &mut Table) {
+    for (mut key, item) in take_sorted(table, &KEY_ORDER) {
+        let item = match item {
+            Item::Value(mut value) => {
+                format_entry(&mut key, &mut value);
+                Item::Value(value)
+            }
+            item => item,
+        };
+
+        table.insert_formatted(&key, item);
+    }
+}
+
+/// Formats a `key = value` line, keeping the comments above it and at its end.
+fn format_entry(key: &mut Key, value: &mut Value) {
+    let comments = take_comments(key);
+    key.decor_mut().set_prefix(comments);
+
+    let suffix = trailing_comment(value.decor().suffix());
+    format_value(value, &KEY_ORDER);
+    *value.decor_mut() = Decor::new(" ", suffix);
+}
+
+/// Formats a value with the default representation, keys of inline tables are sorted in the
+/// given order.
+fn format_value(value: &mut Value, order: &[&str]) {
+    match value {
+        Value::String(formatted) => formatted.fmt(),
+        Value::Integer(formatted) => formatted.fmt(),
+        Value::Float(formatted) => formatted.fmt(),
+        Value::Boolean(formatted) => formatted.fmt(),
+        Value::Datetime(formatted) => formatted.fmt(),
+        Value::Array(array) => format_array(array),
+        Value::InlineTable(table) => format_inline_table(table, order),
+    }
+}
+
+/// Writes an array on one line, arrays spanning multiple lines keep one value per line to not
+/// lose comments between them.
+fn format_array(array: &mut Array) {
+    let is_multiline = array.trailing().as_str().unwrap_or_default().contains('\n')
+        || array.iter().any(|value| {
+            let decor = value.decor();
+            [decor.prefix(), decor.suffix()]
+                .into_iter()
+                .flatten()
+                .any(|raw| raw.as_str().unwrap_or_default().contains('\n'))
+        });
+
+    for value in array.iter_mut() {
+        format_value(value, &KEY_ORDER);
+    }
+
+    if !is_multiline || array.is_empty() {
+        array.fmt();
+        return;
+    }
+
+    for value in array.iter_mut() {
+        let decor = value.decor();
+        let comments = comments(decor.prefix()) + &comments(decor.suffix());
+        *value.decor_mut() = Decor::new(format!("\n{}{INDENT}", indent(&comments)), "");
+    }
+
+    let comments = comments(Some(array.trailing()));
+    array.set_trailing(format!("\n{}", indent(&comments)));
+    array.set_trailing_comma(true);
+}
+
+/// Sorts the keys of an inline table in the given order and writes it on one line.
+fn format_inline_table(table: &mut InlineTable, order: &[&str]) {
+    let keys: Vec<String> = table.iter().map(|(key, _)| key.to_owned()).collect();
+    let mut entries: Vec<(Key, Value)> = keys
+        .iter()
+        .map(|key| table.remove_entry(key).expect("Key exists"))
+        .collect();
+    entries.sort_by_cached_key(|(key, _)| sort_key(key.get(), order));
+
+    for (mut key, mut value) in entries {
+        Key::fmt(&mut key);
+
+        // Fields of inline schemas are sorted by name only
+        let order: &[&str] = if key.get() == "fields" {
+            &[]
+        } else {
+            &KEY_ORDER
+        };
+        format_value(&mut value, order);
+
+        table.insert_formatted(&key, value);
+    }
+
+    table.set_dotted(false);
+    table.set_preamble("");
+    table.fmt();
+}
+
+/// Removes all entries of a table and returns them sorted in the given order of keys, other keys
+/// follow sorted by name.
+fn take_sorted(table: &mut Table, order: &[&str]) -> Vec<(Key, Item)> {
+    let keys: Vec<String> = table.iter().map(|(key, _)| key.to_owned()).collect();
+    let mut entries: Vec<(Key, Item)> = keys
+        .iter()
+        .map(|key| table.remove_entry(key).expect("Key exists"))
+        .collect();
+    entries.sort_by_cached_key(|(key, _)| sort_key(key.get(), order));
+    entries
+}
+
+/// Sorts keys by their position in the given order first and by name second.
+fn sort_key(key: &str, order: &[&str]) -> (usize, String) {
+    let position = order
+        .iter()
+        .position(|ordered| *ordered == key)
+        .unwrap_or(order.len());
+
+    (position, key.to_owned())
+}
+
+/// Formats a key and returns the comments written above it.
+fn take_comments(key: &mut Key) -> String {
+    let comments = comments(key.decor().prefix());
+    key.fmt();
+    comments
+}
+
+/// Returns the comment lines of whitespace in front of a value, without indentation and empty
+/// lines.
+fn comments(raw: Option<&RawString>) -> String {
+    raw.and_then(RawString::as_str)
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with('#'))
+        .map(|line| format!("{line}\n"))
+        .collect()
+}
+
+/// Returns the comment at the end of a line, separated from the value by one space.
+fn trailing_comment(raw: Option<&RawString>) -> String {
+    match raw.and_then(RawString::as_str).map(str::trim) {
+        Some(comment) if comment.starts_with('#') => format!(" {comment}"),
+        _ => String::new(),
+    }
+}
+
+/// Indents every line of the given comments.
+fn indent(comments: &str) -> String {
+    comments
+        .lines()
+        .map(|line| format!("{INDENT}{line}\n"))
+        .collect()
+}
@@ -1,14 +1,22 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use std::collections::BTreeMap;
-use std::path::Path;
-use std::{collections::btree_map::Iter, fmt::Display};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::{
+    collections::btree_map::{Iter, IterMut},
+    fmt::Display,
+};
 
-use anyhow::{Context, Result};
-use p2panda_rs::schema::{FieldName, SchemaDescription, SchemaId, SchemaName};
+use p2panda_rs::schema::{
+    FieldName, FieldType as PandaFieldType, SchemaDescription, SchemaId, SchemaName,
+};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 
-use crate::utils::files;
+use crate::diagnostic::diagnose_schema_file;
+use crate::error::{Error, Result};
+use crate::utils::{env, files, git};
 
 /// Serializable format for definitions of one to many p2panda schemas.
 ///
@@ -27,36 +35,526 @@ use crate::utils::files;
 /// [venue.fields]
 /// name = { type = "str" }
 /// ```
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(deny_unknown_fields)]
-pub struct SchemaFile(BTreeMap<SchemaName, SchemaDefinition>);
+///
+/// Schema definitions of other files can be included, they get merged when resolving the
+/// includes before building. Local paths are relative to the including file and can contain glob
+/// patterns:
+///
+/// ```toml
+/// include = [
+///     "schemas/*.toml",
+///     { git = "https://...", path = "schemas/events.toml", rev = "v1.2" },
+/// ]
+/// ```
+///
+/// A prefix can be prepended to the names of all schemas, for example to deploy the same schemas
+/// for different environments on one node:
+///
+/// ```toml
+/// prefix = "staging_"
+/// ```
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SchemaFile {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    include: Vec<Include>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prefix: Option<String>,
+
+    #[serde(flatten)]
+    schemas: BTreeMap<SchemaName, SchemaDefinition>,
+
+    /// File or directory this schema file was loaded from.
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
 
 impl SchemaFile {
     /// Loads a .toml file from the given path and serialises its content into a new `SchemaFile`
     /// instance.
+    ///
+    /// A directory is loaded as a schema file including all .toml files inside of it. Environment
+    /// variables like `${VAR}` are replaced before parsing.
     pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
-        let data = files::read_file(&path)?;
-        let schema_file: Self =
-            toml::from_str(&data).with_context(|| "Invalid TOML syntax in schema file")?;
+        Self::read(path.as_ref(), true)
+    }
+
+    /// Loads a schema file, replacing environment variables only if `interpolate` is set.
+    ///
+    /// Files of other projects, for example fetched from git, are never interpolated. Otherwise
+    /// they could publish secrets of the environment in schema definitions.
+    fn read(path: &Path, interpolate: bool) -> Result<Self> {
+        if path.is_dir() {
+            return Ok(Self {
+                include: vec![Include::Path("*.toml".into())],
+                path: Some(path.to_path_buf()),
+                ..Self::default()
+            });
+        }
+
+        let data = files::read_file(path)?;
+        let data = if interpolate {
+            env::interpolate(&data, path)?
+        } else {
+            data
+        };
+        let mut schema_file: Self = data.parse().map_err(|err| match err {
+            Error::InvalidSchemaFile(mut diagnostic) => {
+                diagnostic.path = Some(path.to_path_buf());
+                Error::InvalidSchemaFile(diagnostic)
+            }
+            err => err,
+        })?;
+        // Schema files read from stdin are relative to the current directory
+        schema_file.path = (!files::is_stdio(path)).then(|| path.to_path_buf());
+
+        // Key pair files are relative to the schema file defining them, also when included
+        let base_dir = schema_file.base_dir();
+        for schema_definition in schema_file.schemas.values_mut() {
+            if let Some(SchemaKey::Path { path }) = &mut schema_definition.key {
+                *path = base_dir.join(&path);
+            }
+        }
+
         Ok(schema_file)
     }
 
+    /// Returns the directory local includes and external paths are relative to.
+    pub(crate) fn base_dir(&self) -> PathBuf {
+        match &self.path {
+            Some(path) if path.is_dir() => path.clone(),
+            Some(path) => path.parent().map(Path::to_path_buf).unwrap_or_default(),
+            None => PathBuf::new(),
+        }
+    }
+
     /// Returns an iterator over all defined schemas.
-    pub fn iter(&self) -> Iter<SchemaName, SchemaDefinition> {
-        self.0.iter()
+    pub fn iter(&self) -> Iter<'_, SchemaName, SchemaDefinition> {
+        self.schemas.iter()
+    }
+
+    /// Returns the names of all schemas which are disabled with `enabled = false`.
+    pub fn disabled_schemas(&self) -> Vec<SchemaName> {
+        self.schemas
+            .iter()
+            .filter(|(_, schema_definition)| !schema_definition.enabled)
+            .map(|(schema_name, _)| schema_name.clone())
+            .collect()
+    }
+
+    /// Returns a mutable iterator over all defined schemas.
+    pub(crate) fn iter_mut(&mut self) -> IterMut<'_, SchemaName, SchemaDefinition> {
+        self.schemas.iter_mut()
+    }
+
+    /// Returns true if any relation points at a schema of an external project which has not been
+    /// resolved yet.
+    pub fn has_externals(&self) -> bool {
+        self.schemas.values().any(|schema_definition| {
+            schema_definition.fields.iter().any(|(_, schema_field)| {
+                matches!(schema_field, SchemaField::Relation { schema, .. } if schema.external.is_some())
+            })
+        })
+    }
+
+    /// Returns all includes which have not been resolved yet.
+    pub fn includes(&self) -> &[Include] {
+        &self.include
+    }
+
+    /// Loads all included schema files and merges their schema definitions into this one.
+    ///
+    /// Remote files are cached in the given directory. Includes of included files are resolved as
+    /// well, schema names need to be unique across all files.
+    ///
+    /// Environment variables are only replaced in local includes of local files, remote files and
+    /// everything they include are taken as they are.
+    pub fn resolve_includes(&mut self, cache_dir: impl AsRef<Path>) -> Result<()> {
+        // Files are identified by their canonical path, this skips files which were included
+        // multiple times or which are matched by their own glob pattern
+        let mut resolved: HashSet<PathBuf> = HashSet::new();
+        if let Some(path) = &self.path {
+            resolved.insert(path.canonicalize()?);
+        }
+
+        let base_dir = self.base_dir();
+        let mut pending: Vec<(PathBuf, Include, bool)> = self
+            .include
+            .drain(..)
+            .map(|include| (base_dir.clone(), include, true))
+            .collect();
+
+        while let Some((base_dir, include, local)) = pending.pop() {
+            let (paths, local) = match &include {
+                Include::Path(pattern) => (glob_paths(&base_dir, pattern)?, local),
+                Include::Git(git_include) => (vec![git_include.fetch(cache_dir.as_ref())?], false),
+            };
+
+            for path in paths {
+                if !resolved.insert(path.canonicalize()?) {
+                    continue;
+                }
+
+                let included = SchemaFile::read(&path, local)?;
+                let base_dir = included.base_dir();
+
+                if included.prefix.is_some() {
+                    return Err(Error::InvalidInclude(
+                        path.display().to_string(),
+                        "only the including schema file can set a prefix".into(),
+                    ));
+                }
+
+                for (schema_name, schema_definition) in included.schemas {
+                    if self.schemas.contains_key(&schema_name) {
+                        return Err(Error::DuplicateSchema(schema_name, path));
+                    }
+
+                    self.schemas.insert(schema_name, schema_definition);
+                }
+
+                pending.extend(
+                    included
+                        .include
+                        .into_iter()
+                        .map(|include| (base_dir.clone(), include, local)),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the prefix prepended to all schema names, if set.
+    pub fn prefix(&self) -> Option<&str> {
+        self.prefix.as_deref()
+    }
+
+    /// Prepends a prefix to the names of all schemas, relations between them and previous names
+    /// of renamed schemas, using the prefix of the schema file if none is given.
+    ///
+    /// Relations to schemas of external projects and to system schemas keep their names or schema
+    /// ids. Apply the prefix after resolving includes and externals, so all schemas are renamed
+    /// consistently.
+    pub fn apply_prefix(&mut self, prefix: Option<&str>) -> Result<()> {
+        let Some(prefix) = prefix.map(str::to_owned).or_else(|| self.prefix.take()) else {
+            return Ok(());
+        };
+        self.prefix = None;
+
+        let prefixed = |schema_name: &SchemaName| {
+            let name = format!("{prefix}{schema_name}");
+            SchemaName::new(&name).map_err(|_| Error::InvalidSchemaPrefix(prefix.clone(), name))
+        };
+
+        let defined: HashSet<SchemaName> = self.schemas.keys().cloned().collect();
+
+        let mut schemas = BTreeMap::new();
+        for (schema_name, mut schema_definition) in std::mem::take(&mut self.schemas) {
+            for (_, schema_field) in schema_definition.fields.iter_mut() {
+                if let SchemaField::Relation { schema, .. } = schema_field {
+                    if let RelationId::Name(linked_schema) = &schema.id {
+                        if defined.contains(linked_schema)
+                            || system_schema_id(linked_schema).is_none()
+                        {
+                            schema.id = RelationId::Name(prefixed(linked_schema)?);
+                        }
+                    }
+                }
+            }
+
+            if let Some(rename_from) = &schema_definition.rename_from {
+                schema_definition.rename_from = Some(prefixed(rename_from)?);
+            }
+
+            schemas.insert(prefixed(&schema_name)?, schema_definition);
+        }
+        self.schemas = schemas;
+
+        Ok(())
+    }
+
+    /// Adds a schema definition, replacing any previous definition with the same name.
+    ///
+    /// Inline relation schemas of this definition get expanded into separate schemas.
+    pub fn insert(
+        &mut self,
+        schema_name: &SchemaName,
+        schema_definition: SchemaDefinition,
+    ) -> Result<()> {
+        self.schemas.insert(schema_name.clone(), schema_definition);
+        self.expand_inline_schemas(vec![schema_name.clone()])
+    }
+
+    /// Replaces inline relation schemas with regular schemas, named after the schema and field
+    /// they were defined in.
+    fn expand_inline_schemas(&mut self, mut schema_names: Vec<SchemaName>) -> Result<()> {
+        // Expanded schemas can contain inline schemas themselves, so we keep going until no new
+        // schemas were added
+        while let Some(schema_name) = schema_names.pop() {
+            let mut expanded: Vec<(SchemaName, SchemaDefinition)> = Vec::new();
+
+            let schema_definition = self
+                .schemas
+                .get_mut(&schema_name)
+                .expect("Schema needs to exist at this point");
+            let key = schema_definition.key.clone();
+
+            for (field_name, schema_field) in schema_definition.fields.iter_mut() {
+                let SchemaField::Relation { schema, .. } = schema_field else {
+                    continue;
+                };
+
+                let RelationId::Inline(inline_definition) = &schema.id else {
+                    continue;
+                };
+
+                let inline_name = format!("{schema_name}_{field_name}");
+                let inline_name = SchemaName::new(&inline_name)
+                    .map_err(|_| Error::InvalidInlineSchemaName(inline_name))?;
+
+                // Inline schemas are signed like the schema defining them, unless they set a key
+                let mut inline_definition = inline_definition.as_ref().clone();
+                if inline_definition.key.is_none() {
+                    inline_definition.key = key.clone();
+                }
+
+                expanded.push((inline_name.clone(), inline_definition));
+                schema.id = RelationId::Name(inline_name);
+            }
+
+            for (inline_name, inline_definition) in expanded {
+                if self.schemas.contains_key(&inline_name) {
+                    return Err(Error::InlineSchemaConflict(inline_name));
+                }
+
+                self.schemas.insert(inline_name.clone(), inline_definition);
+                schema_names.push(inline_name);
+            }
+        }
+
+        Ok(())
     }
 }
 
-/// Single schema definition with description and its fields.
-#[derive(Debug, Serialize, Deserialize)]
+impl FromStr for SchemaFile {
+    type Err = Error;
+
+    /// Parses the content of a .toml schema file.
+    fn from_str(data: &str) -> Result<Self> {
+        let mut schema_file: Self = toml::from_str(data)
+            .map_err(|err| Error::InvalidSchemaFile(Box::new(diagnose_schema_file(data, err))))?;
+        let schema_names = schema_file.schemas.keys().cloned().collect();
+        schema_file.expand_inline_schemas(schema_names)?;
+        Ok(schema_file)
+    }
+}
+
+/// Schema file whose definitions get merged into the including one.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Include {
+    /// Schema files on the local file system, relative to the including file. Glob patterns like
+    /// `schemas/*.toml` include all matching files.
+    Path(String),
+
+    /// Schema file in a remote git repository.
+    Git(GitInclude),
+}
+
+/// Returns all files matching a glob pattern relative to the given directory, sorted by path.
+fn glob_paths(base_dir: &Path, pattern: &str) -> Result<Vec<PathBuf>> {
+    let full_pattern = base_dir.join(pattern);
+    let mut paths = glob::glob(&full_pattern.to_string_lossy())
+        .map_err(|err| Error::InvalidInclude(pattern.to_owned(), err.to_string()))?
+        .collect::<std::result::Result<Vec<PathBuf>, _>>()
+        .map_err(|err| Error::InvalidInclude(pattern.to_owned(), err.to_string()))?;
+
+    if paths.is_empty() {
+        return Err(Error::IncludeNotFound(pattern.to_owned()));
+    }
+
+    paths.sort();
+    Ok(paths)
+}
+
+/// Schema file in a remote git repository, pinned to a revision.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
+pub struct GitInclude {
+    /// URL of the git repository.
+    pub git: String,
+
+    /// Path to the schema file inside of the repository.
+    pub path: PathBuf,
+
+    /// Tag, branch or commit hash to fetch the file from.
+    pub rev: String,
+}
+
+impl GitInclude {
+    /// Fetches the schema file, or loads it from the cache, and returns its path.
+    fn fetch(&self, cache_dir: &Path) -> Result<PathBuf> {
+        git::fetch_file(&self.git, &self.rev, &self.path, cache_dir)
+    }
+}
+
+/// Single schema definition with description and its fields.
+///
+/// License, authors and homepage attribute shared schema designs, like metadata they do not
+/// change the created schema.
+///
+/// ```toml
+/// [event]
+/// description = "An example schema"
+/// license = "CC0-1.0"
+/// authors = ["Jane <jane@example.org>"]
+/// homepage = "https://example.org/schemas"
+/// ```
+///
+/// A renamed schema keeps updating the document of its previous name when `rename_from` is set,
+/// instead of creating a new schema:
+///
+/// ```toml
+/// [calendar_event]
+/// description = "An example schema"
+/// rename_from = "event"
+/// ```
+///
+/// Work-in-progress schemas can be kept in the file while they are excluded from building and
+/// deploying with `enabled = false`:
+///
+/// ```toml
+/// [draft]
+/// description = "Not ready yet"
+/// enabled = false
+///
+/// [draft.fields]
+/// title = { type = "str" }
+/// ```
+///
+/// Schemas owned by another team can be signed with their own key pair instead of the key pair of
+/// the project, see `SchemaKey`:
+///
+/// ```toml
+/// [venue]
+/// description = "Maintained by the venues team"
+/// key = "venues"
+///
+/// [venue.fields]
+/// name = { type = "str" }
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct SchemaDefinition {
     pub description: SchemaDescription,
     pub fields: SchemaFields,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rename_from: Option<SchemaName>,
+    #[serde(default = "enabled_by_default", skip_serializing_if = "is_enabled")]
+    pub enabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authors: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<SchemaKey>,
+    #[serde(flatten, default)]
+    pub metadata: Metadata,
+}
+
+/// Key pair signing the commits of a schema instead of the key pair of the project.
+///
+/// Named keys are configured in the `[keys]` section of `fishy.toml`, key pair files are relative
+/// to the schema file:
+///
+/// ```toml
+/// [event]
+/// description = "An example schema"
+/// key = "events"
+///
+/// [venue]
+/// description = "Another schema"
+/// key = { path = "keys/venues.txt" }
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum SchemaKey {
+    /// Key pair configured under this name in the project settings.
+    Name(String),
+
+    /// Path to a key pair file.
+    Path { path: PathBuf },
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+fn is_enabled(enabled: &bool) -> bool {
+    *enabled
+}
+
+/// Opaque `x-` prefixed keys of schemas and fields, to annotate them for other tools.
+///
+/// Metadata is preserved when parsing and serializing schema files but does not influence the
+/// created schemas. Any other unknown key is rejected.
+///
+/// ```toml
+/// [event]
+/// description = "An example schema"
+/// x-docs-category = "calendar"
+///
+/// [event.fields]
+/// title = { type = "str", x-max-length = 120 }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Metadata(BTreeMap<String, toml::Value>);
+
+// Metadata values are only compared to each other, never hashed or ordered
+impl Eq for Metadata {}
+
+impl Metadata {
+    /// Prefix all metadata keys need to start with.
+    pub const PREFIX: &'static str = "x-";
+
+    /// Returns true if no metadata is given.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the value of a metadata key, including its `x-` prefix.
+    pub fn get(&self, key: &str) -> Option<&toml::Value> {
+        self.0.get(key)
+    }
+
+    /// Returns an iterator over all metadata keys and values.
+    pub fn iter(&self) -> Iter<'_, String, toml::Value> {
+        self.0.iter()
+    }
+}
+
+impl<'de> Deserialize<'de> for Metadata {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let values = BTreeMap::<String, toml::Value>::deserialize(deserializer)?;
+
+        if let Some(key) = values.keys().find(|key| !key.starts_with(Self::PREFIX)) {
+            return Err(serde::de::Error::custom(format!(
+                "unknown field `{key}`, metadata keys need to start with `{}`",
+                Self::PREFIX
+            )));
+        }
+
+        Ok(Self(values))
+    }
 }
 
 /// Holds one to many schema field definitions.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
 pub struct SchemaFields(BTreeMap<FieldName, SchemaField>);
 
 impl SchemaFields {
@@ -70,38 +568,225 @@ impl SchemaFields {
         self.0.len()
     }
 
+    /// Returns true if no fields are given.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     /// Inserts a new field.
     pub fn insert(&mut self, field_name: &FieldName, field: &SchemaField) {
         self.0.insert(field_name.clone(), field.clone());
     }
 
     /// Returns an iterator over all fields.
-    pub fn iter(&self) -> Iter<FieldName, SchemaField> {
+    pub fn iter(&self) -> Iter<'_, FieldName, SchemaField> {
         self.0.iter()
     }
+
+    /// Returns a mutable iterator over all fields.
+    pub(crate) fn iter_mut(&mut self) -> IterMut<'_, FieldName, SchemaField> {
+        self.0.iter_mut()
+    }
 }
 
 /// Definition of a single schema field.
-#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
-#[serde(untagged, deny_unknown_fields)]
+///
+/// A renamed field keeps updating the document of its previous name when `rename_from` is set:
+///
+/// ```toml
+/// [event.fields]
+/// headline = { type = "str", rename_from = "title" }
+/// ```
+///
+/// Applications which need exactly one document of a schema, for example for their settings, can
+/// give every field a `bootstrap` value. The build creates this document together with the
+/// schema, bytes are given as hex-encoded strings:
+///
+/// ```toml
+/// [settings.fields]
+/// theme = { type = "str", bootstrap = "dark" }
+/// max_upload_size = { type = "int", bootstrap = 1048576 }
+/// ```
+///
+/// Fields can declare `constraints` for their values, see `Constraints`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
 pub enum SchemaField {
     /// This field is either a string, integer, float or boolean.
     Field {
         #[serde(rename = "type")]
         field_type: FieldType,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rename_from: Option<FieldName>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        bootstrap: Option<toml::Value>,
+        #[serde(default, skip_serializing_if = "Constraints::is_empty")]
+        constraints: Constraints,
+        #[serde(flatten, default)]
+        metadata: Metadata,
     },
     /// This field is either a (pinned) relation or relation list.
     Relation {
         #[serde(rename = "type")]
         field_type: RelationType,
         schema: RelationSchema,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rename_from: Option<FieldName>,
+        #[serde(flatten, default)]
+        metadata: Metadata,
     },
 }
 
+// Bootstrap values are only compared to each other, never hashed or ordered
+impl Eq for SchemaField {}
+
+impl SchemaField {
+    /// Returns the `x-` prefixed metadata of this field.
+    pub fn metadata(&self) -> &Metadata {
+        match self {
+            SchemaField::Field { metadata, .. } | SchemaField::Relation { metadata, .. } => {
+                metadata
+            }
+        }
+    }
+
+    /// Returns the previous name of this field, if it got renamed.
+    pub fn rename_from(&self) -> Option<&FieldName> {
+        match self {
+            SchemaField::Field { rename_from, .. } | SchemaField::Relation { rename_from, .. } => {
+                rename_from.as_ref()
+            }
+        }
+    }
+
+    /// Returns the constraints of the field values, relations do not have any.
+    pub fn constraints(&self) -> Option<&Constraints> {
+        match self {
+            SchemaField::Field { constraints, .. } if !constraints.is_empty() => Some(constraints),
+            _ => None,
+        }
+    }
+}
+
+/// Application-level constraints of field values.
+///
+/// p2panda does not enforce them, but fishy checks bootstrap values against them and keeps them
+/// in the lock file for other tools, for example to validate user input in applications:
+///
+/// ```toml
+/// [event.fields]
+/// title = { type = "str", constraints = { max_length = 120, regex = "^[A-Z]" } }
+/// attendees = { type = "int", constraints = { min = 0, max = 500 } }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Constraints {
+    /// Maximum number of characters of a string or bytes of a bytes value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<usize>,
+
+    /// Smallest allowed number.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+
+    /// Largest allowed number.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+
+    /// Regular expression strings need to match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+}
+
+impl Constraints {
+    /// Returns true if no constraint is set.
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Checks that the constraints apply to fields of the given type, returns the reason if not.
+    pub fn validate(&self, field_type: &FieldType) -> std::result::Result<(), String> {
+        if self.max_length.is_some() && !matches!(field_type, FieldType::String | FieldType::Bytes)
+        {
+            return Err("`max_length` only applies to str and bytes fields".into());
+        }
+
+        if (self.min.is_some() || self.max.is_some())
+            && !matches!(field_type, FieldType::Integer | FieldType::Float)
+        {
+            return Err("`min` and `max` only apply to int and float fields".into());
+        }
+
+        if let (Some(min), Some(max)) = (self.min, self.max) {
+            if min > max {
+                return Err(format!("`min` {min} is greater than `max` {max}"));
+            }
+        }
+
+        if let Some(regex) = &self.regex {
+            if field_type != &FieldType::String {
+                return Err("`regex` only applies to str fields".into());
+            }
+
+            Regex::new(regex).map_err(|err| format!("invalid `regex`: {err}"))?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks a value of a field with the given type, like a bootstrap value, against the
+    /// constraints and returns the violated constraint.
+    ///
+    /// Bytes are given as hex-encoded strings. Values of other types than the field type are not
+    /// checked.
+    pub fn check(
+        &self,
+        field_type: &FieldType,
+        value: &toml::Value,
+    ) -> std::result::Result<(), String> {
+        let length = match (field_type, value) {
+            (FieldType::String, toml::Value::String(value)) => Some(value.chars().count()),
+            (FieldType::Bytes, toml::Value::String(value)) => Some(value.len() / 2),
+            _ => None,
+        };
+        if let (Some(length), Some(max_length)) = (length, self.max_length) {
+            if length > max_length {
+                return Err(format!(
+                    "is {length} long, allowed are at most {max_length}"
+                ));
+            }
+        }
+
+        let number = match value {
+            toml::Value::Integer(value) => Some(*value as f64),
+            toml::Value::Float(value) => Some(*value),
+            _ => None,
+        };
+        if let Some(number) = number {
+            if let Some(min) = self.min.filter(|min| number < *min) {
+                return Err(format!("{number} is less than `min` {min}"));
+            }
+
+            if let Some(max) = self.max.filter(|max| number > *max) {
+                return Err(format!("{number} is greater than `max` {max}"));
+            }
+        }
+
+        if let (Some(regex), toml::Value::String(value)) = (&self.regex, value) {
+            let regex = Regex::new(regex).map_err(|err| format!("invalid `regex`: {err}"))?;
+            if !regex.is_match(value) {
+                return Err(format!("\"{value}\" does not match `regex` {regex}"));
+            }
+        }
+
+        Ok(())
+    }
+}
+
 impl Display for SchemaField {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let type_str = match self {
-            SchemaField::Field { field_type } => match field_type {
+            SchemaField::Field { field_type, .. } => match field_type {
                 FieldType::Boolean => "bool",
                 FieldType::Float => "float",
                 FieldType::Integer => "int",
@@ -109,10 +794,14 @@ impl Display for SchemaField {
                 FieldType::Bytes => "bytes",
             }
             .to_string(),
-            SchemaField::Relation { field_type, schema } => {
+            SchemaField::Relation {
+                field_type, schema, ..
+            } => {
                 let name = match &schema.id {
-                    RelationId::Name(name) => name.to_owned(),
-                    RelationId::Id(id) => id.name(),
+                    RelationId::Name(name) => name.to_string(),
+                    RelationId::Id(id @ SchemaId::Application(_, _)) => id.name().to_string(),
+                    RelationId::Id(id) => id.to_string(),
+                    RelationId::Inline(_) => "inline".to_string(),
                 };
 
                 match field_type {
@@ -128,6 +817,70 @@ impl Display for SchemaField {
     }
 }
 
+impl From<&PandaFieldType> for SchemaField {
+    /// Converts a field type of a materialized schema, relations always point at schema ids.
+    fn from(field_type: &PandaFieldType) -> Self {
+        let relation = |field_type, schema_id: &SchemaId| SchemaField::Relation {
+            field_type,
+            schema: RelationSchema {
+                id: RelationId::Id(schema_id.to_owned()),
+                external: None,
+                rev: None,
+            },
+            rename_from: None,
+            metadata: Metadata::default(),
+        };
+
+        match field_type {
+            PandaFieldType::Boolean => SchemaField::Field {
+                field_type: FieldType::Boolean,
+                rename_from: None,
+                bootstrap: None,
+                constraints: Constraints::default(),
+                metadata: Metadata::default(),
+            },
+            PandaFieldType::Integer => SchemaField::Field {
+                field_type: FieldType::Integer,
+                rename_from: None,
+                bootstrap: None,
+                constraints: Constraints::default(),
+                metadata: Metadata::default(),
+            },
+            PandaFieldType::Float => SchemaField::Field {
+                field_type: FieldType::Float,
+                rename_from: None,
+                bootstrap: None,
+                constraints: Constraints::default(),
+                metadata: Metadata::default(),
+            },
+            PandaFieldType::String => SchemaField::Field {
+                field_type: FieldType::String,
+                rename_from: None,
+                bootstrap: None,
+                constraints: Constraints::default(),
+                metadata: Metadata::default(),
+            },
+            PandaFieldType::Bytes => SchemaField::Field {
+                field_type: FieldType::Bytes,
+                rename_from: None,
+                bootstrap: None,
+                constraints: Constraints::default(),
+                metadata: Metadata::default(),
+            },
+            PandaFieldType::Relation(schema_id) => relation(RelationType::Relation, schema_id),
+            PandaFieldType::RelationList(schema_id) => {
+                relation(RelationType::RelationList, schema_id)
+            }
+            PandaFieldType::PinnedRelation(schema_id) => {
+                relation(RelationType::PinnedRelation, schema_id)
+            }
+            PandaFieldType::PinnedRelationList(schema_id) => {
+                relation(RelationType::PinnedRelationList, schema_id)
+            }
+        }
+    }
+}
+
 /// Definition of field type.
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -165,6 +918,9 @@ pub struct RelationSchema {
     pub id: RelationId,
     #[serde(flatten)]
     pub external: Option<RelationSource>,
+    /// Tag, branch or commit hash of an external git repository.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
 }
 
 /// Identifier of schema.
@@ -174,8 +930,24 @@ pub enum RelationId {
     /// Schema id from schema defined in the same document or externally.
     Id(SchemaId),
 
-    /// Name from schema defined in the same document.
+    /// Name from schema defined in the same document or of a p2panda system schema, for example
+    /// `blob_v1`.
     Name(SchemaName),
+
+    /// Schema defined inline within the relation.
+    ///
+    /// Inline schemas get expanded into regular schemas named `<schema>_<field>` when parsing
+    /// the schema file.
+    Inline(Box<SchemaDefinition>),
+}
+
+/// Returns the id of the p2panda system schema with the given name, for example `blob_v1` or
+/// `blob_piece_v1`.
+pub fn system_schema_id(name: &SchemaName) -> Option<SchemaId> {
+    match SchemaId::new(&name.to_string()) {
+        Ok(SchemaId::Application(_, _)) | Err(_) => None,
+        Ok(schema_id) => Some(schema_id),
+    }
 }
 
 /// Definition of schema source.
@@ -184,7 +956,7 @@ pub enum RelationId {
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase", deny_unknown_fields)]
 pub enum RelationSource {
-    /// Cloneable git repository URL from external machine.
+    /// Cloneable git repository URL from external machine, pinned to a `rev`.
     Git(String),
 
     /// File system path on local machine.
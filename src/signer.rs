@@ -0,0 +1,197 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Sign entries without handing private keys to fishy.
+//!
+//! Builds only need a `Signer` to sign the commits they create. `KeyPair` implements it for
+//! local keys, remote signing services, hardware security modules or threshold signers can
+//! implement it as well.
+//!
+//! Schemas can be signed by different authors within one build, `Signers` picks the signer of
+//! every schema.
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+use bamboo_rs_core_ed25519_yasmf::entry::{is_lipmaa_required, MAX_ENTRY_SIZE};
+use bamboo_rs_core_ed25519_yasmf::{Entry as BambooEntry, Signature as BambooSignature};
+use p2panda_rs::api::{next_args, publish};
+use p2panda_rs::entry::decode::decode_entry;
+use p2panda_rs::entry::{EncodedEntry, LogId, SeqNum};
+use p2panda_rs::hash::Hash;
+use p2panda_rs::identity::{KeyPair, PublicKey};
+use p2panda_rs::operation::encode::encode_operation;
+use p2panda_rs::operation::traits::{Actionable, Schematic};
+use p2panda_rs::operation::{EncodedOperation, Operation};
+use p2panda_rs::schema::{Schema, SchemaName};
+use p2panda_rs::test_utils::memory_store::MemoryStore;
+
+use crate::error::{Error, Result};
+
+/// Length of an ed25519 signature in bytes.
+const SIGNATURE_LENGTH: usize = 64;
+
+/// Signs entries on behalf of an author.
+#[async_trait]
+pub trait Signer: Debug + Send + Sync {
+    /// Returns the public key of the author.
+    fn public_key(&self) -> PublicKey;
+
+    /// Signs the bytes of an unsigned entry and returns the ed25519 signature.
+    async fn sign(&self, bytes: &[u8]) -> Result<Vec<u8>>;
+}
+
+#[async_trait]
+impl Signer for KeyPair {
+    fn public_key(&self) -> PublicKey {
+        KeyPair::public_key(self)
+    }
+
+    async fn sign(&self, bytes: &[u8]) -> Result<Vec<u8>> {
+        Ok(KeyPair::sign(self, bytes).to_bytes().to_vec())
+    }
+}
+
+/// Signers of all schemas in a build.
+///
+/// Schemas are signed by the default signer unless another signer is set for them, for example
+/// when different teams own different schemas of one project.
+#[derive(Clone, Debug)]
+pub struct Signers<'a> {
+    default: &'a dyn Signer,
+    schemas: BTreeMap<SchemaName, &'a dyn Signer>,
+}
+
+impl<'a> Signers<'a> {
+    /// Returns a new instance of `Signers`, signing all schemas with the given signer.
+    pub fn new(default: &'a dyn Signer) -> Self {
+        Self {
+            default,
+            schemas: BTreeMap::new(),
+        }
+    }
+
+    /// Signs the schema with the given name, its fields and bootstrap document with another
+    /// signer.
+    pub fn with_schema_signer(mut self, schema_name: &SchemaName, signer: &'a dyn Signer) -> Self {
+        self.schemas.insert(schema_name.clone(), signer);
+        self
+    }
+
+    /// Returns the signer of all schemas without their own signer.
+    pub fn default_signer(&self) -> &'a dyn Signer {
+        self.default
+    }
+
+    /// Returns the signer of the schema with the given name.
+    pub fn get(&self, schema_name: &SchemaName) -> &'a dyn Signer {
+        self.schemas
+            .get(schema_name)
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+/// Signs the entry for an operation with the given arguments and returns it encoded.
+///
+/// The signature gets verified after encoding, so faulty signers are detected before their
+/// entries are used.
+pub(crate) async fn sign_entry(
+    signer: &dyn Signer,
+    log_id: &LogId,
+    seq_num: &SeqNum,
+    skiplink_hash: Option<&Hash>,
+    backlink_hash: Option<&Hash>,
+    payload: &EncodedOperation,
+) -> Result<EncodedEntry> {
+    let payload_hash = payload.hash();
+
+    // Omit skiplink when it is the same as backlink
+    let lipmaa_link = if is_lipmaa_required(seq_num.as_u64()) {
+        skiplink_hash.map(|link| link.into())
+    } else {
+        None
+    };
+
+    let mut entry: BambooEntry<_, &[u8]> = BambooEntry {
+        is_end_of_feed: false,
+        author: signer.public_key().into(),
+        log_id: log_id.as_u64(),
+        seq_num: seq_num.as_u64(),
+        lipmaa_link,
+        backlink: backlink_hash.map(|link| link.into()),
+        payload_size: payload.size(),
+        payload_hash: (&payload_hash).into(),
+        sig: None,
+    };
+
+    let mut entry_bytes = [0u8; MAX_ENTRY_SIZE];
+    let unsigned_size = entry
+        .encode(&mut entry_bytes)
+        .map_err(|err| Error::Sign(err.to_string()))?;
+
+    let signature = signer.sign(&entry_bytes[..unsigned_size]).await?;
+    if signature.len() != SIGNATURE_LENGTH {
+        return Err(Error::Sign(format!(
+            "Expected signature with {SIGNATURE_LENGTH} bytes, got {}",
+            signature.len()
+        )));
+    }
+
+    entry.sig = Some(BambooSignature(&signature[..]));
+    let signed_size = entry
+        .encode(&mut entry_bytes)
+        .map_err(|err| Error::Sign(err.to_string()))?;
+
+    let encoded_entry = EncodedEntry::from_bytes(&entry_bytes[..signed_size]);
+    decode_entry(&encoded_entry)?;
+
+    Ok(encoded_entry)
+}
+
+/// Signs an operation of a system schema and publishes it on the in-memory store, which might
+/// already contain previously published commits.
+pub(crate) async fn sign_and_publish(
+    store: &MemoryStore,
+    signer: &dyn Signer,
+    operation: &Operation,
+) -> Result<(EncodedEntry, EncodedOperation)> {
+    let schema = Schema::get_system(operation.schema_id().to_owned())?;
+    sign_and_publish_with_schema(store, signer, schema, operation).await
+}
+
+/// Signs an operation following the given schema and publishes it on the in-memory store.
+pub(crate) async fn sign_and_publish_with_schema(
+    store: &MemoryStore,
+    signer: &dyn Signer,
+    schema: &Schema,
+    operation: &Operation,
+) -> Result<(EncodedEntry, EncodedOperation)> {
+    let encoded_operation = encode_operation(operation)?;
+
+    let (backlink, skiplink, seq_num, log_id) =
+        next_args(store, &signer.public_key(), operation.previous())
+            .await
+            .map_err(|err| Error::Storage(err.to_string()))?;
+
+    let encoded_entry = sign_entry(
+        signer,
+        &log_id,
+        &seq_num,
+        skiplink.as_ref(),
+        backlink.as_ref(),
+        &encoded_operation,
+    )
+    .await?;
+
+    publish(
+        store,
+        schema,
+        &encoded_entry,
+        &operation.into(),
+        &encoded_operation,
+    )
+    .await
+    .map_err(|err| Error::Storage(err.to_string()))?;
+
+    Ok((encoded_entry, encoded_operation))
+}
@@ -0,0 +1,205 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Compact the history of lock files.
+//!
+//! Every change of a schema adds update operations to the lock file which all consumers need to
+//! replay when deploying it. Squashing replaces the whole history with the create operations of
+//! the current schema versions.
+//!
+//! The squashed schemas get new view ids and with them new schema ids. Nodes which received the
+//! previous commits still know the old schemas, applications need to be deployed to fresh nodes
+//! or signed with a new key pair afterwards.
+use std::collections::HashSet;
+
+use p2panda_rs::hash::{Hash, HashId};
+use p2panda_rs::schema::SchemaId;
+use p2panda_rs::test_utils::memory_store::MemoryStore;
+use tracing::{debug, instrument};
+
+use crate::build::{execute_plan, get_diff, get_previous_schemas, CurrentSchema, PreviousSchemas};
+use crate::error::Result;
+use crate::lock_file::{BuildMetadata, LockFile};
+use crate::schema_file::{RelationId, SchemaField, SchemaFields};
+use crate::signer::Signers;
+
+/// Lock file with the squashed history and the resulting schema ids.
+#[derive(Clone, Debug)]
+pub struct Squash {
+    /// Lock file containing only create operations, with the tooling metadata of the original
+    /// lock file and a single build adding all of them.
+    pub lock_file: LockFile,
+
+    /// Previous and new id of every squashed schema, sorted by name.
+    pub schema_ids: Vec<(SchemaId, SchemaId)>,
+
+    /// Number of commits in the original lock file.
+    pub previous_commits: usize,
+}
+
+/// Rewrites the history of a lock file to the minimal set of create operations reproducing its
+/// current schemas, signed with the given signers.
+///
+/// Relations between schemas of the lock file point at the squashed versions of them, even if
+/// they pointed at older versions before. Relations to other schemas are kept as they are.
+#[instrument(skip_all)]
+pub async fn squash_lock_file(lock_file: &LockFile, signers: &Signers<'_>) -> Result<Squash> {
+    let previous_schemas = get_previous_schemas(lock_file).await?;
+
+    // Operations of this lock file, relations to any of their views point at local schemas
+    let entry_hashes: HashSet<Hash> = lock_file
+        .commits
+        .iter()
+        .flatten()
+        .map(|commit| commit.entry_hash.clone())
+        .collect();
+
+    let current_schemas: Vec<CurrentSchema> = previous_schemas
+        .iter()
+        .map(|(schema_name, previous_schema)| {
+            let mut fields = SchemaFields::new();
+            for (field_name, field_type) in previous_schema.schema.fields().iter() {
+                let schema_field = local_relation(
+                    &previous_schemas,
+                    &entry_hashes,
+                    SchemaField::from(field_type),
+                );
+                fields.insert(field_name, &schema_field);
+            }
+
+            CurrentSchema::new(schema_name, previous_schema.schema.description(), &fields)
+        })
+        .collect();
+
+    // Nothing was committed before, every schema gets created from scratch
+    let diff = get_diff(PreviousSchemas::new(), current_schemas).await?;
+    let (commits, plans) = execute_plan(MemoryStore::default(), signers, diff).await?;
+    debug!("Squashed history into {} commits", commits.len());
+
+    let mut schema_ids: Vec<(SchemaId, SchemaId)> = plans
+        .iter()
+        .map(|plan| {
+            let schema_id = plan.schema_id();
+            let previous_schema_id = previous_schemas
+                .get(&schema_id.name())
+                .expect("Squashed schema needs to be committed before")
+                .schema
+                .id()
+                .to_owned();
+            (previous_schema_id, schema_id)
+        })
+        .collect();
+    schema_ids.sort_by_key(|(_, schema_id)| schema_id.name());
+
+    Ok(Squash {
+        lock_file: LockFile::new(&commits)
+            .with_builds(&[BuildMetadata::new(&commits)])
+            .with_tooling(lock_file.tooling.clone()),
+        schema_ids,
+        previous_commits: lock_file
+            .commits
+            .as_ref()
+            .map_or(0, |commits| commits.len()),
+    })
+}
+
+/// Points a relation field at the schema name if it relates to any version of a schema committed
+/// in the same lock file.
+fn local_relation(
+    previous_schemas: &PreviousSchemas,
+    entry_hashes: &HashSet<Hash>,
+    mut schema_field: SchemaField,
+) -> SchemaField {
+    if let SchemaField::Relation { schema, .. } = &mut schema_field {
+        if let RelationId::Id(SchemaId::Application(name, view_id)) = &schema.id {
+            let is_local = previous_schemas.contains_key(name)
+                && view_id
+                    .iter()
+                    .all(|operation_id| entry_hashes.contains(operation_id.as_hash()));
+
+            if is_local {
+                schema.id = RelationId::Name(name.clone());
+            }
+        }
+    }
+
+    schema_field
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::schema::FieldType;
+
+    use crate::build::get_previous_schemas;
+    use crate::lock_file::{AnnotationAction, LockFile};
+    use crate::signer::Signers;
+    use crate::test_utils::{build_commits, key_pair};
+
+    use super::squash_lock_file;
+
+    #[tokio::test]
+    async fn squashes_updates_and_old_relations_into_creates() {
+        let key_pair = key_pair(1);
+        let mut commits = build_commits(
+            r#"
+            [venues]
+            description = "Places to meet"
+            fields = { name = { type = "str" } }
+            "#,
+            &[],
+            &key_pair,
+        )
+        .await;
+        let venues_v1 = LockFile::new(&commits).schema_ids().unwrap()[0].clone();
+
+        // Venues get updated while events still relate to their first version
+        let schema_file = format!(
+            r#"
+            [venues]
+            description = "Places to meet"
+            fields = {{ name = {{ type = "str" }}, city = {{ type = "str" }} }}
+
+            [events]
+            description = "Things happening"
+            fields = {{ venue = {{ type = "relation", schema = {{ id = "{venues_v1}" }} }} }}
+            "#
+        );
+        commits.extend(build_commits(&schema_file, &commits, &key_pair).await);
+
+        let squash = squash_lock_file(&LockFile::new(&commits), &Signers::new(&key_pair))
+            .await
+            .unwrap();
+        let squashed_commits = squash.lock_file.commits.clone().unwrap();
+
+        assert_eq!(squash.previous_commits, commits.len());
+        assert!(squashed_commits.len() < commits.len());
+        for commit in &squashed_commits {
+            assert_eq!(
+                commit.annotation.as_ref().unwrap().action,
+                AnnotationAction::Create
+            );
+        }
+
+        // Both schemas got new ids, events relate to the squashed venues
+        let schema_id = |name: &str| {
+            squash
+                .schema_ids
+                .iter()
+                .find(|(_, schema_id)| schema_id.name().to_string() == name)
+                .cloned()
+                .unwrap()
+        };
+        let (previous_venues, venues) = schema_id("venues");
+        let (previous_events, events) = schema_id("events");
+        assert_ne!(previous_venues, venues);
+        assert_ne!(previous_events, events);
+        assert_ne!(venues, venues_v1);
+
+        let previous_schemas = get_previous_schemas(&squash.lock_file).await.unwrap();
+        let events_schema = &previous_schemas.get(&events.name()).unwrap().schema;
+        assert_eq!(events_schema.id(), &events);
+        assert_eq!(
+            events_schema.fields().get("venue"),
+            Some(&FieldType::Relation(venues))
+        );
+    }
+}
@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Detect schema documents which have newer updates on a node than in the lock file.
+//!
+//! When the same key pair is used on multiple machines, another machine might have published
+//! updates to the schema documents which are not part of the local lock file yet. Building on top
+//! of the outdated lock file would fork the history of these documents.
+use std::collections::BTreeMap;
+
+use p2panda_rs::document::DocumentId;
+use p2panda_rs::entry::decode::decode_entry;
+use p2panda_rs::entry::traits::AsEntry;
+use p2panda_rs::entry::{LogId, SeqNum};
+use p2panda_rs::identity::PublicKey;
+use tracing::{debug, instrument};
+
+use crate::client::Client;
+use crate::error::Result;
+use crate::lock_file::{Commit, LockFile};
+
+/// Document with more updates on the node than in the lock file.
+#[derive(Clone, Debug)]
+pub struct StaleDocument {
+    /// Id of the schema or field definition document.
+    pub document_id: DocumentId,
+
+    /// Public key of the author of the document.
+    pub public_key: PublicKey,
+
+    /// Log of the document.
+    pub log_id: LogId,
+
+    /// Sequence number of the latest entry in the lock file.
+    pub locked_seq_num: SeqNum,
+
+    /// Sequence number of the next entry expected by the node.
+    pub next_seq_num: SeqNum,
+}
+
+impl StaleDocument {
+    /// Returns the number of updates known to the node but missing in the lock file.
+    pub fn missing_updates(&self) -> u64 {
+        self.next_seq_num.as_u64() - self.locked_seq_num.as_u64() - 1
+    }
+}
+
+/// Returns all documents of the lock file which the node knows newer updates of.
+///
+/// Every document is stored in its own log, the node gets asked for the next sequence number
+/// after the latest entry of every log in the lock file.
+#[instrument(skip_all)]
+pub async fn find_stale_documents(
+    client: &Client,
+    lock_file: &LockFile,
+) -> Result<Vec<StaleDocument>> {
+    // First and latest commit of every log, the first entry hash is the document id
+    let mut logs: BTreeMap<(String, u64), (&Commit, &Commit, SeqNum)> = BTreeMap::new();
+
+    for commit in lock_file.commits.iter().flatten() {
+        let entry = decode_entry(&commit.entry)?;
+        let key = (entry.public_key().to_string(), entry.log_id().as_u64());
+
+        logs.entry(key)
+            .and_modify(|(_, latest, seq_num)| {
+                if entry.seq_num() > seq_num {
+                    *latest = commit;
+                    *seq_num = *entry.seq_num();
+                }
+            })
+            .or_insert((commit, commit, *entry.seq_num()));
+    }
+
+    let mut stale = Vec::new();
+
+    for (first, latest, locked_seq_num) in logs.into_values() {
+        let entry = decode_entry(&latest.entry)?;
+
+        let Some(args) = client
+            .next_args(entry.public_key(), &latest.entry_hash)
+            .await?
+        else {
+            // Node does not know about this document yet
+            continue;
+        };
+
+        if args.seq_num.as_u64() > locked_seq_num.as_u64() + 1 {
+            let document_id: DocumentId = first.entry_hash.clone().into();
+            debug!(%document_id, "Node knows newer updates of document");
+
+            stale.push(StaleDocument {
+                document_id,
+                public_key: *entry.public_key(),
+                log_id: *entry.log_id(),
+                locked_seq_num,
+                next_seq_num: args.seq_num,
+            });
+        }
+    }
+
+    Ok(stale)
+}
@@ -13,3 +13,8 @@ pub fn print_title(title: &str) {
 pub fn print_variable(name: &str, value: impl Display) {
     println!("- {name}: {}", style(value).dim());
 }
+
+/// Prints a nice looking variable value into stderr, for output next to data written to stdout.
+pub fn eprint_variable(name: &str, value: impl Display) {
+    eprintln!("- {name}: {}", style(value).dim());
+}
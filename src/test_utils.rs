@@ -0,0 +1,48 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Helpers to build commits and project files in tests.
+use std::fs;
+use std::path::PathBuf;
+
+use p2panda_rs::identity::KeyPair;
+
+use crate::build::build;
+use crate::lock_file::{Commit, LockFile};
+use crate::schema_file::SchemaFile;
+
+/// Returns a key pair derived from the given seed, commits built with it are always the same.
+pub(crate) fn key_pair(seed: u8) -> KeyPair {
+    KeyPair::from_private_key_str(&hex::encode([seed; 32])).expect("Valid private key")
+}
+
+/// Builds the given schema file on top of the commits of the lock file and returns the new
+/// commits.
+pub(crate) async fn build_commits(
+    schema_file: &str,
+    lock_file: &[Commit],
+    key_pair: &KeyPair,
+) -> Vec<Commit> {
+    let schema_file: SchemaFile = schema_file.parse().expect("Valid schema file");
+
+    build(
+        &schema_file,
+        &LockFile::new(lock_file),
+        &LockFile::new(&[]),
+        key_pair,
+    )
+    .await
+    .expect("Build commits")
+    .commits
+}
+
+/// Returns an empty directory for the files of a test, removing files of earlier runs.
+pub(crate) fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir()
+        .join("fishy-tests")
+        .join(format!("{name}-{}", std::process::id()));
+    if dir.exists() {
+        fs::remove_dir_all(&dir).expect("Remove test directory");
+    }
+    fs::create_dir_all(&dir).expect("Create test directory");
+    dir
+}
@@ -0,0 +1,124 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Send GraphQL queries to a node.
+//!
+//! `Client` does not talk HTTP itself but hands every query to a `GraphQlTransport`. By default
+//! this is an `HttpTransport`, applications which already maintain their own HTTP or GraphQL stack
+//! (with authentication, proxies or instrumentation) can implement the trait to reuse it.
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+/// Sends GraphQL queries to a node.
+#[async_trait]
+pub trait GraphQlTransport: Debug + Send + Sync {
+    /// Sends a query and returns the `data` object of the response.
+    ///
+    /// Errors returned by the node in the `errors` array of the response need to be reported as
+    /// `TransportError::GraphQl`, all other failures as `TransportError::Request`. Only the latter
+    /// get retried by the `Client`.
+    async fn query(&self, query: &str) -> Result<Value, TransportError>;
+}
+
+/// Errors which can occur when sending a GraphQL query.
+#[derive(Clone, Debug, Error)]
+pub enum TransportError {
+    /// Node responded with GraphQL errors, for example because it rejected an entry.
+    #[error("{}", .0.join(", "))]
+    GraphQl(Vec<String>),
+
+    /// Request failed, for example because the node could not be reached.
+    #[error("{0}")]
+    Request(String),
+}
+
+/// Default transport sending GraphQL queries over HTTP.
+#[derive(Clone, Debug)]
+pub struct HttpTransport {
+    client: reqwest::Client,
+    endpoint: String,
+    timeout: Duration,
+    headers: HashMap<String, String>,
+}
+
+impl HttpTransport {
+    /// Returns a new transport sending queries to the given endpoint.
+    ///
+    /// The given headers are sent with every request.
+    pub fn new(endpoint: &str, timeout: Duration, headers: HashMap<String, String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            endpoint: endpoint.to_owned(),
+            timeout,
+            headers,
+        }
+    }
+}
+
+#[async_trait]
+impl GraphQlTransport for HttpTransport {
+    async fn query(&self, query: &str) -> Result<Value, TransportError> {
+        let mut request = self
+            .client
+            .post(&self.endpoint)
+            .timeout(self.timeout)
+            .json(&RequestBody { query });
+
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|err| TransportError::Request(err.to_string()))?;
+
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|err| TransportError::Request(err.to_string()))?;
+
+        let body: ResponseBody = serde_json::from_str(&text).map_err(|_| {
+            TransportError::Request(format!("Invalid response from node ({status}): {text}"))
+        })?;
+
+        if let Some(errors) = body.errors {
+            if !errors.is_empty() {
+                return Err(TransportError::GraphQl(
+                    errors.into_iter().map(|error| error.message).collect(),
+                ));
+            }
+        }
+
+        match body.data {
+            Some(data) if !data.is_null() => Ok(data),
+            _ => Err(TransportError::Request(format!(
+                "No data from node ({status}) for this query"
+            ))),
+        }
+    }
+}
+
+/// GraphQL request sent to the node.
+#[derive(Serialize)]
+struct RequestBody<'a> {
+    query: &'a str,
+}
+
+/// GraphQL response of the node.
+#[derive(Deserialize)]
+struct ResponseBody {
+    data: Option<Value>,
+    errors: Option<Vec<ErrorMessage>>,
+}
+
+#[derive(Deserialize)]
+struct ErrorMessage {
+    message: String,
+}
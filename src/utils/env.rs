@@ -0,0 +1,163 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::path::Path;
+
+use crate::error::{Error, Result};
+
+/// Replaces `${VAR}` with the value of the environment variable `VAR`.
+///
+/// `${VAR:-default}` falls back to the default if the variable is not defined or empty, `$${VAR}`
+/// is kept as `${VAR}` without replacing it. Undefined variables without a default fail with an
+/// error naming the file at the given path.
+///
+/// Variables are replaced in the text before it gets parsed, values containing quotes or newlines
+/// need to be escaped for TOML.
+pub fn interpolate(data: &str, path: &Path) -> Result<String> {
+    interpolate_with(data, |name| std::env::var(name).ok())
+        .map_err(|name| Error::UndefinedVariable(name, path.to_path_buf()))
+}
+
+/// Replaces variables with the values returned by `lookup`, returns the name of the first
+/// undefined variable without a default as error.
+fn interpolate_with(
+    data: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> std::result::Result<String, String> {
+    let mut output = String::with_capacity(data.len());
+    let mut rest = data;
+
+    while let Some(start) = rest.find("${") {
+        // Escaped with another `$`, keep the variable as it is
+        if rest[..start].ends_with('$') {
+            output.push_str(&rest[..start - 1]);
+            output.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        output.push_str(&rest[..start]);
+
+        let Some(length) = rest[start + 2..].find('}') else {
+            // Not terminated, this is not a variable
+            output.push_str(&rest[start..]);
+            return Ok(output);
+        };
+
+        let expression = &rest[start + 2..start + 2 + length];
+        let (name, default) = match expression.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (expression, None),
+        };
+
+        if is_variable_name(name) {
+            let value = lookup(name).filter(|value| !value.is_empty() || default.is_none());
+            match (value, default) {
+                (Some(value), _) => output.push_str(&value),
+                (None, Some(default)) => output.push_str(default),
+                (None, None) => return Err(name.to_owned()),
+            }
+        } else {
+            output.push_str(&rest[start..start + 3 + length]);
+        }
+
+        rest = &rest[start + 3 + length..];
+    }
+
+    output.push_str(rest);
+
+    Ok(output)
+}
+
+/// Returns true if the name consists of letters, digits and underscores and does not start with
+/// a digit.
+fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|char| char.is_ascii_alphanumeric() || char == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::interpolate_with;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "NAMESPACE" => Some("myapp".into()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn replaces_variables() {
+        assert_eq!(
+            interpolate_with("namespace = \"${NAMESPACE}_v2\"", lookup),
+            Ok("namespace = \"myapp_v2\"".into())
+        );
+    }
+
+    #[test]
+    fn keeps_escaped_variables() {
+        assert_eq!(
+            interpolate_with("$${NAMESPACE} ${NAMESPACE}", lookup),
+            Ok("${NAMESPACE} myapp".into())
+        );
+        assert_eq!(
+            interpolate_with("$${UNDEFINED}", lookup),
+            Ok("${UNDEFINED}".into())
+        );
+    }
+
+    #[test]
+    fn falls_back_to_defaults() {
+        assert_eq!(
+            interpolate_with("${UNDEFINED:-fallback}", lookup),
+            Ok("fallback".into())
+        );
+        assert_eq!(
+            interpolate_with("${NAMESPACE:-fallback}", lookup),
+            Ok("myapp".into())
+        );
+        assert_eq!(interpolate_with("${UNDEFINED:-}", lookup), Ok("".into()));
+    }
+
+    #[test]
+    fn handles_empty_values() {
+        // Empty values are used as they are, unless there is a default
+        assert_eq!(interpolate_with("a${EMPTY}b", lookup), Ok("ab".into()));
+        assert_eq!(
+            interpolate_with("${EMPTY:-fallback}", lookup),
+            Ok("fallback".into())
+        );
+    }
+
+    #[test]
+    fn fails_on_undefined_variables() {
+        assert_eq!(
+            interpolate_with("${NAMESPACE} ${UNDEFINED}", lookup),
+            Err("UNDEFINED".into())
+        );
+    }
+
+    #[test]
+    fn keeps_unterminated_expressions() {
+        assert_eq!(
+            interpolate_with("description = \"${NAMESPACE\"", lookup),
+            Ok("description = \"${NAMESPACE\"".into())
+        );
+        assert_eq!(
+            interpolate_with("${NAMESPACE} ${", lookup),
+            Ok("myapp ${".into())
+        );
+    }
+
+    #[test]
+    fn keeps_invalid_names() {
+        for data in ["${1ABC}", "${}", "${A-B}", "${ NAMESPACE }", "${A.B:-x}"] {
+            assert_eq!(interpolate_with(data, lookup), Ok(data.into()), "{data}");
+        }
+    }
+}
@@ -2,12 +2,14 @@
 
 use std::env;
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
 
-use anyhow::{anyhow, Result};
 use path_clean::PathClean;
 
+use crate::constants::STDIO_PATH;
+use crate::error::{Error, Result};
+
 /// Returns the absolute path of a file or directory.
 pub fn absolute_path(path: impl AsRef<Path>) -> Result<PathBuf> {
     let path = path.as_ref();
@@ -22,17 +24,35 @@ pub fn absolute_path(path: impl AsRef<Path>) -> Result<PathBuf> {
     Ok(absolute_path)
 }
 
-/// Helper method to write a string to a file.
+/// Returns true if the path is `-`, standing for stdin or stdout.
+pub fn is_stdio(path: impl AsRef<Path>) -> bool {
+    path.as_ref() == Path::new(STDIO_PATH)
+}
+
+/// Helper method to write a string to a file, or to stdout if the path is `-`.
 pub fn write_file(path: impl AsRef<Path>, content: &str) -> Result<()> {
+    if is_stdio(&path) {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(content.as_bytes())?;
+        stdout.flush()?;
+        return Ok(());
+    }
+
     let mut file = File::create(path)?;
     file.write_all(content.as_bytes())?;
     Ok(())
 }
 
-/// Helper method to read a string from a file.
+/// Helper method to read a string from a file, or from stdin if the path is `-`.
 pub fn read_file(path: impl AsRef<Path>) -> Result<String> {
     let mut buf = String::new();
-    let mut file = File::open(path).map_err(|_| anyhow!("Could not open file"))?;
+    if is_stdio(&path) {
+        io::stdin().read_to_string(&mut buf)?;
+        return Ok(buf);
+    }
+
+    let mut file =
+        File::open(&path).map_err(|err| Error::OpenFile(path.as_ref().to_path_buf(), err))?;
     file.read_to_string(&mut buf)?;
     Ok(buf)
 }
@@ -0,0 +1,285 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+use p2panda_rs::hash::Hash;
+
+use crate::constants::LOCK_SEGMENTS_DIR_NAME;
+use crate::error::{Error, Result};
+
+/// Fetches a single file from a git repository at the given revision and returns its local path.
+///
+/// Only the requested file gets checked out (sparse checkout with a shallow, blob-less fetch).
+/// Checkouts are cached in `cache_dir` per repository, revision and path, so pinned revisions are
+/// only fetched once.
+///
+/// Url, revision and path usually come from schema files of other projects. Url and revision are
+/// validated before passing them to git so they can not be interpreted as options or exotic
+/// transports, the path so it can not point outside of the checkout.
+pub fn fetch_file(url: &str, rev: &str, path: &Path, cache_dir: &Path) -> Result<PathBuf> {
+    fetch_files(url, rev, path, &[], cache_dir)
+}
+
+/// Fetches a lock file from a git repository like `fetch_file`, together with the segment files
+/// in the `locks` directory next to it.
+pub fn fetch_lock_file(url: &str, rev: &str, path: &Path, cache_dir: &Path) -> Result<PathBuf> {
+    let segments_dir = path.with_file_name(LOCK_SEGMENTS_DIR_NAME);
+    fetch_files(url, rev, path, &[&segments_dir], cache_dir)
+}
+
+/// Fetches a file and the given other files or directories next to it, returning the path of the
+/// file.
+fn fetch_files(
+    url: &str,
+    rev: &str,
+    path: &Path,
+    other_paths: &[&Path],
+    cache_dir: &Path,
+) -> Result<PathBuf> {
+    validate_url(url)?;
+    validate_rev(rev)?;
+    validate_path(path)?;
+    for other_path in other_paths {
+        validate_path(other_path)?;
+    }
+
+    let mut key = format!("{url}\n{rev}\n{}", path.display());
+    for other_path in other_paths {
+        key.push_str(&format!("\n{}", other_path.display()));
+    }
+    let key = Hash::new_from_bytes(key.as_bytes());
+    let checkout_dir = cache_dir.join("git").join(key.as_str());
+    let file_path = checkout_dir.join(path);
+
+    if file_path.exists() {
+        return Ok(file_path);
+    }
+
+    // Check out into a temporary directory first, so interrupted fetches do not leave a broken
+    // cache entry behind
+    let temp_dir = checkout_dir.with_extension("tmp");
+    if temp_dir.exists() {
+        fs::remove_dir_all(&temp_dir)?;
+    }
+    fs::create_dir_all(&temp_dir)?;
+
+    let sparse_paths: Vec<String> = [path]
+        .into_iter()
+        .chain(other_paths.iter().copied())
+        .map(|path| format!("/{}", path.display()))
+        .collect();
+    let result = git(&temp_dir, &["init", "--quiet"])
+        .and_then(|_| git(&temp_dir, &["remote", "add", "origin", url]))
+        .and_then(|_| {
+            let mut args = vec!["sparse-checkout", "set", "--no-cone"];
+            args.extend(sparse_paths.iter().map(String::as_str));
+            git(&temp_dir, &args)
+        })
+        .and_then(|_| {
+            git(
+                &temp_dir,
+                &[
+                    "fetch",
+                    "--quiet",
+                    "--depth",
+                    "1",
+                    "--filter=blob:none",
+                    "--end-of-options",
+                    "origin",
+                    rev,
+                ],
+            )
+        })
+        .and_then(|_| git(&temp_dir, &["checkout", "--quiet", "FETCH_HEAD"]));
+
+    if let Err(err) = result {
+        fs::remove_dir_all(&temp_dir)?;
+        return Err(err);
+    }
+
+    if checkout_dir.exists() {
+        fs::remove_dir_all(&checkout_dir)?;
+    }
+    fs::rename(&temp_dir, &checkout_dir)?;
+
+    if !file_path.exists() {
+        fs::remove_dir_all(&checkout_dir)?;
+        return Err(Error::Git(format!(
+            "{} does not exist in {url} at revision {rev}",
+            path.display()
+        )));
+    }
+
+    Ok(file_path)
+}
+
+/// Checks that the url uses one of the transports fishy fetches from.
+///
+/// Other transports like `ext::` run arbitrary commands, urls starting with `-` are read as
+/// options.
+fn validate_url(url: &str) -> Result<()> {
+    let allowed = ["https://", "ssh://", "git@", "file://"]
+        .iter()
+        .any(|prefix| url.starts_with(prefix));
+
+    if !allowed {
+        return Err(Error::InvalidGitUrl(url.to_owned()));
+    }
+
+    Ok(())
+}
+
+/// Checks that the revision is a plain branch, tag or commit name which git can not read as an
+/// option.
+fn validate_rev(rev: &str) -> Result<()> {
+    let valid = !rev.is_empty()
+        && !rev.starts_with('-')
+        && rev
+            .chars()
+            .all(|char| char.is_ascii_alphanumeric() || matches!(char, '.' | '_' | '/' | '-'));
+
+    if !valid {
+        return Err(Error::InvalidGitRevision(rev.to_owned()));
+    }
+
+    Ok(())
+}
+
+/// Checks that the path is relative to the repository root and does not escape it.
+///
+/// Joining an absolute path or one with `..` components onto the checkout directory would point
+/// at files outside of the cache.
+fn validate_path(path: &Path) -> Result<()> {
+    let is_relative = path.components().next().is_some()
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+
+    if !is_relative {
+        return Err(Error::Git(format!(
+            "Path {} needs to be relative to the repository root",
+            path.display()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Returns the content of a file at the given revision of the git repository it is in.
+pub fn show_file(rev: &str, path: &Path) -> Result<String> {
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| Error::Git(format!("{} is not a file", path.display())))?;
+
+    git(
+        dir,
+        &["show", &format!("{rev}:./{}", file_name.to_string_lossy())],
+    )
+}
+
+/// Returns all tags of the git repository the given directory is in.
+pub fn tags(dir: &Path) -> Result<Vec<String>> {
+    let output = git(dir, &["tag", "--list"])?;
+    Ok(output.lines().map(str::to_owned).collect())
+}
+
+/// Runs a git command in the given directory and returns its output.
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|err| Error::Git(format!("Could not run git: {err}")))?;
+
+    if !output.status.success() {
+        return Err(Error::Git(
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use crate::test_utils::temp_dir;
+
+    use super::{fetch_file, validate_rev, validate_url};
+
+    #[test]
+    fn accepts_plain_revisions() {
+        for rev in ["main", "v2.0", "release/1.x", "feature_branch", "4f3a9c1"] {
+            assert!(validate_rev(rev).is_ok(), "{rev}");
+        }
+    }
+
+    #[test]
+    fn rejects_option_like_revisions() {
+        for rev in [
+            "",
+            "--upload-pack=touch /tmp/pwned",
+            "-c",
+            "main;rm -rf /",
+            "main rev",
+            "$(id)",
+        ] {
+            assert!(validate_rev(rev).is_err(), "{rev}");
+        }
+    }
+
+    #[test]
+    fn accepts_known_transports() {
+        for url in [
+            "https://github.com/example/venues",
+            "ssh://git@github.com/example/venues.git",
+            "git@github.com:example/venues.git",
+            "file:///srv/git/venues",
+        ] {
+            assert!(validate_url(url).is_ok(), "{url}");
+        }
+    }
+
+    #[test]
+    fn rejects_other_transports() {
+        for url in [
+            "ext::sh -c touch% /tmp/pwned",
+            "--upload-pack=touch /tmp/pwned",
+            "http://example.org/venues",
+            "../venues",
+            "fd::17",
+        ] {
+            assert!(validate_url(url).is_err(), "{url}");
+        }
+    }
+
+    #[test]
+    fn rejects_paths_outside_of_checkout() {
+        let cache_dir = temp_dir("git-paths");
+        let outside = cache_dir.join("outside.lock");
+        fs::write(&outside, "").unwrap();
+
+        for path in [
+            outside.as_path(),
+            Path::new("../../outside.lock"),
+            Path::new("schemas/../../../outside.lock"),
+            Path::new(""),
+        ] {
+            let result = fetch_file(
+                "https://github.com/example/venues",
+                "main",
+                path,
+                &cache_dir,
+            );
+            assert!(result.is_err(), "{}", path.display());
+        }
+    }
+}
@@ -1,14 +1,18 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use std::fs::{File, Permissions};
+use std::fs::{self, File, Permissions};
 use std::os::unix::prelude::PermissionsExt;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use anyhow::Result;
+use bip39::Mnemonic;
 use p2panda_rs::identity::KeyPair;
 
+use crate::error::{Error, Result};
 use crate::utils::files;
 
+/// Number of hexadecimal characters of an encoded ed25519 private key.
+const PRIVATE_KEY_LENGTH: usize = 64;
+
 /// Writes a hex-encoded ed25519 private key string into a file and sets permission to 0600.
 pub fn write_key_pair(path: impl AsRef<Path>, key_pair: &KeyPair) -> Result<()> {
     let private_key_str = hex::encode(key_pair.private_key());
@@ -21,9 +25,98 @@ pub fn write_key_pair(path: impl AsRef<Path>, key_pair: &KeyPair) -> Result<()>
 }
 
 /// Reads a hex-encoded ed25519 private key string from a file and derives key pair from it.
+///
+/// Surrounding whitespace and lines starting with `#` are ignored, empty files or files not
+/// containing exactly one private key fail with `Error::InvalidPrivateKeyFile`.
 pub fn read_key_pair(path: impl AsRef<Path>) -> Result<KeyPair> {
-    let private_key_str = files::read_file(&path)?;
-    let key_pair = KeyPair::from_private_key_str(&private_key_str)?;
+    let path = path.as_ref();
+    let data = files::read_file(path)?;
+
+    parse_private_key(&data)
+        .map_err(|reason| Error::InvalidPrivateKeyFile(path.to_path_buf(), reason))
+}
+
+/// Replaces the key pair file with a newly generated key pair.
+///
+/// Files which are not empty get moved to `<file>.bak` first, returns the new key pair and the
+/// path of the backup if one was made.
+pub fn regenerate_key_pair(path: impl AsRef<Path>) -> Result<(KeyPair, Option<PathBuf>)> {
+    let path = path.as_ref();
+
+    let backup_path = match fs::read_to_string(path) {
+        Ok(data) if !data.trim().is_empty() => {
+            let mut backup_path = path.as_os_str().to_owned();
+            backup_path.push(".bak");
+            let backup_path = PathBuf::from(backup_path);
+
+            fs::rename(path, &backup_path)?;
+            Some(backup_path)
+        }
+        _ => None,
+    };
+
+    let key_pair = KeyPair::new();
+    write_key_pair(path, &key_pair)?;
+
+    Ok((key_pair, backup_path))
+}
+
+/// Encodes the private key as 24 words of the English BIP39 word list, to back it up offline.
+///
+/// The words encode the private key itself, they can not be used with wallets deriving their keys
+/// from a BIP39 seed.
+pub fn to_mnemonic(key_pair: &KeyPair) -> String {
+    Mnemonic::from_entropy(key_pair.private_key().as_bytes())
+        .expect("Private keys have a valid entropy length")
+        .to_string()
+}
+
+/// Derives the key pair from 24 words created with `to_mnemonic`.
+///
+/// Words are separated by whitespace, the checksum contained in the last word detects typos and
+/// swapped words.
+pub fn from_mnemonic(mnemonic: &str) -> Result<KeyPair> {
+    let words = mnemonic.split_whitespace().collect::<Vec<&str>>().join(" ");
+    let mnemonic = Mnemonic::parse(words).map_err(|err| Error::InvalidMnemonic(err.to_string()))?;
+
+    let (entropy, length) = mnemonic.to_entropy_array();
+    if length != PRIVATE_KEY_LENGTH / 2 {
+        return Err(Error::InvalidMnemonic(format!(
+            "expected 24 words, found {}",
+            mnemonic.word_count()
+        )));
+    }
+
+    let key_pair = KeyPair::from_private_key_str(&hex::encode(&entropy[..length]))?;
 
     Ok(key_pair)
 }
+
+/// Derives the key pair from the content of a key pair file, returns the reason as error if it is
+/// invalid.
+fn parse_private_key(data: &str) -> std::result::Result<KeyPair, String> {
+    let lines: Vec<&str> = data
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+
+    let private_key_str = match lines.as_slice() {
+        [] => return Err("the file is empty".into()),
+        [private_key_str] => *private_key_str,
+        _ => return Err("the file contains more than one line besides comments".into()),
+    };
+
+    if !private_key_str.chars().all(|char| char.is_ascii_hexdigit()) {
+        return Err("the private key contains characters which are not hexadecimal".into());
+    }
+
+    if private_key_str.len() != PRIVATE_KEY_LENGTH {
+        return Err(format!(
+            "the private key needs to be {PRIVATE_KEY_LENGTH} characters long, found {}",
+            private_key_str.len()
+        ));
+    }
+
+    KeyPair::from_private_key_str(private_key_str).map_err(|err| err.to_string())
+}
@@ -1,5 +1,7 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+pub mod env;
 pub mod files;
+pub mod git;
 pub mod key_pair;
-pub mod terminal;
+pub mod time;
@@ -0,0 +1,51 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Verify the integrity of lock files.
+//!
+//! Applications shipping lock files, for example in bundles, can re-validate them at startup
+//! before deploying their commits.
+use p2panda_rs::schema::SchemaId;
+use p2panda_rs::test_utils::memory_store::MemoryStore;
+
+use crate::build::{assemble_previous_schemas, publish_commit};
+use crate::error::{Error, Result};
+use crate::lock_file::LockFile;
+
+/// Result of a successful lock file verification.
+#[derive(Clone, Debug)]
+pub struct Verification {
+    /// Number of verified commits.
+    pub commits: usize,
+
+    /// Ids of all schemas materialized from the commits, sorted by name.
+    pub schema_ids: Vec<SchemaId>,
+}
+
+/// Re-validates every commit of a lock file.
+///
+/// Checks that entry hashes match, operations decode, signatures verify, backlinks and skiplinks
+/// are consistent and that every commit targets a schema or field definition. Finally all schemas
+/// get assembled from the materialized documents. Invalid commits are reported with their index
+/// in the lock file.
+pub async fn verify_lock_file(lock_file: &LockFile) -> Result<Verification> {
+    let store = MemoryStore::default();
+    let commits = lock_file.commits.clone().unwrap_or_default();
+
+    for (index, commit) in commits.iter().enumerate() {
+        publish_commit(&store, commit)
+            .await
+            .map_err(|err| Error::InvalidLockFileCommit(index, Box::new(err)))?;
+    }
+
+    let mut schema_ids: Vec<SchemaId> = assemble_previous_schemas(&store)
+        .await?
+        .into_values()
+        .map(|previous| previous.schema.id().to_owned())
+        .collect();
+    schema_ids.sort_by_key(|schema_id| schema_id.to_string());
+
+    Ok(Verification {
+        commits: commits.len(),
+        schema_ids,
+    })
+}
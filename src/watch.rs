@@ -0,0 +1,117 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Follow changes of the schemas a node knows about.
+//!
+//! Nodes shared by multiple teams receive schemas from different authors. Comparing the schema
+//! definitions of a node with the ones seen before shows which schemas were created, updated or
+//! removed since then and who owns them.
+use std::collections::BTreeMap;
+
+use p2panda_rs::document::DocumentId;
+use p2panda_rs::schema::FieldName;
+use tracing::{debug, instrument};
+
+use crate::client::{Client, RemoteSchema};
+use crate::error::Result;
+
+/// Change of a schema definition document on the node.
+#[derive(Clone, Debug)]
+pub enum SchemaEvent {
+    /// Schema which was not known to the node before.
+    Created(RemoteSchema),
+
+    /// New version of a known schema.
+    Updated {
+        /// Version of the schema seen before.
+        previous: Box<RemoteSchema>,
+
+        /// Latest version of the schema.
+        current: RemoteSchema,
+    },
+
+    /// Schema which is not served by the node anymore, its document was deleted.
+    Removed(RemoteSchema),
+}
+
+impl SchemaEvent {
+    /// Returns the names of the fields which were added and removed with an update.
+    ///
+    /// Created and removed schemas do not have any field changes.
+    pub fn field_changes(&self) -> (Vec<FieldName>, Vec<FieldName>) {
+        let SchemaEvent::Updated { previous, current } = self else {
+            return (Vec::new(), Vec::new());
+        };
+
+        let added = current
+            .fields
+            .iter()
+            .filter(|(name, _)| !previous.fields.iter().any(|(other, _)| other == *name))
+            .map(|(name, _)| name.clone())
+            .collect();
+        let removed = previous
+            .fields
+            .iter()
+            .filter(|(name, _)| !current.fields.iter().any(|(other, _)| other == *name))
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        (added, removed)
+    }
+}
+
+/// Polls the schemas of a node and reports what changed between two polls.
+#[derive(Debug)]
+pub struct SchemaWatcher {
+    client: Client,
+    schemas: BTreeMap<DocumentId, RemoteSchema>,
+}
+
+impl SchemaWatcher {
+    /// Fetches the schemas the node currently knows about, later changes are compared with them.
+    pub async fn start(client: Client) -> Result<Self> {
+        let schemas = client.all_schemas().await?.into_iter().collect();
+
+        Ok(Self { client, schemas })
+    }
+
+    /// Returns the latest versions of all schemas seen on the node.
+    pub fn schemas(&self) -> impl Iterator<Item = &RemoteSchema> {
+        self.schemas.values()
+    }
+
+    /// Fetches the schemas of the node again and returns all changes since the previous poll.
+    ///
+    /// If the node can not be reached, the previous state is kept and the changes are reported
+    /// by the next successful poll.
+    #[instrument(skip_all)]
+    pub async fn poll(&mut self) -> Result<Vec<SchemaEvent>> {
+        let current: BTreeMap<DocumentId, RemoteSchema> =
+            self.client.all_schemas().await?.into_iter().collect();
+
+        let mut events = Vec::new();
+
+        for (document_id, schema) in &current {
+            match self.schemas.get(document_id) {
+                None => events.push(SchemaEvent::Created(schema.clone())),
+                Some(previous) if previous.schema_id != schema.schema_id => {
+                    events.push(SchemaEvent::Updated {
+                        previous: Box::new(previous.clone()),
+                        current: schema.clone(),
+                    })
+                }
+                Some(_) => (),
+            }
+        }
+
+        for (document_id, schema) in &self.schemas {
+            if !current.contains_key(document_id) {
+                events.push(SchemaEvent::Removed(schema.clone()));
+            }
+        }
+
+        debug!("Found {} schema changes", events.len());
+        self.schemas = current;
+
+        Ok(events)
+    }
+}
@@ -0,0 +1,56 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+#![cfg(feature = "macros")]
+
+use fishy::schema_file::{RelationId, SchemaField};
+use fishy::ToP2pandaSchema;
+
+/// A place to meet
+#[derive(ToP2pandaSchema)]
+#[allow(dead_code)]
+struct Venue {
+    name: String,
+}
+
+/// Something happening at a venue
+#[derive(ToP2pandaSchema)]
+#[allow(dead_code)]
+struct Event {
+    #[p2panda(max_length = 120)]
+    title: String,
+    #[p2panda(relation_list, schema = "venue")]
+    venues: Vec<String>,
+    #[p2panda(relation, schema = "blob_v1")]
+    poster: String,
+}
+
+#[test]
+fn derives_schema_relating_to_other_derived_schema() {
+    let schema = Event::to_p2panda_schema().unwrap();
+    assert_eq!(schema.name.to_string(), "event");
+
+    // Relations to other schemas are kept by name, system schemas are resolved to their ids
+    let relation_id =
+        |field_name: &str| match schema.fields.iter().find(|(name, _)| *name == field_name) {
+            Some((_, SchemaField::Relation { schema, .. })) => schema.id.clone(),
+            _ => panic!("Expected relation field {field_name}"),
+        };
+    assert!(matches!(
+        relation_id("venues"),
+        RelationId::Name(schema_name) if schema_name.to_string() == "venue"
+    ));
+    assert!(matches!(
+        relation_id("poster"),
+        RelationId::Id(schema_id) if schema_id.to_string() == "blob_v1"
+    ));
+
+    // Checksums of the same definition are stable
+    assert_eq!(
+        Event::schema_checksum().unwrap(),
+        Event::schema_checksum().unwrap()
+    );
+    assert_ne!(
+        Event::schema_checksum().unwrap(),
+        Venue::schema_checksum().unwrap()
+    );
+}
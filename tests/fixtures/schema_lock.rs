@@ -0,0 +1,45 @@
+// This file is automatically generated by fishy.
+// It is not intended for manual editing.
+
+use fishy::lock_file::LockFile;
+use fishy::p2panda::SchemaId;
+
+/// Lock file with the commits of all schemas.
+pub const SCHEMA_LOCK: &str = r#"# This file is automatically generated by fishy.
+# It is not intended for manual editing.
+
+version = 2
+checksum = "0020a0b6343c5d8c3b5da960b1d50176edec7893db2b5b40a4fcb73623240e46409e"
+
+[[commits]]
+entry_hash = "00200244760cd8a81c3b23e98a4ed3221cc5b20a73b60cf5a5488ce9a5a69e81c285"
+entry = "008a88e3dd7409f195fd52db2d3cba5d72ca6709bf1d94121bf3748801b40f6f5c0001330020d71ccd9dd038adefad3260a41223e484edbc32e5c96b41df467c36bbd8c1d84b4bffba0f53ee8bcc5d36a78570f2ceb9b2cab695fff0a8552f3d4bf1121e35d517150cdb432e171842713c003829fd9d0a1dabfdefa5d4db23df528b2aaeda02"
+operation = "840100781a736368656d615f6669656c645f646566696e6974696f6e5f7631a2646e616d65646e616d65647479706563737472"
+
+[commits.annotation]
+schema = "venues"
+action = "create"
+field = "name"
+
+[[commits]]
+entry_hash = "0020768128fe31b7908249ee5725d935f8e78db6750d417d930333337df0b89af13d"
+entry = "008a88e3dd7409f195fd52db2d3cba5d72ca6709bf1d94121bf3748801b40f6f5c01016d002087247c13775fe039ebaddaf5db1e56bc0b83ced28a25d8bbff4b2f509139f5cebfa431202abe748ada458be39adcf725d9ceaac88e621c0a7bcb7eff9ea0a6b6f32d32a3f499383c124fb7a25d7c544debe2ae063bde55d5793ec87faeb17802"
+operation = "84010074736368656d615f646566696e6974696f6e5f7631a36b6465736372697074696f6e6e506c6163657320746f206d656574666669656c64738181582200200244760cd8a81c3b23e98a4ed3221cc5b20a73b60cf5a5488ce9a5a69e81c285646e616d656676656e756573"
+
+[commits.annotation]
+schema = "venues"
+action = "create"
+"#;
+
+/// Id of the latest version of the `venues` schema.
+pub const VENUES_SCHEMA_ID: &str = "venues_0020768128fe31b7908249ee5725d935f8e78db6750d417d930333337df0b89af13d";
+
+/// Returns the id of the latest version of the `venues` schema.
+pub fn venues_schema_id() -> SchemaId {
+    VENUES_SCHEMA_ID.parse().expect("Embedded schema id is valid")
+}
+
+/// Returns the embedded lock file, ready to be deployed.
+pub fn lock_file() -> LockFile {
+    SCHEMA_LOCK.parse().expect("Embedded lock file is valid")
+}
@@ -0,0 +1,15 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+/// Module generated by `LockFile::to_rust_module`, kept up-to-date by a test of the lock file.
+mod schema_lock {
+    include!("fixtures/schema_lock.rs");
+}
+
+#[test]
+fn compiles_generated_rust_module() {
+    let lock_file = schema_lock::lock_file();
+    let schema_id = schema_lock::venues_schema_id();
+
+    assert_eq!(schema_id.to_string(), schema_lock::VENUES_SCHEMA_ID);
+    assert_eq!(lock_file.schema_ids().unwrap(), vec![schema_id]);
+}